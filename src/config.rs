@@ -29,6 +29,15 @@ const DEFAULT_LDK_WALLET_SYNC_INTERVAL_SECS: u64 = 30;
 const DEFAULT_FEE_RATE_CACHE_UPDATE_INTERVAL_SECS: u64 = 60 * 10;
 const DEFAULT_PROBING_LIQUIDITY_LIMIT_MULTIPLIER: u64 = 3;
 const DEFAULT_ANCHOR_PER_CHANNEL_RESERVE_SATS: u64 = 25_000;
+const DEFAULT_HOLD_INVOICE_CANCEL_MARGIN_BLOCKS: u32 = 2;
+const DEFAULT_FEE_POLICY_MIN_FEE_PROPORTIONAL_MILLIONTHS: u32 = 10;
+const DEFAULT_FEE_POLICY_MAX_FEE_PROPORTIONAL_MILLIONTHS: u32 = 2_500;
+const DEFAULT_FEE_POLICY_MIN_FEE_BASE_MSAT: u32 = 0;
+const DEFAULT_FEE_POLICY_MAX_FEE_BASE_MSAT: u32 = 1_000;
+const DEFAULT_FEE_POLICY_VOLUME_WINDOW_SECS: u64 = 60 * 60 * 24;
+const DEFAULT_FEE_POLICY_HIGH_VOLUME_PERCENT: u8 = 50;
+const DEFAULT_FEE_POLICY_VOLUME_ADJUSTMENT_PERCENT: u8 = 20;
+const DEFAULT_FEE_POLICY_UPDATE_INTERVAL_SECS: u64 = 60 * 60;
 
 /// The default log level.
 pub const DEFAULT_LOG_LEVEL: LogLevel = LogLevel::Debug;
@@ -52,6 +61,46 @@ pub(crate) const LDK_PAYMENT_RETRY_TIMEOUT: Duration = Duration::from_secs(10);
 // The interval (in block height) after which we retry archiving fully resolved channel monitors.
 pub(crate) const RESOLVED_CHANNEL_MONITOR_ARCHIVAL_INTERVAL: u32 = 6;
 
+// The time in-between checks of our held payments' claim deadlines.
+pub(crate) const HELD_PAYMENT_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+// The minimum relative change, in percent, of a channel's proportional fee for which the fee policy
+// engine will update it, so that we don't spam the network with channel updates.
+pub(crate) const FEE_POLICY_MIN_CHANGE_PERCENT: u32 = 5;
+
+// The minimum interval, in seconds, at which the fee policy engine may update channel fees.
+pub(crate) const FEE_POLICY_UPDATE_INTERVAL_MINIMUM_SECS: u64 = 60;
+
+// The time in-between checks whether any payments need to be archived per our retention policy.
+pub(crate) const PAYMENT_ARCHIVAL_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+// The minimum final CLTV expiry delta of invoices for which payments will be held, giving the user
+// some time to claim or fail them.
+pub(crate) const HOLD_INVOICE_MIN_FINAL_CLTV_EXPIRY_DELTA: u16 = 72;
+
+// The number of blocks before automatically failing back a held payment at which we warn the user.
+pub(crate) const HELD_PAYMENT_EXPIRY_WARNING_BLOCKS: u32 = 3;
+
+// The timeout after which we abandon connecting to a peer via a SOCKS5 proxy.
+pub(crate) const SOCKS5_PROXY_CONNECTION_TIMEOUT: Duration = Duration::from_secs(30);
+
+// The recursive DNS resolver queried to resolve BIP 353 human-readable names by default.
+pub(crate) const DEFAULT_HRN_DNS_RESOLVER: ([u8; 4], u16) = ([8, 8, 8, 8], 53);
+
+// The timeout after which we abort building the DNSSEC proof for a BIP 353 human-readable name.
+pub(crate) const HRN_RESOLUTION_TIMEOUT_SECS: u64 = 10;
+
+// The time in-between checks of the payment and channel state of orders placed with our LSPS1
+// service.
+pub(crate) const LSPS1_SERVICE_ORDER_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+// The time in-between checks whether the channels sold via our LSPS2 service still exist.
+pub(crate) const LSPS2_SERVICE_SALE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+// The time in-between polls of the state of channel orders we placed with our LSPS1 liquidity
+// source.
+pub(crate) const LSPS1_CLIENT_ORDER_POLL_INTERVAL: Duration = Duration::from_secs(60);
+
 // The time in-between peer reconnection attempts.
 pub(crate) const PEER_RECONNECTION_INTERVAL: Duration = Duration::from_secs(60);
 
@@ -82,6 +131,12 @@ pub(crate) const TX_BROADCAST_TIMEOUT_SECS: u64 = 5;
 // The timeout after which we abort a RGS sync operation.
 pub(crate) const RGS_SYNC_TIMEOUT_SECS: u64 = 5;
 
+// The timeout after which we abort a request to an LNURL service.
+pub(crate) const LNURL_REQUEST_TIMEOUT_SECS: u64 = 10;
+
+// The expiry of the invoices we hand to LNURL-withdraw services.
+pub(crate) const LNURL_WITHDRAW_INVOICE_EXPIRY_SECS: u32 = 60 * 60;
+
 /// The length in bytes of our wallets' keys seed.
 pub const WALLET_KEYS_SEED_LEN: usize = 64;
 
@@ -106,8 +161,16 @@ pub const WALLET_KEYS_SEED_LEN: usize = 64;
 /// | `log_level`                            | Debug              |
 /// | `anchor_channels_config`               | Some(..)           |
 /// | `sending_parameters`                   | None               |
+/// | `use_external_channel_funding`         | false              |
+/// | `hold_invoice_cancel_margin_blocks`    | 2                  |
+/// | `socks5_proxy`                         | None               |
+/// | `channel_acceptance_policy`            | Default            |
+/// | `emit_payment_path_events`             | false              |
+/// | `payment_retention`                    | Default            |
+/// | `fee_policy`                           | None               |
 ///
-/// See [`AnchorChannelsConfig`] and [`SendingParameters`] for more information regarding their
+/// See [`AnchorChannelsConfig`], [`SendingParameters`], [`ChannelAcceptancePolicy`],
+/// [`PaymentRetentionConfig`], and [`FeePolicyConfig`] for more information regarding their
 /// respective default values.
 ///
 /// [`Node`]: crate::Node
@@ -170,6 +233,70 @@ pub struct Config {
 	/// **Note:** If unset, default parameters will be used, and you will be able to override the
 	/// parameters on a per-payment basis in the corresponding method calls.
 	pub sending_parameters: Option<SendingParameters>,
+	/// Whether the funding transactions of channels we initiate are provided by an external wallet.
+	///
+	/// If set to `true`, our on-chain wallet won't fund outbound channels. Instead, an
+	/// [`Event::FundingPsbtReady`] carrying an unsigned PSBT will be emitted once the channel
+	/// negotiation allows for it, which needs to be funded and signed externally and then be
+	/// handed back via [`Node::finalize_channel_funding`].
+	///
+	/// **Note:** Our on-chain wallet will still need to cover any Anchor channel reserves as
+	/// configured via [`AnchorChannelsConfig::per_channel_reserve_sats`].
+	///
+	/// [`Event::FundingPsbtReady`]: crate::Event::FundingPsbtReady
+	/// [`Node::finalize_channel_funding`]: crate::Node::finalize_channel_funding
+	pub use_external_channel_funding: bool,
+	/// The number of blocks before the claim deadline of a held payment at which we'll
+	/// automatically fail it back, if it hasn't been claimed or failed manually in the meantime.
+	///
+	/// This applies to payments registered via [`Bolt11Payment::receive_for_hash`] and
+	/// [`Bolt11Payment::receive_variable_amount_for_hash`]. An [`Event::PaymentHoldExpiring`] will
+	/// be emitted a few blocks before the payment is automatically failed back.
+	///
+	/// If set to `0`, held payments will only be failed back once the claim deadline is reached.
+	///
+	/// [`Bolt11Payment::receive_for_hash`]: crate::payment::Bolt11Payment::receive_for_hash
+	/// [`Bolt11Payment::receive_variable_amount_for_hash`]: crate::payment::Bolt11Payment::receive_variable_amount_for_hash
+	/// [`Event::PaymentHoldExpiring`]: crate::Event::PaymentHoldExpiring
+	pub hold_invoice_cancel_margin_blocks: u32,
+	/// Configuration options for making outbound peer connections via a SOCKS5 proxy, e.g., a
+	/// Tor daemon.
+	///
+	/// Please refer to [`Socks5ProxyConfig`] for further information.
+	///
+	/// **Note:** If unset, we won't be able to connect to peers via [`SocketAddress::OnionV3`]
+	/// addresses.
+	pub socks5_proxy: Option<Socks5ProxyConfig>,
+	/// The policy applied to inbound channel requests.
+	///
+	/// Please refer to [`ChannelAcceptancePolicy`] for further information.
+	pub channel_acceptance_policy: ChannelAcceptancePolicy,
+	/// Whether an event is emitted for every path of an outbound payment that succeeded or failed.
+	///
+	/// If set to `true`, [`Event::PaymentPathSuccessful`] and [`Event::PaymentPathFailed`] will be
+	/// emitted in addition to the events concerning the payment as a whole.
+	///
+	/// **Note:** The outcomes of probes will always be surfaced via [`Event::ProbeSucceeded`] and
+	/// [`Event::ProbeFailed`].
+	///
+	/// [`Event::PaymentPathSuccessful`]: crate::Event::PaymentPathSuccessful
+	/// [`Event::PaymentPathFailed`]: crate::Event::PaymentPathFailed
+	/// [`Event::ProbeSucceeded`]: crate::Event::ProbeSucceeded
+	/// [`Event::ProbeFailed`]: crate::Event::ProbeFailed
+	pub emit_payment_path_events: bool,
+	/// The policy after which payments are moved from the payment store to the payment archive.
+	///
+	/// Please refer to [`PaymentRetentionConfig`] for further information.
+	pub payment_retention: PaymentRetentionConfig,
+	/// Configuration options for automatically adjusting the forwarding fees of our channels.
+	///
+	/// Please refer to [`FeePolicyConfig`] for further information.
+	///
+	/// If set to `None`, forwarding fees are only ever changed via
+	/// [`Node::update_channel_config`].
+	///
+	/// [`Node::update_channel_config`]: crate::Node::update_channel_config
+	pub fee_policy: Option<FeePolicyConfig>,
 }
 
 impl Default for Config {
@@ -183,6 +310,13 @@ impl Default for Config {
 			probing_liquidity_limit_multiplier: DEFAULT_PROBING_LIQUIDITY_LIMIT_MULTIPLIER,
 			anchor_channels_config: Some(AnchorChannelsConfig::default()),
 			sending_parameters: None,
+			use_external_channel_funding: false,
+			hold_invoice_cancel_margin_blocks: DEFAULT_HOLD_INVOICE_CANCEL_MARGIN_BLOCKS,
+			socks5_proxy: None,
+			channel_acceptance_policy: ChannelAcceptancePolicy::default(),
+			emit_payment_path_events: false,
+			payment_retention: PaymentRetentionConfig::default(),
+			fee_policy: None,
 			node_alias: None,
 		}
 	}
@@ -257,6 +391,259 @@ impl Default for AnchorChannelsConfig {
 	}
 }
 
+/// Configuration options for making outbound peer connections via a SOCKS5 proxy.
+///
+/// This allows to reach peers that are only available via the Tor network by pointing the
+/// `proxy_address` to the SOCKS port of a running Tor daemon.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Socks5ProxyConfig {
+	/// The address of the SOCKS5 proxy, e.g., `127.0.0.1:9050`.
+	pub proxy_address: SocketAddress,
+	/// Whether only connections to [`SocketAddress::OnionV3`] addresses should be routed through
+	/// the proxy.
+	///
+	/// If set to `false`, all outbound peer connections will be routed through the proxy.
+	pub onion_only: bool,
+}
+
+/// The policy applied to inbound channel requests.
+///
+/// Inbound channel requests violating any of the configured limits will be rejected. Note that
+/// these checks are applied in addition to the ones mandated by the [`AnchorChannelsConfig`] and
+/// the node's announcement configuration.
+///
+/// If `manual_acceptance` is set, requests passing the policy won't be accepted automatically.
+/// Instead, an [`Event::InboundChannelRequest`] will be emitted, and the request needs to be
+/// accepted via [`Node::accept_inbound_channel`] or rejected via [`Node::reject_inbound_channel`].
+///
+/// ### Defaults
+///
+/// | Parameter                        | Value  |
+/// |----------------------------------|--------|
+/// | `min_funding_sats`               | None   |
+/// | `max_funding_sats`               | None   |
+/// | `allowed_peers`                  | None   |
+/// | `denied_peers`                   | []     |
+/// | `require_anchor_channels`        | false  |
+/// | `max_pending_channels_per_peer`  | None   |
+/// | `manual_acceptance`              | false  |
+///
+/// [`Event::InboundChannelRequest`]: crate::Event::InboundChannelRequest
+/// [`Node::accept_inbound_channel`]: crate::Node::accept_inbound_channel
+/// [`Node::reject_inbound_channel`]: crate::Node::reject_inbound_channel
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChannelAcceptancePolicy {
+	/// The minimum channel value, in satoshis, we accept for inbound channels.
+	pub min_funding_sats: Option<u64>,
+	/// The maximum channel value, in satoshis, we accept for inbound channels.
+	pub max_funding_sats: Option<u64>,
+	/// If set, we only accept inbound channels from the given peers.
+	pub allowed_peers: Option<Vec<PublicKey>>,
+	/// A list of peers we never accept inbound channels from.
+	///
+	/// This takes precedence over [`ChannelAcceptancePolicy::allowed_peers`].
+	pub denied_peers: Vec<PublicKey>,
+	/// Whether we only accept inbound channels for which the `option_anchors_zero_fee_htlc_tx`
+	/// channel type is negotiated.
+	pub require_anchor_channels: bool,
+	/// The maximum number of channels with a single peer that may be pending, i.e., haven't become
+	/// ready yet, at the time we accept another inbound channel from them.
+	pub max_pending_channels_per_peer: Option<u32>,
+	/// Whether inbound channel requests need to be accepted or rejected manually.
+	///
+	/// If set, an [`Event::InboundChannelRequest`] will be emitted for any request passing the
+	/// policy. Note that LDK will drop requests that haven't been handled within a few minutes.
+	///
+	/// [`Event::InboundChannelRequest`]: crate::Event::InboundChannelRequest
+	pub manual_acceptance: bool,
+}
+
+/// Options determining when payments are archived.
+///
+/// Archived payments are moved to a separate namespace of the store and are no longer returned
+/// by [`Node::list_payments`] or loaded on startup. They can still be retrieved on demand via
+/// [`Node::list_archived_payments`].
+///
+/// Payments that are still pending or held are never archived, and neither are on-chain payments
+/// that were fee-bumped.
+///
+/// ### Defaults
+///
+/// | Parameter                          | Value  |
+/// |------------------------------------|--------|
+/// | `succeeded_payment_retention_days` | None   |
+/// | `failed_payment_retention_days`    | None   |
+///
+/// [`Node::list_payments`]: crate::Node::list_payments
+/// [`Node::list_archived_payments`]: crate::Node::list_archived_payments
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PaymentRetentionConfig {
+	/// The number of days after their last update at which succeeded payments are archived.
+	///
+	/// If set to `None`, succeeded payments are never archived automatically.
+	pub succeeded_payment_retention_days: Option<u32>,
+	/// The number of days after their last update at which failed payments are archived.
+	///
+	/// If set to `None`, failed payments are never archived automatically.
+	pub failed_payment_retention_days: Option<u32>,
+}
+
+impl PaymentRetentionConfig {
+	pub(crate) fn is_enabled(&self) -> bool {
+		self.succeeded_payment_retention_days.is_some()
+			|| self.failed_payment_retention_days.is_some()
+	}
+}
+
+/// Options for the fee policy engine which regularly adjusts the forwarding fees of our channels.
+///
+/// For each usable channel, the fees are set in between the configured bounds depending on the
+/// share of the channel's liquidity that is on our side: the less outbound liquidity is left, the
+/// higher the fees. Additionally, the fees of channels that saw a high forwarding volume within
+/// the [`forwarding_volume_window_secs`] are raised, while the fees of channels that saw no
+/// outbound forwards at all are lowered, each by [`volume_fee_adjustment_percent`], without
+/// leaving the configured bounds.
+///
+/// ### Defaults
+///
+/// | Parameter                                    | Value  |
+/// |----------------------------------------------|--------|
+/// | `min_forwarding_fee_proportional_millionths` | 10     |
+/// | `max_forwarding_fee_proportional_millionths` | 2500   |
+/// | `min_forwarding_fee_base_msat`               | 0      |
+/// | `max_forwarding_fee_base_msat`               | 1000   |
+/// | `forwarding_volume_window_secs`              | 86400  |
+/// | `high_forwarding_volume_percent`             | 50     |
+/// | `volume_fee_adjustment_percent`              | 20     |
+/// | `update_interval_secs`                       | 3600   |
+///
+/// [`forwarding_volume_window_secs`]: Self::forwarding_volume_window_secs
+/// [`volume_fee_adjustment_percent`]: Self::volume_fee_adjustment_percent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeePolicyConfig {
+	/// The proportional fee, in parts-per-million of the forwarded amount, charged for channels
+	/// whose liquidity is entirely on our side.
+	///
+	/// See [`ChannelConfig::forwarding_fee_proportional_millionths`].
+	pub min_forwarding_fee_proportional_millionths: u32,
+	/// The proportional fee, in parts-per-million of the forwarded amount, charged for channels
+	/// whose liquidity is entirely on our counterparty's side.
+	///
+	/// See [`ChannelConfig::forwarding_fee_proportional_millionths`].
+	pub max_forwarding_fee_proportional_millionths: u32,
+	/// The base fee, in milli-satoshis, charged for channels whose liquidity is entirely on our
+	/// side.
+	pub min_forwarding_fee_base_msat: u32,
+	/// The base fee, in milli-satoshis, charged for channels whose liquidity is entirely on our
+	/// counterparty's side.
+	pub max_forwarding_fee_base_msat: u32,
+	/// The time window, in seconds, for which the forwarding volume of a channel is considered.
+	pub forwarding_volume_window_secs: u64,
+	/// The volume, in percent of the channel's capacity, forwarded out of a channel within the
+	/// [`forwarding_volume_window_secs`] at which we consider it to be in high demand.
+	///
+	/// [`forwarding_volume_window_secs`]: Self::forwarding_volume_window_secs
+	pub high_forwarding_volume_percent: u8,
+	/// The percentage by which we raise the fees of channels in high demand and lower the fees of
+	/// channels without any outbound forwards.
+	pub volume_fee_adjustment_percent: u8,
+	/// The time in-between fee updates, in seconds.
+	///
+	/// **Note:** A minimum interval of 60 seconds is enforced.
+	pub update_interval_secs: u64,
+}
+
+impl Default for FeePolicyConfig {
+	fn default() -> Self {
+		Self {
+			min_forwarding_fee_proportional_millionths:
+				DEFAULT_FEE_POLICY_MIN_FEE_PROPORTIONAL_MILLIONTHS,
+			max_forwarding_fee_proportional_millionths:
+				DEFAULT_FEE_POLICY_MAX_FEE_PROPORTIONAL_MILLIONTHS,
+			min_forwarding_fee_base_msat: DEFAULT_FEE_POLICY_MIN_FEE_BASE_MSAT,
+			max_forwarding_fee_base_msat: DEFAULT_FEE_POLICY_MAX_FEE_BASE_MSAT,
+			forwarding_volume_window_secs: DEFAULT_FEE_POLICY_VOLUME_WINDOW_SECS,
+			high_forwarding_volume_percent: DEFAULT_FEE_POLICY_HIGH_VOLUME_PERCENT,
+			volume_fee_adjustment_percent: DEFAULT_FEE_POLICY_VOLUME_ADJUSTMENT_PERCENT,
+			update_interval_secs: DEFAULT_FEE_POLICY_UPDATE_INTERVAL_SECS,
+		}
+	}
+}
+
+impl ChannelAcceptancePolicy {
+	pub(crate) fn check_inbound_channel(
+		&self, counterparty_node_id: &PublicKey, funding_sats: u64, is_anchor_channel: bool,
+		num_pending_channels: usize,
+	) -> Result<(), ChannelAcceptanceError> {
+		if self.denied_peers.contains(counterparty_node_id) {
+			return Err(ChannelAcceptanceError::PeerDenied);
+		}
+
+		if let Some(allowed_peers) = &self.allowed_peers {
+			if !allowed_peers.contains(counterparty_node_id) {
+				return Err(ChannelAcceptanceError::PeerNotAllowed);
+			}
+		}
+
+		if let Some(min_funding_sats) = self.min_funding_sats {
+			if funding_sats < min_funding_sats {
+				return Err(ChannelAcceptanceError::FundingBelowMinimum { min_funding_sats });
+			}
+		}
+
+		if let Some(max_funding_sats) = self.max_funding_sats {
+			if funding_sats > max_funding_sats {
+				return Err(ChannelAcceptanceError::FundingAboveMaximum { max_funding_sats });
+			}
+		}
+
+		if self.require_anchor_channels && !is_anchor_channel {
+			return Err(ChannelAcceptanceError::AnchorsRequired);
+		}
+
+		if let Some(max_pending_channels) = self.max_pending_channels_per_peer {
+			if num_pending_channels >= max_pending_channels as usize {
+				return Err(ChannelAcceptanceError::TooManyPendingChannels {
+					max_pending_channels,
+				});
+			}
+		}
+
+		Ok(())
+	}
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum ChannelAcceptanceError {
+	PeerDenied,
+	PeerNotAllowed,
+	FundingBelowMinimum { min_funding_sats: u64 },
+	FundingAboveMaximum { max_funding_sats: u64 },
+	AnchorsRequired,
+	TooManyPendingChannels { max_pending_channels: u32 },
+}
+
+impl fmt::Display for ChannelAcceptanceError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			ChannelAcceptanceError::PeerDenied => write!(f, "Peer is denied"),
+			ChannelAcceptanceError::PeerNotAllowed => write!(f, "Peer is not allowed"),
+			ChannelAcceptanceError::FundingBelowMinimum { min_funding_sats } => {
+				write!(f, "Channel value is below the minimum of {}sats", min_funding_sats)
+			},
+			ChannelAcceptanceError::FundingAboveMaximum { max_funding_sats } => {
+				write!(f, "Channel value is above the maximum of {}sats", max_funding_sats)
+			},
+			ChannelAcceptanceError::AnchorsRequired => {
+				write!(f, "Channel type is not an Anchor channel")
+			},
+			ChannelAcceptanceError::TooManyPendingChannels { max_pending_channels } => {
+				write!(f, "Peer already has {} pending channels", max_pending_channels)
+			},
+		}
+	}
+}
+
 /// Returns a [`Config`] object populated with default values.
 ///
 /// See the documentation of [`Config`] for more information on the used defaults.
@@ -531,8 +918,11 @@ mod tests {
 
 	use super::may_announce_channel;
 	use super::AnnounceError;
+	use super::ChannelAcceptanceError;
+	use super::ChannelAcceptancePolicy;
 	use super::Config;
 	use super::NodeAlias;
+	use super::PublicKey;
 	use super::SocketAddress;
 
 	#[test]
@@ -580,4 +970,60 @@ mod tests {
 		}
 		assert!(may_announce_channel(&node_config).is_ok());
 	}
+
+	#[test]
+	fn channel_acceptance_policy() {
+		let peer_a = PublicKey::from_str(
+			"02eec7245d6b7d2ccb30380bfbe2a3648cd7a942653f5aa340edcea1f283686619",
+		)
+		.unwrap();
+		let peer_b = PublicKey::from_str(
+			"0324653eac434488002cc06bbfb7f10fe18991e35f9fe4302dbea6d2353dc0ab1c",
+		)
+		.unwrap();
+
+		// The default policy accepts everything.
+		let mut policy = ChannelAcceptancePolicy::default();
+		assert!(policy.check_inbound_channel(&peer_a, 1, false, 100).is_ok());
+
+		policy.min_funding_sats = Some(20_000);
+		policy.max_funding_sats = Some(1_000_000);
+		assert_eq!(
+			policy.check_inbound_channel(&peer_a, 19_999, false, 0),
+			Err(ChannelAcceptanceError::FundingBelowMinimum { min_funding_sats: 20_000 })
+		);
+		assert_eq!(
+			policy.check_inbound_channel(&peer_a, 1_000_001, false, 0),
+			Err(ChannelAcceptanceError::FundingAboveMaximum { max_funding_sats: 1_000_000 })
+		);
+		assert!(policy.check_inbound_channel(&peer_a, 1_000_000, false, 0).is_ok());
+
+		policy.require_anchor_channels = true;
+		assert_eq!(
+			policy.check_inbound_channel(&peer_a, 100_000, false, 0),
+			Err(ChannelAcceptanceError::AnchorsRequired)
+		);
+		assert!(policy.check_inbound_channel(&peer_a, 100_000, true, 0).is_ok());
+
+		policy.max_pending_channels_per_peer = Some(2);
+		assert!(policy.check_inbound_channel(&peer_a, 100_000, true, 1).is_ok());
+		assert_eq!(
+			policy.check_inbound_channel(&peer_a, 100_000, true, 2),
+			Err(ChannelAcceptanceError::TooManyPendingChannels { max_pending_channels: 2 })
+		);
+
+		policy.allowed_peers = Some(vec![peer_a]);
+		assert_eq!(
+			policy.check_inbound_channel(&peer_b, 100_000, true, 0),
+			Err(ChannelAcceptanceError::PeerNotAllowed)
+		);
+		assert!(policy.check_inbound_channel(&peer_a, 100_000, true, 0).is_ok());
+
+		// The deny list takes precedence over the allow list.
+		policy.denied_peers = vec![peer_a];
+		assert_eq!(
+			policy.check_inbound_channel(&peer_a, 100_000, true, 0),
+			Err(ChannelAcceptanceError::PeerDenied)
+		);
+	}
 }
@@ -5,6 +5,7 @@
 // http://opensource.org/licenses/MIT>, at your option. You may not use this file except in
 // accordance with one or both of these licenses.
 
+use crate::config::{Socks5ProxyConfig, SOCKS5_PROXY_CONNECTION_TIMEOUT};
 use crate::logger::{log_error, log_info, LdkLogger};
 use crate::types::PeerManager;
 use crate::Error;
@@ -14,18 +15,22 @@ use lightning::ln::msgs::SocketAddress;
 use bitcoin::secp256k1::PublicKey;
 
 use std::collections::hash_map::{self, HashMap};
+use std::future::Future;
 use std::net::ToSocketAddrs;
 use std::ops::Deref;
+use std::pin::Pin;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
+type PendingConnectionSenders = Vec<tokio::sync::oneshot::Sender<Result<(), Error>>>;
+
 pub(crate) struct ConnectionManager<L: Deref + Clone + Sync + Send>
 where
 	L::Target: LdkLogger,
 {
-	pending_connections:
-		Mutex<HashMap<PublicKey, Vec<tokio::sync::oneshot::Sender<Result<(), Error>>>>>,
+	pending_connections: Mutex<HashMap<PublicKey, PendingConnectionSenders>>,
 	peer_manager: Arc<PeerManager>,
+	socks5_proxy: Option<Socks5ProxyConfig>,
 	logger: L,
 }
 
@@ -33,9 +38,11 @@ impl<L: Deref + Clone + Sync + Send> ConnectionManager<L>
 where
 	L::Target: LdkLogger,
 {
-	pub(crate) fn new(peer_manager: Arc<PeerManager>, logger: L) -> Self {
+	pub(crate) fn new(
+		peer_manager: Arc<PeerManager>, socks5_proxy: Option<Socks5ProxyConfig>, logger: L,
+	) -> Self {
 		let pending_connections = Mutex::new(HashMap::new());
-		Self { pending_connections, peer_manager, logger }
+		Self { pending_connections, peer_manager, socks5_proxy, logger }
 	}
 
 	pub(crate) async fn connect_peer_if_necessary(
@@ -65,42 +72,72 @@ where
 
 		log_info!(self.logger, "Connecting to peer: {}@{}", node_id, addr);
 
-		let socket_addr = addr
-			.to_socket_addrs()
-			.map_err(|e| {
-				log_error!(self.logger, "Failed to resolve network address {}: {}", addr, e);
-				self.propagate_result_to_subscribers(&node_id, Err(Error::InvalidSocketAddress));
-				Error::InvalidSocketAddress
-			})?
-			.next()
-			.ok_or_else(|| {
-				log_error!(self.logger, "Failed to resolve network address {}", addr);
-				self.propagate_result_to_subscribers(&node_id, Err(Error::InvalidSocketAddress));
-				Error::InvalidSocketAddress
-			})?;
+		let connection_future = match self.socks5_proxy.as_ref() {
+			Some(proxy) if !proxy.onion_only || matches!(addr, SocketAddress::OnionV3 { .. }) => {
+				self.connect_via_proxy(node_id, &addr, &proxy.proxy_address).await
+			},
+			_ => {
+				if matches!(addr, SocketAddress::OnionV3 { .. }) {
+					log_error!(
+						self.logger,
+						"Failed to connect to {} as connecting to Tor onion services requires a SOCKS5 proxy to be configured",
+						addr
+					);
+					self.propagate_result_to_subscribers(
+						&node_id,
+						Err(Error::InvalidSocketAddress),
+					);
+					return Err(Error::InvalidSocketAddress);
+				}
 
-		let connection_future = lightning_net_tokio::connect_outbound(
-			Arc::clone(&self.peer_manager),
-			node_id,
-			socket_addr,
-		);
-
-		let res = match connection_future.await {
-			Some(connection_closed_future) => {
-				let mut connection_closed_future = Box::pin(connection_closed_future);
-				loop {
-					tokio::select! {
-						_ = &mut connection_closed_future => {
-							log_info!(self.logger, "Peer connection closed: {}@{}", node_id, addr);
-							break Err(Error::ConnectionFailed);
-						},
-						_ = tokio::time::sleep(Duration::from_millis(10)) => {},
-					};
-
-					match self.peer_manager.peer_by_node_id(&node_id) {
-						Some(_) => break Ok(()),
-						None => continue,
-					}
+				let socket_addr = addr
+					.to_socket_addrs()
+					.map_err(|e| {
+						log_error!(
+							self.logger,
+							"Failed to resolve network address {}: {}",
+							addr,
+							e
+						);
+						self.propagate_result_to_subscribers(
+							&node_id,
+							Err(Error::InvalidSocketAddress),
+						);
+						Error::InvalidSocketAddress
+					})?
+					.next()
+					.ok_or_else(|| {
+						log_error!(self.logger, "Failed to resolve network address {}", addr);
+						self.propagate_result_to_subscribers(
+							&node_id,
+							Err(Error::InvalidSocketAddress),
+						);
+						Error::InvalidSocketAddress
+					})?;
+
+				lightning_net_tokio::connect_outbound(
+					Arc::clone(&self.peer_manager),
+					node_id,
+					socket_addr,
+				)
+				.await
+				.map(|fut| Box::pin(fut) as Pin<Box<dyn Future<Output = ()> + Send>>)
+			},
+		};
+
+		let res = match connection_future {
+			Some(mut connection_closed_future) => loop {
+				tokio::select! {
+					_ = &mut connection_closed_future => {
+						log_info!(self.logger, "Peer connection closed: {}@{}", node_id, addr);
+						break Err(Error::ConnectionFailed);
+					},
+					_ = tokio::time::sleep(Duration::from_millis(10)) => {},
+				};
+
+				match self.peer_manager.peer_by_node_id(&node_id) {
+					Some(_) => break Ok(()),
+					None => continue,
 				}
 			},
 			None => {
@@ -114,6 +151,58 @@ where
 		res
 	}
 
+	async fn connect_via_proxy(
+		&self, node_id: PublicKey, addr: &SocketAddress, proxy_address: &SocketAddress,
+	) -> Option<Pin<Box<dyn Future<Output = ()> + Send>>> {
+		let proxy_addr = match proxy_address.to_socket_addrs().ok().and_then(|mut a| a.next()) {
+			Some(proxy_addr) => proxy_addr,
+			None => {
+				log_error!(self.logger, "Failed to resolve SOCKS5 proxy address {}", proxy_address);
+				return None;
+			},
+		};
+
+		// We leave resolving the target address to the proxy, which is required for onion
+		// services and avoids leaking DNS requests otherwise.
+		let target = addr.to_string();
+		match connect_socks5(proxy_addr, &target, SOCKS5_PROXY_CONNECTION_TIMEOUT).await {
+			Ok(stream) => {
+				let stream = match stream.into_std() {
+					Ok(stream) => stream,
+					Err(e) => {
+						log_error!(self.logger, "Failed to set up proxied connection: {}", e);
+						return None;
+					},
+				};
+				let fut = lightning_net_tokio::setup_outbound(
+					Arc::clone(&self.peer_manager),
+					node_id,
+					stream,
+				);
+				Some(Box::pin(fut))
+			},
+			Err(Socks5ConnectError::Proxy(e)) => {
+				log_error!(
+					self.logger,
+					"Failed to connect to {} via SOCKS5 proxy {}: {}",
+					addr,
+					proxy_address,
+					e
+				);
+				None
+			},
+			Err(Socks5ConnectError::Timeout) => {
+				log_error!(
+					self.logger,
+					"Timed out connecting to {} via SOCKS5 proxy {}",
+					addr,
+					proxy_address
+				);
+				None
+			},
+		}
+	}
+
 	fn register_or_subscribe_pending_connection(
 		&self, node_id: &PublicKey,
 	) -> Option<tokio::sync::oneshot::Receiver<Result<(), Error>>> {
@@ -152,3 +241,146 @@ where
 		}
 	}
 }
+
+#[derive(Debug)]
+enum Socks5ConnectError {
+	Proxy(tokio_socks::Error),
+	Timeout,
+}
+
+// Connects to `target` via the SOCKS5 proxy at `proxy_addr`, giving up after `timeout`.
+async fn connect_socks5(
+	proxy_addr: std::net::SocketAddr, target: &str, timeout: Duration,
+) -> Result<tokio::net::TcpStream, Socks5ConnectError> {
+	let connect_fut = tokio_socks::tcp::Socks5Stream::connect(proxy_addr, target);
+	match tokio::time::timeout(timeout, connect_fut).await {
+		Ok(Ok(stream)) => Ok(stream.into_inner()),
+		Ok(Err(e)) => Err(Socks5ConnectError::Proxy(e)),
+		Err(_) => Err(Socks5ConnectError::Timeout),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use std::str::FromStr;
+
+	use tokio::io::{AsyncReadExt, AsyncWriteExt};
+	use tokio::net::TcpListener;
+
+	#[derive(Clone, Copy)]
+	enum StubBehavior {
+		Accept,
+		Refuse,
+		Stall,
+	}
+
+	// Spawns a minimal SOCKS5 proxy, returning its address and a receiver for the (address type,
+	// address, port) requested by the first client. Accepted connections are echoed back.
+	async fn spawn_socks5_stub(
+		behavior: StubBehavior,
+	) -> (std::net::SocketAddr, tokio::sync::oneshot::Receiver<(u8, Vec<u8>, u16)>) {
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let proxy_addr = listener.local_addr().unwrap();
+		let (target_sender, target_receiver) = tokio::sync::oneshot::channel();
+		tokio::spawn(async move {
+			let (mut stream, _) = listener.accept().await.unwrap();
+
+			// Method negotiation: we only offer "no authentication required".
+			let mut greeting = [0u8; 2];
+			stream.read_exact(&mut greeting).await.unwrap();
+			assert_eq!(greeting[0], 0x05);
+			let mut methods = vec![0u8; greeting[1] as usize];
+			stream.read_exact(&mut methods).await.unwrap();
+			assert!(methods.contains(&0x00));
+			stream.write_all(&[0x05, 0x00]).await.unwrap();
+
+			// The CONNECT request.
+			let mut request = [0u8; 4];
+			stream.read_exact(&mut request).await.unwrap();
+			assert_eq!(request[..3], [0x05, 0x01, 0x00]);
+			let address_type = request[3];
+			let address_len = match address_type {
+				0x01 => 4,
+				0x03 => stream.read_u8().await.unwrap() as usize,
+				0x04 => 16,
+				_ => panic!("Unexpected address type"),
+			};
+			let mut address = vec![0u8; address_len];
+			stream.read_exact(&mut address).await.unwrap();
+			let port = stream.read_u16().await.unwrap();
+			target_sender.send((address_type, address, port)).unwrap();
+
+			let reply_code = match behavior {
+				StubBehavior::Accept => 0x00,
+				StubBehavior::Refuse => 0x05,
+				StubBehavior::Stall => {
+					tokio::time::sleep(Duration::from_secs(60)).await;
+					return;
+				},
+			};
+			stream.write_all(&[0x05, reply_code, 0x00, 0x01, 0, 0, 0, 0, 0, 0]).await.unwrap();
+
+			let mut buf = [0u8; 64];
+			while let Ok(n) = stream.read(&mut buf).await {
+				if n == 0 || stream.write_all(&buf[..n]).await.is_err() {
+					break;
+				}
+			}
+		});
+		(proxy_addr, target_receiver)
+	}
+
+	#[tokio::test]
+	async fn socks5_connect_tunnels_to_target() {
+		let (proxy_addr, target_receiver) = spawn_socks5_stub(StubBehavior::Accept).await;
+
+		let addr = SocketAddress::from_str("10.0.0.1:9735").unwrap();
+		let mut stream =
+			connect_socks5(proxy_addr, &addr.to_string(), Duration::from_secs(5)).await.unwrap();
+		assert_eq!(target_receiver.await.unwrap(), (0x01, vec![10, 0, 0, 1], 9735));
+
+		stream.write_all(b"ping").await.unwrap();
+		let mut buf = [0u8; 4];
+		stream.read_exact(&mut buf).await.unwrap();
+		assert_eq!(&buf, b"ping");
+	}
+
+	#[tokio::test]
+	async fn socks5_connect_leaves_onion_resolution_to_proxy() {
+		let (proxy_addr, target_receiver) = spawn_socks5_stub(StubBehavior::Accept).await;
+
+		let onion = "pg6mmjiyjmcrsslvykfwnntlaru7p5svn6y2ymmju6nubxndf4pscryd.onion";
+		let addr = SocketAddress::from_str(&format!("{}:9735", onion)).unwrap();
+		assert!(matches!(addr, SocketAddress::OnionV3 { .. }));
+		connect_socks5(proxy_addr, &addr.to_string(), Duration::from_secs(5)).await.unwrap();
+
+		let (address_type, address, port) = target_receiver.await.unwrap();
+		assert_eq!(address_type, 0x03);
+		// Onion addresses are case-insensitive, while we render them in upper case.
+		assert_eq!(String::from_utf8(address).unwrap().to_lowercase(), onion);
+		assert_eq!(port, 9735);
+	}
+
+	#[tokio::test]
+	async fn socks5_connect_reports_proxy_errors() {
+		let (proxy_addr, _target_receiver) = spawn_socks5_stub(StubBehavior::Refuse).await;
+		let res = connect_socks5(proxy_addr, "10.0.0.1:9735", Duration::from_secs(5)).await;
+		assert!(matches!(res, Err(Socks5ConnectError::Proxy(_))));
+
+		// Nothing is listening on the proxy address anymore.
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let unused_addr = listener.local_addr().unwrap();
+		drop(listener);
+		let res = connect_socks5(unused_addr, "10.0.0.1:9735", Duration::from_secs(5)).await;
+		assert!(matches!(res, Err(Socks5ConnectError::Proxy(_))));
+	}
+
+	#[tokio::test]
+	async fn socks5_connect_times_out() {
+		let (proxy_addr, _target_receiver) = spawn_socks5_stub(StubBehavior::Stall).await;
+		let res = connect_socks5(proxy_addr, "10.0.0.1:9735", Duration::from_millis(100)).await;
+		assert!(matches!(res, Err(Socks5ConnectError::Timeout)));
+	}
+}
@@ -11,8 +11,7 @@ use crate::Error;
 
 use lightning::util::ser::{Readable, Writeable};
 
-use std::collections::hash_map;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::ops::Deref;
 use std::sync::{Arc, Mutex};
 
@@ -25,7 +24,7 @@ pub(crate) trait StorableObject: Clone + Readable + Writeable {
 	fn to_update(&self) -> Self::Update;
 }
 
-pub(crate) trait StorableObjectId: std::hash::Hash + PartialEq + Eq {
+pub(crate) trait StorableObjectId: Clone + std::hash::Hash + PartialEq + Eq {
 	fn encode_to_hex_str(&self) -> String;
 }
 
@@ -45,6 +44,10 @@ where
 	L::Target: LdkLogger,
 {
 	objects: Mutex<HashMap<SO::Id, SO>>,
+	// If set, only up to this many of the most recently used objects are held in memory, while any
+	// others are read from the store on demand.
+	max_cached_objects: Option<usize>,
+	recently_used: Mutex<VecDeque<SO::Id>>,
 	primary_namespace: String,
 	secondary_namespace: String,
 	kv_store: Arc<DynStore>,
@@ -61,14 +64,37 @@ where
 	) -> Self {
 		let objects =
 			Mutex::new(HashMap::from_iter(objects.into_iter().map(|obj| (obj.id(), obj))));
-		Self { objects, primary_namespace, secondary_namespace, kv_store, logger }
+		let max_cached_objects = None;
+		let recently_used = Mutex::new(VecDeque::new());
+		Self {
+			objects,
+			max_cached_objects,
+			recently_used,
+			primary_namespace,
+			secondary_namespace,
+			kv_store,
+			logger,
+		}
+	}
+
+	/// Creates a store that doesn't hold all objects in memory, but only caches up to
+	/// `max_cached_objects` of the most recently used ones.
+	pub(crate) fn with_cache_limit(
+		max_cached_objects: usize, primary_namespace: String, secondary_namespace: String,
+		kv_store: Arc<DynStore>, logger: L,
+	) -> Self {
+		let mut store =
+			Self::new(Vec::new(), primary_namespace, secondary_namespace, kv_store, logger);
+		store.max_cached_objects = Some(max_cached_objects);
+		store
 	}
 
 	pub(crate) fn insert(&self, object: SO) -> Result<bool, Error> {
 		let mut locked_objects = self.objects.lock().unwrap();
 
+		let updated = self.load(&mut locked_objects, &object.id())?.is_some();
 		self.persist(&object)?;
-		let updated = locked_objects.insert(object.id(), object).is_some();
+		self.cache(&mut locked_objects, object);
 		Ok(updated)
 	}
 
@@ -76,17 +102,18 @@ where
 		let mut locked_objects = self.objects.lock().unwrap();
 
 		let updated;
-		match locked_objects.entry(object.id()) {
-			hash_map::Entry::Occupied(mut e) => {
+		match self.load(&mut locked_objects, &object.id())? {
+			Some(mut existing) => {
 				let update = object.to_update();
-				updated = e.get_mut().update(&update);
+				updated = existing.update(&update);
 				if updated {
-					self.persist(&e.get())?;
+					self.persist(&existing)?;
+					self.cache(&mut locked_objects, existing);
 				}
 			},
-			hash_map::Entry::Vacant(e) => {
-				e.insert(object.clone());
+			None => {
 				self.persist(&object)?;
+				self.cache(&mut locked_objects, object);
 				updated = true;
 			},
 		}
@@ -95,8 +122,11 @@ where
 	}
 
 	pub(crate) fn remove(&self, id: &SO::Id) -> Result<(), Error> {
-		let removed = self.objects.lock().unwrap().remove(id).is_some();
+		let mut locked_objects = self.objects.lock().unwrap();
+
+		let removed = self.load(&mut locked_objects, id)?.is_some();
 		if removed {
+			locked_objects.remove(id);
 			let store_key = id.encode_to_hex_str();
 			self.kv_store
 				.remove(&self.primary_namespace, &self.secondary_namespace, &store_key, false)
@@ -116,16 +146,18 @@ where
 	}
 
 	pub(crate) fn get(&self, id: &SO::Id) -> Option<SO> {
-		self.objects.lock().unwrap().get(id).cloned()
+		let mut locked_objects = self.objects.lock().unwrap();
+		self.load(&mut locked_objects, id).ok().flatten()
 	}
 
 	pub(crate) fn update(&self, update: &SO::Update) -> Result<DataStoreUpdateResult, Error> {
 		let mut locked_objects = self.objects.lock().unwrap();
 
-		if let Some(object) = locked_objects.get_mut(&update.id()) {
+		if let Some(mut object) = self.load(&mut locked_objects, &update.id())? {
 			let updated = object.update(update);
 			if updated {
 				self.persist(&object)?;
+				self.cache(&mut locked_objects, object);
 				Ok(DataStoreUpdateResult::Updated)
 			} else {
 				Ok(DataStoreUpdateResult::Unchanged)
@@ -135,8 +167,127 @@ where
 		}
 	}
 
-	pub(crate) fn list_filter<F: FnMut(&&SO) -> bool>(&self, f: F) -> Vec<SO> {
-		self.objects.lock().unwrap().values().filter(f).cloned().collect::<Vec<SO>>()
+	/// Returns all objects matching the given filter.
+	///
+	/// Note that if the store's cache is limited, this will read all objects that aren't cached
+	/// from the underlying store.
+	pub(crate) fn list_filter<F: FnMut(&&SO) -> bool>(&self, mut f: F) -> Vec<SO> {
+		let locked_objects = self.objects.lock().unwrap();
+		if self.max_cached_objects.is_none() {
+			return locked_objects.values().filter(f).cloned().collect::<Vec<SO>>();
+		}
+
+		let keys = match self.kv_store.list(&self.primary_namespace, &self.secondary_namespace) {
+			Ok(keys) => keys,
+			Err(e) => {
+				log_error!(
+					self.logger,
+					"Listing keys in namespace {}/{} failed due to: {}",
+					&self.primary_namespace,
+					&self.secondary_namespace,
+					e
+				);
+				return Vec::new();
+			},
+		};
+		let mut res = locked_objects.values().filter(&mut f).cloned().collect::<Vec<SO>>();
+		let cached_keys =
+			locked_objects.keys().map(|id| id.encode_to_hex_str()).collect::<HashSet<_>>();
+		for key in keys {
+			if cached_keys.contains(&key) {
+				continue;
+			}
+			// We don't cache objects here, as we'd otherwise just evict all recently used ones.
+			match self.read(&key) {
+				Ok(Some(object)) => {
+					if f(&&object) {
+						res.push(object);
+					}
+				},
+				Ok(None) => {},
+				Err(_) => {},
+			}
+		}
+		res
+	}
+
+	// Returns the object with the given id, reading it from the underlying store if it's not
+	// cached.
+	fn load(
+		&self, locked_objects: &mut HashMap<SO::Id, SO>, id: &SO::Id,
+	) -> Result<Option<SO>, Error> {
+		if let Some(object) = locked_objects.get(id) {
+			let object = object.clone();
+			self.touch(id);
+			return Ok(Some(object));
+		}
+
+		if self.max_cached_objects.is_none() {
+			return Ok(None);
+		}
+
+		let object = self.read(&id.encode_to_hex_str())?;
+		if let Some(object) = object.as_ref() {
+			self.cache(locked_objects, object.clone());
+		}
+		Ok(object)
+	}
+
+	fn read(&self, store_key: &str) -> Result<Option<SO>, Error> {
+		let data =
+			match self.kv_store.read(&self.primary_namespace, &self.secondary_namespace, store_key)
+			{
+				Ok(data) => data,
+				Err(e) if e.kind() == lightning::io::ErrorKind::NotFound => return Ok(None),
+				Err(e) => {
+					log_error!(
+						self.logger,
+						"Read for key {}/{}/{} failed due to: {}",
+						&self.primary_namespace,
+						&self.secondary_namespace,
+						store_key,
+						e
+					);
+					return Err(Error::PersistenceFailed);
+				},
+			};
+		SO::read(&mut lightning::io::Cursor::new(data)).map(Some).map_err(|e| {
+			log_error!(
+				self.logger,
+				"Failed to deserialize object data for key {}/{}/{}: {}",
+				&self.primary_namespace,
+				&self.secondary_namespace,
+				store_key,
+				e
+			);
+			Error::PersistenceFailed
+		})
+	}
+
+	fn cache(&self, locked_objects: &mut HashMap<SO::Id, SO>, object: SO) {
+		let id = object.id();
+		self.touch(&id);
+		locked_objects.insert(id, object);
+
+		if let Some(max_cached_objects) = self.max_cached_objects {
+			let mut recently_used = self.recently_used.lock().unwrap();
+			while locked_objects.len() > max_cached_objects {
+				match recently_used.pop_front() {
+					Some(evicted_id) => {
+						locked_objects.remove(&evicted_id);
+					},
+					None => break,
+				}
+			}
+		}
+	}
+
+	fn touch(&self, id: &SO::Id) {
+		if self.max_cached_objects.is_some() {
+			let mut recently_used = self.recently_used.lock().unwrap();
+			recently_used.retain(|i| i != id);
+			recently_used.push_back(id.clone());
+		}
 	}
 
 	fn persist(&self, object: &SO) -> Result<(), Error> {
@@ -284,4 +435,45 @@ mod tests {
 		new_iou_object.data[0] += 1;
 		assert_eq!(Ok(true), data_store.insert_or_update(new_iou_object));
 	}
+
+	#[test]
+	fn objects_are_loaded_on_demand_if_cache_is_limited() {
+		let store: Arc<DynStore> = Arc::new(TestStore::new(false));
+		let logger = Arc::new(TestLogger::new());
+		let primary_namespace = "datastore_test_primary".to_string();
+		let secondary_namespace = "datastore_test_secondary".to_string();
+		let data_store: DataStore<TestObject, Arc<TestLogger>> = DataStore::with_cache_limit(
+			2,
+			primary_namespace.clone(),
+			secondary_namespace.clone(),
+			Arc::clone(&store),
+			logger,
+		);
+
+		let objects = (0..5u8)
+			.map(|i| TestObject { id: TestObjectId { id: [i; 4] }, data: [i; 3] })
+			.collect::<Vec<_>>();
+		for object in &objects {
+			assert_eq!(Ok(false), data_store.insert(*object));
+		}
+
+		// Check we only keep the most recently used objects in memory.
+		assert_eq!(2, data_store.objects.lock().unwrap().len());
+
+		// Check evicted objects are still retrievable and updatable.
+		assert_eq!(Some(objects[0]), data_store.get(&objects[0].id));
+		let update = TestObjectUpdate { id: objects[1].id, data: [42u8; 3] };
+		assert_eq!(Ok(DataStoreUpdateResult::Updated), data_store.update(&update));
+		assert_eq!(data_store.get(&objects[1].id).unwrap().data, [42u8; 3]);
+		assert_eq!(Ok(true), data_store.insert(objects[2]));
+		assert_eq!(2, data_store.objects.lock().unwrap().len());
+
+		// Check listing includes objects that aren't cached.
+		assert_eq!(5, data_store.list_filter(|_| true).len());
+
+		// Check removing evicted objects also removes them from the underlying store.
+		data_store.remove(&objects[3].id).unwrap();
+		assert!(data_store.get(&objects[3].id).is_none());
+		assert_eq!(4, data_store.list_filter(|_| true).len());
+	}
 }
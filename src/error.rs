@@ -120,6 +120,32 @@ pub enum Error {
 	LiquiditySourceUnavailable,
 	/// The given operation failed due to the LSP's required opening fee being too high.
 	LiquidityFeeTooHigh,
+	/// The given transaction could not be found in the on-chain wallet.
+	TransactionNotFound,
+	/// The given transaction is already confirmed and can't be modified anymore.
+	TransactionAlreadyConfirmed,
+	/// The given UTXO is invalid, e.g., it is unknown, already spent, or locked.
+	InvalidUtxo,
+	/// The given PSBT is invalid.
+	InvalidPsbt,
+	/// The given human-readable name is invalid.
+	InvalidHumanReadableName,
+	/// Resolving a human-readable name failed.
+	HrnResolutionFailed,
+	/// The DNSSEC proof for a human-readable name could not be validated.
+	HrnProofValidationFailed,
+	/// No unique payment instructions were found for a human-readable name.
+	HrnRecordNotFound,
+	/// The given LNURL is invalid.
+	InvalidLnurl,
+	/// A request to an LNURL service failed.
+	LnurlRequestFailed,
+	/// The given store is in use by a running node.
+	StoreInUse,
+	/// Migrating data between stores failed.
+	StoreMigrationFailed,
+	/// No route for the given payment could be found.
+	RouteNotFound,
 }
 
 impl fmt::Display for Error {
@@ -193,6 +219,29 @@ impl fmt::Display for Error {
 			Self::LiquidityFeeTooHigh => {
 				write!(f, "The given operation failed due to the LSP's required opening fee being too high.")
 			},
+			Self::TransactionNotFound => {
+				write!(f, "The given transaction could not be found in the on-chain wallet.")
+			},
+			Self::TransactionAlreadyConfirmed => {
+				write!(f, "The given transaction is already confirmed.")
+			},
+			Self::InvalidUtxo => write!(f, "The given UTXO is invalid."),
+			Self::InvalidPsbt => write!(f, "The given PSBT is invalid."),
+			Self::InvalidHumanReadableName => {
+				write!(f, "The given human-readable name is invalid.")
+			},
+			Self::HrnResolutionFailed => write!(f, "Failed to resolve the human-readable name."),
+			Self::HrnProofValidationFailed => {
+				write!(f, "The DNSSEC proof for the human-readable name could not be validated.")
+			},
+			Self::HrnRecordNotFound => {
+				write!(f, "No unique payment instructions found for the human-readable name.")
+			},
+			Self::InvalidLnurl => write!(f, "The given LNURL is invalid."),
+			Self::LnurlRequestFailed => write!(f, "A request to the LNURL service failed."),
+			Self::StoreInUse => write!(f, "The given store is in use by a running node."),
+			Self::StoreMigrationFailed => write!(f, "Migrating data between stores failed."),
+			Self::RouteNotFound => write!(f, "Failed to find a route for the given payment."),
 		}
 	}
 }
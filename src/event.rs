@@ -5,7 +5,10 @@
 // http://opensource.org/licenses/MIT>, at your option. You may not use this file except in
 // accordance with one or both of these licenses.
 
-use crate::types::{CustomTlvRecord, DynStore, PaymentStore, Sweeper, Wallet};
+use crate::types::{
+	CustomTlvRecord, DynStore, ForwardingStore, FundingPsbt, PaymentPathHop, PaymentStore, Sweeper,
+	Wallet,
+};
 
 use crate::{
 	hex_utils, BumpTransactionEventHandler, ChannelManager, Error, Graph, PeerInfo, PeerStore,
@@ -16,42 +19,47 @@ use crate::config::{may_announce_channel, Config};
 use crate::connection::ConnectionManager;
 use crate::data_store::DataStoreUpdateResult;
 use crate::fee_estimator::ConfirmationTarget;
-use crate::liquidity::LiquiditySource;
+use crate::liquidity::{LSPS1OrderId, LSPS1OrderState, LiquiditySource};
 use crate::logger::Logger;
 
 use crate::payment::store::{
 	PaymentDetails, PaymentDetailsUpdate, PaymentDirection, PaymentKind, PaymentStatus,
 };
+use crate::payment::{ForwardedPaymentDetails, ForwardedPaymentId};
 
 use crate::io::{
 	EVENT_QUEUE_PERSISTENCE_KEY, EVENT_QUEUE_PERSISTENCE_PRIMARY_NAMESPACE,
-	EVENT_QUEUE_PERSISTENCE_SECONDARY_NAMESPACE,
+	EVENT_QUEUE_PERSISTENCE_SECONDARY_NAMESPACE, PENDING_CHANNEL_FUNDINGS_PERSISTENCE_KEY,
+	PENDING_CHANNEL_FUNDINGS_PERSISTENCE_PRIMARY_NAMESPACE,
+	PENDING_CHANNEL_FUNDINGS_PERSISTENCE_SECONDARY_NAMESPACE,
 };
 use crate::logger::{log_debug, log_error, log_info, LdkLogger};
 
 use lightning::events::bump_transaction::BumpTransactionEvent;
-use lightning::events::{ClosureReason, PaymentPurpose, ReplayEvent};
+use lightning::events::{ClosureReason, InboundChannelFunds, PaymentPurpose, ReplayEvent};
 use lightning::events::{Event as LdkEvent, PaymentFailureReason};
-use lightning::impl_writeable_tlv_based_enum;
 use lightning::ln::channelmanager::PaymentId;
 use lightning::ln::types::ChannelId;
 use lightning::routing::gossip::NodeId;
 use lightning::util::errors::APIError;
 use lightning::util::ser::{Readable, ReadableArgs, Writeable, Writer};
+use lightning::{impl_writeable_tlv_based, impl_writeable_tlv_based_enum};
 
 use lightning_types::payment::{PaymentHash, PaymentPreimage};
 
 use lightning_liquidity::lsps2::utils::compute_opening_fee;
 
 use bitcoin::blockdata::locktime::absolute::LockTime;
+use bitcoin::psbt::Psbt;
 use bitcoin::secp256k1::PublicKey;
-use bitcoin::{Amount, OutPoint};
+use bitcoin::transaction::Version;
+use bitcoin::{Amount, OutPoint, ScriptBuf, Transaction, TxOut};
 
 use rand::{thread_rng, Rng};
 
 use core::future::Future;
 use core::task::{Poll, Waker};
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::ops::Deref;
 use std::sync::{Arc, Condvar, Mutex, RwLock};
 use std::time::Duration;
@@ -96,6 +104,92 @@ pub enum Event {
 		/// This will be `None` for events serialized by LDK Node v0.2.1 and prior.
 		reason: Option<PaymentFailureReason>,
 	},
+	/// A path of a sent payment was successful.
+	///
+	/// This will only be emitted if [`Config::emit_payment_path_events`] is set.
+	///
+	/// [`Config::emit_payment_path_events`]: crate::config::Config::emit_payment_path_events
+	PaymentPathSuccessful {
+		/// A local identifier used to track the payment.
+		payment_id: PaymentId,
+		/// The hash of the payment.
+		///
+		/// This will be `None` if the payment was sent by a node version not tracking it.
+		payment_hash: Option<PaymentHash>,
+		/// The hops of the path.
+		///
+		/// Note that this won't include the hops of a blinded path at the end of the path, if any.
+		hops: Vec<PaymentPathHop>,
+		/// The amount, in milli-satoshis, delivered to the recipient via the path.
+		amount_msat: u64,
+		/// The total fee, in milli-satoshis, paid to intermediate hops along the path.
+		fee_msat: u64,
+	},
+	/// A path of a sent payment has failed.
+	///
+	/// Note that the payment as a whole may still succeed via other paths or retries.
+	///
+	/// This will only be emitted if [`Config::emit_payment_path_events`] is set.
+	///
+	/// [`Config::emit_payment_path_events`]: crate::config::Config::emit_payment_path_events
+	PaymentPathFailed {
+		/// A local identifier used to track the payment.
+		///
+		/// This will be `None` if the payment was sent by a node version not tracking it.
+		payment_id: Option<PaymentId>,
+		/// The hash of the payment.
+		payment_hash: PaymentHash,
+		/// Whether the payment as a whole failed permanently, i.e., won't be retried.
+		payment_failed_permanently: bool,
+		/// The hops of the path.
+		///
+		/// Note that this won't include the hops of a blinded path at the end of the path, if any.
+		hops: Vec<PaymentPathHop>,
+		/// The short channel id of the channel responsible for the failure, if known.
+		short_channel_id: Option<u64>,
+		/// The amount, in milli-satoshis, that was to be delivered to the recipient via the path.
+		amount_msat: u64,
+		/// The total fee, in milli-satoshis, that was to be paid to intermediate hops along the path.
+		fee_msat: u64,
+	},
+	/// A probe sent via [`Bolt11Payment::send_probes`] or [`SpontaneousPayment::send_probes`]
+	/// reached its destination.
+	///
+	/// [`Bolt11Payment::send_probes`]: crate::payment::Bolt11Payment::send_probes
+	/// [`SpontaneousPayment::send_probes`]: crate::payment::SpontaneousPayment::send_probes
+	ProbeSucceeded {
+		/// A local identifier used to track the probe.
+		payment_id: PaymentId,
+		/// The hash generated for the probe.
+		payment_hash: PaymentHash,
+		/// The hops of the probed path.
+		hops: Vec<PaymentPathHop>,
+		/// The amount, in milli-satoshis, probed along the path.
+		amount_msat: u64,
+		/// The total fee, in milli-satoshis, that would be paid to intermediate hops along the path.
+		fee_msat: u64,
+	},
+	/// A probe sent via [`Bolt11Payment::send_probes`] or [`SpontaneousPayment::send_probes`]
+	/// failed before reaching its destination.
+	///
+	/// [`Bolt11Payment::send_probes`]: crate::payment::Bolt11Payment::send_probes
+	/// [`SpontaneousPayment::send_probes`]: crate::payment::SpontaneousPayment::send_probes
+	ProbeFailed {
+		/// A local identifier used to track the probe.
+		payment_id: PaymentId,
+		/// The hash generated for the probe.
+		payment_hash: PaymentHash,
+		/// The hops of the probed path.
+		hops: Vec<PaymentPathHop>,
+		/// The short channel id of the channel responsible for the failure, if known.
+		///
+		/// This indicates the channel lacks sufficient liquidity to forward the probed amount.
+		short_channel_id: Option<u64>,
+		/// The amount, in milli-satoshis, probed along the path.
+		amount_msat: u64,
+		/// The total fee, in milli-satoshis, that would be paid to intermediate hops along the path.
+		fee_msat: u64,
+	},
 	/// A payment has been received.
 	PaymentReceived {
 		/// A local identifier used to track the payment.
@@ -187,6 +281,25 @@ pub enum Event {
 		/// Custom TLV records attached to the payment
 		custom_records: Vec<CustomTlvRecord>,
 	},
+	/// A held payment is about to be failed back as its claim deadline is approaching.
+	///
+	/// The payment needs to be claimed via [`claim_for_hash`] or failed via [`fail_for_hash`]
+	/// before the `cancel_height` is reached, at which point it will be failed back
+	/// automatically. See [`Config::hold_invoice_cancel_margin_blocks`] for more information.
+	///
+	/// [`claim_for_hash`]: crate::payment::Bolt11Payment::claim_for_hash
+	/// [`fail_for_hash`]: crate::payment::Bolt11Payment::fail_for_hash
+	/// [`Config::hold_invoice_cancel_margin_blocks`]: crate::config::Config::hold_invoice_cancel_margin_blocks
+	PaymentHoldExpiring {
+		/// A local identifier used to track the payment.
+		payment_id: PaymentId,
+		/// The hash of the payment.
+		payment_hash: PaymentHash,
+		/// The block height at which this payment will no longer be eligible for claiming.
+		claim_deadline: u32,
+		/// The block height at which this payment will be failed back automatically.
+		cancel_height: u32,
+	},
 	/// A channel has been created and is pending confirmation on-chain.
 	ChannelPending {
 		/// The `channel_id` of the channel.
@@ -211,6 +324,54 @@ pub enum Event {
 		/// This will be `None` for events serialized by LDK Node v0.1.0 and prior.
 		counterparty_node_id: Option<PublicKey>,
 	},
+	/// The funding transaction of a channel we initiated needs to be provided externally.
+	///
+	/// This will only be emitted if [`Config::use_external_channel_funding`] is set. The given
+	/// `psbt` has no inputs and only contains the channel's funding output. It should be funded,
+	/// i.e., have inputs and optionally a change output added, and signed by the external wallet,
+	/// before being passed to [`Node::finalize_channel_funding`].
+	///
+	/// Note that all inputs of the funding transaction need to spend SegWit outputs. Also note that
+	/// the channel will be abandoned if the funding transaction isn't provided in a timely manner,
+	/// or if the node is restarted in the meantime.
+	///
+	/// [`Config::use_external_channel_funding`]: crate::config::Config::use_external_channel_funding
+	/// [`Node::finalize_channel_funding`]: crate::Node::finalize_channel_funding
+	FundingPsbtReady {
+		/// The `user_channel_id` of the channel.
+		user_channel_id: UserChannelId,
+		/// The `node_id` of the channel counterparty.
+		counterparty_node_id: PublicKey,
+		/// The value, in satoshis, of the channel's funding output.
+		channel_value_sats: u64,
+		/// The unsigned PSBT containing the channel's funding output.
+		psbt: FundingPsbt,
+	},
+	/// An inbound channel request is awaiting manual approval.
+	///
+	/// This will only be emitted if [`ChannelAcceptancePolicy::manual_acceptance`] is set, and the
+	/// request passed the configured [`ChannelAcceptancePolicy`]. The request needs to be accepted
+	/// via [`Node::accept_inbound_channel`] or rejected via [`Node::reject_inbound_channel`].
+	///
+	/// Note that the request will be dropped by the counterparty or LDK if it isn't handled in a
+	/// timely manner, or if the node is restarted in the meantime.
+	///
+	/// [`ChannelAcceptancePolicy::manual_acceptance`]: crate::config::ChannelAcceptancePolicy::manual_acceptance
+	/// [`ChannelAcceptancePolicy`]: crate::config::ChannelAcceptancePolicy
+	/// [`Node::accept_inbound_channel`]: crate::Node::accept_inbound_channel
+	/// [`Node::reject_inbound_channel`]: crate::Node::reject_inbound_channel
+	InboundChannelRequest {
+		/// The temporary `channel_id` of the requested channel.
+		temporary_channel_id: ChannelId,
+		/// The `node_id` of the channel counterparty.
+		counterparty_node_id: PublicKey,
+		/// The channel value, in satoshis, funded by the counterparty.
+		funding_sats: u64,
+		/// The amount, in milli-satoshis, the counterparty pushes to us on channel open.
+		push_msat: u64,
+		/// Whether the channel is to be announced to the gossip network.
+		is_announced: bool,
+	},
 	/// A channel has been closed.
 	ChannelClosed {
 		/// The `channel_id` of the channel.
@@ -224,6 +385,17 @@ pub enum Event {
 		/// This will be `None` for events serialized by LDK Node v0.2.1 and prior.
 		reason: Option<ClosureReason>,
 	},
+	/// The state of a channel order placed with our LSPS1 liquidity source changed.
+	///
+	/// Details of the order can be retrieved via [`LSPS1Liquidity::list_orders`].
+	///
+	/// [`LSPS1Liquidity::list_orders`]: crate::liquidity::LSPS1Liquidity::list_orders
+	LSPS1OrderUpdated {
+		/// The id of the channel order.
+		order_id: LSPS1OrderId,
+		/// The new state of the channel order.
+		state: LSPS1OrderState,
+	},
 }
 
 impl_writeable_tlv_based_enum!(Event,
@@ -280,6 +452,60 @@ impl_writeable_tlv_based_enum!(Event,
 		(10, skimmed_fee_msat, option),
 		(12, claim_from_onchain_tx, required),
 		(14, outbound_amount_forwarded_msat, option),
+	},
+	(8, FundingPsbtReady) => {
+		(0, user_channel_id, required),
+		(2, counterparty_node_id, required),
+		(4, channel_value_sats, required),
+		(6, psbt, required),
+	},
+	(9, PaymentHoldExpiring) => {
+		(0, payment_id, required),
+		(2, payment_hash, required),
+		(4, claim_deadline, required),
+		(6, cancel_height, required),
+	},
+	(10, InboundChannelRequest) => {
+		(0, temporary_channel_id, required),
+		(2, counterparty_node_id, required),
+		(4, funding_sats, required),
+		(6, push_msat, required),
+		(8, is_announced, required),
+	},
+	(11, PaymentPathSuccessful) => {
+		(0, payment_id, required),
+		(1, payment_hash, option),
+		(2, hops, required_vec),
+		(4, amount_msat, required),
+		(6, fee_msat, required),
+	},
+	(12, PaymentPathFailed) => {
+		(0, payment_hash, required),
+		(1, payment_id, option),
+		(2, payment_failed_permanently, required),
+		(4, hops, required_vec),
+		(5, short_channel_id, option),
+		(6, amount_msat, required),
+		(8, fee_msat, required),
+	},
+	(13, ProbeSucceeded) => {
+		(0, payment_id, required),
+		(2, payment_hash, required),
+		(4, hops, required_vec),
+		(6, amount_msat, required),
+		(8, fee_msat, required),
+	},
+	(14, ProbeFailed) => {
+		(0, payment_id, required),
+		(1, short_channel_id, option),
+		(2, payment_hash, required),
+		(4, hops, required_vec),
+		(6, amount_msat, required),
+		(8, fee_msat, required),
+	},
+	(15, LSPS1OrderUpdated) => {
+		(0, order_id, required),
+		(2, state, required),
 	}
 );
 
@@ -325,6 +551,11 @@ where
 		locked_queue.front().cloned()
 	}
 
+	#[cfg(feature = "metrics")]
+	pub(crate) fn pending_event_count(&self) -> usize {
+		self.queue.lock().unwrap().len()
+	}
+
 	pub(crate) async fn next_event_async(&self) -> Event {
 		EventFuture { event_queue: Arc::clone(&self.queue), waker: Arc::clone(&self.waker) }.await
 	}
@@ -437,6 +668,94 @@ impl Future for EventFuture {
 	}
 }
 
+/// A channel funding awaiting the externally signed funding transaction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct PendingChannelFunding {
+	pub user_channel_id: u128,
+	pub temporary_channel_id: ChannelId,
+	pub counterparty_node_id: PublicKey,
+	pub output_script: ScriptBuf,
+	pub channel_value_sats: u64,
+}
+
+impl_writeable_tlv_based!(PendingChannelFunding, {
+	(0, user_channel_id, required),
+	(2, temporary_channel_id, required),
+	(4, counterparty_node_id, required),
+	(6, output_script, required),
+	(8, channel_value_sats, required),
+});
+
+/// The persisted set of channel fundings awaiting the externally signed funding transaction.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct PersistedChannelFundings {
+	pub fundings: Vec<PendingChannelFunding>,
+}
+
+impl_writeable_tlv_based!(PersistedChannelFundings, {
+	(0, fundings, required_vec),
+});
+
+/// The channel fundings awaiting the externally signed funding transaction, keyed by their
+/// `user_channel_id`.
+///
+/// LDK doesn't persist channels before they are funded, i.e., they are gone after a restart. We
+/// persist the pending fundings nevertheless, so that we can let the user know about any
+/// abandoned channel on startup.
+pub(crate) struct PendingChannelFundings {
+	fundings: Mutex<HashMap<u128, PendingChannelFunding>>,
+	kv_store: Arc<DynStore>,
+	logger: Arc<Logger>,
+}
+
+impl PendingChannelFundings {
+	pub(crate) fn new(kv_store: Arc<DynStore>, logger: Arc<Logger>) -> Self {
+		Self { fundings: Mutex::new(HashMap::new()), kv_store, logger }
+	}
+
+	pub(crate) fn insert(&self, funding: PendingChannelFunding) -> Result<(), Error> {
+		let mut locked_fundings = self.fundings.lock().unwrap();
+		locked_fundings.insert(funding.user_channel_id, funding);
+		self.persist(&locked_fundings)
+	}
+
+	pub(crate) fn get(&self, user_channel_id: u128) -> Option<PendingChannelFunding> {
+		self.fundings.lock().unwrap().get(&user_channel_id).cloned()
+	}
+
+	pub(crate) fn remove(&self, user_channel_id: u128) -> Result<(), Error> {
+		let mut locked_fundings = self.fundings.lock().unwrap();
+		if locked_fundings.remove(&user_channel_id).is_some() {
+			self.persist(&locked_fundings)?;
+		}
+		Ok(())
+	}
+
+	fn persist(&self, locked_fundings: &HashMap<u128, PendingChannelFunding>) -> Result<(), Error> {
+		let data =
+			PersistedChannelFundings { fundings: locked_fundings.values().cloned().collect() }
+				.encode();
+		self.kv_store
+			.write(
+				PENDING_CHANNEL_FUNDINGS_PERSISTENCE_PRIMARY_NAMESPACE,
+				PENDING_CHANNEL_FUNDINGS_PERSISTENCE_SECONDARY_NAMESPACE,
+				PENDING_CHANNEL_FUNDINGS_PERSISTENCE_KEY,
+				&data,
+			)
+			.map_err(|e| {
+				log_error!(
+					self.logger,
+					"Write for key {}/{}/{} failed due to: {}",
+					PENDING_CHANNEL_FUNDINGS_PERSISTENCE_PRIMARY_NAMESPACE,
+					PENDING_CHANNEL_FUNDINGS_PERSISTENCE_SECONDARY_NAMESPACE,
+					PENDING_CHANNEL_FUNDINGS_PERSISTENCE_KEY,
+					e
+				);
+				Error::PersistenceFailed
+			})
+	}
+}
+
 pub(crate) struct EventHandler<L: Deref + Clone + Sync + Send + 'static>
 where
 	L::Target: LdkLogger,
@@ -450,7 +769,9 @@ where
 	network_graph: Arc<Graph>,
 	liquidity_source: Option<Arc<LiquiditySource<Arc<Logger>>>>,
 	payment_store: Arc<PaymentStore>,
+	forwarding_store: Arc<ForwardingStore>,
 	peer_store: Arc<PeerStore<L>>,
+	pending_channel_fundings: Arc<PendingChannelFundings>,
 	runtime: Arc<RwLock<Option<Arc<tokio::runtime::Runtime>>>>,
 	logger: L,
 	config: Arc<Config>,
@@ -466,7 +787,8 @@ where
 		channel_manager: Arc<ChannelManager>, connection_manager: Arc<ConnectionManager<L>>,
 		output_sweeper: Arc<Sweeper>, network_graph: Arc<Graph>,
 		liquidity_source: Option<Arc<LiquiditySource<Arc<Logger>>>>,
-		payment_store: Arc<PaymentStore>, peer_store: Arc<PeerStore<L>>,
+		payment_store: Arc<PaymentStore>, forwarding_store: Arc<ForwardingStore>,
+		peer_store: Arc<PeerStore<L>>, pending_channel_fundings: Arc<PendingChannelFundings>,
 		runtime: Arc<RwLock<Option<Arc<tokio::runtime::Runtime>>>>, logger: L, config: Arc<Config>,
 	) -> Self {
 		Self {
@@ -479,7 +801,9 @@ where
 			network_graph,
 			liquidity_source,
 			payment_store,
+			forwarding_store,
 			peer_store,
+			pending_channel_fundings,
 			logger,
 			runtime,
 			config,
@@ -493,16 +817,69 @@ where
 				counterparty_node_id,
 				channel_value_satoshis,
 				output_script,
-				..
+				user_channel_id,
 			} => {
 				// Construct the raw transaction with the output that is paid the amount of the
 				// channel.
-				let confirmation_target = ConfirmationTarget::ChannelFunding;
+				let confirmation_target = self
+					.wallet
+					.take_funding_confirmation_target(user_channel_id)
+					.unwrap_or(ConfirmationTarget::ChannelFunding);
 
 				// We set nLockTime to the current height to discourage fee sniping.
 				let cur_height = self.channel_manager.current_best_block().height;
 				let locktime = LockTime::from_height(cur_height).unwrap_or(LockTime::ZERO);
 
+				if self.config.use_external_channel_funding {
+					// Hand an unsigned PSBT with just the funding output to the user, who will
+					// fund and sign it externally before passing it to `finalize_channel_funding`.
+					let unsigned_tx = Transaction {
+						version: Version::TWO,
+						lock_time: locktime,
+						input: vec![],
+						output: vec![TxOut {
+							value: Amount::from_sat(channel_value_satoshis),
+							script_pubkey: output_script.clone(),
+						}],
+					};
+					let psbt = match Psbt::from_unsigned_tx(unsigned_tx) {
+						Ok(psbt) => psbt,
+						Err(e) => {
+							log_error!(self.logger, "Failed to create funding PSBT: {}", e);
+							return Err(ReplayEvent());
+						},
+					};
+
+					let pending_funding = PendingChannelFunding {
+						user_channel_id,
+						temporary_channel_id,
+						counterparty_node_id,
+						output_script,
+						channel_value_sats: channel_value_satoshis,
+					};
+					if let Err(e) = self.pending_channel_fundings.insert(pending_funding) {
+						log_error!(self.logger, "Failed to persist pending channel funding: {}", e);
+						return Err(ReplayEvent());
+					}
+
+					let event = Event::FundingPsbtReady {
+						user_channel_id: UserChannelId(user_channel_id),
+						counterparty_node_id,
+						channel_value_sats: channel_value_satoshis,
+						psbt: FundingPsbt(psbt),
+					};
+					match self.event_queue.add_event(event) {
+						Ok(_) => return Ok(()),
+						Err(e) => {
+							log_error!(self.logger, "Failed to push to event queue: {}", e);
+							return Err(ReplayEvent());
+						},
+					};
+				}
+
+				// Use the UTXOs selected when opening the channel, if any.
+				let utxos = self.wallet.take_funding_utxos(user_channel_id);
+
 				// Sign the final funding transaction and broadcast it.
 				let channel_amount = Amount::from_sat(channel_value_satoshis);
 				match self.wallet.create_funding_transaction(
@@ -510,6 +887,7 @@ where
 					channel_amount,
 					confirmation_target,
 					locktime,
+					utxos,
 				) {
 					Ok(final_tx) => {
 						// Give the funding transaction back to LDK for opening the channel.
@@ -564,7 +942,7 @@ where
 				purpose,
 				amount_msat,
 				receiver_node_id: _,
-				via_channel_id: _,
+				via_channel_id,
 				via_user_channel_id: _,
 				claim_deadline,
 				onion_fields,
@@ -573,6 +951,38 @@ where
 			} => {
 				let payment_id = PaymentId(payment_hash.0);
 				if let Some(info) = self.payment_store.get(&payment_id) {
+					if let PaymentKind::Rebalance {
+						preimage: Some(preimage), to_channel_id, ..
+					} = info.kind
+					{
+						// Only claim the payment if it returns to us exactly as we sent it out.
+						let is_expected_amount = info.amount_msat == Some(amount_msat);
+						let is_expected_channel = via_channel_id == Some(to_channel_id);
+						let is_pending = info.status == PaymentStatus::Pending;
+						if is_expected_amount && is_expected_channel && is_pending {
+							log_info!(
+								self.logger,
+								"Claiming returning rebalance payment with ID {} of {}msat.",
+								payment_id,
+								amount_msat,
+							);
+							self.channel_manager.claim_funds(preimage);
+							return Ok(());
+						}
+
+						log_error!(
+							self.logger,
+							"Refused returning rebalance payment with ID {} of {}msat via channel {:?}: expected a pending payment of {:?}msat via channel {}.",
+							payment_id,
+							amount_msat,
+							via_channel_id,
+							info.amount_msat,
+							to_channel_id,
+						);
+						self.channel_manager.fail_htlc_backwards(&payment_hash);
+						return Ok(());
+					}
+
 					if info.direction == PaymentDirection::Outbound {
 						log_info!(
 							self.logger,
@@ -690,6 +1100,20 @@ where
 									"We would have registered the preimage if we knew"
 								);
 
+								let update = PaymentDetailsUpdate {
+									status: Some(PaymentStatus::Held),
+									claim_deadline: Some(claim_deadline),
+									..PaymentDetailsUpdate::new(payment_id)
+								};
+								if let Err(e) = self.payment_store.update(&update) {
+									log_error!(
+										self.logger,
+										"Failed to access payment store: {}",
+										e
+									);
+									return Err(ReplayEvent());
+								}
+
 								let custom_records = onion_fields
 									.map(|cf| {
 										cf.custom_tlvs().into_iter().map(|tlv| tlv.into()).collect()
@@ -865,6 +1289,13 @@ where
 					amount_msat,
 				);
 
+				if let Some(info) = self.payment_store.get(&payment_id) {
+					if matches!(info.kind, PaymentKind::Rebalance { .. }) {
+						// The outbound side of the rebalance will be updated on `PaymentSent`.
+						return Ok(());
+					}
+				}
+
 				let update = match purpose {
 					PaymentPurpose::Bolt11InvoicePayment {
 						payment_preimage,
@@ -928,6 +1359,10 @@ where
 					},
 				}
 
+				if let Some(liquidity_source) = self.liquidity_source.as_ref() {
+					liquidity_source.handle_payment_claimed(payment_hash);
+				}
+
 				let event = Event::PaymentReceived {
 					payment_id: Some(payment_id),
 					payment_hash,
@@ -1036,10 +1471,105 @@ where
 				};
 			},
 
-			LdkEvent::PaymentPathSuccessful { .. } => {},
-			LdkEvent::PaymentPathFailed { .. } => {},
-			LdkEvent::ProbeSuccessful { .. } => {},
-			LdkEvent::ProbeFailed { .. } => {},
+			LdkEvent::PaymentPathSuccessful { payment_id, payment_hash, path } => {
+				if !self.config.emit_payment_path_events {
+					return Ok(());
+				}
+
+				let event = Event::PaymentPathSuccessful {
+					payment_id,
+					payment_hash,
+					hops: path.hops.iter().map(PaymentPathHop::from).collect(),
+					amount_msat: path.final_value_msat(),
+					fee_msat: path.fee_msat(),
+				};
+				match self.event_queue.add_event(event) {
+					Ok(_) => {},
+					Err(e) => {
+						log_error!(self.logger, "Failed to push to event queue: {}", e);
+						return Err(ReplayEvent());
+					},
+				};
+			},
+			LdkEvent::PaymentPathFailed {
+				payment_id,
+				payment_hash,
+				payment_failed_permanently,
+				path,
+				short_channel_id,
+				..
+			} => {
+				if !self.config.emit_payment_path_events {
+					return Ok(());
+				}
+
+				let event = Event::PaymentPathFailed {
+					payment_id,
+					payment_hash,
+					payment_failed_permanently,
+					hops: path.hops.iter().map(PaymentPathHop::from).collect(),
+					short_channel_id,
+					amount_msat: path.final_value_msat(),
+					fee_msat: path.fee_msat(),
+				};
+				match self.event_queue.add_event(event) {
+					Ok(_) => {},
+					Err(e) => {
+						log_error!(self.logger, "Failed to push to event queue: {}", e);
+						return Err(ReplayEvent());
+					},
+				};
+			},
+			LdkEvent::ProbeSuccessful { payment_id, payment_hash, path } => {
+				log_debug!(
+					self.logger,
+					"Probe {} of {}msat along {} hops succeeded",
+					payment_id,
+					path.final_value_msat(),
+					path.hops.len()
+				);
+
+				let event = Event::ProbeSucceeded {
+					payment_id,
+					payment_hash,
+					hops: path.hops.iter().map(PaymentPathHop::from).collect(),
+					amount_msat: path.final_value_msat(),
+					fee_msat: path.fee_msat(),
+				};
+				match self.event_queue.add_event(event) {
+					Ok(_) => {},
+					Err(e) => {
+						log_error!(self.logger, "Failed to push to event queue: {}", e);
+						return Err(ReplayEvent());
+					},
+				};
+			},
+			LdkEvent::ProbeFailed { payment_id, payment_hash, path, short_channel_id } => {
+				log_debug!(
+					self.logger,
+					"Probe {} of {}msat along {} hops failed at channel {:?}",
+					payment_id,
+					path.final_value_msat(),
+					path.hops.len(),
+					short_channel_id
+				);
+
+				let event = Event::ProbeFailed {
+					payment_id,
+					payment_hash,
+					hops: path.hops.iter().map(PaymentPathHop::from).collect(),
+					short_channel_id,
+					amount_msat: path.final_value_msat(),
+					fee_msat: path.fee_msat(),
+				};
+				match self.event_queue.add_event(event) {
+					Ok(_) => {},
+					Err(e) => {
+						log_error!(self.logger, "Failed to push to event queue: {}", e);
+						return Err(ReplayEvent());
+					},
+				};
+			},
 			LdkEvent::HTLCHandlingFailed { failed_next_destination, .. } => {
 				if let Some(liquidity_source) = self.liquidity_source.as_ref() {
 					liquidity_source.handle_htlc_handling_failed(failed_next_destination);
@@ -1075,7 +1605,7 @@ where
 				counterparty_node_id,
 				funding_satoshis,
 				channel_type,
-				channel_negotiation_type: _,
+				channel_negotiation_type,
 				is_announced,
 				params: _,
 			} => {
@@ -1097,6 +1627,38 @@ where
 				}
 
 				let anchor_channel = channel_type.requires_anchors_zero_fee_htlc_tx();
+
+				let num_pending_channels = self
+					.channel_manager
+					.list_channels_with_counterparty(&counterparty_node_id)
+					.iter()
+					.filter(|c| !c.is_channel_ready)
+					.count();
+				if let Err(err) = self.config.channel_acceptance_policy.check_inbound_channel(
+					&counterparty_node_id,
+					funding_satoshis,
+					anchor_channel,
+					num_pending_channels,
+				) {
+					log_error!(
+						self.logger,
+						"Rejecting inbound channel of {}sats from peer {} due to our channel acceptance policy: {}",
+						funding_satoshis,
+						counterparty_node_id,
+						err,
+					);
+					self.channel_manager
+						.force_close_without_broadcasting_txn(
+							&temporary_channel_id,
+							&counterparty_node_id,
+							"Channel request rejected".to_string(),
+						)
+						.unwrap_or_else(|e| {
+							log_error!(self.logger, "Failed to reject channel: {:?}", e)
+						});
+					return Ok(());
+				}
+
 				if anchor_channel {
 					if let Some(anchor_channels_config) =
 						self.config.anchor_channels_config.as_ref()
@@ -1157,6 +1719,27 @@ where
 					}
 				}
 
+				if self.config.channel_acceptance_policy.manual_acceptance {
+					let push_msat = match channel_negotiation_type {
+						InboundChannelFunds::PushMsat(push_msat) => push_msat,
+						InboundChannelFunds::DualFunded => 0,
+					};
+					let event = Event::InboundChannelRequest {
+						temporary_channel_id,
+						counterparty_node_id,
+						funding_sats: funding_satoshis,
+						push_msat,
+						is_announced,
+					};
+					match self.event_queue.add_event(event) {
+						Ok(_) => return Ok(()),
+						Err(e) => {
+							log_error!(self.logger, "Failed to push to event queue: {}", e);
+							return Err(ReplayEvent());
+						},
+					};
+				}
+
 				let user_channel_id: u128 = rand::thread_rng().gen::<u128>();
 				let allow_0conf = self.config.trusted_peers_0conf.contains(&counterparty_node_id);
 				let res = if allow_0conf {
@@ -1263,12 +1846,30 @@ where
 				}
 
 				if let Some(liquidity_source) = self.liquidity_source.as_ref() {
-					liquidity_source.handle_payment_forwarded(next_channel_id);
+					liquidity_source
+						.handle_payment_forwarded(next_channel_id, next_user_channel_id);
 				}
 
+				let prev_channel_id = prev_channel_id.expect("prev_channel_id expected for events generated by LDK versions greater than 0.0.107.");
+				let next_channel_id = next_channel_id.expect("next_channel_id expected for events generated by LDK versions greater than 0.0.107.");
+
+				let forward = ForwardedPaymentDetails::new(
+					ForwardedPaymentId(thread_rng().gen()),
+					prev_channel_id,
+					next_channel_id,
+					prev_user_channel_id.map(UserChannelId),
+					next_user_channel_id.map(UserChannelId),
+					prev_node_id,
+					next_node_id,
+					total_fee_earned_msat,
+					skimmed_fee_msat,
+					claim_from_onchain_tx,
+					outbound_amount_forwarded_msat,
+				);
+
 				let event = Event::PaymentForwarded {
-					prev_channel_id: prev_channel_id.expect("prev_channel_id expected for events generated by LDK versions greater than 0.0.107."),
-					next_channel_id: next_channel_id.expect("next_channel_id expected for events generated by LDK versions greater than 0.0.107."),
+					prev_channel_id,
+					next_channel_id,
 					prev_user_channel_id: prev_user_channel_id.map(UserChannelId),
 					next_user_channel_id: next_user_channel_id.map(UserChannelId),
 					prev_node_id,
@@ -1282,6 +1883,15 @@ where
 					log_error!(self.logger, "Failed to push to event queue: {}", e);
 					ReplayEvent()
 				})?;
+
+				// We only record the forward once the event was queued, as `PaymentForwarded`
+				// doesn't carry anything that would allow us to recognize a replayed forward. Note
+				// that replaying the event if we fail to persist the record might have us queue the
+				// event twice, which is preferable to losing the forward from the history.
+				self.forwarding_store.insert(forward).map_err(|e| {
+					log_error!(self.logger, "Failed to persist forwarded payment: {}", e);
+					ReplayEvent()
+				})?;
 			},
 			LdkEvent::ChannelPending {
 				channel_id,
@@ -1313,6 +1923,10 @@ where
 					},
 				};
 
+				if let Some(liquidity_source) = self.liquidity_source.as_ref() {
+					liquidity_source.handle_channel_pending(user_channel_id, funding_txo);
+				}
+
 				let network_graph = self.network_graph.read_only();
 				let channels =
 					self.channel_manager.list_channels_with_counterparty(&counterparty_node_id);
@@ -1385,6 +1999,17 @@ where
 			} => {
 				log_info!(self.logger, "Channel {} closed due to: {}", channel_id, reason);
 
+				// Drop any funding we may have still been waiting for.
+				if let Err(e) = self.pending_channel_fundings.remove(user_channel_id) {
+					log_error!(self.logger, "Failed to remove pending channel funding: {}", e);
+				}
+				self.wallet.take_funding_utxos(user_channel_id);
+				self.wallet.take_funding_confirmation_target(user_channel_id);
+
+				if let Some(liquidity_source) = self.liquidity_source.as_ref() {
+					liquidity_source.handle_channel_closed(user_channel_id);
+				}
+
 				let event = Event::ChannelClosed {
 					channel_id,
 					user_channel_id: UserChannelId(user_channel_id),
@@ -1489,7 +2114,9 @@ where
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use bitcoin::hashes::Hash;
 	use lightning::util::test_utils::{TestLogger, TestStore};
+	use lightning_liquidity::lsps1::msgs::OrderId;
 	use std::sync::atomic::{AtomicU16, Ordering};
 	use std::time::Duration;
 
@@ -1530,6 +2157,148 @@ mod tests {
 		assert_eq!(event_queue.next_event(), None);
 	}
 
+	#[test]
+	fn event_serialization_round_trip() {
+		let secp_ctx = bitcoin::secp256k1::Secp256k1::new();
+		let node_id = |i: u8| {
+			PublicKey::from_secret_key(
+				&secp_ctx,
+				&bitcoin::secp256k1::SecretKey::from_slice(&[i; 32]).unwrap(),
+			)
+		};
+		let unsigned_tx = Transaction {
+			version: Version::TWO,
+			lock_time: LockTime::ZERO,
+			input: vec![],
+			output: vec![TxOut {
+				value: Amount::from_sat(100_000),
+				script_pubkey: ScriptBuf::new_op_return([23u8; 32]),
+			}],
+		};
+		let funding_txo = OutPoint { txid: bitcoin::Txid::from_slice(&[23; 32]).unwrap(), vout: 1 };
+		let hops = vec![PaymentPathHop {
+			node_id: node_id(1),
+			short_channel_id: 42,
+			fee_msat: 1_000,
+			cltv_expiry_delta: 40,
+		}];
+
+		let events = vec![
+			Event::PaymentSuccessful {
+				payment_id: Some(PaymentId([42u8; 32])),
+				payment_hash: PaymentHash([43u8; 32]),
+				payment_preimage: Some(PaymentPreimage([44u8; 32])),
+				fee_paid_msat: Some(1_000),
+			},
+			Event::PaymentReceived {
+				payment_id: Some(PaymentId([42u8; 32])),
+				payment_hash: PaymentHash([43u8; 32]),
+				amount_msat: 100_000,
+				custom_records: vec![CustomTlvRecord { type_num: 65537, value: vec![1, 2, 3] }],
+			},
+			Event::ChannelPending {
+				channel_id: ChannelId([23u8; 32]),
+				user_channel_id: UserChannelId(2323),
+				former_temporary_channel_id: ChannelId([24u8; 32]),
+				counterparty_node_id: node_id(2),
+				funding_txo,
+			},
+			Event::PaymentForwarded {
+				prev_channel_id: ChannelId([23u8; 32]),
+				next_channel_id: ChannelId([24u8; 32]),
+				prev_user_channel_id: Some(UserChannelId(2323)),
+				next_user_channel_id: None,
+				prev_node_id: Some(node_id(2)),
+				next_node_id: Some(node_id(3)),
+				total_fee_earned_msat: Some(1_000),
+				skimmed_fee_msat: None,
+				claim_from_onchain_tx: false,
+				outbound_amount_forwarded_msat: Some(100_000),
+			},
+			Event::FundingPsbtReady {
+				user_channel_id: UserChannelId(2323),
+				counterparty_node_id: node_id(2),
+				channel_value_sats: 100_000,
+				psbt: FundingPsbt(Psbt::from_unsigned_tx(unsigned_tx).unwrap()),
+			},
+			Event::InboundChannelRequest {
+				temporary_channel_id: ChannelId([23u8; 32]),
+				counterparty_node_id: node_id(2),
+				funding_sats: 100_000,
+				push_msat: 0,
+				is_announced: true,
+			},
+			Event::PaymentPathSuccessful {
+				payment_id: PaymentId([42u8; 32]),
+				payment_hash: None,
+				hops: hops.clone(),
+				amount_msat: 100_000,
+				fee_msat: 1_000,
+			},
+			Event::PaymentPathFailed {
+				payment_id: Some(PaymentId([42u8; 32])),
+				payment_hash: PaymentHash([43u8; 32]),
+				payment_failed_permanently: false,
+				hops,
+				short_channel_id: Some(42),
+				amount_msat: 100_000,
+				fee_msat: 1_000,
+			},
+			Event::LSPS1OrderUpdated {
+				order_id: LSPS1OrderId(OrderId("order-42".to_string())),
+				state: LSPS1OrderState::ChannelOpened { funding_txo },
+			},
+		];
+
+		for event in events {
+			let encoded_event = event.encode();
+			let decoded_event: Event = Readable::read(&mut &encoded_event[..]).unwrap();
+			assert_eq!(decoded_event, event);
+		}
+	}
+
+	#[test]
+	fn probe_failed_serialization() {
+		let secp_ctx = bitcoin::secp256k1::Secp256k1::new();
+		let hops = (1u8..=3)
+			.map(|i| PaymentPathHop {
+				node_id: PublicKey::from_secret_key(
+					&secp_ctx,
+					&bitcoin::secp256k1::SecretKey::from_slice(&[i; 32]).unwrap(),
+				),
+				short_channel_id: i as u64,
+				fee_msat: 1_000,
+				cltv_expiry_delta: 40,
+			})
+			.collect();
+		let event = Event::ProbeFailed {
+			payment_id: PaymentId([42u8; 32]),
+			payment_hash: PaymentHash([43u8; 32]),
+			hops,
+			short_channel_id: Some(2),
+			amount_msat: 100_000,
+			fee_msat: 2_000,
+		};
+
+		let encoded_event = event.encode();
+		let decoded_event: Event = Readable::read(&mut &encoded_event[..]).unwrap();
+		assert_eq!(decoded_event, event);
+	}
+
+	#[test]
+	fn payment_hold_expiring_serialization() {
+		let event = Event::PaymentHoldExpiring {
+			payment_id: PaymentId([42u8; 32]),
+			payment_hash: PaymentHash([43u8; 32]),
+			claim_deadline: 850_000,
+			cancel_height: 849_998,
+		};
+
+		let encoded_event = event.encode();
+		let decoded_event: Event = Readable::read(&mut &encoded_event[..]).unwrap();
+		assert_eq!(decoded_event, event);
+	}
+
 	#[tokio::test]
 	async fn event_queue_concurrency() {
 		let store: Arc<DynStore> = Arc::new(TestStore::new(false));
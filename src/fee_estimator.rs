@@ -101,6 +101,18 @@ pub(crate) fn get_num_block_defaults_for_target(target: ConfirmationTarget) -> u
 	}
 }
 
+/// Returns the target used for funding transactions that need to confirm within `num_blocks`.
+pub(crate) fn get_funding_target_for_num_blocks(num_blocks: u16) -> ConfirmationTarget {
+	[
+		ConfirmationTarget::ChannelFunding,
+		ConfirmationTarget::OnchainPayment,
+		LdkConfirmationTarget::MaximumFeeEstimate.into(),
+	]
+	.into_iter()
+	.find(|target| get_num_block_defaults_for_target(*target) <= num_blocks as usize)
+	.unwrap_or(LdkConfirmationTarget::MaximumFeeEstimate.into())
+}
+
 pub(crate) fn get_fallback_rate_for_target(target: ConfirmationTarget) -> u32 {
 	match target {
 		ConfirmationTarget::OnchainPayment => 5000,
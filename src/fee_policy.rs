@@ -0,0 +1,235 @@
+// This file is Copyright its original authors, visible in version control history.
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. You may not use this file except in
+// accordance with one or both of these licenses.
+
+//! Holds the fee policy engine regularly adjusting the forwarding fees of our channels.
+
+use crate::config::{FeePolicyConfig, FEE_POLICY_MIN_CHANGE_PERCENT};
+use crate::logger::{log_debug, log_error, log_info, LdkLogger, Logger};
+use crate::payment::forwarding::{summarize_forwards, ForwardFilter};
+use crate::types::{ChannelManager, ForwardingStore};
+
+use lightning::ln::types::ChannelId;
+use lightning::util::config::ChannelConfigUpdate;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Adjusts the forwarding fees of our usable channels based on their liquidity balance and recent
+/// forwarding volume, as configured via [`FeePolicyConfig`].
+pub(crate) struct FeePolicyEngine {
+	channel_manager: Arc<ChannelManager>,
+	forwarding_store: Arc<ForwardingStore>,
+	config: FeePolicyConfig,
+	logger: Arc<Logger>,
+}
+
+impl FeePolicyEngine {
+	pub(crate) fn new(
+		channel_manager: Arc<ChannelManager>, forwarding_store: Arc<ForwardingStore>,
+		config: FeePolicyConfig, logger: Arc<Logger>,
+	) -> Self {
+		Self { channel_manager, forwarding_store, config, logger }
+	}
+
+	pub(crate) fn update_fees(&self) {
+		let cur_timestamp = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap_or(Duration::from_secs(0))
+			.as_secs();
+		let filter = ForwardFilter {
+			start_timestamp: Some(
+				cur_timestamp.saturating_sub(self.config.forwarding_volume_window_secs),
+			),
+			end_timestamp: None,
+			channel_id: None,
+		};
+		let forwards = self.forwarding_store.list_filter(|f| filter.matches(f));
+		let forwarded_volumes = summarize_forwards(&forwards)
+			.channels
+			.into_iter()
+			.map(|c| (c.channel_id, c.outbound_amount_forwarded_msat))
+			.collect::<HashMap<ChannelId, u64>>();
+
+		for channel in self.channel_manager.list_usable_channels() {
+			let cur_config = match channel.config {
+				Some(config) => config,
+				None => continue,
+			};
+
+			let forwarded_msat = forwarded_volumes.get(&channel.channel_id).copied().unwrap_or(0);
+			let (new_base_msat, new_proportional_millionths) = compute_channel_fees(
+				&self.config,
+				channel.outbound_capacity_msat,
+				channel.inbound_capacity_msat,
+				forwarded_msat,
+				channel.channel_value_satoshis.saturating_mul(1000),
+			);
+
+			let cur_base_msat = cur_config.forwarding_fee_base_msat;
+			let cur_proportional_millionths = cur_config.forwarding_fee_proportional_millionths;
+			if !is_significant_change(cur_base_msat, new_base_msat)
+				&& !is_significant_change(cur_proportional_millionths, new_proportional_millionths)
+			{
+				log_debug!(
+					self.logger,
+					"Keeping forwarding fees of channel {} as the change would be insignificant.",
+					channel.channel_id,
+				);
+				continue;
+			}
+
+			let update = ChannelConfigUpdate {
+				forwarding_fee_proportional_millionths: Some(new_proportional_millionths),
+				forwarding_fee_base_msat: Some(new_base_msat),
+				cltv_expiry_delta: None,
+				max_dust_htlc_exposure_msat: None,
+				force_close_avoidance_max_fee_satoshis: None,
+			};
+			match self.channel_manager.update_partial_channel_config(
+				&channel.counterparty.node_id,
+				&[channel.channel_id],
+				&update,
+			) {
+				Ok(()) => {
+					log_info!(
+						self.logger,
+						"Updated forwarding fees of channel {} with counterparty {}: base fee {}msat -> {}msat, proportional fee {}ppm -> {}ppm (outbound liquidity: {}msat, inbound liquidity: {}msat, recently forwarded: {}msat)",
+						channel.channel_id,
+						channel.counterparty.node_id,
+						cur_base_msat,
+						new_base_msat,
+						cur_proportional_millionths,
+						new_proportional_millionths,
+						channel.outbound_capacity_msat,
+						channel.inbound_capacity_msat,
+						forwarded_msat,
+					);
+				},
+				Err(e) => {
+					log_error!(
+						self.logger,
+						"Failed to update forwarding fees of channel {}: {:?}",
+						channel.channel_id,
+						e
+					);
+				},
+			}
+		}
+	}
+}
+
+/// Returns the base fee, in milli-satoshis, and the proportional fee, in millionths, for a channel
+/// with the given liquidity balance that forwarded the given amount recently.
+fn compute_channel_fees(
+	config: &FeePolicyConfig, outbound_msat: u64, inbound_msat: u64, forwarded_msat: u64,
+	capacity_msat: u64,
+) -> (u32, u32) {
+	// The share of the channel's liquidity on our counterparty's side, in millionths.
+	let total_msat = outbound_msat.saturating_add(inbound_msat);
+	let depletion_millionths = if total_msat == 0 {
+		0
+	} else {
+		(inbound_msat as u128 * 1_000_000 / total_msat as u128) as u64
+	};
+
+	let high_volume_msat =
+		(capacity_msat as u128 * config.high_forwarding_volume_percent as u128 / 100) as u64;
+	let adjustment_percent = config.volume_fee_adjustment_percent as u64;
+	let volume_factor_percent = if forwarded_msat == 0 {
+		100u64.saturating_sub(adjustment_percent)
+	} else if forwarded_msat >= high_volume_msat {
+		100 + adjustment_percent
+	} else {
+		100
+	};
+
+	let compute_fee = |min: u32, max: u32| -> u32 {
+		let max = max.max(min);
+		let fee = min as u64 + (max - min) as u64 * depletion_millionths / 1_000_000;
+		let fee = fee * volume_factor_percent / 100;
+		fee.clamp(min as u64, max as u64) as u32
+	};
+
+	(
+		compute_fee(config.min_forwarding_fee_base_msat, config.max_forwarding_fee_base_msat),
+		compute_fee(
+			config.min_forwarding_fee_proportional_millionths,
+			config.max_forwarding_fee_proportional_millionths,
+		),
+	)
+}
+
+// Returns whether a fee change is large enough to be worth broadcasting a channel update for.
+fn is_significant_change(cur: u32, new: u32) -> bool {
+	cur != new
+		&& cur.abs_diff(new) as u64 * 100 >= cur as u64 * FEE_POLICY_MIN_CHANGE_PERCENT as u64
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn fees_follow_liquidity_and_volume() {
+		let config = FeePolicyConfig {
+			min_forwarding_fee_proportional_millionths: 100,
+			max_forwarding_fee_proportional_millionths: 1100,
+			min_forwarding_fee_base_msat: 0,
+			max_forwarding_fee_base_msat: 1000,
+			high_forwarding_volume_percent: 50,
+			volume_fee_adjustment_percent: 20,
+			..Default::default()
+		};
+		let capacity_msat = 1_000_000_000;
+		let some_volume_msat = 1_000;
+
+		// Fees scale with the share of liquidity on the counterparty's side.
+		assert_eq!(
+			compute_channel_fees(&config, 1_000_000_000, 0, some_volume_msat, capacity_msat),
+			(0, 100)
+		);
+		assert_eq!(
+			compute_channel_fees(
+				&config,
+				500_000_000,
+				500_000_000,
+				some_volume_msat,
+				capacity_msat
+			),
+			(500, 600)
+		);
+		assert_eq!(
+			compute_channel_fees(&config, 0, 1_000_000_000, some_volume_msat, capacity_msat),
+			(1000, 1100)
+		);
+
+		// Fees are raised for channels in high demand and lowered for idle ones, within bounds.
+		assert_eq!(
+			compute_channel_fees(&config, 500_000_000, 500_000_000, 600_000_000, capacity_msat),
+			(600, 720)
+		);
+		assert_eq!(
+			compute_channel_fees(&config, 500_000_000, 500_000_000, 0, capacity_msat),
+			(400, 480)
+		);
+		assert_eq!(
+			compute_channel_fees(&config, 0, 1_000_000_000, 600_000_000, capacity_msat),
+			(1000, 1100)
+		);
+		assert_eq!(compute_channel_fees(&config, 1_000_000_000, 0, 0, capacity_msat), (0, 100));
+	}
+
+	#[test]
+	fn insignificant_changes_are_skipped() {
+		assert!(!is_significant_change(1000, 1000));
+		assert!(!is_significant_change(1000, 1049));
+		assert!(is_significant_change(1000, 1050));
+		assert!(is_significant_change(1000, 950));
+		assert!(is_significant_change(0, 1));
+	}
+}
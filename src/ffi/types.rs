@@ -11,16 +11,26 @@
 // Make sure to add any re-exported items that need to be used in uniffi below.
 
 pub use crate::config::{
-	default_config, AnchorChannelsConfig, BackgroundSyncConfig, ElectrumSyncConfig,
-	EsploraSyncConfig, MaxDustHTLCExposure,
+	default_config, AnchorChannelsConfig, BackgroundSyncConfig, ChannelAcceptancePolicy,
+	ElectrumSyncConfig, EsploraSyncConfig, FeePolicyConfig, MaxDustHTLCExposure,
+	PaymentRetentionConfig, Socks5ProxyConfig,
 };
 pub use crate::graph::{ChannelInfo, ChannelUpdateInfo, NodeAnnouncementInfo, NodeInfo};
-pub use crate::liquidity::{LSPS1OrderStatus, LSPS2ServiceConfig, OnchainPaymentInfo, PaymentInfo};
-pub use crate::logger::{LogLevel, LogRecord, LogWriter};
+pub use crate::hrn::HrnResolver;
+pub use crate::liquidity::{
+	LSPS1OrderDetails, LSPS1OrderId, LSPS1OrderState, LSPS1OrderStatus, LSPS1ServiceConfig,
+	LSPS2SaleDetails, LSPS2SaleState, LSPS2ServiceConfig, OnchainPaymentInfo, PaymentInfo,
+};
+pub use crate::logger::{LogFormat, LogLevel, LogRecord, LogRotation, LogWriter};
 pub use crate::payment::store::{
-	ConfirmationStatus, LSPFeeLimits, PaymentDirection, PaymentKind, PaymentStatus,
+	ConfirmationStatus, LSPFeeLimits, PaymentDirection, PaymentKind, PaymentStatus, PaymentType,
+};
+pub use crate::payment::{
+	ChannelForwardingStats, ForwardFilter, ForwardedPaymentDetails, ForwardedPaymentId,
+	ForwardingSummary, MaxTotalRoutingFeeLimit, PaymentCursor, PaymentExportFilter,
+	PaymentExportFormat, PaymentFeeEstimate, PaymentPage, PaymentPathEstimate, PaymentQuery,
+	QrPaymentResult, SendingParameters,
 };
-pub use crate::payment::{MaxTotalRoutingFeeLimit, QrPaymentResult, SendingParameters};
 
 pub use lightning::chain::channelmonitor::BalanceSource;
 pub use lightning::events::{ClosureReason, PaymentFailureReason};
@@ -36,6 +46,7 @@ pub use lightning_invoice::{Description, SignedRawBolt11Invoice};
 pub use lightning_liquidity::lsps1::msgs::ChannelInfo as ChannelOrderInfo;
 pub use lightning_liquidity::lsps1::msgs::{OrderId, OrderParameters, PaymentState};
 
+pub use bitcoin::psbt::Psbt;
 pub use bitcoin::{Address, BlockHash, FeeRate, Network, OutPoint, Txid};
 
 pub use bip39::Mnemonic;
@@ -49,7 +60,7 @@ use crate::UniffiCustomTypeConverter;
 use crate::builder::sanitize_alias;
 use crate::error::Error;
 use crate::hex_utils;
-use crate::{SocketAddress, UserChannelId};
+use crate::{FundingPsbt, SocketAddress, UserChannelId};
 
 use bitcoin::hashes::sha256::Hash as Sha256;
 use bitcoin::hashes::Hash;
@@ -649,6 +660,24 @@ impl UniffiCustomTypeConverter for PaymentId {
 	}
 }
 
+impl UniffiCustomTypeConverter for ForwardedPaymentId {
+	type Builtin = String;
+
+	fn into_custom(val: Self::Builtin) -> uniffi::Result<Self> {
+		if let Some(bytes_vec) = hex_utils::to_vec(&val) {
+			let bytes_res = bytes_vec.try_into();
+			if let Ok(bytes) = bytes_res {
+				return Ok(ForwardedPaymentId(bytes));
+			}
+		}
+		Err(Error::InvalidPaymentId.into())
+	}
+
+	fn from_custom(obj: Self) -> Self::Builtin {
+		hex_utils::to_string(&obj.0)
+	}
+}
+
 impl UniffiCustomTypeConverter for PaymentHash {
 	type Builtin = String;
 
@@ -743,6 +772,32 @@ impl UniffiCustomTypeConverter for Txid {
 	}
 }
 
+impl UniffiCustomTypeConverter for Psbt {
+	type Builtin = String;
+	fn into_custom(val: Self::Builtin) -> uniffi::Result<Self> {
+		if let Ok(psbt) = Psbt::from_str(&val) {
+			return Ok(psbt);
+		}
+
+		Err(Error::InvalidPsbt.into())
+	}
+
+	fn from_custom(obj: Self) -> Self::Builtin {
+		obj.to_string()
+	}
+}
+
+impl UniffiCustomTypeConverter for FundingPsbt {
+	type Builtin = String;
+	fn into_custom(val: Self::Builtin) -> uniffi::Result<Self> {
+		Ok(FundingPsbt(Psbt::into_custom(val)?))
+	}
+
+	fn from_custom(obj: Self) -> Self::Builtin {
+		Psbt::from_custom(obj.0)
+	}
+}
+
 impl UniffiCustomTypeConverter for BlockHash {
 	type Builtin = String;
 	fn into_custom(val: Self::Builtin) -> uniffi::Result<Self> {
@@ -1105,6 +1160,18 @@ impl UniffiCustomTypeConverter for OrderId {
 	}
 }
 
+impl UniffiCustomTypeConverter for LSPS1OrderId {
+	type Builtin = String;
+
+	fn into_custom(val: Self::Builtin) -> uniffi::Result<Self> {
+		Ok(Self(OrderId(val)))
+	}
+
+	fn from_custom(obj: Self) -> Self::Builtin {
+		obj.0 .0
+	}
+}
+
 impl UniffiCustomTypeConverter for DateTime {
 	type Builtin = String;
 
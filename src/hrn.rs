@@ -0,0 +1,188 @@
+// This file is Copyright its original authors, visible in version control history.
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. You may not use this file except in
+// accordance with one or both of these licenses.
+
+//! Objects and traits for resolving [BIP 353] human-readable names to payment instructions.
+//!
+//! [BIP 353]: https://github.com/bitcoin/bips/blob/master/bip-0353.mediawiki
+
+use crate::config::HRN_RESOLUTION_TIMEOUT_SECS;
+use crate::error::Error;
+use crate::logger::{log_error, LdkLogger, Logger};
+
+use lightning::onion_message::dns_resolution::HumanReadableName;
+
+use dnssec_prover::query::build_txt_proof;
+use dnssec_prover::rr::{Name, RR};
+use dnssec_prover::ser::parse_rr_stream;
+use dnssec_prover::validation::verify_rr_stream;
+
+use std::net::SocketAddr;
+use std::sync::mpsc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const URI_PREFIX: &str = "bitcoin:";
+
+/// Resolves the DNS `TXT` records a [BIP 353] human-readable name points to.
+///
+/// Implementations are responsible for making sure the returned records are authentic, e.g., by
+/// validating a DNSSEC proof as done by [`DnssecHrnResolver`]. Selecting the payment instructions
+/// from the returned records is left to the [`Node`].
+///
+/// [BIP 353]: https://github.com/bitcoin/bips/blob/master/bip-0353.mediawiki
+/// [`Node`]: crate::Node
+pub trait HrnResolver: Send + Sync {
+	/// Returns the validated `TXT` records stored at the given fully-qualified DNS name.
+	fn resolve_txt_records(&self, name: String) -> Result<Vec<String>, Error>;
+}
+
+/// A [`HrnResolver`] querying the given recursive DNS resolver and validating the returned
+/// DNSSEC proof locally.
+pub struct DnssecHrnResolver {
+	dns_resolver: SocketAddr,
+}
+
+impl DnssecHrnResolver {
+	/// Creates a new resolver querying the recursive DNS resolver at the given address.
+	pub fn new(dns_resolver: SocketAddr) -> Self {
+		Self { dns_resolver }
+	}
+}
+
+impl HrnResolver for DnssecHrnResolver {
+	fn resolve_txt_records(&self, name: String) -> Result<Vec<String>, Error> {
+		let name = Name::try_from(name).map_err(|()| Error::InvalidHumanReadableName)?;
+
+		// `build_txt_proof` blocks on the DNS queries without any timeout, so we run it on a
+		// separate thread and stop waiting on it after `HRN_RESOLUTION_TIMEOUT_SECS`.
+		let (sender, receiver) = mpsc::channel();
+		let dns_resolver = self.dns_resolver;
+		let query_name = name.clone();
+		std::thread::spawn(move || {
+			let _ = sender.send(build_txt_proof(dns_resolver, &query_name));
+		});
+		let (proof, _ttl) = receiver
+			.recv_timeout(Duration::from_secs(HRN_RESOLUTION_TIMEOUT_SECS))
+			.map_err(|_| Error::HrnResolutionFailed)?
+			.map_err(|_| Error::HrnResolutionFailed)?;
+
+		let rrs = parse_rr_stream(&proof).map_err(|()| Error::HrnProofValidationFailed)?;
+		let verified_rrs = verify_rr_stream(&rrs).map_err(|_| Error::HrnProofValidationFailed)?;
+
+		let now = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map_err(|_| Error::HrnProofValidationFailed)?
+			.as_secs();
+		if now < verified_rrs.valid_from || now > verified_rrs.expires {
+			return Err(Error::HrnProofValidationFailed);
+		}
+
+		let records = verified_rrs
+			.resolve_name(&name)
+			.into_iter()
+			.filter_map(|rr| if let RR::Txt(txt) = rr { Some(txt.data.as_vec()) } else { None })
+			.filter_map(|data| String::from_utf8(data).ok())
+			.collect();
+		Ok(records)
+	}
+}
+
+/// Resolves the given encoded human-readable name (e.g., `₿user@domain`) to a [BIP 21] URI.
+///
+/// [BIP 21]: https://github.com/bitcoin/bips/blob/master/bip-0021.mediawiki
+pub(crate) fn resolve_hrn(
+	resolver: &dyn HrnResolver, hrn: &str, logger: &Logger,
+) -> Result<String, Error> {
+	let hrn = HumanReadableName::from_encoded(hrn).map_err(|()| {
+		log_error!(logger, "Failed to parse human-readable name: {}", hrn);
+		Error::InvalidHumanReadableName
+	})?;
+	let dns_name = format!("{}.user._bitcoin-payment.{}.", hrn.user(), hrn.domain());
+
+	let records = resolver.resolve_txt_records(dns_name.clone()).map_err(|e| {
+		log_error!(logger, "Failed to resolve {}: {}", dns_name, e);
+		e
+	})?;
+
+	// BIP 353 requires exactly one `TXT` record beginning with `bitcoin:`.
+	let mut candidate_records = records.into_iter().filter(|record| {
+		record.len() > URI_PREFIX.len()
+			&& record.as_bytes()[..URI_PREFIX.len()].eq_ignore_ascii_case(URI_PREFIX.as_bytes())
+	});
+	match (candidate_records.next(), candidate_records.next()) {
+		(Some(uri), None) => Ok(uri),
+		_ => {
+			log_error!(logger, "Found no unique payment instructions for {}", dns_name);
+			Err(Error::HrnRecordNotFound)
+		},
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use std::sync::Mutex;
+
+	struct StaticHrnResolver {
+		records: Vec<String>,
+		queried_names: Mutex<Vec<String>>,
+	}
+
+	impl StaticHrnResolver {
+		fn new(records: &[&str]) -> Self {
+			let records = records.iter().map(|r| r.to_string()).collect();
+			Self { records, queried_names: Mutex::new(Vec::new()) }
+		}
+	}
+
+	impl HrnResolver for StaticHrnResolver {
+		fn resolve_txt_records(&self, name: String) -> Result<Vec<String>, Error> {
+			self.queried_names.lock().unwrap().push(name);
+			Ok(self.records.clone())
+		}
+	}
+
+	#[test]
+	fn resolves_unique_bitcoin_record() {
+		let logger = Logger::new_log_facade();
+		let uri = "bitcoin:bc1qexample?lightning=lnbc1example";
+		let resolver = StaticHrnResolver::new(&["v=spf1 -all", uri]);
+
+		assert_eq!(resolve_hrn(&resolver, "₿matt@mattcorallo.com", &logger), Ok(uri.to_string()));
+		assert_eq!(resolve_hrn(&resolver, "matt@mattcorallo.com", &logger), Ok(uri.to_string()));
+		assert_eq!(
+			*resolver.queried_names.lock().unwrap(),
+			vec![
+				"matt.user._bitcoin-payment.mattcorallo.com.".to_string(),
+				"matt.user._bitcoin-payment.mattcorallo.com.".to_string(),
+			]
+		);
+	}
+
+	#[test]
+	fn rejects_invalid_names_and_records() {
+		let logger = Logger::new_log_facade();
+		let resolver = StaticHrnResolver::new(&["bitcoin:bc1qexample"]);
+		assert_eq!(
+			resolve_hrn(&resolver, "not a name", &logger),
+			Err(Error::InvalidHumanReadableName)
+		);
+		assert!(resolver.queried_names.lock().unwrap().is_empty());
+
+		let resolver = StaticHrnResolver::new(&["v=spf1 -all"]);
+		assert_eq!(
+			resolve_hrn(&resolver, "₿user@example.com", &logger),
+			Err(Error::HrnRecordNotFound)
+		);
+
+		let resolver = StaticHrnResolver::new(&["bitcoin:bc1qexample", "BITCOIN:bc1qother"]);
+		assert_eq!(
+			resolve_hrn(&resolver, "₿user@example.com", &logger),
+			Err(Error::HrnRecordNotFound)
+		);
+	}
+}
@@ -0,0 +1,294 @@
+// This file is Copyright its original authors, visible in version control history.
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. You may not use this file except in
+// accordance with one or both of these licenses.
+
+use crate::io::*;
+use crate::Error;
+
+use lightning::io;
+use lightning::util::persist::{
+	KVStore, ARCHIVED_CHANNEL_MONITOR_PERSISTENCE_PRIMARY_NAMESPACE,
+	ARCHIVED_CHANNEL_MONITOR_PERSISTENCE_SECONDARY_NAMESPACE, CHANNEL_MANAGER_PERSISTENCE_KEY,
+	CHANNEL_MANAGER_PERSISTENCE_PRIMARY_NAMESPACE, CHANNEL_MANAGER_PERSISTENCE_SECONDARY_NAMESPACE,
+	CHANNEL_MONITOR_PERSISTENCE_PRIMARY_NAMESPACE, CHANNEL_MONITOR_PERSISTENCE_SECONDARY_NAMESPACE,
+	CHANNEL_MONITOR_UPDATE_PERSISTENCE_PRIMARY_NAMESPACE, NETWORK_GRAPH_PERSISTENCE_KEY,
+	NETWORK_GRAPH_PERSISTENCE_PRIMARY_NAMESPACE, NETWORK_GRAPH_PERSISTENCE_SECONDARY_NAMESPACE,
+	OUTPUT_SWEEPER_PERSISTENCE_KEY, OUTPUT_SWEEPER_PERSISTENCE_PRIMARY_NAMESPACE,
+	OUTPUT_SWEEPER_PERSISTENCE_SECONDARY_NAMESPACE, SCORER_PERSISTENCE_KEY,
+	SCORER_PERSISTENCE_PRIMARY_NAMESPACE, SCORER_PERSISTENCE_SECONDARY_NAMESPACE,
+};
+
+use std::sync::Arc;
+
+// The individual keys we persist. Note that not all stores support listing the top-level
+// namespace, which is why we need to enumerate them explicitly.
+//
+// The `ChannelManager` is deliberately not part of this list, as it is copied last: its presence
+// in the destination store indicates that a migration completed.
+const MIGRATED_KEYS: [(&str, &str, &str); 7] = [
+	(
+		EVENT_QUEUE_PERSISTENCE_PRIMARY_NAMESPACE,
+		EVENT_QUEUE_PERSISTENCE_SECONDARY_NAMESPACE,
+		EVENT_QUEUE_PERSISTENCE_KEY,
+	),
+	(
+		PEER_INFO_PERSISTENCE_PRIMARY_NAMESPACE,
+		PEER_INFO_PERSISTENCE_SECONDARY_NAMESPACE,
+		PEER_INFO_PERSISTENCE_KEY,
+	),
+	(
+		LOCKED_UTXOS_PERSISTENCE_PRIMARY_NAMESPACE,
+		LOCKED_UTXOS_PERSISTENCE_SECONDARY_NAMESPACE,
+		LOCKED_UTXOS_PERSISTENCE_KEY,
+	),
+	(NODE_METRICS_PRIMARY_NAMESPACE, NODE_METRICS_SECONDARY_NAMESPACE, NODE_METRICS_KEY),
+	(
+		NETWORK_GRAPH_PERSISTENCE_PRIMARY_NAMESPACE,
+		NETWORK_GRAPH_PERSISTENCE_SECONDARY_NAMESPACE,
+		NETWORK_GRAPH_PERSISTENCE_KEY,
+	),
+	(
+		SCORER_PERSISTENCE_PRIMARY_NAMESPACE,
+		SCORER_PERSISTENCE_SECONDARY_NAMESPACE,
+		SCORER_PERSISTENCE_KEY,
+	),
+	(
+		OUTPUT_SWEEPER_PERSISTENCE_PRIMARY_NAMESPACE,
+		OUTPUT_SWEEPER_PERSISTENCE_SECONDARY_NAMESPACE,
+		OUTPUT_SWEEPER_PERSISTENCE_KEY,
+	),
+];
+
+// The namespaces we persist a variable set of keys under.
+const MIGRATED_NAMESPACES: [(&str, &str); 11] = [
+	(PAYMENT_INFO_PERSISTENCE_PRIMARY_NAMESPACE, PAYMENT_INFO_PERSISTENCE_SECONDARY_NAMESPACE),
+	(
+		ARCHIVED_PAYMENT_INFO_PERSISTENCE_PRIMARY_NAMESPACE,
+		ARCHIVED_PAYMENT_INFO_PERSISTENCE_SECONDARY_NAMESPACE,
+	),
+	(
+		FORWARDED_PAYMENT_INFO_PERSISTENCE_PRIMARY_NAMESPACE,
+		FORWARDED_PAYMENT_INFO_PERSISTENCE_SECONDARY_NAMESPACE,
+	),
+	(
+		LSPS1_SERVICE_ORDER_PERSISTENCE_PRIMARY_NAMESPACE,
+		LSPS1_SERVICE_ORDER_PERSISTENCE_SECONDARY_NAMESPACE,
+	),
+	(
+		LSPS1_CLIENT_ORDER_PERSISTENCE_PRIMARY_NAMESPACE,
+		LSPS1_CLIENT_ORDER_PERSISTENCE_SECONDARY_NAMESPACE,
+	),
+	(LSPS2_SALE_PERSISTENCE_PRIMARY_NAMESPACE, LSPS2_SALE_PERSISTENCE_SECONDARY_NAMESPACE),
+	(BDK_WALLET_DESCRIPTOR_PRIMARY_NAMESPACE, BDK_WALLET_DESCRIPTOR_SECONDARY_NAMESPACE),
+	(
+		CHANNEL_MONITOR_PERSISTENCE_PRIMARY_NAMESPACE,
+		CHANNEL_MONITOR_PERSISTENCE_SECONDARY_NAMESPACE,
+	),
+	(
+		ARCHIVED_CHANNEL_MONITOR_PERSISTENCE_PRIMARY_NAMESPACE,
+		ARCHIVED_CHANNEL_MONITOR_PERSISTENCE_SECONDARY_NAMESPACE,
+	),
+	(RESTORED_CHANNEL_MONITORS_PRIMARY_NAMESPACE, RESTORED_CHANNEL_MONITORS_SECONDARY_NAMESPACE),
+	(
+		DEPRECATED_SPENDABLE_OUTPUT_INFO_PERSISTENCE_PRIMARY_NAMESPACE,
+		DEPRECATED_SPENDABLE_OUTPUT_INFO_PERSISTENCE_SECONDARY_NAMESPACE,
+	),
+];
+
+/// Copies all data persisted by LDK Node from the store `from` to the store `to`, e.g., to move
+/// an existing node from a [`SqliteStore`] to a [VSS] backend.
+///
+/// Every copied entry is read back from `to` and compared to the original. The `ChannelManager`
+/// is copied last, so that an interrupted migration can simply be retried.
+///
+/// The migration is refused with [`Error::StoreInUse`] if a [`Node`] is running on either store.
+/// Note that a [`Node`] that was not shut down via [`Node::stop`] leaves the respective store
+/// marked as in use until it is started and stopped again. It is further refused with
+/// [`Error::StoreMigrationFailed`] if `to` already holds a `ChannelManager`.
+///
+/// The source store is left untouched. After a successful migration, the [`Node`] should be
+/// built on top of `to` and `from` should no longer be used.
+///
+/// [`SqliteStore`]: crate::io::sqlite_store::SqliteStore
+/// [VSS]: https://github.com/lightningdevkit/vss-server/blob/main/README.md
+/// [`Node`]: crate::Node
+/// [`Node::stop`]: crate::Node::stop
+pub fn migrate_store(
+	from: Arc<dyn KVStore + Sync + Send>, to: Arc<dyn KVStore + Sync + Send>,
+) -> Result<(), Error> {
+	for store in [&from, &to] {
+		if read_if_exists(
+			&**store,
+			NODE_RUNNING_MARKER_PRIMARY_NAMESPACE,
+			NODE_RUNNING_MARKER_SECONDARY_NAMESPACE,
+			NODE_RUNNING_MARKER_KEY,
+		)?
+		.is_some()
+		{
+			return Err(Error::StoreInUse);
+		}
+	}
+
+	if read_if_exists(
+		&*to,
+		CHANNEL_MANAGER_PERSISTENCE_PRIMARY_NAMESPACE,
+		CHANNEL_MANAGER_PERSISTENCE_SECONDARY_NAMESPACE,
+		CHANNEL_MANAGER_PERSISTENCE_KEY,
+	)?
+	.is_some()
+	{
+		return Err(Error::StoreMigrationFailed);
+	}
+
+	for (primary_namespace, secondary_namespace, key) in MIGRATED_KEYS {
+		migrate_key(&*from, &*to, primary_namespace, secondary_namespace, key)?;
+	}
+
+	for (primary_namespace, secondary_namespace) in MIGRATED_NAMESPACES {
+		migrate_namespace(&*from, &*to, primary_namespace, secondary_namespace)?;
+	}
+
+	// The updates of each `ChannelMonitor` live in a secondary namespace named after it.
+	let monitor_keys = from
+		.list(
+			CHANNEL_MONITOR_PERSISTENCE_PRIMARY_NAMESPACE,
+			CHANNEL_MONITOR_PERSISTENCE_SECONDARY_NAMESPACE,
+		)
+		.map_err(|_| Error::StoreMigrationFailed)?;
+	for monitor_key in monitor_keys {
+		migrate_namespace(
+			&*from,
+			&*to,
+			CHANNEL_MONITOR_UPDATE_PERSISTENCE_PRIMARY_NAMESPACE,
+			&monitor_key,
+		)?;
+	}
+
+	migrate_key(
+		&*from,
+		&*to,
+		CHANNEL_MANAGER_PERSISTENCE_PRIMARY_NAMESPACE,
+		CHANNEL_MANAGER_PERSISTENCE_SECONDARY_NAMESPACE,
+		CHANNEL_MANAGER_PERSISTENCE_KEY,
+	)
+}
+
+fn migrate_namespace(
+	from: &dyn KVStore, to: &dyn KVStore, primary_namespace: &str, secondary_namespace: &str,
+) -> Result<(), Error> {
+	let keys = from
+		.list(primary_namespace, secondary_namespace)
+		.map_err(|_| Error::StoreMigrationFailed)?;
+	for key in &keys {
+		migrate_key(from, to, primary_namespace, secondary_namespace, key)?;
+	}
+
+	// Make sure the destination lists everything we copied, as otherwise it would be lost on read.
+	let migrated_keys =
+		to.list(primary_namespace, secondary_namespace).map_err(|_| Error::StoreMigrationFailed)?;
+	if keys.iter().any(|key| !migrated_keys.contains(key)) {
+		return Err(Error::StoreMigrationFailed);
+	}
+	Ok(())
+}
+
+fn migrate_key(
+	from: &dyn KVStore, to: &dyn KVStore, primary_namespace: &str, secondary_namespace: &str,
+	key: &str,
+) -> Result<(), Error> {
+	let value = match read_if_exists(from, primary_namespace, secondary_namespace, key)? {
+		Some(value) => value,
+		None => return Ok(()),
+	};
+
+	to.write(primary_namespace, secondary_namespace, key, &value)
+		.map_err(|_| Error::StoreMigrationFailed)?;
+
+	let written_value = to
+		.read(primary_namespace, secondary_namespace, key)
+		.map_err(|_| Error::StoreMigrationFailed)?;
+	if written_value != value {
+		return Err(Error::StoreMigrationFailed);
+	}
+	Ok(())
+}
+
+fn read_if_exists(
+	store: &dyn KVStore, primary_namespace: &str, secondary_namespace: &str, key: &str,
+) -> Result<Option<Vec<u8>>, Error> {
+	match store.read(primary_namespace, secondary_namespace, key) {
+		Ok(value) => Ok(Some(value)),
+		Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+		Err(_) => Err(Error::StoreMigrationFailed),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::io::sqlite_store::SqliteStore;
+	use crate::io::test_utils::random_storage_path;
+
+	use lightning_persister::fs_store::FilesystemStore;
+
+	fn populate_store(store: &dyn KVStore) {
+		store.write("", "", EVENT_QUEUE_PERSISTENCE_KEY, &[1; 32]).unwrap();
+		store.write("", "", CHANNEL_MANAGER_PERSISTENCE_KEY, &[2; 32]).unwrap();
+		store.write("payments", "", "payment_a", &[3; 32]).unwrap();
+		store.write("payments", "", "payment_b", &[4; 32]).unwrap();
+		store.write("bdk_wallet", "", "descriptor", &[5; 32]).unwrap();
+		store.write("monitors", "", "monitor_a", &[6; 32]).unwrap();
+		store.write("monitor_updates", "monitor_a", "1", &[7; 32]).unwrap();
+	}
+
+	#[test]
+	fn migrates_all_data() {
+		let from: Arc<dyn KVStore + Sync + Send> =
+			Arc::new(FilesystemStore::new(random_storage_path()));
+		let to: Arc<dyn KVStore + Sync + Send> =
+			Arc::new(SqliteStore::new(random_storage_path(), None, None).unwrap());
+		populate_store(&*from);
+
+		migrate_store(Arc::clone(&from), Arc::clone(&to)).unwrap();
+
+		for (primary_namespace, secondary_namespace, key) in [
+			("", "", EVENT_QUEUE_PERSISTENCE_KEY),
+			("", "", CHANNEL_MANAGER_PERSISTENCE_KEY),
+			("payments", "", "payment_a"),
+			("payments", "", "payment_b"),
+			("bdk_wallet", "", "descriptor"),
+			("monitors", "", "monitor_a"),
+			("monitor_updates", "monitor_a", "1"),
+		] {
+			assert_eq!(
+				to.read(primary_namespace, secondary_namespace, key).unwrap(),
+				from.read(primary_namespace, secondary_namespace, key).unwrap()
+			);
+		}
+
+		// Migrating into a store that already holds a node's state is refused.
+		assert_eq!(migrate_store(from, to), Err(Error::StoreMigrationFailed));
+	}
+
+	#[test]
+	fn refuses_to_migrate_store_in_use() {
+		let from: Arc<dyn KVStore + Sync + Send> =
+			Arc::new(FilesystemStore::new(random_storage_path()));
+		let to: Arc<dyn KVStore + Sync + Send> =
+			Arc::new(SqliteStore::new(random_storage_path(), None, None).unwrap());
+		populate_store(&*from);
+		from.write(
+			NODE_RUNNING_MARKER_PRIMARY_NAMESPACE,
+			NODE_RUNNING_MARKER_SECONDARY_NAMESPACE,
+			NODE_RUNNING_MARKER_KEY,
+			&[],
+		)
+		.unwrap();
+
+		assert_eq!(migrate_store(Arc::clone(&from), Arc::clone(&to)), Err(Error::StoreInUse));
+		assert!(to.list("payments", "").unwrap().is_empty());
+	}
+}
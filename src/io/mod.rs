@@ -7,6 +7,9 @@
 
 //! Objects and traits for data persistence.
 
+pub(crate) mod migration;
+#[cfg(feature = "postgres")]
+pub mod postgres_store;
 pub mod sqlite_store;
 #[cfg(test)]
 pub(crate) mod test_utils;
@@ -23,15 +26,58 @@ pub(crate) const PEER_INFO_PERSISTENCE_PRIMARY_NAMESPACE: &str = "";
 pub(crate) const PEER_INFO_PERSISTENCE_SECONDARY_NAMESPACE: &str = "";
 pub(crate) const PEER_INFO_PERSISTENCE_KEY: &str = "peers";
 
+/// The locked UTXOs of the on-chain wallet will be persisted under this key.
+pub(crate) const LOCKED_UTXOS_PERSISTENCE_PRIMARY_NAMESPACE: &str = "";
+pub(crate) const LOCKED_UTXOS_PERSISTENCE_SECONDARY_NAMESPACE: &str = "";
+pub(crate) const LOCKED_UTXOS_PERSISTENCE_KEY: &str = "locked_utxos";
+
 /// The payment information will be persisted under this prefix.
 pub(crate) const PAYMENT_INFO_PERSISTENCE_PRIMARY_NAMESPACE: &str = "payments";
 pub(crate) const PAYMENT_INFO_PERSISTENCE_SECONDARY_NAMESPACE: &str = "";
 
+/// The information about archived payments will be persisted under this prefix.
+pub(crate) const ARCHIVED_PAYMENT_INFO_PERSISTENCE_PRIMARY_NAMESPACE: &str = "archived_payments";
+pub(crate) const ARCHIVED_PAYMENT_INFO_PERSISTENCE_SECONDARY_NAMESPACE: &str = "";
+
+/// The information about forwarded payments will be persisted under this prefix.
+pub(crate) const FORWARDED_PAYMENT_INFO_PERSISTENCE_PRIMARY_NAMESPACE: &str = "forwarded_payments";
+pub(crate) const FORWARDED_PAYMENT_INFO_PERSISTENCE_SECONDARY_NAMESPACE: &str = "";
+
+/// The orders placed with our LSPS1 service will be persisted under this prefix.
+pub(crate) const LSPS1_SERVICE_ORDER_PERSISTENCE_PRIMARY_NAMESPACE: &str = "lsps1_service_orders";
+pub(crate) const LSPS1_SERVICE_ORDER_PERSISTENCE_SECONDARY_NAMESPACE: &str = "";
+
+/// The orders we placed with our LSPS1 liquidity source will be persisted under this prefix.
+pub(crate) const LSPS1_CLIENT_ORDER_PERSISTENCE_PRIMARY_NAMESPACE: &str = "lsps1_client_orders";
+pub(crate) const LSPS1_CLIENT_ORDER_PERSISTENCE_SECONDARY_NAMESPACE: &str = "";
+
+/// The JIT channels sold via our LSPS2 service will be persisted under this prefix.
+pub(crate) const LSPS2_SALE_PERSISTENCE_PRIMARY_NAMESPACE: &str = "lsps2_sales";
+pub(crate) const LSPS2_SALE_PERSISTENCE_SECONDARY_NAMESPACE: &str = "";
+
+/// The channel fundings awaiting an externally signed funding transaction will be persisted under
+/// this key.
+pub(crate) const PENDING_CHANNEL_FUNDINGS_PERSISTENCE_PRIMARY_NAMESPACE: &str = "";
+pub(crate) const PENDING_CHANNEL_FUNDINGS_PERSISTENCE_SECONDARY_NAMESPACE: &str = "";
+pub(crate) const PENDING_CHANNEL_FUNDINGS_PERSISTENCE_KEY: &str = "pending_channel_fundings";
+
+/// The keys of the `ChannelMonitor`s restored from a channel backup will be persisted under this
+/// prefix.
+pub(crate) const RESTORED_CHANNEL_MONITORS_PRIMARY_NAMESPACE: &str = "restored_channel_monitors";
+pub(crate) const RESTORED_CHANNEL_MONITORS_SECONDARY_NAMESPACE: &str = "";
+
 /// The spendable output information used to persisted under this prefix until LDK Node v0.3.0.
 pub(crate) const DEPRECATED_SPENDABLE_OUTPUT_INFO_PERSISTENCE_PRIMARY_NAMESPACE: &str =
 	"spendable_outputs";
 pub(crate) const DEPRECATED_SPENDABLE_OUTPUT_INFO_PERSISTENCE_SECONDARY_NAMESPACE: &str = "";
 
+/// The marker indicating that a [`Node`] is running on the store will be persisted under this key.
+///
+/// [`Node`]: crate::Node
+pub(crate) const NODE_RUNNING_MARKER_PRIMARY_NAMESPACE: &str = "";
+pub(crate) const NODE_RUNNING_MARKER_SECONDARY_NAMESPACE: &str = "";
+pub(crate) const NODE_RUNNING_MARKER_KEY: &str = "node_running";
+
 /// The node metrics will be persisted under this key.
 pub(crate) const NODE_METRICS_PRIMARY_NAMESPACE: &str = "";
 pub(crate) const NODE_METRICS_SECONDARY_NAMESPACE: &str = "";
@@ -0,0 +1,107 @@
+// This file is Copyright its original authors, visible in version control history.
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. You may not use this file except in
+// accordance with one or both of these licenses.
+
+use deadpool_postgres::Transaction;
+
+use lightning::io;
+
+pub(super) async fn migrate_schema(
+	tx: &Transaction<'_>, kv_table_name: &str, version_table_name: &str, from_version: i32,
+	to_version: i32,
+) -> io::Result<()> {
+	assert!(from_version < to_version);
+	for version in from_version..to_version {
+		let statements = match version {
+			// Version 0 denotes a fresh database, i.e., we need to create the initial table layout
+			// matching the one of `SqliteStore`.
+			0 => vec![format!(
+				"CREATE TABLE IF NOT EXISTS {} (
+				primary_namespace TEXT NOT NULL,
+				secondary_namespace TEXT DEFAULT '' NOT NULL,
+				key TEXT NOT NULL CHECK (key <> ''),
+				value BYTEA, PRIMARY KEY ( primary_namespace, secondary_namespace, key )
+				);",
+				kv_table_name
+			)],
+			_ => {
+				let msg = format!(
+					"Failed to migrate table {}: no migration from schema version {} to {}",
+					kv_table_name,
+					version,
+					version + 1
+				);
+				return Err(io::Error::new(io::ErrorKind::Other, msg));
+			},
+		};
+
+		for sql in statements {
+			tx.batch_execute(&sql).await.map_err(|e| {
+				let msg = format!(
+					"Failed to migrate table {} from schema version {} to {}: {}",
+					kv_table_name,
+					version,
+					version + 1,
+					e
+				);
+				io::Error::new(io::ErrorKind::Other, msg)
+			})?;
+		}
+	}
+
+	let sql = format!("DELETE FROM {};", version_table_name);
+	tx.execute(&sql, &[]).await.map_err(|e| {
+		let msg = format!(
+			"Failed to upgrade schema version from {} to {}: {}",
+			from_version, to_version, e
+		);
+		io::Error::new(io::ErrorKind::Other, msg)
+	})?;
+
+	let sql = format!("INSERT INTO {} (version) VALUES ($1);", version_table_name);
+	tx.execute(&sql, &[&to_version]).await.map_err(|e| {
+		let msg = format!(
+			"Failed to upgrade schema version from {} to {}: {}",
+			from_version, to_version, e
+		);
+		io::Error::new(io::ErrorKind::Other, msg)
+	})?;
+	Ok(())
+}
+
+#[cfg(all(test, postgres_test))]
+mod tests {
+	use crate::io::postgres_store::tests::{random_table_name, test_connection_string};
+	use crate::io::postgres_store::{PostgresStore, SCHEMA_VERSION};
+
+	use lightning::util::persist::KVStore;
+
+	#[test]
+	fn reopen_store_with_existing_schema() {
+		let kv_table_name = random_table_name();
+
+		let store_0 =
+			PostgresStore::new(test_connection_string(), Some(kv_table_name.clone())).unwrap();
+		store_0.write("testspace", "testsubspace", "testkey", &[42u8; 32]).unwrap();
+
+		// Reopening the store must neither fail nor touch the existing data.
+		let store_1 =
+			PostgresStore::new(test_connection_string(), Some(kv_table_name.clone())).unwrap();
+		assert_eq!(store_1.read("testspace", "testsubspace", "testkey").unwrap(), vec![42u8; 32]);
+
+		// Opening a store that was written by a newer version must fail.
+		let sql = format!(
+			"UPDATE {}_schema_version SET version = {};",
+			kv_table_name,
+			SCHEMA_VERSION + 1
+		);
+		store_1
+			.runtime
+			.block_on(async { store_1.pool.get().await.unwrap().batch_execute(&sql).await })
+			.unwrap();
+		assert!(PostgresStore::new(test_connection_string(), Some(kv_table_name)).is_err());
+	}
+}
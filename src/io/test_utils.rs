@@ -18,7 +18,7 @@ use lightning::{check_added_monitors, check_closed_broadcast, check_closed_event
 use rand::distributions::Alphanumeric;
 use rand::{thread_rng, Rng};
 
-use std::panic::RefUnwindSafe;
+use std::panic::{catch_unwind, AssertUnwindSafe};
 use std::path::PathBuf;
 
 pub(crate) fn random_storage_path() -> PathBuf {
@@ -29,7 +29,7 @@ pub(crate) fn random_storage_path() -> PathBuf {
 	temp_path
 }
 
-pub(crate) fn do_read_write_remove_list_persist<K: KVStore + RefUnwindSafe>(kv_store: &K) {
+pub(crate) fn do_read_write_remove_list_persist<K: KVStore>(kv_store: &K) {
 	let data = [42u8; 32];
 
 	let primary_namespace = "testspace";
@@ -42,11 +42,12 @@ pub(crate) fn do_read_write_remove_list_persist<K: KVStore + RefUnwindSafe>(kv_s
 	// Test empty primary/secondary namespaces are allowed, but not empty primary namespace and non-empty
 	// secondary primary_namespace, and not empty key.
 	kv_store.write("", "", key, &data).unwrap();
-	let res = std::panic::catch_unwind(|| kv_store.write("", secondary_namespace, key, &data));
+	let res =
+		catch_unwind(AssertUnwindSafe(|| kv_store.write("", secondary_namespace, key, &data)));
 	assert!(res.is_err());
-	let res = std::panic::catch_unwind(|| {
+	let res = catch_unwind(AssertUnwindSafe(|| {
 		kv_store.write(primary_namespace, secondary_namespace, "", &data)
-	});
+	}));
 	assert!(res.is_err());
 
 	let listed_keys = kv_store.list(primary_namespace, secondary_namespace).unwrap();
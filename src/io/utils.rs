@@ -9,15 +9,19 @@ use super::*;
 use crate::config::WALLET_KEYS_SEED_LEN;
 
 use crate::chain::ChainSource;
+use crate::event::PersistedChannelFundings;
 use crate::fee_estimator::OnchainFeeEstimator;
 use crate::io::{
 	NODE_METRICS_KEY, NODE_METRICS_PRIMARY_NAMESPACE, NODE_METRICS_SECONDARY_NAMESPACE,
 };
+use crate::liquidity::{LSPS1OrderDetails, LSPS1ServiceOrder, LSPS2SaleDetails};
 use crate::logger::{log_error, LdkLogger, Logger};
+use crate::payment::ForwardedPaymentDetails;
 use crate::peer_store::PeerStore;
 use crate::sweep::DeprecatedSpendableOutputInfo;
 use crate::types::{Broadcaster, DynStore, KeysManager, Sweeper};
 use crate::wallet::ser::{ChangeSetDeserWrapper, ChangeSetSerWrapper};
+use crate::wallet::LockedUtxos;
 use crate::{Error, EventQueue, NodeMetrics, PaymentDetails};
 
 use lightning::io::Cursor;
@@ -202,15 +206,15 @@ where
 	})
 }
 
-/// Read previously persisted payments information from the store.
-pub(crate) fn read_payments<L: Deref>(
-	kv_store: Arc<DynStore>, logger: L,
-) -> Result<Vec<PaymentDetails>, std::io::Error>
+/// Read previously persisted payments information from the store, handing each payment to `f`.
+///
+/// Payments are read one by one so that callers don't need to hold all of them in memory.
+pub(crate) fn read_payments<L: Deref, F: FnMut(PaymentDetails)>(
+	kv_store: Arc<DynStore>, logger: L, mut f: F,
+) -> Result<(), std::io::Error>
 where
 	L::Target: LdkLogger,
 {
-	let mut res = Vec::new();
-
 	for stored_key in kv_store.list(
 		PAYMENT_INFO_PERSISTENCE_PRIMARY_NAMESPACE,
 		PAYMENT_INFO_PERSISTENCE_SECONDARY_NAMESPACE,
@@ -227,11 +231,171 @@ where
 				"Failed to deserialize PaymentDetails",
 			)
 		})?;
-		res.push(payment);
+		f(payment);
+	}
+	Ok(())
+}
+
+/// Read previously persisted forwarded payments information from the store.
+pub(crate) fn read_forwarded_payments<L: Deref>(
+	kv_store: Arc<DynStore>, logger: L,
+) -> Result<Vec<ForwardedPaymentDetails>, std::io::Error>
+where
+	L::Target: LdkLogger,
+{
+	let mut res = Vec::new();
+
+	for stored_key in kv_store.list(
+		FORWARDED_PAYMENT_INFO_PERSISTENCE_PRIMARY_NAMESPACE,
+		FORWARDED_PAYMENT_INFO_PERSISTENCE_SECONDARY_NAMESPACE,
+	)? {
+		let mut reader = Cursor::new(kv_store.read(
+			FORWARDED_PAYMENT_INFO_PERSISTENCE_PRIMARY_NAMESPACE,
+			FORWARDED_PAYMENT_INFO_PERSISTENCE_SECONDARY_NAMESPACE,
+			&stored_key,
+		)?);
+		let forward = ForwardedPaymentDetails::read(&mut reader).map_err(|e| {
+			log_error!(logger, "Failed to deserialize ForwardedPaymentDetails: {}", e);
+			std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				"Failed to deserialize ForwardedPaymentDetails",
+			)
+		})?;
+		res.push(forward);
+	}
+	Ok(res)
+}
+
+/// Read previously persisted orders placed with our LSPS1 service from the store.
+pub(crate) fn read_lsps1_service_orders<L: Deref>(
+	kv_store: Arc<DynStore>, logger: L,
+) -> Result<Vec<LSPS1ServiceOrder>, std::io::Error>
+where
+	L::Target: LdkLogger,
+{
+	let mut res = Vec::new();
+
+	for stored_key in kv_store.list(
+		LSPS1_SERVICE_ORDER_PERSISTENCE_PRIMARY_NAMESPACE,
+		LSPS1_SERVICE_ORDER_PERSISTENCE_SECONDARY_NAMESPACE,
+	)? {
+		let mut reader = Cursor::new(kv_store.read(
+			LSPS1_SERVICE_ORDER_PERSISTENCE_PRIMARY_NAMESPACE,
+			LSPS1_SERVICE_ORDER_PERSISTENCE_SECONDARY_NAMESPACE,
+			&stored_key,
+		)?);
+		let order = LSPS1ServiceOrder::read(&mut reader).map_err(|e| {
+			log_error!(logger, "Failed to deserialize LSPS1ServiceOrder: {}", e);
+			std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				"Failed to deserialize LSPS1ServiceOrder",
+			)
+		})?;
+		res.push(order);
+	}
+	Ok(res)
+}
+
+/// Read previously persisted channel orders placed with our LSPS1 liquidity source from the store.
+pub(crate) fn read_lsps1_client_orders<L: Deref>(
+	kv_store: Arc<DynStore>, logger: L,
+) -> Result<Vec<LSPS1OrderDetails>, std::io::Error>
+where
+	L::Target: LdkLogger,
+{
+	let mut res = Vec::new();
+
+	for stored_key in kv_store.list(
+		LSPS1_CLIENT_ORDER_PERSISTENCE_PRIMARY_NAMESPACE,
+		LSPS1_CLIENT_ORDER_PERSISTENCE_SECONDARY_NAMESPACE,
+	)? {
+		let mut reader = Cursor::new(kv_store.read(
+			LSPS1_CLIENT_ORDER_PERSISTENCE_PRIMARY_NAMESPACE,
+			LSPS1_CLIENT_ORDER_PERSISTENCE_SECONDARY_NAMESPACE,
+			&stored_key,
+		)?);
+		let order = LSPS1OrderDetails::read(&mut reader).map_err(|e| {
+			log_error!(logger, "Failed to deserialize LSPS1OrderDetails: {}", e);
+			std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				"Failed to deserialize LSPS1OrderDetails",
+			)
+		})?;
+		res.push(order);
+	}
+	Ok(res)
+}
+
+/// Read previously persisted JIT channel sales of our LSPS2 service from the store.
+pub(crate) fn read_lsps2_sales<L: Deref>(
+	kv_store: Arc<DynStore>, logger: L,
+) -> Result<Vec<LSPS2SaleDetails>, std::io::Error>
+where
+	L::Target: LdkLogger,
+{
+	let mut res = Vec::new();
+
+	for stored_key in kv_store.list(
+		LSPS2_SALE_PERSISTENCE_PRIMARY_NAMESPACE,
+		LSPS2_SALE_PERSISTENCE_SECONDARY_NAMESPACE,
+	)? {
+		let mut reader = Cursor::new(kv_store.read(
+			LSPS2_SALE_PERSISTENCE_PRIMARY_NAMESPACE,
+			LSPS2_SALE_PERSISTENCE_SECONDARY_NAMESPACE,
+			&stored_key,
+		)?);
+		let sale = LSPS2SaleDetails::read(&mut reader).map_err(|e| {
+			log_error!(logger, "Failed to deserialize LSPS2SaleDetails: {}", e);
+			std::io::Error::new(
+				std::io::ErrorKind::InvalidData,
+				"Failed to deserialize LSPS2SaleDetails",
+			)
+		})?;
+		res.push(sale);
 	}
 	Ok(res)
 }
 
+/// Read previously persisted locked UTXOs of the on-chain wallet from the store.
+pub(crate) fn read_locked_utxos<L: Deref>(
+	kv_store: Arc<DynStore>, logger: L,
+) -> Result<LockedUtxos, std::io::Error>
+where
+	L::Target: LdkLogger,
+{
+	let mut reader = Cursor::new(kv_store.read(
+		LOCKED_UTXOS_PERSISTENCE_PRIMARY_NAMESPACE,
+		LOCKED_UTXOS_PERSISTENCE_SECONDARY_NAMESPACE,
+		LOCKED_UTXOS_PERSISTENCE_KEY,
+	)?);
+	LockedUtxos::read(&mut reader).map_err(|e| {
+		log_error!(logger, "Failed to deserialize locked UTXOs: {}", e);
+		std::io::Error::new(std::io::ErrorKind::InvalidData, "Failed to deserialize LockedUtxos")
+	})
+}
+
+/// Read previously persisted channel fundings awaiting an externally signed funding transaction
+/// from the store.
+pub(crate) fn read_pending_channel_fundings<L: Deref>(
+	kv_store: Arc<DynStore>, logger: L,
+) -> Result<PersistedChannelFundings, std::io::Error>
+where
+	L::Target: LdkLogger,
+{
+	let mut reader = Cursor::new(kv_store.read(
+		PENDING_CHANNEL_FUNDINGS_PERSISTENCE_PRIMARY_NAMESPACE,
+		PENDING_CHANNEL_FUNDINGS_PERSISTENCE_SECONDARY_NAMESPACE,
+		PENDING_CHANNEL_FUNDINGS_PERSISTENCE_KEY,
+	)?);
+	PersistedChannelFundings::read(&mut reader).map_err(|e| {
+		log_error!(logger, "Failed to deserialize pending channel fundings: {}", e);
+		std::io::Error::new(
+			std::io::ErrorKind::InvalidData,
+			"Failed to deserialize PersistedChannelFundings",
+		)
+	})
+}
+
 /// Read `OutputSweeper` state from the store.
 pub(crate) fn read_output_sweeper(
 	broadcaster: Arc<Broadcaster>, fee_estimator: Arc<OnchainFeeEstimator>,
@@ -48,7 +48,7 @@
 //!
 //! 	let node_id = PublicKey::from_str("NODE_ID").unwrap();
 //! 	let node_addr = SocketAddress::from_str("IP_ADDR:PORT").unwrap();
-//! 	node.open_channel(node_id, node_addr, 10000, None, None).unwrap();
+//! 	node.open_channel(node_id, node_addr, 10000, None, None, None).unwrap();
 //!
 //! 	let event = node.wait_next_event();
 //! 	println!("EVENT: {:?}", event);
@@ -78,20 +78,25 @@
 mod balance;
 mod builder;
 mod chain;
+mod channel_backup;
 pub mod config;
 mod connection;
 mod data_store;
 mod error;
 mod event;
 mod fee_estimator;
+mod fee_policy;
 mod ffi;
 mod gossip;
 pub mod graph;
 mod hex_utils;
+pub mod hrn;
 pub mod io;
 pub mod liquidity;
 pub mod logger;
 mod message_handler;
+#[cfg(feature = "metrics")]
+mod metrics;
 pub mod payment;
 mod peer_store;
 mod sweep;
@@ -113,6 +118,7 @@ use error::Error;
 
 pub use event::Event;
 
+pub use io::migration::migrate_store;
 pub use io::utils::generate_entropy_mnemonic;
 
 #[cfg(feature = "uniffi")]
@@ -125,27 +131,38 @@ pub use builder::BuildError;
 pub use builder::NodeBuilder as Builder;
 
 use chain::ChainSource;
+use channel_backup::{derive_channel_backup_key, ChannelBackup};
 use config::{
 	default_user_config, may_announce_channel, ChannelConfig, Config,
-	LDK_EVENT_HANDLER_SHUTDOWN_TIMEOUT_SECS, NODE_ANN_BCAST_INTERVAL, PEER_RECONNECTION_INTERVAL,
-	RGS_SYNC_INTERVAL,
+	FEE_POLICY_UPDATE_INTERVAL_MINIMUM_SECS, HELD_PAYMENT_CHECK_INTERVAL,
+	LDK_EVENT_HANDLER_SHUTDOWN_TIMEOUT_SECS, LSPS1_CLIENT_ORDER_POLL_INTERVAL,
+	LSPS1_SERVICE_ORDER_CHECK_INTERVAL, LSPS2_SERVICE_SALE_CHECK_INTERVAL, NODE_ANN_BCAST_INTERVAL,
+	PAYMENT_ARCHIVAL_INTERVAL, PEER_RECONNECTION_INTERVAL, RGS_SYNC_INTERVAL,
 };
 use connection::ConnectionManager;
-use event::{EventHandler, EventQueue};
+use event::{EventHandler, EventQueue, PendingChannelFundings};
+use fee_policy::FeePolicyEngine;
 use gossip::GossipSource;
 use graph::NetworkGraph;
+use hrn::HrnResolver;
 use io::utils::write_node_metrics;
-use liquidity::{LSPS1Liquidity, LiquiditySource};
+use liquidity::{LSPS1Liquidity, LSPS2SaleDetails, LiquiditySource};
 use payment::{
-	Bolt11Payment, Bolt12Payment, OnchainPayment, PaymentDetails, SpontaneousPayment,
-	UnifiedQrPayment,
+	Bolt11Payment, Bolt12Payment, ForwardFilter, ForwardedPaymentDetails, ForwardingSummary,
+	HeldPaymentWatchdog, LnurlPayment, OnchainPayment, PaymentCursor, PaymentDetails,
+	PaymentExportFilter, PaymentExportFormat, PaymentPage, PaymentQuery, PaymentStatus,
+	SpontaneousPayment, UnifiedQrPayment,
 };
 use peer_store::{PeerInfo, PeerStore};
 use types::{
-	Broadcaster, BumpTransactionEventHandler, ChainMonitor, ChannelManager, DynStore, Graph,
-	KeysManager, OnionMessenger, PaymentStore, PeerManager, Router, Scorer, Sweeper, Wallet,
+	ArchivedPaymentStore, Broadcaster, BumpTransactionEventHandler, ChainMonitor, ChannelManager,
+	DynStore, ForwardingStore, Graph, KeysManager, OnionMessenger, PaymentStore, PeerManager,
+	Router, Scorer, Sweeper, Wallet,
+};
+pub use types::{
+	ChannelDetails, CustomTlvRecord, FundingPsbt, PaymentPathHop, PeerDetails, UserChannelId,
+	UtxoDetails, WalletKeychain,
 };
-pub use types::{ChannelDetails, CustomTlvRecord, PeerDetails, UserChannelId};
 
 use logger::{log_debug, log_error, log_info, log_trace, LdkLogger, Logger};
 
@@ -155,14 +172,19 @@ use lightning::impl_writeable_tlv_based;
 use lightning::ln::channel_state::ChannelShutdownState;
 use lightning::ln::channelmanager::PaymentId;
 use lightning::ln::msgs::SocketAddress;
+use lightning::ln::types::ChannelId;
 use lightning::routing::gossip::NodeAlias;
+use lightning::util::ser::Writeable;
 
 use lightning_background_processor::process_events_async;
 
+use bitcoin::psbt::Psbt;
 use bitcoin::secp256k1::PublicKey;
+use bitcoin::OutPoint;
 
 use rand::Rng;
 
+use std::collections::HashSet;
 use std::default::Default;
 use std::net::ToSocketAddrs;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -196,12 +218,18 @@ pub struct Node {
 	liquidity_source: Option<Arc<LiquiditySource<Arc<Logger>>>>,
 	kv_store: Arc<DynStore>,
 	logger: Arc<Logger>,
-	_router: Arc<Router>,
+	router: Arc<Router>,
 	scorer: Arc<Mutex<Scorer>>,
 	peer_store: Arc<PeerStore<Arc<Logger>>>,
 	payment_store: Arc<PaymentStore>,
+	archived_payment_store: Arc<ArchivedPaymentStore>,
+	forwarding_store: Arc<ForwardingStore>,
+	pending_channel_fundings: Arc<PendingChannelFundings>,
 	is_listening: Arc<AtomicBool>,
 	node_metrics: Arc<RwLock<NodeMetrics>>,
+	#[cfg_attr(not(feature = "metrics"), allow(dead_code))]
+	metrics_listening_address: Option<SocketAddress>,
+	hrn_resolver: Arc<dyn HrnResolver>,
 }
 
 impl Node {
@@ -239,6 +267,19 @@ impl Node {
 			self.config.network
 		);
 
+		// Mark the store as in use, so that it won't be migrated from under us.
+		self.kv_store
+			.write(
+				io::NODE_RUNNING_MARKER_PRIMARY_NAMESPACE,
+				io::NODE_RUNNING_MARKER_SECONDARY_NAMESPACE,
+				io::NODE_RUNNING_MARKER_KEY,
+				&[],
+			)
+			.map_err(|e| {
+				log_error!(self.logger, "Failed to mark store as in use: {}", e);
+				Error::PersistenceFailed
+			})?;
+
 		// Start up any runtime-dependant chain sources (e.g. Electrum)
 		self.chain_source.start(Arc::clone(&runtime)).map_err(|e| {
 			log_error!(self.logger, "Failed to start chain syncing: {}", e);
@@ -521,6 +562,175 @@ impl Node {
 			}
 		});
 
+		#[cfg(feature = "metrics")]
+		if let Some(metrics_listening_address) = &self.metrics_listening_address {
+			let bind_addrs = metrics_listening_address
+				.to_socket_addrs()
+				.map_err(|e| {
+					log_error!(
+						self.logger,
+						"Unable to resolve metrics listening address: {:?}. Error details: {}",
+						metrics_listening_address,
+						e,
+					);
+					Error::InvalidSocketAddress
+				})?
+				.collect::<Vec<_>>();
+
+			let metrics_source = Arc::new(metrics::MetricsSource::new(
+				Arc::clone(&self.config),
+				Arc::clone(&self.wallet),
+				Arc::clone(&self.channel_manager),
+				Arc::clone(&self.chain_monitor),
+				Arc::clone(&self.peer_manager),
+				Arc::clone(&self.peer_store),
+				Arc::clone(&self.payment_store),
+				Arc::clone(&self.event_queue),
+				Arc::clone(&self.node_metrics),
+				Arc::clone(&self.logger),
+			));
+			let metrics_logger = Arc::clone(&self.logger);
+			let mut stop_metrics = self.stop_sender.subscribe();
+			runtime.spawn(async move {
+				let listener = match tokio::net::TcpListener::bind(&*bind_addrs).await {
+					Ok(listener) => listener,
+					Err(e) => {
+						log_error!(
+							metrics_logger,
+							"Failed to bind to metrics listening address - is something else already listening on it?: {}",
+							e
+						);
+						return;
+					},
+				};
+
+				loop {
+					tokio::select! {
+						_ = stop_metrics.changed() => {
+							log_debug!(
+								metrics_logger,
+								"Stopping serving metrics.",
+							);
+							return;
+						}
+						res = listener.accept() => {
+							match res {
+								Ok((tcp_stream, _)) => {
+									let metrics_source = Arc::clone(&metrics_source);
+									tokio::spawn(async move {
+										metrics_source.handle_connection(tcp_stream).await;
+									});
+								}
+								Err(e) => {
+									log_error!(metrics_logger, "Failed to accept metrics connection: {}", e);
+								}
+							}
+						}
+					}
+				}
+			});
+		}
+
+		// Regularly check whether any held payments are about to expire.
+		let held_payment_watchdog = HeldPaymentWatchdog::new(
+			Arc::clone(&self.channel_manager),
+			Arc::clone(&self.payment_store),
+			Arc::clone(&self.event_queue),
+			Arc::clone(&self.config),
+			Arc::clone(&self.logger),
+		);
+		let held_payment_logger = Arc::clone(&self.logger);
+		let mut stop_held_payment_watchdog = self.stop_sender.subscribe();
+		runtime.spawn(async move {
+			let mut interval = tokio::time::interval(HELD_PAYMENT_CHECK_INTERVAL);
+			interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+			loop {
+				tokio::select! {
+						_ = stop_held_payment_watchdog.changed() => {
+							log_debug!(
+								held_payment_logger,
+								"Stopping checking held payments.",
+							);
+							return;
+						}
+						_ = interval.tick() => {
+							held_payment_watchdog.check_held_payments();
+						}
+				}
+			}
+		});
+
+		// Regularly adjust our forwarding fees per our fee policy, if any.
+		if let Some(fee_policy_config) = self.config.fee_policy.clone() {
+			let update_interval = Duration::from_secs(
+				fee_policy_config.update_interval_secs.max(FEE_POLICY_UPDATE_INTERVAL_MINIMUM_SECS),
+			);
+			let fee_policy_engine = FeePolicyEngine::new(
+				Arc::clone(&self.channel_manager),
+				Arc::clone(&self.forwarding_store),
+				fee_policy_config,
+				Arc::clone(&self.logger),
+			);
+			let fee_policy_logger = Arc::clone(&self.logger);
+			let mut stop_fee_policy = self.stop_sender.subscribe();
+			runtime.spawn(async move {
+				let mut interval = tokio::time::interval(update_interval);
+				interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+				loop {
+					tokio::select! {
+							_ = stop_fee_policy.changed() => {
+								log_debug!(
+									fee_policy_logger,
+									"Stopping adjusting forwarding fees.",
+								);
+								return;
+							}
+							_ = interval.tick() => {
+								fee_policy_engine.update_fees();
+							}
+					}
+				}
+			});
+		}
+
+		// Regularly archive payments per our retention policy, if any.
+		if self.config.payment_retention.is_enabled() {
+			let payment_store = Arc::clone(&self.payment_store);
+			let archived_payment_store = Arc::clone(&self.archived_payment_store);
+			let archival_config = Arc::clone(&self.config);
+			let archival_node_metrics = Arc::clone(&self.node_metrics);
+			let archival_kv_store = Arc::clone(&self.kv_store);
+			let archival_logger = Arc::clone(&self.logger);
+			let mut stop_archival = self.stop_sender.subscribe();
+			runtime.spawn(async move {
+				let mut interval = tokio::time::interval(PAYMENT_ARCHIVAL_INTERVAL);
+				interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+				loop {
+					tokio::select! {
+							_ = stop_archival.changed() => {
+								log_debug!(
+									archival_logger,
+									"Stopping archiving payments.",
+								);
+								return;
+							}
+							_ = interval.tick() => {
+								if let Err(e) = payment::index::archive_expired_payments(
+									&payment_store,
+									&archived_payment_store,
+									&archival_config.payment_retention,
+									&archival_node_metrics,
+									Arc::clone(&archival_kv_store),
+									Arc::clone(&archival_logger),
+								) {
+									log_error!(archival_logger, "Failed to archive payments: {}", e);
+								}
+							}
+					}
+				}
+			});
+		}
+
 		let bump_tx_event_handler = Arc::new(BumpTransactionEventHandler::new(
 			Arc::clone(&self.tx_broadcaster),
 			Arc::new(LdkWallet::new(Arc::clone(&self.wallet), Arc::clone(&self.logger))),
@@ -538,7 +748,9 @@ impl Node {
 			Arc::clone(&self.network_graph),
 			self.liquidity_source.clone(),
 			Arc::clone(&self.payment_store),
+			Arc::clone(&self.forwarding_store),
 			Arc::clone(&self.peer_store),
+			Arc::clone(&self.pending_channel_fundings),
 			Arc::clone(&self.runtime),
 			Arc::clone(&self.logger),
 			Arc::clone(&self.config),
@@ -618,6 +830,90 @@ impl Node {
 					}
 				}
 			});
+
+			let mut stop_order_check = self.stop_sender.subscribe();
+			let order_check_liquidity_source = Arc::clone(&liquidity_source);
+			let order_check_logger = Arc::clone(&self.logger);
+			runtime.spawn(async move {
+				let mut interval = tokio::time::interval(LSPS1_SERVICE_ORDER_CHECK_INTERVAL);
+				interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+				loop {
+					tokio::select! {
+							_ = stop_order_check.changed() => {
+								log_debug!(
+									order_check_logger,
+									"Stopping checking LSPS1 service orders.",
+								);
+								return;
+							}
+							_ = interval.tick() => {
+								order_check_liquidity_source.process_lsps1_service_orders();
+							}
+					}
+				}
+			});
+
+			let mut stop_sale_check = self.stop_sender.subscribe();
+			let sale_check_liquidity_source = Arc::clone(&liquidity_source);
+			let sale_check_logger = Arc::clone(&self.logger);
+			runtime.spawn(async move {
+				let mut interval = tokio::time::interval(LSPS2_SERVICE_SALE_CHECK_INTERVAL);
+				interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+				loop {
+					tokio::select! {
+							_ = stop_sale_check.changed() => {
+								log_debug!(
+									sale_check_logger,
+									"Stopping checking LSPS2 service sales.",
+								);
+								return;
+							}
+							_ = interval.tick() => {
+								sale_check_liquidity_source.process_lsps2_service_sales();
+							}
+					}
+				}
+			});
+
+			if let Some((lsp_node_id, lsp_address)) = liquidity_source.get_lsps1_lsp_details() {
+				let mut stop_order_poll = self.stop_sender.subscribe();
+				let order_poll_liquidity_source = Arc::clone(&liquidity_source);
+				let order_poll_cm = Arc::clone(&self.connection_manager);
+				let order_poll_logger = Arc::clone(&self.logger);
+				runtime.spawn(async move {
+					let mut interval = tokio::time::interval(LSPS1_CLIENT_ORDER_POLL_INTERVAL);
+					interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+					loop {
+						tokio::select! {
+								_ = stop_order_poll.changed() => {
+									log_debug!(
+										order_poll_logger,
+										"Stopping polling LSPS1 client orders.",
+									);
+									return;
+								}
+								_ = interval.tick() => {
+									if !order_poll_liquidity_source.lsps1_has_pending_orders() {
+										continue;
+									}
+									if let Err(e) = order_poll_cm
+										.connect_peer_if_necessary(lsp_node_id, lsp_address.clone())
+										.await
+									{
+										log_error!(
+											order_poll_logger,
+											"Failed to connect to LSP {} to poll orders: {}",
+											lsp_node_id,
+											e
+										);
+										continue;
+									}
+									order_poll_liquidity_source.lsps1_poll_orders().await;
+								}
+						}
+					}
+				});
+			}
 		}
 
 		*runtime_lock = Some(runtime);
@@ -704,6 +1000,15 @@ impl Node {
 			);
 		}
 
+		if let Err(e) = self.kv_store.remove(
+			io::NODE_RUNNING_MARKER_PRIMARY_NAMESPACE,
+			io::NODE_RUNNING_MARKER_SECONDARY_NAMESPACE,
+			io::NODE_RUNNING_MARKER_KEY,
+			false,
+		) {
+			log_error!(self.logger, "Failed to remove the store's in-use marker: {}", e);
+		}
+
 		log_info!(self.logger, "Shutdown complete.");
 		Ok(())
 	}
@@ -726,6 +1031,8 @@ impl Node {
 			locked_node_metrics.latest_node_announcement_broadcast_timestamp;
 		let latest_channel_monitor_archival_height =
 			locked_node_metrics.latest_channel_monitor_archival_height;
+		let latest_payment_archival_timestamp =
+			locked_node_metrics.latest_payment_archival_timestamp;
 
 		NodeStatus {
 			is_running,
@@ -737,6 +1044,7 @@ impl Node {
 			latest_rgs_snapshot_timestamp,
 			latest_node_announcement_broadcast_timestamp,
 			latest_channel_monitor_archival_height,
+			latest_payment_archival_timestamp,
 		}
 	}
 
@@ -826,6 +1134,8 @@ impl Node {
 		Bolt11Payment::new(
 			Arc::clone(&self.runtime),
 			Arc::clone(&self.channel_manager),
+			Arc::clone(&self.router),
+			Arc::clone(&self.scorer),
 			Arc::clone(&self.connection_manager),
 			self.liquidity_source.clone(),
 			Arc::clone(&self.payment_store),
@@ -843,6 +1153,8 @@ impl Node {
 		Arc::new(Bolt11Payment::new(
 			Arc::clone(&self.runtime),
 			Arc::clone(&self.channel_manager),
+			Arc::clone(&self.router),
+			Arc::clone(&self.scorer),
 			Arc::clone(&self.connection_manager),
 			self.liquidity_source.clone(),
 			Arc::clone(&self.payment_store),
@@ -860,6 +1172,9 @@ impl Node {
 		Bolt12Payment::new(
 			Arc::clone(&self.runtime),
 			Arc::clone(&self.channel_manager),
+			Arc::clone(&self.router),
+			Arc::clone(&self.scorer),
+			Arc::clone(&self.network_graph),
 			Arc::clone(&self.payment_store),
 			Arc::clone(&self.logger),
 		)
@@ -873,6 +1188,9 @@ impl Node {
 		Arc::new(Bolt12Payment::new(
 			Arc::clone(&self.runtime),
 			Arc::clone(&self.channel_manager),
+			Arc::clone(&self.router),
+			Arc::clone(&self.scorer),
+			Arc::clone(&self.network_graph),
 			Arc::clone(&self.payment_store),
 			Arc::clone(&self.logger),
 		))
@@ -884,6 +1202,8 @@ impl Node {
 		SpontaneousPayment::new(
 			Arc::clone(&self.runtime),
 			Arc::clone(&self.channel_manager),
+			Arc::clone(&self.router),
+			Arc::clone(&self.scorer),
 			Arc::clone(&self.keys_manager),
 			Arc::clone(&self.payment_store),
 			Arc::clone(&self.config),
@@ -897,6 +1217,8 @@ impl Node {
 		Arc::new(SpontaneousPayment::new(
 			Arc::clone(&self.runtime),
 			Arc::clone(&self.channel_manager),
+			Arc::clone(&self.router),
+			Arc::clone(&self.scorer),
 			Arc::clone(&self.keys_manager),
 			Arc::clone(&self.payment_store),
 			Arc::clone(&self.config),
@@ -940,6 +1262,7 @@ impl Node {
 			self.onchain_payment().into(),
 			self.bolt11_payment().into(),
 			self.bolt12_payment().into(),
+			Arc::clone(&self.hrn_resolver),
 			Arc::clone(&self.config),
 			Arc::clone(&self.logger),
 		)
@@ -957,11 +1280,56 @@ impl Node {
 			self.onchain_payment(),
 			self.bolt11_payment(),
 			self.bolt12_payment(),
+			Arc::clone(&self.hrn_resolver),
+			Arc::clone(&self.config),
+			Arc::clone(&self.logger),
+		))
+	}
+
+	/// Returns a payment handler allowing to pay [LNURL-pay] requests and [Lightning Addresses],
+	/// and to withdraw funds from [LNURL-withdraw] services.
+	///
+	/// [LNURL-pay]: https://github.com/lnurl/luds/blob/luds/06.md
+	/// [Lightning Addresses]: https://github.com/lnurl/luds/blob/luds/16.md
+	/// [LNURL-withdraw]: https://github.com/lnurl/luds/blob/luds/03.md
+	#[cfg(not(feature = "uniffi"))]
+	pub fn lnurl(&self) -> LnurlPayment {
+		LnurlPayment::new(
+			Arc::clone(&self.runtime),
+			self.bolt11_payment().into(),
+			Arc::clone(&self.config),
+			Arc::clone(&self.logger),
+		)
+	}
+
+	/// Returns a payment handler allowing to pay [LNURL-pay] requests and [Lightning Addresses],
+	/// and to withdraw funds from [LNURL-withdraw] services.
+	///
+	/// [LNURL-pay]: https://github.com/lnurl/luds/blob/luds/06.md
+	/// [Lightning Addresses]: https://github.com/lnurl/luds/blob/luds/16.md
+	/// [LNURL-withdraw]: https://github.com/lnurl/luds/blob/luds/03.md
+	#[cfg(feature = "uniffi")]
+	pub fn lnurl(&self) -> Arc<LnurlPayment> {
+		Arc::new(LnurlPayment::new(
+			Arc::clone(&self.runtime),
+			self.bolt11_payment(),
 			Arc::clone(&self.config),
 			Arc::clone(&self.logger),
 		))
 	}
 
+	/// Resolves the given [BIP 353] human-readable name (e.g., `₿user@domain`) to the [BIP 21] URI
+	/// it points to.
+	///
+	/// The returned URI can be paid via [`UnifiedQrPayment::send`], or the name can be paid
+	/// directly via [`UnifiedQrPayment::send_to_hrn`].
+	///
+	/// [BIP 353]: https://github.com/bitcoin/bips/blob/master/bip-0353.mediawiki
+	/// [BIP 21]: https://github.com/bitcoin/bips/blob/master/bip-0021.mediawiki
+	pub fn resolve_hrn(&self, hrn: String) -> Result<String, Error> {
+		hrn::resolve_hrn(&*self.hrn_resolver, &hrn, &self.logger)
+	}
+
 	/// Returns a liquidity handler allowing to request channels via the [bLIP-51 / LSPS1] protocol.
 	///
 	/// [bLIP-51 / LSPS1]: https://github.com/lightning/blips/blob/master/blip-0051.md
@@ -972,6 +1340,8 @@ impl Node {
 			Arc::clone(&self.wallet),
 			Arc::clone(&self.connection_manager),
 			self.liquidity_source.clone(),
+			self.bolt11_payment().into(),
+			self.onchain_payment().into(),
 			Arc::clone(&self.logger),
 		)
 	}
@@ -986,10 +1356,36 @@ impl Node {
 			Arc::clone(&self.wallet),
 			Arc::clone(&self.connection_manager),
 			self.liquidity_source.clone(),
+			self.bolt11_payment(),
+			self.onchain_payment(),
 			Arc::clone(&self.logger),
 		))
 	}
 
+	/// Exports an encrypted static backup of all our channels.
+	///
+	/// The backup holds the latest state of our channel monitors as well as the addresses of our
+	/// peers and is encrypted with a key derived from the node's seed. If the storage directory is
+	/// lost, it can be handed to [`Builder::restore_from_channel_backup`] to recover the channels'
+	/// funds by having the peers force-close them.
+	///
+	/// As the backup only reflects the channels' state at the time of the export, it should be
+	/// re-exported whenever channels are opened or closed.
+	pub fn export_channel_backup(&self) -> Vec<u8> {
+		let monitors = self
+			.chain_monitor
+			.list_monitors()
+			.into_iter()
+			.filter_map(|(funding_txo, _)| {
+				self.chain_monitor.get_monitor(funding_txo).ok().map(|monitor| monitor.encode())
+			})
+			.collect();
+		let peers = self.peer_store.list_peers();
+
+		let encryption_key = derive_channel_backup_key(&self.keys_manager.get_node_secret_key());
+		ChannelBackup { monitors, peers }.encrypt(encryption_key)
+	}
+
 	/// Retrieve a list of known channels.
 	pub fn list_channels(&self) -> Vec<ChannelDetails> {
 		self.channel_manager.list_channels().into_iter().map(|c| c.into()).collect()
@@ -1056,7 +1452,7 @@ impl Node {
 	fn open_channel_inner(
 		&self, node_id: PublicKey, address: SocketAddress, channel_amount_sats: u64,
 		push_to_counterparty_msat: Option<u64>, channel_config: Option<ChannelConfig>,
-		announce_for_forwarding: bool,
+		announce_for_forwarding: bool, utxos: Option<Vec<OutPoint>>,
 	) -> Result<UserChannelId, Error> {
 		let rt_lock = self.runtime.read().unwrap();
 		if rt_lock.is_none() {
@@ -1075,15 +1471,40 @@ impl Node {
 		let spendable_amount_sats =
 			self.wallet.get_spendable_amount_sats(cur_anchor_reserve_sats).unwrap_or(0);
 
+		// If the funding transaction is provided externally, our wallet doesn't need to cover the
+		// channel value.
+		let wallet_funding_amount_sats =
+			if self.config.use_external_channel_funding { 0 } else { channel_amount_sats };
+
 		// Fail early if we have less than the channel value available.
-		if spendable_amount_sats < channel_amount_sats {
+		if spendable_amount_sats < wallet_funding_amount_sats {
 			log_error!(self.logger,
 				"Unable to create channel due to insufficient funds. Available: {}sats, Required: {}sats",
-				spendable_amount_sats, channel_amount_sats
+				spendable_amount_sats, wallet_funding_amount_sats
 			);
 			return Err(Error::InsufficientFunds);
 		}
 
+		if self.config.use_external_channel_funding && utxos.is_some() {
+			log_error!(
+				self.logger,
+				"Unable to select UTXOs for a channel that will be funded externally."
+			);
+			return Err(Error::InvalidUtxo);
+		}
+
+		// Fail early if the selected UTXOs can't be spent or don't cover the channel value.
+		if let Some(utxos) = &utxos {
+			let selected_amount_sats = self.wallet.check_utxo_selection(utxos)?.to_sat();
+			if selected_amount_sats < channel_amount_sats {
+				log_error!(self.logger,
+					"Unable to create channel due to insufficient funds in selected UTXOs. Available: {}sats, Required: {}sats",
+					selected_amount_sats, channel_amount_sats
+				);
+				return Err(Error::InsufficientFunds);
+			}
+		}
+
 		// We need to use our main runtime here as a local runtime might not be around to poll
 		// connection futures going forward.
 		tokio::task::block_in_place(move || {
@@ -1098,7 +1519,7 @@ impl Node {
 			.peer_by_node_id(&node_id)
 			.ok_or(Error::ConnectionFailed)?
 			.init_features;
-		let required_funds_sats = channel_amount_sats
+		let required_funds_sats = wallet_funding_amount_sats
 			+ self.config.anchor_channels_config.as_ref().map_or(0, |c| {
 				if init_features.requires_anchors_zero_fee_htlc_tx()
 					&& !c.trusted_peers_no_reserve.contains(&node_id)
@@ -1132,6 +1553,10 @@ impl Node {
 		let push_msat = push_to_counterparty_msat.unwrap_or(0);
 		let user_channel_id: u128 = rand::thread_rng().gen::<u128>();
 
+		if let Some(utxos) = utxos {
+			self.wallet.set_funding_utxos(user_channel_id, utxos);
+		}
+
 		match self.channel_manager.create_channel(
 			peer_info.node_id,
 			channel_amount_sats,
@@ -1151,6 +1576,7 @@ impl Node {
 			},
 			Err(e) => {
 				log_error!(self.logger, "Failed to initiate channel creation: {:?}", e);
+				self.wallet.take_funding_utxos(user_channel_id);
 				Err(Error::ChannelCreationFailed)
 			},
 		}
@@ -1170,12 +1596,20 @@ impl Node {
 	/// [`AnchorChannelsConfig::per_channel_reserve_sats`] is available and will be retained before
 	/// opening the channel.
 	///
+	/// If `utxos` is set, only the given UTXOs will be spent by the funding transaction. Note that
+	/// they need to cover at least `channel_amount_sats` and can't be locked.
+	///
+	/// If [`Config::use_external_channel_funding`] is set, the funding transaction won't be created
+	/// by our on-chain wallet. Instead, an [`Event::FundingPsbtReady`] will be emitted, and the
+	/// funding needs to be completed via [`Node::finalize_channel_funding`].
+	///
 	/// Returns a [`UserChannelId`] allowing to locally keep track of the channel.
 	///
 	/// [`AnchorChannelsConfig::per_channel_reserve_sats`]: crate::config::AnchorChannelsConfig::per_channel_reserve_sats
 	pub fn open_channel(
 		&self, node_id: PublicKey, address: SocketAddress, channel_amount_sats: u64,
 		push_to_counterparty_msat: Option<u64>, channel_config: Option<ChannelConfig>,
+		utxos: Option<Vec<OutPoint>>,
 	) -> Result<UserChannelId, Error> {
 		self.open_channel_inner(
 			node_id,
@@ -1184,6 +1618,7 @@ impl Node {
 			push_to_counterparty_msat,
 			channel_config,
 			false,
+			utxos,
 		)
 	}
 
@@ -1205,12 +1640,20 @@ impl Node {
 	/// [`AnchorChannelsConfig::per_channel_reserve_sats`] is available and will be retained before
 	/// opening the channel.
 	///
+	/// If `utxos` is set, only the given UTXOs will be spent by the funding transaction. Note that
+	/// they need to cover at least `channel_amount_sats` and can't be locked.
+	///
+	/// If [`Config::use_external_channel_funding`] is set, the funding transaction won't be created
+	/// by our on-chain wallet. Instead, an [`Event::FundingPsbtReady`] will be emitted, and the
+	/// funding needs to be completed via [`Node::finalize_channel_funding`].
+	///
 	/// Returns a [`UserChannelId`] allowing to locally keep track of the channel.
 	///
 	/// [`AnchorChannelsConfig::per_channel_reserve_sats`]: crate::config::AnchorChannelsConfig::per_channel_reserve_sats
 	pub fn open_announced_channel(
 		&self, node_id: PublicKey, address: SocketAddress, channel_amount_sats: u64,
 		push_to_counterparty_msat: Option<u64>, channel_config: Option<ChannelConfig>,
+		utxos: Option<Vec<OutPoint>>,
 	) -> Result<UserChannelId, Error> {
 		if let Err(err) = may_announce_channel(&self.config) {
 			log_error!(self.logger, "Failed to open announced channel as the node hasn't been sufficiently configured to act as a forwarding node: {}", err);
@@ -1224,9 +1667,193 @@ impl Node {
 			push_to_counterparty_msat,
 			channel_config,
 			true,
+			utxos,
 		)
 	}
 
+	/// Provides the externally funded and signed funding transaction of a channel for which an
+	/// [`Event::FundingPsbtReady`] was emitted.
+	///
+	/// The given `signed_psbt` needs to contain the channel's funding output and needs to be fully
+	/// signed, spending only SegWit outputs. Once handed to LDK, the funding transaction will be
+	/// broadcast as soon as the counterparty provided their signature for our initial commitment
+	/// transaction, after which an [`Event::ChannelPending`] will be emitted.
+	///
+	/// As channels are only persisted once funded, pending fundings don't survive a restart. Any
+	/// channel still awaiting its funding transaction when the node stopped is abandoned on the
+	/// next startup, for which an [`Event::ChannelClosed`] is emitted. The funding transaction of
+	/// such a channel must not be published.
+	///
+	/// **Note:** This is only applicable if [`Config::use_external_channel_funding`] is set.
+	pub fn finalize_channel_funding(
+		&self, user_channel_id: &UserChannelId, signed_psbt: Psbt,
+	) -> Result<(), Error> {
+		let rt_lock = self.runtime.read().unwrap();
+		if rt_lock.is_none() {
+			return Err(Error::NotRunning);
+		}
+
+		let pending_funding =
+			self.pending_channel_fundings.get(user_channel_id.0).ok_or_else(|| {
+				log_error!(
+					self.logger,
+					"Failed to finalize channel funding: no pending funding for channel {}",
+					user_channel_id.0
+				);
+				Error::InvalidChannelId
+			})?;
+
+		// We leave checking the fee rate to the external wallet, which has all input values at hand.
+		let funding_tx = signed_psbt.extract_tx_unchecked_fee_rate();
+
+		if !funding_tx.output.iter().any(|o| {
+			o.script_pubkey == pending_funding.output_script
+				&& o.value.to_sat() == pending_funding.channel_value_sats
+		}) {
+			log_error!(
+				self.logger,
+				"Failed to finalize channel funding: funding output for channel {} is missing",
+				user_channel_id.0
+			);
+			return Err(Error::InvalidPsbt);
+		}
+
+		if funding_tx.input.is_empty() || funding_tx.input.iter().any(|i| i.witness.is_empty()) {
+			log_error!(
+				self.logger,
+				"Failed to finalize channel funding: funding transaction for channel {} isn't fully signed or spends non-SegWit outputs",
+				user_channel_id.0
+			);
+			return Err(Error::InvalidPsbt);
+		}
+
+		let funding_txid = funding_tx.compute_txid();
+		match self.channel_manager.funding_transaction_generated(
+			pending_funding.temporary_channel_id,
+			pending_funding.counterparty_node_id,
+			funding_tx,
+		) {
+			Ok(()) => {
+				// We only drop the pending funding once LDK took over, so the user may retry
+				// finalizing it otherwise. Failing to persist the removal is fine, as we won't
+				// abandon the channel on startup if it made it by then.
+				if let Err(e) = self.pending_channel_fundings.remove(user_channel_id.0) {
+					log_error!(self.logger, "Failed to remove pending channel funding: {}", e);
+				}
+				log_info!(
+					self.logger,
+					"Provided funding transaction {} for channel {}",
+					funding_txid,
+					user_channel_id.0
+				);
+				Ok(())
+			},
+			Err(e) => {
+				log_error!(self.logger, "Failed to finalize channel funding: {:?}", e);
+				Err(Error::ChannelCreationFailed)
+			},
+		}
+	}
+
+	/// Accepts an inbound channel request for which an [`Event::InboundChannelRequest`] was
+	/// emitted.
+	///
+	/// Channels from peers listed in [`Config::trusted_peers_0conf`] will be accepted as
+	/// zero-confirmation channels.
+	///
+	/// Returns a [`UserChannelId`] allowing to locally keep track of the channel.
+	///
+	/// **Note:** This is only applicable if [`ChannelAcceptancePolicy::manual_acceptance`] is set.
+	///
+	/// [`ChannelAcceptancePolicy::manual_acceptance`]: crate::config::ChannelAcceptancePolicy::manual_acceptance
+	pub fn accept_inbound_channel(
+		&self, temporary_channel_id: &ChannelId, counterparty_node_id: PublicKey,
+	) -> Result<UserChannelId, Error> {
+		let rt_lock = self.runtime.read().unwrap();
+		if rt_lock.is_none() {
+			return Err(Error::NotRunning);
+		}
+
+		let user_channel_id: u128 = rand::thread_rng().gen::<u128>();
+		let allow_0conf = self.config.trusted_peers_0conf.contains(&counterparty_node_id);
+		let res = if allow_0conf {
+			self.channel_manager.accept_inbound_channel_from_trusted_peer_0conf(
+				temporary_channel_id,
+				&counterparty_node_id,
+				user_channel_id,
+			)
+		} else {
+			self.channel_manager.accept_inbound_channel(
+				temporary_channel_id,
+				&counterparty_node_id,
+				user_channel_id,
+			)
+		};
+
+		match res {
+			Ok(()) => {
+				log_info!(
+					self.logger,
+					"Accepting inbound{} channel from{} peer {}",
+					if allow_0conf { " 0conf" } else { "" },
+					if allow_0conf { " trusted" } else { "" },
+					counterparty_node_id,
+				);
+				Ok(UserChannelId(user_channel_id))
+			},
+			Err(e) => {
+				log_error!(
+					self.logger,
+					"Failed to accept inbound channel {} from peer {}: {:?}",
+					temporary_channel_id,
+					counterparty_node_id,
+					e
+				);
+				Err(Error::ChannelCreationFailed)
+			},
+		}
+	}
+
+	/// Rejects an inbound channel request for which an [`Event::InboundChannelRequest`] was
+	/// emitted.
+	///
+	/// **Note:** This is only applicable if [`ChannelAcceptancePolicy::manual_acceptance`] is set.
+	///
+	/// [`ChannelAcceptancePolicy::manual_acceptance`]: crate::config::ChannelAcceptancePolicy::manual_acceptance
+	pub fn reject_inbound_channel(
+		&self, temporary_channel_id: &ChannelId, counterparty_node_id: PublicKey,
+	) -> Result<(), Error> {
+		let rt_lock = self.runtime.read().unwrap();
+		if rt_lock.is_none() {
+			return Err(Error::NotRunning);
+		}
+
+		self.channel_manager
+			.force_close_without_broadcasting_txn(
+				temporary_channel_id,
+				&counterparty_node_id,
+				"Channel request rejected".to_string(),
+			)
+			.map_err(|e| {
+				log_error!(
+					self.logger,
+					"Failed to reject inbound channel {} from peer {}: {:?}",
+					temporary_channel_id,
+					counterparty_node_id,
+					e
+				);
+				Error::ChannelClosingFailed
+			})?;
+
+		log_info!(
+			self.logger,
+			"Rejected inbound channel {} from peer {}",
+			temporary_channel_id,
+			counterparty_node_id
+		);
+		Ok(())
+	}
+
 	/// Manually sync the LDK and BDK wallets with the current chain state and update the fee rate
 	/// cache.
 	///
@@ -1468,6 +2095,9 @@ impl Node {
 	/// # let node = builder.build().unwrap();
 	/// node.list_payments_with_filter(|p| p.direction == PaymentDirection::Outbound);
 	/// ```
+	///
+	/// Note that this reads all payments from the underlying store. Nodes with a large payment
+	/// history should prefer [`Node::list_payments_page`].
 	pub fn list_payments_with_filter<F: FnMut(&&PaymentDetails) -> bool>(
 		&self, f: F,
 	) -> Vec<PaymentDetails> {
@@ -1479,6 +2109,165 @@ impl Node {
 		self.payment_store.list_filter(|_| true)
 	}
 
+	/// Moves all payments with one of the given `statuses` that were last updated before
+	/// `before_timestamp` to the payment archive.
+	///
+	/// Archived payments are no longer returned by [`Node::list_payments`] and related methods,
+	/// but can still be retrieved via [`Node::archived_payment`] and
+	/// [`Node::list_archived_payments`].
+	///
+	/// Payments that are still [`PaymentStatus::Pending`] or [`PaymentStatus::Held`] are never
+	/// archived, as they may still be updated. On-chain payments that were fee-bumped are never
+	/// archived either.
+	///
+	/// Returns the number of archived payments.
+	///
+	/// Please refer to [`Config::payment_retention`] to have payments be archived automatically.
+	///
+	/// [`PaymentStatus::Pending`]: crate::payment::PaymentStatus::Pending
+	/// [`PaymentStatus::Held`]: crate::payment::PaymentStatus::Held
+	pub fn prune_payments(
+		&self, before_timestamp: u64, statuses: Vec<PaymentStatus>,
+	) -> Result<u64, Error> {
+		let num_archived = self.payment_store.archive(
+			&self.archived_payment_store,
+			before_timestamp,
+			&statuses,
+		)?;
+		log_info!(
+			self.logger,
+			"Archived {} payments last updated before {}.",
+			num_archived,
+			before_timestamp
+		);
+		Ok(num_archived as u64)
+	}
+
+	/// Retrieve the details of a specific archived payment with the given id.
+	///
+	/// Returns `Some` if the payment was archived and `None` otherwise.
+	pub fn archived_payment(&self, payment_id: &PaymentId) -> Option<PaymentDetails> {
+		self.archived_payment_store.get(payment_id)
+	}
+
+	/// Retrieves all archived payments.
+	///
+	/// Note that this reads all archived payments from the underlying store.
+	pub fn list_archived_payments(&self) -> Vec<PaymentDetails> {
+		self.archived_payment_store.list_filter(|_| true)
+	}
+
+	/// Retrieves up to `limit` payments matching the given `query`, most recently updated first.
+	///
+	/// To retrieve the next page of results, call this method again with the
+	/// [`PaymentPage::next_cursor`] of the previous page.
+	///
+	/// Payments are looked up via an in-memory index, so only the returned payments are read from
+	/// the underlying store.
+	pub fn list_payments_page(
+		&self, query: PaymentQuery, cursor: Option<PaymentCursor>, limit: u32,
+	) -> PaymentPage {
+		self.payment_store.list_page(&query, cursor.as_ref(), limit as usize)
+	}
+
+	/// Retrieves the payments we forwarded that match the given `filter`, ordered by the time
+	/// they were completed at.
+	pub fn list_forwards(&self, filter: ForwardFilter) -> Vec<ForwardedPaymentDetails> {
+		let mut forwards = self.forwarding_store.list_filter(|f| filter.matches(f));
+		forwards.sort_by_key(|f| f.forwarded_at_timestamp);
+		forwards
+	}
+
+	/// Retrieves the JIT channels sold via our LSPS2 service, ordered by the time they were
+	/// bought at.
+	///
+	/// Will be empty if we're not configured as an LSPS2 liquidity provider via
+	/// [`Builder::set_liquidity_provider_lsps2`].
+	pub fn list_lsps2_sales(&self) -> Vec<LSPS2SaleDetails> {
+		let mut sales =
+			self.liquidity_source.as_ref().map(|l| l.list_lsps2_sales()).unwrap_or_default();
+		sales.sort_by_key(|s| s.created_at);
+		sales
+	}
+
+	/// Returns aggregated statistics, such as the fees earned in total and per channel, over the
+	/// payments we forwarded that match the given `filter`.
+	pub fn forwarding_summary(&self, filter: ForwardFilter) -> ForwardingSummary {
+		let forwards = self.forwarding_store.list_filter(|f| filter.matches(f));
+		payment::forwarding::summarize_forwards(&forwards)
+	}
+
+	/// Moves `amount_msat` of liquidity from the channel with `from_channel_id` to the channel
+	/// with `to_channel_id` by sending a circular payment to ourselves.
+	///
+	/// The payment leaves via `from_channel_id`, decreasing our outbound liquidity on it, and
+	/// returns via `to_channel_id`, increasing our outbound liquidity there. The route in between
+	/// is found using the node's router and scorer and is required to not exceed `max_fee_msat`
+	/// in routing fees, including the fee charged by our counterparty on `to_channel_id`.
+	///
+	/// The rebalance is tracked in the payment store as a [`PaymentKind::Rebalance`] payment
+	/// under the returned [`PaymentId`] and will result in an [`Event::PaymentSuccessful`] or
+	/// [`Event::PaymentFailed`] once it's resolved.
+	///
+	/// [`PaymentKind::Rebalance`]: crate::payment::PaymentKind::Rebalance
+	pub fn rebalance(
+		&self, from_channel_id: ChannelId, to_channel_id: ChannelId, amount_msat: u64,
+		max_fee_msat: u64,
+	) -> Result<PaymentId, Error> {
+		let rt_lock = self.runtime.read().unwrap();
+		if rt_lock.is_none() {
+			return Err(Error::NotRunning);
+		}
+
+		payment::send_rebalance(
+			from_channel_id,
+			to_channel_id,
+			amount_msat,
+			max_fee_msat,
+			&self.channel_manager,
+			&self.router,
+			&self.keys_manager,
+			&self.payment_store,
+			&self.logger,
+		)
+	}
+
+	/// Exports the node's payment history in the given `format`, e.g., for accounting purposes.
+	///
+	/// Besides all known payments, including the ones archived via [`Node::prune_payments`] or
+	/// [`Config::payment_retention`], the export includes the payments we forwarded, along with the
+	/// fees we earned, as well as any on-chain sweeps of funds from closed channels. The latter
+	/// are only recognized as such while they are tracked by the node, i.e., until they reached
+	/// sufficient confirmations, after which they are exported as regular on-chain payments.
+	///
+	/// Records are ordered by their timestamp, i.e., the time they were last updated at for
+	/// payments and the time they were completed at for forwards. The given `filter` allows to
+	/// restrict the export to a subset of records.
+	///
+	/// Note that this reads all archived payments from the underlying store.
+	///
+	/// This doesn't require the node to be running.
+	pub fn export_payments(
+		&self, format: PaymentExportFormat, filter: PaymentExportFilter,
+	) -> String {
+		let cur_timestamp = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap_or(Duration::from_secs(0))
+			.as_secs();
+		let mut payments = self.payment_store.list_filter(|_| true);
+		// As payments are archived before being removed, we might find them in both stores.
+		let payment_ids = payments.iter().map(|p| p.id).collect::<HashSet<_>>();
+		payments.extend(self.archived_payment_store.list_filter(|p| !payment_ids.contains(&p.id)));
+		payment::export::export_payments(
+			format,
+			&filter,
+			payments,
+			self.forwarding_store.list_filter(|_| true),
+			self.output_sweeper.tracked_spendable_outputs(),
+			cur_timestamp,
+		)
+	}
+
 	/// Retrieves a list of known peers.
 	pub fn list_peers(&self) -> Vec<PeerDetails> {
 		let mut peers = Vec::new();
@@ -1614,6 +2403,13 @@ pub struct NodeStatus {
 	///
 	/// Will be `None` if we haven't archived any monitors of closed channels yet.
 	pub latest_channel_monitor_archival_height: Option<u32>,
+	/// The timestamp, in seconds since start of the UNIX epoch, when we last archived payments
+	/// according to the configured [`PaymentRetentionConfig`].
+	///
+	/// Will be `None` if payments have never been archived automatically.
+	///
+	/// [`PaymentRetentionConfig`]: crate::config::PaymentRetentionConfig
+	pub latest_payment_archival_timestamp: Option<u64>,
 }
 
 /// Status fields that are persisted across restarts.
@@ -1625,6 +2421,7 @@ pub(crate) struct NodeMetrics {
 	latest_rgs_snapshot_timestamp: Option<u32>,
 	latest_node_announcement_broadcast_timestamp: Option<u64>,
 	latest_channel_monitor_archival_height: Option<u32>,
+	latest_payment_archival_timestamp: Option<u64>,
 }
 
 impl Default for NodeMetrics {
@@ -1636,6 +2433,7 @@ impl Default for NodeMetrics {
 			latest_rgs_snapshot_timestamp: None,
 			latest_node_announcement_broadcast_timestamp: None,
 			latest_channel_monitor_archival_height: None,
+			latest_payment_archival_timestamp: None,
 		}
 	}
 }
@@ -1647,6 +2445,7 @@ impl_writeable_tlv_based!(NodeMetrics, {
 	(6, latest_rgs_snapshot_timestamp, option),
 	(8, latest_node_announcement_broadcast_timestamp, option),
 	(10, latest_channel_monitor_archival_height, option),
+	(12, latest_payment_archival_timestamp, option),
 });
 
 pub(crate) fn total_anchor_channels_reserve_sats(
@@ -9,23 +9,53 @@
 
 use crate::chain::ChainSource;
 use crate::connection::ConnectionManager;
+use crate::data_store::{
+	DataStoreUpdateResult, StorableObject, StorableObjectId, StorableObjectUpdate,
+};
+use crate::event::EventQueue;
+use crate::fee_estimator::get_funding_target_for_num_blocks;
+use crate::hex_utils;
 use crate::logger::{log_debug, log_error, log_info, LdkLogger, Logger};
-use crate::types::{ChannelManager, KeysManager, LiquidityManager, PeerManager, Wallet};
+use crate::payment::store::{PaymentDetails, PaymentDirection, PaymentKind, PaymentStatus};
+use crate::payment::{Bolt11Payment, OnchainPayment, SendingParameters};
+use crate::types::{
+	ChannelManager, KeysManager, LSPS1OrderStore, LSPS1ServiceOrderStore, LSPS2SaleStore,
+	LiquidityManager, PaymentStore, PeerManager, UserChannelId, Wallet,
+};
+use crate::wallet::OnchainSendAmount;
 use crate::{total_anchor_channels_reserve_sats, Config, Error};
 
 use lightning::events::HTLCDestination;
-use lightning::ln::channelmanager::{InterceptId, MIN_FINAL_CLTV_EXPIRY_DELTA};
-use lightning::ln::msgs::SocketAddress;
+use lightning::ln::channelmanager::{
+	Bolt11InvoiceParameters, InterceptId, PaymentId, MIN_FINAL_CLTV_EXPIRY_DELTA,
+};
+use lightning::ln::msgs::{DecodeError, ErrorAction, LightningError, SocketAddress};
+use lightning::ln::peer_handler::CustomMessageHandler;
 use lightning::ln::types::ChannelId;
 use lightning::routing::router::{RouteHint, RouteHintHop};
-
-use lightning_invoice::{Bolt11Invoice, Bolt11InvoiceDescription, InvoiceBuilder, RoutingFees};
+use lightning::sign::EntropySource;
+use lightning::util::logger::Level;
+use lightning::util::ser::{Readable, Writeable, Writer};
+use lightning::{
+	_init_and_read_len_prefixed_tlv_fields, impl_writeable_tlv_based,
+	impl_writeable_tlv_based_enum, write_tlv_fields,
+};
+
+use lightning_invoice::{
+	Bolt11Invoice, Bolt11InvoiceDescription, Description, InvoiceBuilder, RoutingFees,
+};
 
 use lightning_liquidity::events::Event;
-use lightning_liquidity::lsps0::ser::RequestId;
+use lightning_liquidity::lsps0::msgs::{LSPS0Message, LSPS0Response, ListProtocolsResponse};
+use lightning_liquidity::lsps0::ser::{LSPSMessage, RawLSPSMessage, RequestId, ResponseError};
 use lightning_liquidity::lsps1::client::LSPS1ClientConfig as LdkLSPS1ClientConfig;
 use lightning_liquidity::lsps1::event::LSPS1ClientEvent;
-use lightning_liquidity::lsps1::msgs::{ChannelInfo, LSPS1Options, OrderId, OrderParameters};
+use lightning_liquidity::lsps1::msgs::{
+	Bolt11PaymentInfo as LdkBolt11PaymentInfo, ChannelInfo, CreateOrderRequest,
+	CreateOrderResponse, GetInfoResponse, GetOrderRequest, LSPS1Message, LSPS1Options,
+	LSPS1Response, OnchainPaymentInfo as LdkOnchainPaymentInfo, OrderId, OrderParameters,
+	OrderState, PaymentInfo as LdkPaymentInfo, PaymentState,
+};
 use lightning_liquidity::lsps2::client::LSPS2ClientConfig as LdkLSPS2ClientConfig;
 use lightning_liquidity::lsps2::event::{LSPS2ClientEvent, LSPS2ServiceEvent};
 use lightning_liquidity::lsps2::msgs::{OpeningFeeParams, RawOpeningFeeParams};
@@ -36,7 +66,9 @@ use lightning_liquidity::{LiquidityClientConfig, LiquidityServiceConfig};
 use lightning_types::payment::PaymentHash;
 
 use bitcoin::hashes::{sha256, Hash};
+use bitcoin::hex::FromHex;
 use bitcoin::secp256k1::{PublicKey, Secp256k1};
+use bitcoin::{FeeRate, Network, OutPoint, Txid};
 
 use tokio::sync::oneshot;
 
@@ -45,9 +77,11 @@ use chrono::{DateTime, Utc};
 use rand::Rng;
 
 use std::collections::HashMap;
+use std::fmt;
 use std::ops::Deref;
+use std::str::FromStr;
 use std::sync::{Arc, Mutex, RwLock};
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 const LIQUIDITY_REQUEST_TIMEOUT_SECS: u64 = 5;
 
@@ -55,6 +89,14 @@ const LSPS2_GETINFO_REQUEST_EXPIRY: Duration = Duration::from_secs(60 * 60 * 24)
 const LSPS2_CLIENT_TRUSTS_LSP_MODE: bool = true;
 const LSPS2_CHANNEL_CLTV_EXPIRY_DELTA: u32 = 72;
 
+const LSPS0_LIST_PROTOCOLS_METHOD_NAME: &str = "lsps0.list_protocols";
+const LSPS1_GET_INFO_METHOD_NAME: &str = "lsps1.get_info";
+const LSPS1_CREATE_ORDER_METHOD_NAME: &str = "lsps1.create_order";
+const LSPS1_GET_ORDER_METHOD_NAME: &str = "lsps1.get_order";
+const LSPS1_INVALID_PARAMS_ERROR_CODE: i32 = -32602;
+const LSPS1_INTERNAL_ERROR_CODE: i32 = -32603;
+const LSPS1_OPTION_MISMATCH_ERROR_CODE: i32 = 100;
+
 struct LSPS1Client {
 	lsp_node_id: PublicKey,
 	lsp_address: SocketAddress,
@@ -65,6 +107,8 @@ struct LSPS1Client {
 	pending_create_order_requests: Mutex<HashMap<RequestId, oneshot::Sender<LSPS1OrderStatus>>>,
 	pending_check_order_status_requests:
 		Mutex<HashMap<RequestId, oneshot::Sender<LSPS1OrderStatus>>>,
+	order_store: Arc<LSPS1OrderStore>,
+	event_queue: Arc<EventQueue<Arc<Logger>>>,
 }
 
 #[derive(Debug, Clone)]
@@ -90,9 +134,30 @@ pub(crate) struct LSPS2ClientConfig {
 	pub token: Option<String>,
 }
 
+struct LSPS1Service {
+	service_config: LSPS1ServiceConfig,
+	order_store: Arc<LSPS1ServiceOrderStore>,
+	pending_messages: Mutex<Vec<(PublicKey, RawLSPSMessage)>>,
+	// Serializes order state transitions, so that we never open more than one channel per order.
+	order_state_lock: Mutex<()>,
+}
+
 struct LSPS2Service {
 	service_config: LSPS2ServiceConfig,
 	ldk_service_config: LdkLSPS2ServiceConfig,
+	sale_store: Arc<LSPS2SaleStore>,
+	// Serializes sale state transitions, so that we never exceed our configured limits.
+	sale_state_lock: Mutex<()>,
+	// The sales keyed by their intercept short channel id, allowing us to look up the sale of
+	// an intercepted payment without iterating all sales.
+	sales_by_intercept_scid: Mutex<HashMap<u64, UserChannelId>>,
+}
+
+impl LSPS2Service {
+	fn sale_for_intercept_scid(&self, intercept_scid: u64) -> Option<LSPS2SaleDetails> {
+		let user_channel_id = *self.sales_by_intercept_scid.lock().unwrap().get(&intercept_scid)?;
+		self.sale_store.get(&user_channel_id)
+	}
 }
 
 /// Represents the configuration of the LSPS2 service.
@@ -131,6 +196,606 @@ pub struct LSPS2ServiceConfig {
 	pub min_payment_size_msat: u64,
 	/// The maximum payment size that we will accept when opening a channel.
 	pub max_payment_size_msat: u64,
+	/// The maximum number of channel purchases we accept from a single client within an hour.
+	///
+	/// If unset, we don't limit the rate of purchases.
+	pub max_client_requests_per_hour: Option<u32>,
+	/// The maximum number of channel purchases of a single client that may be outstanding at any
+	/// time, i.e., that are not yet completed, failed, or expired.
+	///
+	/// If unset, we don't limit the number of outstanding purchases.
+	pub max_pending_requests_per_client: Option<u32>,
+	/// The maximum total size, in satoshis, of the JIT channels we're opening but haven't
+	/// received our opening fee for yet.
+	///
+	/// Payments to a JIT channel that would exceed this budget are failed back.
+	///
+	/// If unset, we open channels as long as we have sufficient on-chain funds available.
+	pub max_outstanding_liquidity_sat: Option<u64>,
+}
+
+impl LSPS2ServiceConfig {
+	// Checks whether a client with the given previous purchases may buy another channel.
+	fn check_client_request(
+		&self, client_sales: &[LSPS2SaleDetails], cur_timestamp: u64,
+	) -> Result<(), &'static str> {
+		if let Some(max_requests) = self.max_client_requests_per_hour {
+			let recent_requests = client_sales
+				.iter()
+				.filter(|s| s.created_at.saturating_add(3600) > cur_timestamp)
+				.count();
+			if recent_requests >= max_requests as usize {
+				return Err("too many requests within the last hour");
+			}
+		}
+		if let Some(max_pending_requests) = self.max_pending_requests_per_client {
+			let pending_requests =
+				client_sales.iter().filter(|s| s.is_pending(cur_timestamp)).count();
+			if pending_requests >= max_pending_requests as usize {
+				return Err("too many pending requests");
+			}
+		}
+		Ok(())
+	}
+
+	// Returns the size, in satoshis, of the channel we open to forward the given amount.
+	fn channel_amount_sat(&self, amt_to_forward_msat: u64) -> u64 {
+		let over_provisioning_msat =
+			(amt_to_forward_msat * self.channel_over_provisioning_ppm as u64) / 1_000_000;
+		(amt_to_forward_msat + over_provisioning_msat) / 1000
+	}
+}
+
+/// Represents the configuration of the LSPS1 service.
+///
+/// See [bLIP-51 / LSPS1] for more information.
+///
+/// [bLIP-51 / LSPS1]: https://github.com/lightning/blips/blob/master/blip-0051.md
+#[derive(Debug, Clone)]
+pub struct LSPS1ServiceConfig {
+	/// A token we may require to be sent by the clients.
+	///
+	/// If set, only requests matching this token will be accepted.
+	pub require_token: Option<String>,
+	/// Indicates whether the LSPS service will be announced via the gossip network.
+	pub advertise_service: bool,
+	/// The minimum size of the channels we sell, i.e., the sum of the LSP- and client-side
+	/// balances.
+	pub min_channel_size_sat: u64,
+	/// The maximum size of the channels we sell, i.e., the sum of the LSP- and client-side
+	/// balances.
+	pub max_channel_size_sat: u64,
+	/// The maximum balance the client may purchase on its side of the channel.
+	///
+	/// Any such balance is paid for by the client as part of the order and pushed to it when the
+	/// channel is opened. Set this to `0` to only sell inbound liquidity.
+	pub max_client_balance_sat: u64,
+	/// The maximum number of blocks we promise to keep a sold channel open for.
+	pub max_channel_expiry_blocks: u32,
+	/// The minimum number of confirmations the client may require for the funding transaction
+	/// before the channel is considered ready.
+	pub min_required_channel_confirmations: u16,
+	/// The minimum number of blocks within which we promise the funding transaction to confirm.
+	///
+	/// The fee rate of the funding transaction is chosen based on the number of blocks requested
+	/// by the client.
+	pub min_funding_confirms_within_blocks: u16,
+	/// The fixed part of the fee we charge for opening a channel.
+	pub channel_opening_fee_base_sat: u64,
+	/// The part of the fee we charge for opening a channel that is proportional to the LSP-side
+	/// balance of the channel, in parts-per-million.
+	pub channel_opening_fee_ppm: u32,
+	/// Indicates whether clients may pay for their orders via a BOLT11 invoice.
+	pub accept_bolt11_payments: bool,
+	/// Indicates whether clients may pay for their orders via an on-chain payment.
+	pub accept_onchain_payments: bool,
+	/// The number of confirmations an on-chain payment needs before we consider the order paid.
+	pub min_onchain_payment_confirmations: u16,
+	/// The time, in seconds, after which orders that haven't been paid expire.
+	pub order_expiry_secs: u32,
+}
+
+impl LSPS1ServiceConfig {
+	fn supported_options(&self) -> LSPS1Options {
+		LSPS1Options {
+			min_required_channel_confirmations: self.min_required_channel_confirmations,
+			min_funding_confirms_within_blocks: self.min_funding_confirms_within_blocks,
+			supports_zero_channel_reserve: false,
+			max_channel_expiry_blocks: self.max_channel_expiry_blocks,
+			min_initial_client_balance_sat: 0,
+			max_initial_client_balance_sat: self.max_client_balance_sat,
+			min_initial_lsp_balance_sat: 0,
+			max_initial_lsp_balance_sat: self.max_channel_size_sat,
+			min_channel_balance_sat: self.min_channel_size_sat,
+			max_channel_balance_sat: self.max_channel_size_sat,
+		}
+	}
+
+	// Returns a description of why the given order is unsupported, if it is.
+	fn check_order(&self, order: &OrderParameters) -> Result<(), &'static str> {
+		let channel_size_sat = order.lsp_balance_sat.saturating_add(order.client_balance_sat);
+		if channel_size_sat < self.min_channel_size_sat
+			|| channel_size_sat > self.max_channel_size_sat
+		{
+			return Err("channel size out of range");
+		}
+		if order.client_balance_sat > self.max_client_balance_sat {
+			return Err("client balance out of range");
+		}
+		if order.channel_expiry_blocks > self.max_channel_expiry_blocks {
+			return Err("channel expiry out of range");
+		}
+		if order.required_channel_confirmations < self.min_required_channel_confirmations {
+			return Err("required channel confirmations out of range");
+		}
+		if order.funding_confirms_within_blocks < self.min_funding_confirms_within_blocks {
+			return Err("funding confirmation target out of range");
+		}
+		Ok(())
+	}
+
+	fn fee_total_sat(&self, order: &OrderParameters) -> u64 {
+		let proportional_fee_sat =
+			((order.lsp_balance_sat as u128 * self.channel_opening_fee_ppm as u128 + 999_999)
+				/ 1_000_000) as u64;
+		self.channel_opening_fee_base_sat.saturating_add(proportional_fee_sat)
+	}
+}
+
+/// The identifier of a channel order placed with our LSPS1 service.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct LSPS1ServiceOrderId(pub [u8; 32]);
+
+impl LSPS1ServiceOrderId {
+	fn to_order_id(self) -> OrderId {
+		OrderId(hex_utils::to_string(&self.0))
+	}
+
+	fn from_order_id(order_id: &OrderId) -> Option<Self> {
+		<[u8; 32]>::from_hex(&order_id.0).ok().map(Self)
+	}
+}
+
+impl Writeable for LSPS1ServiceOrderId {
+	fn write<W: Writer>(&self, writer: &mut W) -> Result<(), lightning::io::Error> {
+		self.0.write(writer)
+	}
+}
+
+impl Readable for LSPS1ServiceOrderId {
+	fn read<R: lightning::io::Read>(reader: &mut R) -> Result<Self, DecodeError> {
+		Ok(Self(Readable::read(reader)?))
+	}
+}
+
+impl StorableObjectId for LSPS1ServiceOrderId {
+	fn encode_to_hex_str(&self) -> String {
+		hex_utils::to_string(&self.0)
+	}
+}
+
+/// The state of a channel order placed with our LSPS1 service.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum LSPS1ServiceOrderState {
+	/// We're waiting for the client to pay for the order.
+	AwaitingPayment,
+	/// The order was paid and we're about to open the channel.
+	Paid,
+	/// We initiated opening the channel.
+	ChannelOpening { user_channel_id: u128 },
+	/// We published the funding transaction of the channel.
+	Completed { user_channel_id: u128, funding_txo: OutPoint, funded_at: u64 },
+	/// The order expired before it was paid.
+	Failed,
+	/// The order expired before it was paid in full and we refunded the on-chain payments we
+	/// received for it.
+	Refunded { txid: Txid },
+}
+
+impl_writeable_tlv_based_enum!(LSPS1ServiceOrderState,
+	(0, AwaitingPayment) => {},
+	(2, Paid) => {},
+	(4, ChannelOpening) => {
+		(0, user_channel_id, required),
+	},
+	(6, Completed) => {
+		(0, user_channel_id, required),
+		(2, funding_txo, required),
+		(4, funded_at, required),
+	},
+	(8, Failed) => {},
+	(10, Refunded) => {
+		(0, txid, required),
+	}
+);
+
+/// A channel order placed with our LSPS1 service.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct LSPS1ServiceOrder {
+	pub id: LSPS1ServiceOrderId,
+	pub counterparty_node_id: PublicKey,
+	pub lsp_balance_sat: u64,
+	pub client_balance_sat: u64,
+	pub required_channel_confirmations: u16,
+	pub funding_confirms_within_blocks: u16,
+	pub channel_expiry_blocks: u32,
+	pub token: Option<String>,
+	pub announce_channel: bool,
+	pub fee_total_sat: u64,
+	pub created_at: u64,
+	pub expires_at: u64,
+	pub bolt11_invoice: Option<String>,
+	pub payment_hash: Option<PaymentHash>,
+	pub onchain_address: Option<String>,
+	pub min_onchain_payment_confirmations: Option<u16>,
+	pub refund_onchain_address: Option<String>,
+	pub state: LSPS1ServiceOrderState,
+}
+
+impl_writeable_tlv_based!(LSPS1ServiceOrder, {
+	(0, id, required),
+	(2, counterparty_node_id, required),
+	(4, lsp_balance_sat, required),
+	(6, client_balance_sat, required),
+	(8, required_channel_confirmations, required),
+	(10, funding_confirms_within_blocks, required),
+	(12, channel_expiry_blocks, required),
+	(14, token, option),
+	(16, announce_channel, required),
+	(18, fee_total_sat, required),
+	(20, created_at, required),
+	(22, expires_at, required),
+	(24, bolt11_invoice, option),
+	(26, payment_hash, option),
+	(28, onchain_address, option),
+	(30, min_onchain_payment_confirmations, option),
+	(32, refund_onchain_address, option),
+	(34, state, required),
+});
+
+impl LSPS1ServiceOrder {
+	fn order_total_sat(&self) -> u64 {
+		self.fee_total_sat.saturating_add(self.client_balance_sat)
+	}
+
+	fn order_params(&self) -> OrderParameters {
+		OrderParameters {
+			lsp_balance_sat: self.lsp_balance_sat,
+			client_balance_sat: self.client_balance_sat,
+			required_channel_confirmations: self.required_channel_confirmations,
+			funding_confirms_within_blocks: self.funding_confirms_within_blocks,
+			channel_expiry_blocks: self.channel_expiry_blocks,
+			token: self.token.clone(),
+			announce_channel: self.announce_channel,
+		}
+	}
+
+	fn to_response(&self) -> CreateOrderResponse {
+		let order_state = match self.state {
+			LSPS1ServiceOrderState::AwaitingPayment
+			| LSPS1ServiceOrderState::Paid
+			| LSPS1ServiceOrderState::ChannelOpening { .. } => OrderState::Created,
+			LSPS1ServiceOrderState::Completed { .. } => OrderState::Completed,
+			LSPS1ServiceOrderState::Failed | LSPS1ServiceOrderState::Refunded { .. } => {
+				OrderState::Failed
+			},
+		};
+		let payment_state = match self.state {
+			LSPS1ServiceOrderState::AwaitingPayment | LSPS1ServiceOrderState::Failed => {
+				PaymentState::ExpectPayment
+			},
+			LSPS1ServiceOrderState::Refunded { .. } => PaymentState::Refunded,
+			_ => PaymentState::Paid,
+		};
+		let expires_at = timestamp_to_datetime(self.expires_at);
+
+		let bolt11 =
+			self.bolt11_invoice.as_ref().and_then(|i| Bolt11Invoice::from_str(i).ok()).map(
+				|invoice| LdkBolt11PaymentInfo {
+					state: payment_state.clone(),
+					expires_at,
+					fee_total_sat: self.fee_total_sat,
+					order_total_sat: self.order_total_sat(),
+					invoice,
+				},
+			);
+		let onchain = self.onchain_address.as_ref().and_then(|a| parse_address(a)).map(|address| {
+			LdkOnchainPaymentInfo {
+				state: payment_state.clone(),
+				expires_at,
+				fee_total_sat: self.fee_total_sat,
+				order_total_sat: self.order_total_sat(),
+				address,
+				min_onchain_payment_confirmations: self.min_onchain_payment_confirmations,
+				// We never accept 0-conf payments, but need to give some value here.
+				min_fee_for_0conf: FeeRate::from_sat_per_kwu(u32::MAX as u64),
+				refund_onchain_address: self
+					.refund_onchain_address
+					.as_ref()
+					.and_then(|a| parse_address(a)),
+			}
+		});
+
+		let channel = match self.state {
+			LSPS1ServiceOrderState::Completed { funding_txo, funded_at, .. } => {
+				// We assume an average block time of ten minutes.
+				let lease_secs = self.channel_expiry_blocks as u64 * 600;
+				Some(ChannelInfo {
+					funded_at: timestamp_to_datetime(funded_at),
+					funding_outpoint: funding_txo,
+					expires_at: timestamp_to_datetime(funded_at.saturating_add(lease_secs)),
+				})
+			},
+			_ => None,
+		};
+
+		CreateOrderResponse {
+			order_id: self.id.to_order_id(),
+			order: self.order_params(),
+			created_at: timestamp_to_datetime(self.created_at),
+			order_state,
+			payment: LdkPaymentInfo { bolt11, onchain },
+			channel,
+		}
+	}
+}
+
+/// Updates the state of an [`LSPS1ServiceOrder`].
+pub(crate) struct LSPS1ServiceOrderUpdate {
+	pub id: LSPS1ServiceOrderId,
+	pub state: Option<LSPS1ServiceOrderState>,
+}
+
+impl StorableObject for LSPS1ServiceOrder {
+	type Id = LSPS1ServiceOrderId;
+	type Update = LSPS1ServiceOrderUpdate;
+
+	fn id(&self) -> Self::Id {
+		self.id
+	}
+
+	fn update(&mut self, update: &Self::Update) -> bool {
+		debug_assert_eq!(self.id, update.id, "We should only ever update orders with the same id");
+
+		let mut updated = false;
+		if let Some(state) = update.state.as_ref() {
+			if *state != self.state {
+				self.state = state.clone();
+				updated = true;
+			}
+		}
+		updated
+	}
+
+	fn to_update(&self) -> Self::Update {
+		LSPS1ServiceOrderUpdate { id: self.id, state: Some(self.state.clone()) }
+	}
+}
+
+impl StorableObjectUpdate<LSPS1ServiceOrder> for LSPS1ServiceOrderUpdate {
+	fn id(&self) -> <LSPS1ServiceOrder as StorableObject>::Id {
+		self.id
+	}
+}
+
+/// The state of a JIT channel sold via our LSPS2 service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LSPS2SaleState {
+	/// We provided the client with invoice parameters and are waiting for the payment to arrive.
+	AwaitingPayment,
+	/// We intercepted the payment and are opening the channel.
+	ChannelOpening,
+	/// The channel is ready and we're about to forward the payment.
+	ChannelReady {
+		/// The id of the channel.
+		channel_id: ChannelId,
+	},
+	/// We forwarded the payment, thereby collecting the opening fee.
+	Completed {
+		/// The id of the channel.
+		channel_id: ChannelId,
+	},
+	/// We failed to open the channel.
+	Failed,
+}
+
+impl_writeable_tlv_based_enum!(LSPS2SaleState,
+	(0, AwaitingPayment) => {},
+	(2, ChannelOpening) => {},
+	(4, ChannelReady) => {
+		(0, channel_id, required),
+	},
+	(6, Completed) => {
+		(0, channel_id, required),
+	},
+	(8, Failed) => {}
+);
+
+/// Details of a JIT channel sold via our LSPS2 service.
+///
+/// Sales are retrieved via [`Node::list_lsps2_sales`].
+///
+/// [`Node::list_lsps2_sales`]: crate::Node::list_lsps2_sales
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LSPS2SaleDetails {
+	/// The `user_channel_id` of the channel we open for the client.
+	pub user_channel_id: UserChannelId,
+	/// The node id of the client that bought the channel.
+	pub counterparty_node_id: PublicKey,
+	/// The intercept short channel id the client includes in its invoice's route hint.
+	pub intercept_scid: u64,
+	/// The size, in milli-satoshis, of the payment the client requested to receive, if it
+	/// requested a fixed amount.
+	pub payment_size_msat: Option<u64>,
+	/// The size, in satoshis, of the channel we open, once we intercepted the payment.
+	///
+	/// Until the channel opening is initiated, this is our estimate based on the intercepted
+	/// payment.
+	pub channel_amount_sat: Option<u64>,
+	/// The opening fee, in milli-satoshis, we withhold from the payment, once we intercepted it.
+	pub opening_fee_msat: Option<u64>,
+	/// The state of the sale.
+	pub state: LSPS2SaleState,
+	/// The time, in seconds since start of the UNIX epoch, at which the client bought the channel.
+	pub created_at: u64,
+	/// The timestamp, in seconds since start of the UNIX epoch, when this entry was last updated.
+	pub latest_update_timestamp: u64,
+}
+
+impl_writeable_tlv_based!(LSPS2SaleDetails, {
+	(0, user_channel_id, required),
+	(2, counterparty_node_id, required),
+	(4, intercept_scid, required),
+	(6, payment_size_msat, option),
+	(8, channel_amount_sat, option),
+	(10, opening_fee_msat, option),
+	(12, state, required),
+	(14, created_at, required),
+	(16, latest_update_timestamp, required),
+});
+
+impl LSPS2SaleDetails {
+	// Returns whether the sale still counts towards the client's pending requests.
+	fn is_pending(&self, cur_timestamp: u64) -> bool {
+		match self.state {
+			LSPS2SaleState::AwaitingPayment => {
+				cur_timestamp
+					< self.created_at.saturating_add(LSPS2_GETINFO_REQUEST_EXPIRY.as_secs())
+			},
+			LSPS2SaleState::ChannelOpening | LSPS2SaleState::ChannelReady { .. } => true,
+			LSPS2SaleState::Completed { .. } | LSPS2SaleState::Failed => false,
+		}
+	}
+
+	// Returns whether the sale waits on a channel we started opening, i.e., whether it needs to be
+	// failed if the channel is closed.
+	fn awaits_channel(&self) -> bool {
+		match self.state {
+			// We only set the opening fee once we initiated opening the channel.
+			LSPS2SaleState::ChannelOpening => self.opening_fee_msat.is_some(),
+			LSPS2SaleState::ChannelReady { .. } => true,
+			_ => false,
+		}
+	}
+
+	// Returns the channel liquidity, in satoshis, we committed to the sale without having been
+	// paid for it yet.
+	fn outstanding_liquidity_sat(&self) -> u64 {
+		match self.state {
+			LSPS2SaleState::ChannelOpening | LSPS2SaleState::ChannelReady { .. } => {
+				self.channel_amount_sat.unwrap_or(0)
+			},
+			_ => 0,
+		}
+	}
+}
+
+impl StorableObjectId for UserChannelId {
+	fn encode_to_hex_str(&self) -> String {
+		hex_utils::to_string(&self.0.to_be_bytes())
+	}
+}
+
+impl StorableObject for LSPS2SaleDetails {
+	type Id = UserChannelId;
+	type Update = LSPS2SaleDetailsUpdate;
+
+	fn id(&self) -> Self::Id {
+		self.user_channel_id
+	}
+
+	fn update(&mut self, update: &Self::Update) -> bool {
+		debug_assert_eq!(
+			self.user_channel_id, update.user_channel_id,
+			"We should only ever update sales with the same id"
+		);
+
+		let mut updated = false;
+
+		macro_rules! update_if_necessary {
+			($val: expr, $update: expr) => {
+				if $val != $update {
+					$val = $update;
+					updated = true;
+				}
+			};
+		}
+
+		if let Some(channel_amount_sat) = update.channel_amount_sat {
+			update_if_necessary!(self.channel_amount_sat, Some(channel_amount_sat));
+		}
+		if let Some(opening_fee_msat) = update.opening_fee_msat {
+			update_if_necessary!(self.opening_fee_msat, Some(opening_fee_msat));
+		}
+		if let Some(state) = update.state {
+			update_if_necessary!(self.state, state);
+		}
+
+		if updated {
+			self.latest_update_timestamp = SystemTime::now()
+				.duration_since(UNIX_EPOCH)
+				.unwrap_or(Duration::from_secs(0))
+				.as_secs();
+		}
+
+		updated
+	}
+
+	fn to_update(&self) -> Self::Update {
+		LSPS2SaleDetailsUpdate {
+			user_channel_id: self.user_channel_id,
+			channel_amount_sat: self.channel_amount_sat,
+			opening_fee_msat: self.opening_fee_msat,
+			state: Some(self.state),
+		}
+	}
+}
+
+/// Updates the details of an [`LSPS2SaleDetails`].
+pub(crate) struct LSPS2SaleDetailsUpdate {
+	pub user_channel_id: UserChannelId,
+	pub channel_amount_sat: Option<u64>,
+	pub opening_fee_msat: Option<u64>,
+	pub state: Option<LSPS2SaleState>,
+}
+
+impl LSPS2SaleDetailsUpdate {
+	fn new(user_channel_id: UserChannelId) -> Self {
+		Self { user_channel_id, channel_amount_sat: None, opening_fee_msat: None, state: None }
+	}
+}
+
+impl StorableObjectUpdate<LSPS2SaleDetails> for LSPS2SaleDetailsUpdate {
+	fn id(&self) -> <LSPS2SaleDetails as StorableObject>::Id {
+		self.user_channel_id
+	}
+}
+
+// Returns the request id, method, and parameters if the given message is an LSPS1 request we
+// handle ourselves. As `LiquidityManager` is unaware of our LSPS1 service, we also answer
+// `lsps0.list_protocols` requests so that LSPS1 is advertised to clients.
+fn parse_lsps1_request(payload: &str) -> Option<(RequestId, String, serde_json::Value)> {
+	let mut value = serde_json::from_str::<serde_json::Value>(payload).ok()?;
+	let object = value.as_object_mut()?;
+	let method = object.get("method")?.as_str()?.to_string();
+	match method.as_str() {
+		LSPS0_LIST_PROTOCOLS_METHOD_NAME
+		| LSPS1_GET_INFO_METHOD_NAME
+		| LSPS1_CREATE_ORDER_METHOD_NAME
+		| LSPS1_GET_ORDER_METHOD_NAME => {},
+		_ => return None,
+	}
+	let request_id = RequestId(object.get("id")?.as_str()?.to_string());
+	let params = object.remove("params").unwrap_or(serde_json::Value::Object(Default::default()));
+	Some((request_id, method, params))
+}
+
+fn timestamp_to_datetime(timestamp: u64) -> DateTime<Utc> {
+	DateTime::from_timestamp(timestamp as i64, 0).unwrap_or_default()
+}
+
+fn parse_address(address: &str) -> Option<bitcoin::Address> {
+	bitcoin::Address::from_str(address).ok().map(|a| a.assume_checked())
 }
 
 pub(crate) struct LiquiditySourceBuilder<L: Deref>
@@ -138,12 +803,14 @@ where
 	L::Target: LdkLogger,
 {
 	lsps1_client: Option<LSPS1Client>,
+	lsps1_service: Option<LSPS1Service>,
 	lsps2_client: Option<LSPS2Client>,
 	lsps2_service: Option<LSPS2Service>,
 	wallet: Arc<Wallet>,
 	channel_manager: Arc<ChannelManager>,
 	keys_manager: Arc<KeysManager>,
 	chain_source: Arc<ChainSource>,
+	payment_store: Arc<PaymentStore>,
 	config: Arc<Config>,
 	logger: L,
 }
@@ -154,19 +821,23 @@ where
 {
 	pub(crate) fn new(
 		wallet: Arc<Wallet>, channel_manager: Arc<ChannelManager>, keys_manager: Arc<KeysManager>,
-		chain_source: Arc<ChainSource>, config: Arc<Config>, logger: L,
+		chain_source: Arc<ChainSource>, payment_store: Arc<PaymentStore>, config: Arc<Config>,
+		logger: L,
 	) -> Self {
 		let lsps1_client = None;
+		let lsps1_service = None;
 		let lsps2_client = None;
 		let lsps2_service = None;
 		Self {
 			lsps1_client,
+			lsps1_service,
 			lsps2_client,
 			lsps2_service,
 			wallet,
 			channel_manager,
 			keys_manager,
 			chain_source,
+			payment_store,
 			config,
 			logger,
 		}
@@ -174,6 +845,7 @@ where
 
 	pub(crate) fn lsps1_client(
 		&mut self, lsp_node_id: PublicKey, lsp_address: SocketAddress, token: Option<String>,
+		order_store: Arc<LSPS1OrderStore>, event_queue: Arc<EventQueue<Arc<Logger>>>,
 	) -> &mut Self {
 		// TODO: allow to set max_channel_fees_msat
 		let ldk_client_config = LdkLSPS1ClientConfig { max_channel_fees_msat: None };
@@ -188,10 +860,22 @@ where
 			pending_opening_params_requests,
 			pending_create_order_requests,
 			pending_check_order_status_requests,
+			order_store,
+			event_queue,
 		});
 		self
 	}
 
+	pub(crate) fn lsps1_service(
+		&mut self, service_config: LSPS1ServiceConfig, order_store: Arc<LSPS1ServiceOrderStore>,
+	) -> &mut Self {
+		let pending_messages = Mutex::new(Vec::new());
+		let order_state_lock = Mutex::new(());
+		self.lsps1_service =
+			Some(LSPS1Service { service_config, order_store, pending_messages, order_state_lock });
+		self
+	}
+
 	pub(crate) fn lsps2_client(
 		&mut self, lsp_node_id: PublicKey, lsp_address: SocketAddress, token: Option<String>,
 	) -> &mut Self {
@@ -211,18 +895,45 @@ where
 
 	pub(crate) fn lsps2_service(
 		&mut self, promise_secret: [u8; 32], service_config: LSPS2ServiceConfig,
+		sale_store: Arc<LSPS2SaleStore>,
 	) -> &mut Self {
 		let ldk_service_config = LdkLSPS2ServiceConfig { promise_secret };
-		self.lsps2_service = Some(LSPS2Service { service_config, ldk_service_config });
+		let sale_state_lock = Mutex::new(());
+		let sales_by_intercept_scid = Mutex::new(
+			sale_store
+				.list_filter(|_| true)
+				.into_iter()
+				.map(|s| (s.intercept_scid, s.user_channel_id))
+				.collect(),
+		);
+		self.lsps2_service = Some(LSPS2Service {
+			service_config,
+			ldk_service_config,
+			sale_store,
+			sale_state_lock,
+			sales_by_intercept_scid,
+		});
 		self
 	}
 
 	pub(crate) fn build(self) -> LiquiditySource<L> {
-		let liquidity_service_config = self.lsps2_service.as_ref().map(|s| {
-			let lsps2_service_config = Some(s.ldk_service_config.clone());
-			let advertise_service = s.service_config.advertise_service;
-			LiquidityServiceConfig { lsps2_service_config, advertise_service }
-		});
+		// LSPS1 requests are handled by us directly, but we still need `LiquidityManager` to act as
+		// an LSPS0 service and advertise our services.
+		let liquidity_service_config = if self.lsps1_service.is_some()
+			|| self.lsps2_service.is_some()
+		{
+			let lsps2_service_config =
+				self.lsps2_service.as_ref().map(|s| s.ldk_service_config.clone());
+			let advertise_service =
+				self.lsps1_service.as_ref().map_or(false, |s| s.service_config.advertise_service)
+					|| self
+						.lsps2_service
+						.as_ref()
+						.map_or(false, |s| s.service_config.advertise_service);
+			Some(LiquidityServiceConfig { lsps2_service_config, advertise_service })
+		} else {
+			None
+		};
 
 		let lsps1_client_config = self.lsps1_client.as_ref().map(|s| s.ldk_client_config.clone());
 		let lsps2_client_config = self.lsps2_client.as_ref().map(|s| s.ldk_client_config.clone());
@@ -240,12 +951,14 @@ where
 
 		LiquiditySource {
 			lsps1_client: self.lsps1_client,
+			lsps1_service: self.lsps1_service,
 			lsps2_client: self.lsps2_client,
 			lsps2_service: self.lsps2_service,
 			wallet: self.wallet,
 			channel_manager: self.channel_manager,
 			peer_manager: RwLock::new(None),
 			keys_manager: self.keys_manager,
+			payment_store: self.payment_store,
 			liquidity_manager,
 			config: self.config,
 			logger: self.logger,
@@ -258,12 +971,14 @@ where
 	L::Target: LdkLogger,
 {
 	lsps1_client: Option<LSPS1Client>,
+	lsps1_service: Option<LSPS1Service>,
 	lsps2_client: Option<LSPS2Client>,
 	lsps2_service: Option<LSPS2Service>,
 	wallet: Arc<Wallet>,
 	channel_manager: Arc<ChannelManager>,
 	peer_manager: RwLock<Option<Arc<PeerManager>>>,
 	keys_manager: Arc<KeysManager>,
+	payment_store: Arc<PaymentStore>,
 	liquidity_manager: Arc<LiquidityManager>,
 	config: Arc<Config>,
 	logger: L,
@@ -283,6 +998,31 @@ where
 		self.liquidity_manager.as_ref()
 	}
 
+	pub(crate) fn handle_custom_message(
+		&self, msg: RawLSPSMessage, sender_node_id: PublicKey,
+	) -> Result<(), LightningError> {
+		if let Some(lsps1_service) = self.lsps1_service.as_ref() {
+			if let Some((request_id, method, params)) = parse_lsps1_request(&msg.payload) {
+				return self.handle_lsps1_request(
+					lsps1_service,
+					request_id,
+					&method,
+					params,
+					sender_node_id,
+				);
+			}
+		}
+		self.liquidity_manager.handle_custom_message(msg, sender_node_id)
+	}
+
+	pub(crate) fn get_and_clear_pending_msg(&self) -> Vec<(PublicKey, RawLSPSMessage)> {
+		let mut msgs = self.liquidity_manager.get_and_clear_pending_msg();
+		if let Some(lsps1_service) = self.lsps1_service.as_ref() {
+			msgs.append(&mut lsps1_service.pending_messages.lock().unwrap());
+		}
+		msgs
+	}
+
 	pub(crate) fn get_lsps1_lsp_details(&self) -> Option<(PublicKey, SocketAddress)> {
 		self.lsps1_client.as_ref().map(|s| (s.lsp_node_id, s.lsp_address.clone()))
 	}
@@ -541,14 +1281,13 @@ where
 				if let Some(lsps2_service_handler) =
 					self.liquidity_manager.lsps2_service_handler().as_ref()
 				{
-					let service_config = if let Some(service_config) =
-						self.lsps2_service.as_ref().map(|s| s.service_config.clone())
-					{
-						service_config
+					let lsps2_service = if let Some(lsps2_service) = self.lsps2_service.as_ref() {
+						lsps2_service
 					} else {
 						log_error!(self.logger, "Failed to handle LSPS2ServiceEvent as LSPS2 liquidity service was not configured.",);
 						return;
 					};
+					let service_config = &lsps2_service.service_config;
 
 					let user_channel_id: u128 = rand::thread_rng().gen::<u128>();
 					let intercept_scid = self.channel_manager.get_intercept_scid();
@@ -576,6 +1315,29 @@ where
 						}
 					}
 
+					let _sale_state_lock = lsps2_service.sale_state_lock.lock().unwrap();
+					let cur_timestamp = SystemTime::now()
+						.duration_since(UNIX_EPOCH)
+						.unwrap_or(Duration::from_secs(0))
+						.as_secs();
+					let client_sales = lsps2_service
+						.sale_store
+						.list_filter(|s| s.counterparty_node_id == counterparty_node_id);
+					if let Err(reason) =
+						service_config.check_client_request(&client_sales, cur_timestamp)
+					{
+						// TODO: We might want to eventually send back an error here, but we
+						// currently can't.
+						log_error!(
+							self.logger,
+							"Rejecting to handle LSPS2 buy request {:?} from counterparty {}: {}.",
+							request_id,
+							counterparty_node_id,
+							reason
+						);
+						return;
+					}
+
 					match lsps2_service_handler.invoice_parameters_generated(
 						&counterparty_node_id,
 						request_id,
@@ -584,7 +1346,32 @@ where
 						LSPS2_CLIENT_TRUSTS_LSP_MODE,
 						user_channel_id,
 					) {
-						Ok(()) => {},
+						Ok(()) => {
+							let sale = LSPS2SaleDetails {
+								user_channel_id: UserChannelId(user_channel_id),
+								counterparty_node_id,
+								intercept_scid,
+								payment_size_msat,
+								channel_amount_sat: None,
+								opening_fee_msat: None,
+								state: LSPS2SaleState::AwaitingPayment,
+								created_at: cur_timestamp,
+								latest_update_timestamp: cur_timestamp,
+							};
+							if let Err(e) = lsps2_service.sale_store.insert(sale) {
+								log_error!(
+									self.logger,
+									"Failed to persist LSPS2 sale to {}: {}",
+									counterparty_node_id,
+									e
+								);
+							}
+							lsps2_service
+								.sales_by_intercept_scid
+								.lock()
+								.unwrap()
+								.insert(intercept_scid, UserChannelId(user_channel_id));
+						},
 						Err(e) => {
 							log_error!(
 								self.logger,
@@ -602,7 +1389,7 @@ where
 			Event::LSPS2Service(LSPS2ServiceEvent::OpenChannel {
 				their_network_key,
 				amt_to_forward_msat,
-				opening_fee_msat: _,
+				opening_fee_msat,
 				user_channel_id,
 				intercept_scid: _,
 			}) => {
@@ -611,14 +1398,18 @@ where
 					return;
 				};
 
-				let service_config = if let Some(service_config) =
-					self.lsps2_service.as_ref().map(|s| s.service_config.clone())
-				{
-					service_config
+				let lsps2_service = if let Some(lsps2_service) = self.lsps2_service.as_ref() {
+					lsps2_service
 				} else {
 					log_error!(self.logger, "Failed to handle LSPS2ServiceEvent as LSPS2 liquidity service was not configured.",);
 					return;
 				};
+				let service_config = &lsps2_service.service_config;
+
+				let channel_amount_sats = service_config.channel_amount_sat(amt_to_forward_msat);
+				let mut sale_update = LSPS2SaleDetailsUpdate::new(UserChannelId(user_channel_id));
+				sale_update.channel_amount_sat = Some(channel_amount_sats);
+				sale_update.opening_fee_msat = Some(opening_fee_msat);
 
 				let init_features = if let Some(peer_manager) =
 					self.peer_manager.read().unwrap().as_ref()
@@ -635,6 +1426,8 @@ where
 							"Failed to open LSPS2 channel to {} due to peer not being not connected.",
 							their_network_key,
 						);
+						sale_update.state = Some(LSPS2SaleState::Failed);
+						self.lsps2_update_sale(lsps2_service, &sale_update);
 						return;
 					}
 				} else {
@@ -644,10 +1437,6 @@ where
 				};
 
 				// Fail if we have insufficient onchain funds available.
-				let over_provisioning_msat = (amt_to_forward_msat
-					* service_config.channel_over_provisioning_ppm as u64)
-					/ 1_000_000;
-				let channel_amount_sats = (amt_to_forward_msat + over_provisioning_msat) / 1000;
 				let cur_anchor_reserve_sats =
 					total_anchor_channels_reserve_sats(&self.channel_manager, &self.config);
 				let spendable_amount_sats =
@@ -670,6 +1459,8 @@ where
 					// TODO: We just silently fail here. Eventually we will need to remember
 					// the pending requests and regularly retry opening the channel until we
 					// succeed.
+					sale_update.state = Some(LSPS2SaleState::Failed);
+					self.lsps2_update_sale(lsps2_service, &sale_update);
 					return;
 				}
 
@@ -698,7 +1489,10 @@ where
 					None,
 					Some(config),
 				) {
-					Ok(_) => {},
+					Ok(_) => {
+						sale_update.state = Some(LSPS2SaleState::ChannelOpening);
+						self.lsps2_update_sale(lsps2_service, &sale_update);
+					},
 					Err(e) => {
 						// TODO: We just silently fail here. Eventually we will need to remember
 						// the pending requests and regularly retry opening the channel until we
@@ -709,6 +1503,8 @@ where
 							their_network_key,
 							e
 						);
+						sale_update.state = Some(LSPS2SaleState::Failed);
+						self.lsps2_update_sale(lsps2_service, &sale_update);
 						return;
 					},
 				}
@@ -948,6 +1744,9 @@ where
 			return Err(Error::LiquidityRequestFailed);
 		}
 
+		let order = LSPS1OrderDetails::new(&response, lsps1_client.lsp_node_id);
+		lsps1_client.order_store.insert(order)?;
+
 		Ok(response)
 	}
 
@@ -982,9 +1781,97 @@ where
 			Error::LiquidityRequestFailed
 		})?;
 
+		self.lsps1_update_order(lsps1_client, &response)?;
+
 		Ok(response)
 	}
 
+	// Updates the state of the tracked order, and notifies the user if it changed.
+	fn lsps1_update_order(
+		&self, lsps1_client: &LSPS1Client, status: &LSPS1OrderStatus,
+	) -> Result<(), Error> {
+		let cur_timestamp = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap_or(Duration::from_secs(0))
+			.as_secs();
+		let state = LSPS1OrderState::from_order_status(status, cur_timestamp);
+		let mut update = LSPS1OrderDetailsUpdate::new(status.order_id.clone());
+		update.state = Some(state);
+
+		match lsps1_client.order_store.update(&update)? {
+			DataStoreUpdateResult::Updated => {
+				log_info!(
+					self.logger,
+					"LSPS1 order {} changed its state to {:?}.",
+					status.order_id.0,
+					state
+				);
+				let event = crate::Event::LSPS1OrderUpdated {
+					order_id: LSPS1OrderId(status.order_id.clone()),
+					state,
+				};
+				lsps1_client.event_queue.add_event(event)?;
+			},
+			DataStoreUpdateResult::Unchanged => {},
+			DataStoreUpdateResult::NotFound => {
+				// We didn't track the order yet, e.g., as it was placed before we persisted orders.
+				let order = LSPS1OrderDetails::new(status, lsps1_client.lsp_node_id);
+				lsps1_client.order_store.insert(order)?;
+			},
+		}
+		Ok(())
+	}
+
+	pub(crate) fn lsps1_list_orders(&self) -> Vec<LSPS1OrderDetails> {
+		self.lsps1_client.as_ref().map(|c| c.order_store.list_filter(|_| true)).unwrap_or_default()
+	}
+
+	pub(crate) fn lsps1_get_order(&self, order_id: &OrderId) -> Option<LSPS1OrderDetails> {
+		self.lsps1_client.as_ref().and_then(|c| c.order_store.get(order_id))
+	}
+
+	pub(crate) fn lsps1_record_order_payment(
+		&self, order_id: OrderId, payment_id: Option<PaymentId>, payment_txid: Option<Txid>,
+	) -> Result<(), Error> {
+		let lsps1_client = self.lsps1_client.as_ref().ok_or(Error::LiquiditySourceUnavailable)?;
+		let mut update = LSPS1OrderDetailsUpdate::new(order_id);
+		update.payment_id = payment_id;
+		update.payment_txid = payment_txid;
+		lsps1_client.order_store.update(&update)?;
+		Ok(())
+	}
+
+	// Returns whether we track orders placed with our current LSP that haven't reached a final
+	// state yet.
+	pub(crate) fn lsps1_has_pending_orders(&self) -> bool {
+		self.lsps1_client.as_ref().map_or(false, |c| {
+			!c.order_store
+				.list_filter(|o| o.lsp_node_id == c.lsp_node_id && !o.state.is_final())
+				.is_empty()
+		})
+	}
+
+	pub(crate) async fn lsps1_poll_orders(&self) {
+		let lsps1_client = match self.lsps1_client.as_ref() {
+			Some(lsps1_client) => lsps1_client,
+			None => return,
+		};
+
+		let pending_orders = lsps1_client
+			.order_store
+			.list_filter(|o| o.lsp_node_id == lsps1_client.lsp_node_id && !o.state.is_final());
+		for order in pending_orders {
+			if let Err(e) = self.lsps1_check_order_status(order.order_id.clone()).await {
+				log_error!(
+					self.logger,
+					"Failed to poll the state of LSPS1 order {}: {}",
+					order.order_id.0,
+					e
+				);
+			}
+		}
+	}
+
 	pub(crate) async fn lsps2_receive_to_jit_channel(
 		&self, amount_msat: u64, description: &Bolt11InvoiceDescription, expiry_secs: u32,
 		max_total_lsp_fee_limit_msat: Option<u64>,
@@ -1217,29 +2104,131 @@ where
 			})
 	}
 
-	pub(crate) fn handle_channel_ready(
-		&self, user_channel_id: u128, channel_id: &ChannelId, counterparty_node_id: &PublicKey,
-	) {
-		if let Some(lsps2_service_handler) = self.liquidity_manager.lsps2_service_handler() {
-			if let Err(e) = lsps2_service_handler.channel_ready(
-				user_channel_id,
-				channel_id,
-				counterparty_node_id,
-			) {
+	fn lsps2_update_sale(&self, lsps2_service: &LSPS2Service, update: &LSPS2SaleDetailsUpdate) {
+		let _sale_state_lock = lsps2_service.sale_state_lock.lock().unwrap();
+		match lsps2_service.sale_store.update(update) {
+			Ok(DataStoreUpdateResult::Updated) => {
+				log_debug!(
+					self.logger,
+					"Updated LSPS2 sale with user_channel_id {}.",
+					update.user_channel_id.0
+				);
+			},
+			Ok(DataStoreUpdateResult::Unchanged) | Ok(DataStoreUpdateResult::NotFound) => {},
+			Err(e) => {
 				log_error!(
 					self.logger,
-					"LSPS2 service failed to handle ChannelReady event: {:?}",
+					"Failed to update LSPS2 sale with user_channel_id {}: {}",
+					update.user_channel_id.0,
 					e
 				);
-			}
+			},
 		}
 	}
 
-	pub(crate) fn handle_htlc_intercepted(
-		&self, intercept_scid: u64, intercept_id: InterceptId, expected_outbound_amount_msat: u64,
-		payment_hash: PaymentHash,
-	) {
+	pub(crate) fn list_lsps2_sales(&self) -> Vec<LSPS2SaleDetails> {
+		self.lsps2_service.as_ref().map(|s| s.sale_store.list_filter(|_| true)).unwrap_or_default()
+	}
+
+	// Checks the JIT channel we'd open for the given intercepted payment against our outstanding
+	// liquidity budget and reserves the liquidity if it fits. Returns `false` if the payment
+	// needs to be failed back.
+	fn lsps2_reserve_liquidity(
+		&self, intercept_scid: u64, expected_outbound_amount_msat: u64,
+	) -> bool {
+		let lsps2_service = match self.lsps2_service.as_ref() {
+			Some(lsps2_service) => lsps2_service,
+			None => return true,
+		};
+
+		let _sale_state_lock = lsps2_service.sale_state_lock.lock().unwrap();
+		let sale = match lsps2_service.sale_for_intercept_scid(intercept_scid) {
+			Some(sale) => sale,
+			None => return true,
+		};
+		if sale.state != LSPS2SaleState::AwaitingPayment {
+			// We already reserved the liquidity when intercepting an earlier part of the payment.
+			return true;
+		}
+
+		let payment_amount_msat = sale.payment_size_msat.unwrap_or(expected_outbound_amount_msat);
+		let channel_amount_sat =
+			lsps2_service.service_config.channel_amount_sat(payment_amount_msat);
+		if let Some(max_outstanding_sat) =
+			lsps2_service.service_config.max_outstanding_liquidity_sat
+		{
+			let outstanding_sat: u64 = lsps2_service
+				.sale_store
+				.list_filter(|s| s.outstanding_liquidity_sat() > 0)
+				.iter()
+				.map(|s| s.outstanding_liquidity_sat())
+				.sum();
+			if outstanding_sat.saturating_add(channel_amount_sat) > max_outstanding_sat {
+				log_error!(
+					self.logger,
+					"Failing payment to JIT channel of {} as opening a {}sat channel would exceed our outstanding liquidity budget of {}sat ({}sat outstanding).",
+					sale.counterparty_node_id,
+					channel_amount_sat,
+					max_outstanding_sat,
+					outstanding_sat
+				);
+				return false;
+			}
+		}
+
+		let mut update = LSPS2SaleDetailsUpdate::new(sale.user_channel_id);
+		update.channel_amount_sat = Some(channel_amount_sat);
+		update.state = Some(LSPS2SaleState::ChannelOpening);
+		if let Err(e) = lsps2_service.sale_store.update(&update) {
+			// If we can't record the reservation, we'd otherwise open the channel without it
+			// counting towards our budget.
+			log_error!(
+				self.logger,
+				"Failing payment to JIT channel of {} as we failed to update the LSPS2 sale: {}",
+				sale.counterparty_node_id,
+				e
+			);
+			return false;
+		}
+		true
+	}
+
+	pub(crate) fn handle_channel_ready(
+		&self, user_channel_id: u128, channel_id: &ChannelId, counterparty_node_id: &PublicKey,
+	) {
+		if let Some(lsps2_service) = self.lsps2_service.as_ref() {
+			let mut update = LSPS2SaleDetailsUpdate::new(UserChannelId(user_channel_id));
+			update.state = Some(LSPS2SaleState::ChannelReady { channel_id: *channel_id });
+			self.lsps2_update_sale(lsps2_service, &update);
+		}
+
+		if let Some(lsps2_service_handler) = self.liquidity_manager.lsps2_service_handler() {
+			if let Err(e) = lsps2_service_handler.channel_ready(
+				user_channel_id,
+				channel_id,
+				counterparty_node_id,
+			) {
+				log_error!(
+					self.logger,
+					"LSPS2 service failed to handle ChannelReady event: {:?}",
+					e
+				);
+			}
+		}
+	}
+
+	pub(crate) fn handle_htlc_intercepted(
+		&self, intercept_scid: u64, intercept_id: InterceptId, expected_outbound_amount_msat: u64,
+		payment_hash: PaymentHash,
+	) {
 		if let Some(lsps2_service_handler) = self.liquidity_manager.lsps2_service_handler() {
+			if !self.lsps2_reserve_liquidity(intercept_scid, expected_outbound_amount_msat) {
+				if let Err(e) = self.channel_manager.fail_intercepted_htlc(intercept_id) {
+					log_error!(self.logger, "Failed to fail intercepted HTLC: {:?}", e);
+				}
+				return;
+			}
+
 			if let Err(e) = lsps2_service_handler.htlc_intercepted(
 				intercept_scid,
 				intercept_id,
@@ -1256,6 +2245,11 @@ where
 	}
 
 	pub(crate) fn handle_htlc_handling_failed(&self, failed_next_destination: HTLCDestination) {
+		if let HTLCDestination::UnknownNextHop { requested_forward_scid } = failed_next_destination
+		{
+			self.lsps2_release_liquidity(requested_forward_scid);
+		}
+
 		if let Some(lsps2_service_handler) = self.liquidity_manager.lsps2_service_handler() {
 			if let Err(e) = lsps2_service_handler.htlc_handling_failed(failed_next_destination) {
 				log_error!(
@@ -1267,7 +2261,51 @@ where
 		}
 	}
 
-	pub(crate) fn handle_payment_forwarded(&self, next_channel_id: Option<ChannelId>) {
+	// Releases the liquidity reserved for a JIT channel if the intercepted payment was failed
+	// before we started opening the channel.
+	fn lsps2_release_liquidity(&self, intercept_scid: u64) {
+		let lsps2_service = match self.lsps2_service.as_ref() {
+			Some(lsps2_service) => lsps2_service,
+			None => return,
+		};
+
+		let _sale_state_lock = lsps2_service.sale_state_lock.lock().unwrap();
+		let sale = lsps2_service
+			.sale_for_intercept_scid(intercept_scid)
+			.filter(|s| s.state == LSPS2SaleState::ChannelOpening && s.opening_fee_msat.is_none());
+		if let Some(sale) = sale {
+			let mut update = LSPS2SaleDetailsUpdate::new(sale.user_channel_id);
+			update.state = Some(LSPS2SaleState::AwaitingPayment);
+			if let Err(e) = lsps2_service.sale_store.update(&update) {
+				log_error!(self.logger, "Failed to update LSPS2 sale: {}", e);
+			}
+		}
+	}
+
+	pub(crate) fn handle_payment_forwarded(
+		&self, next_channel_id: Option<ChannelId>, next_user_channel_id: Option<u128>,
+	) {
+		if let (Some(lsps2_service), Some(next_channel_id), Some(next_user_channel_id)) =
+			(self.lsps2_service.as_ref(), next_channel_id, next_user_channel_id)
+		{
+			let sale =
+				lsps2_service.sale_store.get(&UserChannelId(next_user_channel_id)).filter(|s| {
+					s.state == LSPS2SaleState::ChannelReady { channel_id: next_channel_id }
+				});
+			if let Some(sale) = sale {
+				log_info!(
+					self.logger,
+					"Collected LSPS2 opening fee of {}msat from {} for channel {}.",
+					sale.opening_fee_msat.unwrap_or(0),
+					sale.counterparty_node_id,
+					next_channel_id
+				);
+				let mut update = LSPS2SaleDetailsUpdate::new(sale.user_channel_id);
+				update.state = Some(LSPS2SaleState::Completed { channel_id: next_channel_id });
+				self.lsps2_update_sale(lsps2_service, &update);
+			}
+		}
+
 		if let Some(next_channel_id) = next_channel_id {
 			if let Some(lsps2_service_handler) = self.liquidity_manager.lsps2_service_handler() {
 				if let Err(e) = lsps2_service_handler.payment_forwarded(next_channel_id) {
@@ -1280,6 +2318,651 @@ where
 			}
 		}
 	}
+
+	pub(crate) fn handle_payment_claimed(&self, payment_hash: PaymentHash) {
+		let lsps1_service = match self.lsps1_service.as_ref() {
+			Some(lsps1_service) => lsps1_service,
+			None => return,
+		};
+
+		let _order_state_lock = lsps1_service.order_state_lock.lock().unwrap();
+		let order = match lsps1_service
+			.order_store
+			.list_filter(|o| o.payment_hash == Some(payment_hash))
+			.pop()
+		{
+			Some(order) => order,
+			None => return,
+		};
+
+		// We also accept late payments for orders that already expired as we received the funds.
+		match order.state {
+			LSPS1ServiceOrderState::AwaitingPayment
+			| LSPS1ServiceOrderState::Failed
+			| LSPS1ServiceOrderState::Refunded { .. } => {},
+			_ => return,
+		}
+
+		log_info!(self.logger, "LSPS1 order {} was paid via BOLT11.", order.id.to_order_id().0);
+		if let Some(order) =
+			self.update_lsps1_order_state(lsps1_service, &order, LSPS1ServiceOrderState::Paid)
+		{
+			self.lsps1_open_channel(lsps1_service, &order);
+		}
+	}
+
+	pub(crate) fn handle_channel_pending(&self, user_channel_id: u128, funding_txo: OutPoint) {
+		let lsps1_service = match self.lsps1_service.as_ref() {
+			Some(lsps1_service) => lsps1_service,
+			None => return,
+		};
+
+		let _order_state_lock = lsps1_service.order_state_lock.lock().unwrap();
+		for order in lsps1_service
+			.order_store
+			.list_filter(|o| o.state == LSPS1ServiceOrderState::ChannelOpening { user_channel_id })
+		{
+			log_info!(
+				self.logger,
+				"Published funding transaction for LSPS1 order {}: {}",
+				order.id.to_order_id().0,
+				funding_txo
+			);
+			let funded_at = SystemTime::now()
+				.duration_since(UNIX_EPOCH)
+				.unwrap_or(Duration::from_secs(0))
+				.as_secs();
+			let state =
+				LSPS1ServiceOrderState::Completed { user_channel_id, funding_txo, funded_at };
+			self.update_lsps1_order_state(lsps1_service, &order, state);
+		}
+	}
+
+	pub(crate) fn handle_channel_closed(&self, user_channel_id: u128) {
+		if let Some(lsps2_service) = self.lsps2_service.as_ref() {
+			let sale = lsps2_service.sale_store.get(&UserChannelId(user_channel_id));
+			if let Some(sale) = sale.filter(|s| s.awaits_channel()) {
+				log_error!(
+					self.logger,
+					"JIT channel for {} was closed before we got paid for it.",
+					sale.counterparty_node_id
+				);
+				let mut update = LSPS2SaleDetailsUpdate::new(sale.user_channel_id);
+				update.state = Some(LSPS2SaleState::Failed);
+				self.lsps2_update_sale(lsps2_service, &update);
+			}
+		}
+
+		let lsps1_service = match self.lsps1_service.as_ref() {
+			Some(lsps1_service) => lsps1_service,
+			None => return,
+		};
+
+		// If the channel was closed before its funding transaction was published, we'll retry
+		// opening it.
+		let _order_state_lock = lsps1_service.order_state_lock.lock().unwrap();
+		for order in lsps1_service
+			.order_store
+			.list_filter(|o| o.state == LSPS1ServiceOrderState::ChannelOpening { user_channel_id })
+		{
+			log_error!(
+				self.logger,
+				"Failed to open channel for LSPS1 order {}, will retry.",
+				order.id.to_order_id().0,
+			);
+			self.update_lsps1_order_state(lsps1_service, &order, LSPS1ServiceOrderState::Paid);
+		}
+	}
+
+	/// Fails LSPS2 sales whose channel is gone, e.g., as it was closed while we were offline, so
+	/// they no longer hold on to our liquidity budget.
+	pub(crate) fn process_lsps2_service_sales(&self) {
+		let lsps2_service = match self.lsps2_service.as_ref() {
+			Some(lsps2_service) => lsps2_service,
+			None => return,
+		};
+
+		let _sale_state_lock = lsps2_service.sale_state_lock.lock().unwrap();
+		let pending_sales = lsps2_service.sale_store.list_filter(|s| s.awaits_channel());
+		if pending_sales.is_empty() {
+			return;
+		}
+		let channels = self.channel_manager.list_channels();
+
+		for sale in pending_sales {
+			if channels.iter().any(|c| c.user_channel_id == sale.user_channel_id.0) {
+				continue;
+			}
+			log_error!(
+				self.logger,
+				"JIT channel for {} is gone before we got paid for it.",
+				sale.counterparty_node_id
+			);
+			let mut update = LSPS2SaleDetailsUpdate::new(sale.user_channel_id);
+			update.state = Some(LSPS2SaleState::Failed);
+			if let Err(e) = lsps2_service.sale_store.update(&update) {
+				log_error!(self.logger, "Failed to update LSPS2 sale: {}", e);
+			}
+		}
+	}
+
+	/// Checks for on-chain payments of, expires, and retries opening channels for pending LSPS1
+	/// orders.
+	///
+	/// We keep watching expired orders for on-chain payments: like late BOLT11 payments, late
+	/// on-chain payments covering the order are accepted, while any partial payment is refunded to
+	/// the client's refund address.
+	pub(crate) fn process_lsps1_service_orders(&self) {
+		let lsps1_service = match self.lsps1_service.as_ref() {
+			Some(lsps1_service) => lsps1_service,
+			None => return,
+		};
+
+		let _order_state_lock = lsps1_service.order_state_lock.lock().unwrap();
+		let cur_timestamp = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap_or(Duration::from_secs(0))
+			.as_secs();
+		let pending_orders = lsps1_service.order_store.list_filter(|o| match o.state {
+			LSPS1ServiceOrderState::AwaitingPayment
+			| LSPS1ServiceOrderState::Paid
+			| LSPS1ServiceOrderState::ChannelOpening { .. } => true,
+			LSPS1ServiceOrderState::Failed => o.onchain_address.is_some(),
+			LSPS1ServiceOrderState::Completed { .. } | LSPS1ServiceOrderState::Refunded { .. } => {
+				false
+			},
+		});
+		if pending_orders.is_empty() {
+			return;
+		}
+		let channels = self.channel_manager.list_channels();
+
+		for order in pending_orders {
+			match order.state {
+				LSPS1ServiceOrderState::AwaitingPayment => {
+					let min_confirmations =
+						order.min_onchain_payment_confirmations.unwrap_or(1) as u32;
+					let onchain_paid = self.lsps1_onchain_received_sats(&order, min_confirmations)
+						>= order.order_total_sat();
+					if onchain_paid {
+						log_info!(
+							self.logger,
+							"LSPS1 order {} was paid on-chain.",
+							order.id.to_order_id().0
+						);
+						if let Some(order) = self.update_lsps1_order_state(
+							lsps1_service,
+							&order,
+							LSPS1ServiceOrderState::Paid,
+						) {
+							self.lsps1_open_channel(lsps1_service, &order);
+						}
+					} else if cur_timestamp >= order.expires_at {
+						log_info!(
+							self.logger,
+							"LSPS1 order {} expired before being paid.",
+							order.id.to_order_id().0
+						);
+						self.update_lsps1_order_state(
+							lsps1_service,
+							&order,
+							LSPS1ServiceOrderState::Failed,
+						);
+					}
+				},
+				LSPS1ServiceOrderState::Paid => {
+					self.lsps1_open_channel(lsps1_service, &order);
+				},
+				LSPS1ServiceOrderState::ChannelOpening { user_channel_id } => {
+					// Channels that weren't funded yet are lost on restart, in which case we retry.
+					if !channels.iter().any(|c| c.user_channel_id == user_channel_id) {
+						self.update_lsps1_order_state(
+							lsps1_service,
+							&order,
+							LSPS1ServiceOrderState::Paid,
+						);
+					}
+				},
+				LSPS1ServiceOrderState::Failed => {
+					let min_confirmations =
+						order.min_onchain_payment_confirmations.unwrap_or(1) as u32;
+					let received_sats = self.lsps1_onchain_received_sats(&order, min_confirmations);
+					if received_sats >= order.order_total_sat() {
+						log_info!(
+							self.logger,
+							"LSPS1 order {} was paid on-chain after it expired.",
+							order.id.to_order_id().0
+						);
+						if let Some(order) = self.update_lsps1_order_state(
+							lsps1_service,
+							&order,
+							LSPS1ServiceOrderState::Paid,
+						) {
+							self.lsps1_open_channel(lsps1_service, &order);
+						}
+					} else if received_sats > 0
+						&& received_sats == self.lsps1_onchain_received_sats(&order, 0)
+					{
+						// We only refund once no further payments are waiting for confirmation.
+						self.lsps1_refund_order(lsps1_service, &order, received_sats);
+					}
+				},
+				LSPS1ServiceOrderState::Completed { .. }
+				| LSPS1ServiceOrderState::Refunded { .. } => {},
+			}
+		}
+	}
+
+	fn lsps1_onchain_received_sats(
+		&self, order: &LSPS1ServiceOrder, min_confirmations: u32,
+	) -> u64 {
+		order
+			.onchain_address
+			.as_ref()
+			.and_then(|a| parse_address(a))
+			.map_or(0, |address| self.wallet.get_received_amount_sats(&address, min_confirmations))
+	}
+
+	fn lsps1_refund_order(
+		&self, lsps1_service: &LSPS1Service, order: &LSPS1ServiceOrder, amount_sats: u64,
+	) {
+		let order_id = order.id.to_order_id().0;
+		let refund_address =
+			match order.refund_onchain_address.as_ref().and_then(|a| parse_address(a)) {
+				Some(refund_address) => refund_address,
+				None => return,
+			};
+		if amount_sats < refund_address.script_pubkey().minimal_non_dust().to_sat() {
+			return;
+		}
+
+		// We pay the fees of the refund transaction, so the client gets back what it sent.
+		let cur_anchor_reserve_sats =
+			total_anchor_channels_reserve_sats(&self.channel_manager, &self.config);
+		let send_amount =
+			OnchainSendAmount::ExactRetainingReserve { amount_sats, cur_anchor_reserve_sats };
+		match self.wallet.send_to_address(&refund_address, send_amount, None, None) {
+			Ok(txid) => {
+				log_info!(
+					self.logger,
+					"Refunded {}sats received for expired LSPS1 order {} in transaction {}.",
+					amount_sats,
+					order_id,
+					txid
+				);
+				self.update_lsps1_order_state(
+					lsps1_service,
+					order,
+					LSPS1ServiceOrderState::Refunded { txid },
+				);
+			},
+			Err(e) => {
+				log_error!(
+					self.logger,
+					"Failed to refund expired LSPS1 order {}, will retry: {}",
+					order_id,
+					e
+				);
+			},
+		}
+	}
+
+	fn handle_lsps1_request(
+		&self, lsps1_service: &LSPS1Service, request_id: RequestId, method: &str,
+		params: serde_json::Value, counterparty_node_id: PublicKey,
+	) -> Result<(), LightningError> {
+		let invalid_params = |message: &str| ResponseError {
+			code: LSPS1_INVALID_PARAMS_ERROR_CODE,
+			message: message.to_string(),
+			data: None,
+		};
+
+		if method == LSPS0_LIST_PROTOCOLS_METHOD_NAME {
+			let mut protocols = vec![1];
+			if self.lsps2_service.is_some() {
+				protocols.push(2);
+			}
+			let response = LSPS0Response::ListProtocols(ListProtocolsResponse { protocols });
+			let msg = LSPSMessage::LSPS0(LSPS0Message::Response(request_id, response));
+			return self.enqueue_lsps1_service_message(lsps1_service, msg, counterparty_node_id);
+		}
+
+		let response = match method {
+			LSPS1_GET_INFO_METHOD_NAME => LSPS1Response::GetInfo(GetInfoResponse {
+				options: lsps1_service.service_config.supported_options(),
+			}),
+			LSPS1_CREATE_ORDER_METHOD_NAME => {
+				match serde_json::from_value::<CreateOrderRequest>(params) {
+					Ok(request) => {
+						match self.lsps1_create_order(lsps1_service, request, counterparty_node_id)
+						{
+							Ok(response) => LSPS1Response::CreateOrder(response),
+							Err(e) => LSPS1Response::CreateOrderError(e),
+						}
+					},
+					Err(_) => LSPS1Response::CreateOrderError(invalid_params("Invalid order")),
+				}
+			},
+			LSPS1_GET_ORDER_METHOD_NAME => {
+				let order = serde_json::from_value::<GetOrderRequest>(params)
+					.ok()
+					.and_then(|request| LSPS1ServiceOrderId::from_order_id(&request.order_id))
+					.and_then(|id| lsps1_service.order_store.get(&id))
+					.filter(|order| order.counterparty_node_id == counterparty_node_id);
+				match order {
+					Some(order) => LSPS1Response::GetOrder(order.to_response()),
+					None => LSPS1Response::GetOrderError(invalid_params("Order not found")),
+				}
+			},
+			_ => {
+				debug_assert!(false, "We only ever intercept known LSPS1 methods");
+				return Ok(());
+			},
+		};
+
+		let msg = LSPSMessage::LSPS1(LSPS1Message::Response(request_id, response));
+		self.enqueue_lsps1_service_message(lsps1_service, msg, counterparty_node_id)
+	}
+
+	fn enqueue_lsps1_service_message(
+		&self, lsps1_service: &LSPS1Service, msg: LSPSMessage, counterparty_node_id: PublicKey,
+	) -> Result<(), LightningError> {
+		let payload = serde_json::to_string(&msg).map_err(|e| LightningError {
+			err: format!("Failed to serialize LSPS1 response: {}", e),
+			action: ErrorAction::IgnoreAndLog(Level::Error),
+		})?;
+		lsps1_service
+			.pending_messages
+			.lock()
+			.unwrap()
+			.push((counterparty_node_id, RawLSPSMessage { payload }));
+
+		if let Some(peer_manager) = self.peer_manager.read().unwrap().as_ref() {
+			peer_manager.process_events();
+		}
+		Ok(())
+	}
+
+	fn lsps1_create_order(
+		&self, lsps1_service: &LSPS1Service, request: CreateOrderRequest,
+		counterparty_node_id: PublicKey,
+	) -> Result<CreateOrderResponse, ResponseError> {
+		let service_config = &lsps1_service.service_config;
+		let invalid_params = |message: &str| ResponseError {
+			code: LSPS1_INVALID_PARAMS_ERROR_CODE,
+			message: message.to_string(),
+			data: None,
+		};
+		let internal_error = |message: &str| ResponseError {
+			code: LSPS1_INTERNAL_ERROR_CODE,
+			message: message.to_string(),
+			data: None,
+		};
+
+		if let Some(required_token) = service_config.require_token.as_ref() {
+			if request.order.token.as_ref() != Some(required_token) {
+				log_error!(
+					self.logger,
+					"Rejecting LSPS1 order from {} due to an invalid token.",
+					counterparty_node_id
+				);
+				return Err(invalid_params("Unrecognized token"));
+			}
+		}
+
+		if let Err(reason) = service_config.check_order(&request.order) {
+			log_info!(
+				self.logger,
+				"Rejecting LSPS1 order from {}: {}",
+				counterparty_node_id,
+				reason
+			);
+			return Err(ResponseError {
+				code: LSPS1_OPTION_MISMATCH_ERROR_CODE,
+				message: format!("Unsupported order: {}", reason),
+				data: serde_json::to_string(&service_config.supported_options()).ok(),
+			});
+		}
+
+		let order_params = request.order;
+		let id = LSPS1ServiceOrderId(self.keys_manager.get_secure_random_bytes());
+		let fee_total_sat = service_config.fee_total_sat(&order_params);
+		let order_total_sat = fee_total_sat.saturating_add(order_params.client_balance_sat);
+		let created_at = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap_or(Duration::from_secs(0))
+			.as_secs();
+		let expires_at = created_at.saturating_add(service_config.order_expiry_secs as u64);
+
+		let needs_payment = order_total_sat > 0;
+		if needs_payment
+			&& !service_config.accept_bolt11_payments
+			&& !service_config.accept_onchain_payments
+		{
+			log_error!(
+				self.logger,
+				"Rejecting LSPS1 order as neither BOLT11 nor on-chain payments are accepted."
+			);
+			return Err(internal_error("No payment option available"));
+		}
+
+		let (bolt11_invoice, payment_hash) =
+			if needs_payment && service_config.accept_bolt11_payments {
+				let invoice = self
+					.lsps1_create_invoice(&id, order_total_sat, service_config.order_expiry_secs)
+					.map_err(|_| internal_error("Failed to create invoice"))?;
+				let payment_hash = PaymentHash(invoice.payment_hash().to_byte_array());
+				(Some(invoice.to_string()), Some(payment_hash))
+			} else {
+				(None, None)
+			};
+
+		let (onchain_address, min_onchain_payment_confirmations) =
+			if needs_payment && service_config.accept_onchain_payments {
+				let address = self
+					.wallet
+					.get_new_address()
+					.map_err(|_| internal_error("Failed to generate address"))?;
+				(Some(address.to_string()), Some(service_config.min_onchain_payment_confirmations))
+			} else {
+				(None, None)
+			};
+
+		let state = if needs_payment {
+			LSPS1ServiceOrderState::AwaitingPayment
+		} else {
+			LSPS1ServiceOrderState::Paid
+		};
+
+		let order = LSPS1ServiceOrder {
+			id,
+			counterparty_node_id,
+			lsp_balance_sat: order_params.lsp_balance_sat,
+			client_balance_sat: order_params.client_balance_sat,
+			required_channel_confirmations: order_params.required_channel_confirmations,
+			funding_confirms_within_blocks: order_params.funding_confirms_within_blocks,
+			channel_expiry_blocks: order_params.channel_expiry_blocks,
+			token: order_params.token,
+			announce_channel: order_params.announce_channel,
+			fee_total_sat,
+			created_at,
+			expires_at,
+			bolt11_invoice,
+			payment_hash,
+			onchain_address,
+			min_onchain_payment_confirmations,
+			refund_onchain_address: request.refund_onchain_address.map(|a| a.to_string()),
+			state,
+		};
+		lsps1_service.order_store.insert(order.clone()).map_err(|e| {
+			log_error!(self.logger, "Failed to persist LSPS1 order: {}", e);
+			internal_error("Failed to persist order")
+		})?;
+
+		log_info!(
+			self.logger,
+			"Created LSPS1 order {} for {}: {}sat LSP balance, {}sat client balance, {}sat fee.",
+			id.to_order_id().0,
+			counterparty_node_id,
+			order.lsp_balance_sat,
+			order.client_balance_sat,
+			fee_total_sat,
+		);
+
+		if order.state == LSPS1ServiceOrderState::Paid {
+			let _order_state_lock = lsps1_service.order_state_lock.lock().unwrap();
+			self.lsps1_open_channel(lsps1_service, &order);
+		}
+
+		Ok(order.to_response())
+	}
+
+	fn lsps1_create_invoice(
+		&self, id: &LSPS1ServiceOrderId, amount_sat: u64, expiry_secs: u32,
+	) -> Result<Bolt11Invoice, Error> {
+		let description = Description::new(format!("LSPS1 order {}", id.to_order_id().0))
+			.map_err(|_| Error::InvoiceCreationFailed)?;
+		let invoice_params = Bolt11InvoiceParameters {
+			amount_msats: Some(amount_sat * 1000),
+			description: Bolt11InvoiceDescription::Direct(description),
+			invoice_expiry_delta_secs: Some(expiry_secs),
+			..Default::default()
+		};
+		let invoice = self.channel_manager.create_bolt11_invoice(invoice_params).map_err(|e| {
+			log_error!(self.logger, "Failed to create invoice for LSPS1 order: {}", e);
+			Error::InvoiceCreationFailed
+		})?;
+
+		// Register the payment so it will be claimed when it arrives.
+		let payment_hash = PaymentHash(invoice.payment_hash().to_byte_array());
+		let payment_secret = *invoice.payment_secret();
+		let preimage = self.channel_manager.get_payment_preimage(payment_hash, payment_secret).ok();
+		let kind = PaymentKind::Bolt11 {
+			hash: payment_hash,
+			preimage,
+			secret: Some(payment_secret),
+			claim_deadline: None,
+		};
+		let payment = PaymentDetails::new(
+			PaymentId(payment_hash.0),
+			kind,
+			Some(amount_sat * 1000),
+			None,
+			PaymentDirection::Inbound,
+			PaymentStatus::Pending,
+		);
+		self.payment_store.insert(payment)?;
+
+		Ok(invoice)
+	}
+
+	fn lsps1_open_channel(&self, lsps1_service: &LSPS1Service, order: &LSPS1ServiceOrder) {
+		let their_network_key = order.counterparty_node_id;
+		let order_id = order.id.to_order_id().0;
+
+		// If any of the below fails, we'll retry opening the channel on the next order check.
+		let init_features = if let Some(peer_manager) = self.peer_manager.read().unwrap().as_ref() {
+			if let Some(peer) = peer_manager.peer_by_node_id(&their_network_key) {
+				peer.init_features
+			} else {
+				log_error!(
+					self.logger,
+					"Failed to open channel for LSPS1 order {} due to peer {} not being connected.",
+					order_id,
+					their_network_key,
+				);
+				return;
+			}
+		} else {
+			log_error!(
+				self.logger,
+				"Failed to open channel for LSPS1 order {} as peer manager isn't available.",
+				order_id
+			);
+			return;
+		};
+
+		let channel_amount_sats = order.lsp_balance_sat + order.client_balance_sat;
+		let cur_anchor_reserve_sats =
+			total_anchor_channels_reserve_sats(&self.channel_manager, &self.config);
+		let spendable_amount_sats =
+			self.wallet.get_spendable_amount_sats(cur_anchor_reserve_sats).unwrap_or(0);
+		let required_funds_sats = channel_amount_sats
+			+ self.config.anchor_channels_config.as_ref().map_or(0, |c| {
+				if init_features.requires_anchors_zero_fee_htlc_tx()
+					&& !c.trusted_peers_no_reserve.contains(&their_network_key)
+				{
+					c.per_channel_reserve_sats
+				} else {
+					0
+				}
+			});
+		if spendable_amount_sats < required_funds_sats {
+			log_error!(self.logger,
+				"Unable to open channel for LSPS1 order {} due to insufficient funds. Available: {}sats, Required: {}sats",
+				order_id, spendable_amount_sats, required_funds_sats
+			);
+			return;
+		}
+
+		let mut config = *self.channel_manager.get_current_default_configuration();
+		config.channel_handshake_config.announce_for_forwarding = order.announce_channel;
+
+		let user_channel_id: u128 = rand::thread_rng().gen::<u128>();
+		// Pay enough fees for the funding transaction to confirm within the agreed upon blocks.
+		let confirmation_target =
+			get_funding_target_for_num_blocks(order.funding_confirms_within_blocks);
+		self.wallet.set_funding_confirmation_target(user_channel_id, confirmation_target);
+		match self.channel_manager.create_channel(
+			their_network_key,
+			channel_amount_sats,
+			order.client_balance_sat * 1000,
+			user_channel_id,
+			None,
+			Some(config),
+		) {
+			Ok(_) => {
+				log_info!(
+					self.logger,
+					"Initiated opening channel of {}sats to {} for LSPS1 order {}.",
+					channel_amount_sats,
+					their_network_key,
+					order_id
+				);
+				self.update_lsps1_order_state(
+					lsps1_service,
+					order,
+					LSPS1ServiceOrderState::ChannelOpening { user_channel_id },
+				);
+			},
+			Err(e) => {
+				log_error!(
+					self.logger,
+					"Failed to open channel for LSPS1 order {}: {:?}",
+					order_id,
+					e
+				);
+				self.wallet.take_funding_confirmation_target(user_channel_id);
+			},
+		}
+	}
+
+	fn update_lsps1_order_state(
+		&self, lsps1_service: &LSPS1Service, order: &LSPS1ServiceOrder,
+		state: LSPS1ServiceOrderState,
+	) -> Option<LSPS1ServiceOrder> {
+		let update = LSPS1ServiceOrderUpdate { id: order.id, state: Some(state) };
+		if let Err(e) = lsps1_service.order_store.update(&update) {
+			log_error!(
+				self.logger,
+				"Failed to update LSPS1 order {}: {}",
+				order.id.to_order_id().0,
+				e
+			);
+			return None;
+		}
+		lsps1_service.order_store.get(&order.id)
+	}
 }
 
 #[derive(Debug, Clone)]
@@ -1364,6 +3047,307 @@ impl From<lightning_liquidity::lsps1::msgs::OnchainPaymentInfo> for OnchainPayme
 	}
 }
 
+/// The state of a channel order placed with our LSPS1 liquidity source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LSPS1OrderState {
+	/// The LSP is waiting for the order to be paid.
+	AwaitingPayment,
+	/// The LSP received the payment and is about to open the channel.
+	Paid,
+	/// The LSP opened the channel.
+	ChannelOpened {
+		/// The funding transaction output of the channel.
+		funding_txo: OutPoint,
+	},
+	/// The LSP refunded the payment as it failed to open the channel.
+	Refunded,
+	/// The order expired before the LSP received the payment.
+	Expired,
+}
+
+impl LSPS1OrderState {
+	/// Returns whether the order reached a state in which it won't change anymore.
+	pub fn is_final(&self) -> bool {
+		match self {
+			Self::AwaitingPayment | Self::Paid => false,
+			Self::ChannelOpened { .. } | Self::Refunded | Self::Expired => true,
+		}
+	}
+
+	fn from_order_status(status: &LSPS1OrderStatus, cur_timestamp: u64) -> Self {
+		if let Some(channel) = status.channel_state.as_ref() {
+			return Self::ChannelOpened { funding_txo: channel.funding_outpoint };
+		}
+
+		let bolt11 = status.payment_options.bolt11.as_ref();
+		let onchain = status.payment_options.onchain.as_ref();
+		let payment_states = bolt11
+			.map(|b| &b.state)
+			.into_iter()
+			.chain(onchain.map(|o| &o.state))
+			.collect::<Vec<_>>();
+		if payment_states.iter().any(|s| **s == PaymentState::Refunded) {
+			return Self::Refunded;
+		}
+		if payment_states.iter().any(|s| **s == PaymentState::Paid) {
+			return Self::Paid;
+		}
+
+		let expires_at = bolt11.map(|b| b.expires_at).or(onchain.map(|o| o.expires_at));
+		match expires_at {
+			Some(expires_at) if expires_at.timestamp() <= cur_timestamp as i64 => Self::Expired,
+			_ => Self::AwaitingPayment,
+		}
+	}
+}
+
+impl_writeable_tlv_based_enum!(LSPS1OrderState,
+	(0, AwaitingPayment) => {},
+	(2, Paid) => {},
+	(4, ChannelOpened) => {
+		(0, funding_txo, required),
+	},
+	(6, Refunded) => {},
+	(8, Expired) => {}
+);
+
+/// Details of a channel order placed with our LSPS1 liquidity source.
+///
+/// Orders are retrieved via [`LSPS1Liquidity::list_orders`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LSPS1OrderDetails {
+	/// The id of the channel order.
+	pub order_id: OrderId,
+	/// The node id of the LSP the order was placed with.
+	pub lsp_node_id: PublicKey,
+	/// The balance, in satoshis, the LSP provides on its side of the channel.
+	pub lsp_balance_sat: u64,
+	/// The balance, in satoshis, the LSP pushes to our side of the channel.
+	pub client_balance_sat: u64,
+	/// The number of blocks the LSP promises to keep the channel open.
+	pub channel_expiry_blocks: u32,
+	/// Whether the channel is to be announced to the gossip network.
+	pub announce_channel: bool,
+	/// The total fee, in satoshis, the LSP charges for opening the channel.
+	pub fee_total_sat: u64,
+	/// The amount, in satoshis, we need to pay to have the channel opened.
+	pub order_total_sat: u64,
+	/// The time, in seconds since start of the UNIX epoch, at which the order's payment options
+	/// expire.
+	pub payment_expires_at: u64,
+	/// The latest known state of the order.
+	pub state: LSPS1OrderState,
+	/// The id of the Lightning payment we made to pay for the order, if any.
+	pub payment_id: Option<PaymentId>,
+	/// The id of the on-chain transaction we sent to pay for the order, if any.
+	pub payment_txid: Option<Txid>,
+	/// The time, in seconds since start of the UNIX epoch, at which the order was placed.
+	pub created_at: u64,
+	/// The timestamp, in seconds since start of the UNIX epoch, when this entry was last updated.
+	pub latest_update_timestamp: u64,
+}
+
+impl LSPS1OrderDetails {
+	fn new(status: &LSPS1OrderStatus, lsp_node_id: PublicKey) -> Self {
+		let cur_timestamp = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap_or(Duration::from_secs(0))
+			.as_secs();
+		let bolt11 = status.payment_options.bolt11.as_ref();
+		let onchain = status.payment_options.onchain.as_ref();
+		let (fee_total_sat, order_total_sat) = bolt11
+			.map(|b| (b.fee_total_sat, b.order_total_sat))
+			.or(onchain.map(|o| (o.fee_total_sat, o.order_total_sat)))
+			.unwrap_or((0, 0));
+		let payment_expires_at = bolt11
+			.map(|b| b.expires_at)
+			.or(onchain.map(|o| o.expires_at))
+			.map_or(0, |e| e.timestamp().max(0) as u64);
+		Self {
+			order_id: status.order_id.clone(),
+			lsp_node_id,
+			lsp_balance_sat: status.order_params.lsp_balance_sat,
+			client_balance_sat: status.order_params.client_balance_sat,
+			channel_expiry_blocks: status.order_params.channel_expiry_blocks,
+			announce_channel: status.order_params.announce_channel,
+			fee_total_sat,
+			order_total_sat,
+			payment_expires_at,
+			state: LSPS1OrderState::from_order_status(status, cur_timestamp),
+			payment_id: None,
+			payment_txid: None,
+			created_at: cur_timestamp,
+			latest_update_timestamp: cur_timestamp,
+		}
+	}
+}
+
+/// The identifier of a channel order placed with an LSPS1 liquidity source.
+///
+/// Wraps [`OrderId`] so it can be persisted as part of [`Event::LSPS1OrderUpdated`].
+///
+/// [`Event::LSPS1OrderUpdated`]: crate::Event::LSPS1OrderUpdated
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LSPS1OrderId(pub OrderId);
+
+impl Writeable for LSPS1OrderId {
+	fn write<W: Writer>(&self, writer: &mut W) -> Result<(), lightning::io::Error> {
+		self.0 .0.write(writer)
+	}
+}
+
+impl Readable for LSPS1OrderId {
+	fn read<R: lightning::io::Read>(reader: &mut R) -> Result<Self, DecodeError> {
+		Ok(Self(OrderId(Readable::read(reader)?)))
+	}
+}
+
+// Note that we can't use `impl_writeable_tlv_based` here, as `OrderId` doesn't implement
+// `Writeable`/`Readable`.
+impl Writeable for LSPS1OrderDetails {
+	fn write<W: Writer>(&self, writer: &mut W) -> Result<(), lightning::io::Error> {
+		write_tlv_fields!(writer, {
+			(0, self.order_id.0, required),
+			(2, self.lsp_node_id, required),
+			(4, self.lsp_balance_sat, required),
+			(6, self.client_balance_sat, required),
+			(8, self.channel_expiry_blocks, required),
+			(10, self.announce_channel, required),
+			(12, self.fee_total_sat, required),
+			(14, self.order_total_sat, required),
+			(16, self.payment_expires_at, required),
+			(18, self.state, required),
+			(20, self.payment_id, option),
+			(22, self.payment_txid, option),
+			(24, self.created_at, required),
+			(26, self.latest_update_timestamp, required),
+		});
+		Ok(())
+	}
+}
+
+impl Readable for LSPS1OrderDetails {
+	fn read<R: lightning::io::Read>(reader: &mut R) -> Result<Self, DecodeError> {
+		_init_and_read_len_prefixed_tlv_fields!(reader, {
+			(0, order_id, required),
+			(2, lsp_node_id, required),
+			(4, lsp_balance_sat, required),
+			(6, client_balance_sat, required),
+			(8, channel_expiry_blocks, required),
+			(10, announce_channel, required),
+			(12, fee_total_sat, required),
+			(14, order_total_sat, required),
+			(16, payment_expires_at, required),
+			(18, state, required),
+			(20, payment_id, option),
+			(22, payment_txid, option),
+			(24, created_at, required),
+			(26, latest_update_timestamp, required),
+		});
+
+		let order_id: String = order_id.0.ok_or(DecodeError::InvalidValue)?;
+		Ok(Self {
+			order_id: OrderId(order_id),
+			lsp_node_id: lsp_node_id.0.ok_or(DecodeError::InvalidValue)?,
+			lsp_balance_sat: lsp_balance_sat.0.ok_or(DecodeError::InvalidValue)?,
+			client_balance_sat: client_balance_sat.0.ok_or(DecodeError::InvalidValue)?,
+			channel_expiry_blocks: channel_expiry_blocks.0.ok_or(DecodeError::InvalidValue)?,
+			announce_channel: announce_channel.0.ok_or(DecodeError::InvalidValue)?,
+			fee_total_sat: fee_total_sat.0.ok_or(DecodeError::InvalidValue)?,
+			order_total_sat: order_total_sat.0.ok_or(DecodeError::InvalidValue)?,
+			payment_expires_at: payment_expires_at.0.ok_or(DecodeError::InvalidValue)?,
+			state: state.0.ok_or(DecodeError::InvalidValue)?,
+			payment_id,
+			payment_txid,
+			created_at: created_at.0.ok_or(DecodeError::InvalidValue)?,
+			latest_update_timestamp: latest_update_timestamp.0.ok_or(DecodeError::InvalidValue)?,
+		})
+	}
+}
+
+impl StorableObjectId for OrderId {
+	fn encode_to_hex_str(&self) -> String {
+		// Order ids are chosen by the LSP, so we hash them to get keys of bounded length and
+		// charset.
+		sha256::Hash::hash(self.0.as_bytes()).to_string()
+	}
+}
+
+impl StorableObject for LSPS1OrderDetails {
+	type Id = OrderId;
+	type Update = LSPS1OrderDetailsUpdate;
+
+	fn id(&self) -> Self::Id {
+		self.order_id.clone()
+	}
+
+	fn update(&mut self, update: &Self::Update) -> bool {
+		debug_assert_eq!(
+			self.order_id, update.order_id,
+			"We should only ever update orders with the same id"
+		);
+
+		let mut updated = false;
+
+		macro_rules! update_if_necessary {
+			($val: expr, $update: expr) => {
+				if $val != $update {
+					$val = $update;
+					updated = true;
+				}
+			};
+		}
+
+		if let Some(state) = update.state {
+			update_if_necessary!(self.state, state);
+		}
+		if let Some(payment_id) = update.payment_id {
+			update_if_necessary!(self.payment_id, Some(payment_id));
+		}
+		if let Some(payment_txid) = update.payment_txid {
+			update_if_necessary!(self.payment_txid, Some(payment_txid));
+		}
+
+		if updated {
+			self.latest_update_timestamp = SystemTime::now()
+				.duration_since(UNIX_EPOCH)
+				.unwrap_or(Duration::from_secs(0))
+				.as_secs();
+		}
+
+		updated
+	}
+
+	fn to_update(&self) -> Self::Update {
+		LSPS1OrderDetailsUpdate {
+			order_id: self.order_id.clone(),
+			state: Some(self.state),
+			payment_id: self.payment_id,
+			payment_txid: self.payment_txid,
+		}
+	}
+}
+
+/// Updates the details of an [`LSPS1OrderDetails`].
+pub(crate) struct LSPS1OrderDetailsUpdate {
+	pub order_id: OrderId,
+	pub state: Option<LSPS1OrderState>,
+	pub payment_id: Option<PaymentId>,
+	pub payment_txid: Option<Txid>,
+}
+
+impl LSPS1OrderDetailsUpdate {
+	fn new(order_id: OrderId) -> Self {
+		Self { order_id, state: None, payment_id: None, payment_txid: None }
+	}
+}
+
+impl StorableObjectUpdate<LSPS1OrderDetails> for LSPS1OrderDetailsUpdate {
+	fn id(&self) -> <LSPS1OrderDetails as StorableObject>::Id {
+		self.order_id.clone()
+	}
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct LSPS2FeeResponse {
 	opening_fee_params_menu: Vec<OpeningFeeParams>,
@@ -1392,6 +3376,8 @@ pub struct LSPS1Liquidity {
 	wallet: Arc<Wallet>,
 	connection_manager: Arc<ConnectionManager<Arc<Logger>>>,
 	liquidity_source: Option<Arc<LiquiditySource<Arc<Logger>>>>,
+	bolt11_payment: Arc<Bolt11Payment>,
+	onchain_payment: Arc<OnchainPayment>,
 	logger: Arc<Logger>,
 }
 
@@ -1399,9 +3385,19 @@ impl LSPS1Liquidity {
 	pub(crate) fn new(
 		runtime: Arc<RwLock<Option<Arc<tokio::runtime::Runtime>>>>, wallet: Arc<Wallet>,
 		connection_manager: Arc<ConnectionManager<Arc<Logger>>>,
-		liquidity_source: Option<Arc<LiquiditySource<Arc<Logger>>>>, logger: Arc<Logger>,
+		liquidity_source: Option<Arc<LiquiditySource<Arc<Logger>>>>,
+		bolt11_payment: Arc<Bolt11Payment>, onchain_payment: Arc<OnchainPayment>,
+		logger: Arc<Logger>,
 	) -> Self {
-		Self { runtime, wallet, connection_manager, liquidity_source, logger }
+		Self {
+			runtime,
+			wallet,
+			connection_manager,
+			liquidity_source,
+			bolt11_payment,
+			onchain_payment,
+			logger,
+		}
 	}
 
 	/// Connects to the configured LSP and places an order for an inbound channel.
@@ -1486,4 +3482,450 @@ impl LSPS1Liquidity {
 
 		Ok(response)
 	}
+
+	/// Connects to the configured LSP, places an order for an inbound channel, and pays for it.
+	///
+	/// The order is paid via the BOLT11 invoice returned by the LSP if available, and via an
+	/// on-chain payment to the returned address otherwise.
+	///
+	/// If the total fee the LSP charges for opening the channel exceeds `max_fee_total_sat`, the
+	/// order is left unpaid and [`Error::LiquidityFeeTooHigh`] is returned. Likewise, the order is
+	/// left unpaid if the invoice amount doesn't match the order total or the address isn't valid
+	/// on our network.
+	///
+	/// When paying via BOLT11, the routing fees are limited to what remains of `max_fee_total_sat`
+	/// after the LSP's fee. Note that the on-chain transaction fee is not covered by
+	/// `max_fee_total_sat`, i.e., when paying on-chain only the LSP's fee is capped and the
+	/// transaction fee is paid on top of the order total at the estimated fee rate.
+	///
+	/// The state of the order will be tracked in the background, and changes will be signaled via
+	/// [`Event::LSPS1OrderUpdated`].
+	///
+	/// [`Event::LSPS1OrderUpdated`]: crate::Event::LSPS1OrderUpdated
+	pub fn request_channel_with_auto_pay(
+		&self, lsp_balance_sat: u64, client_balance_sat: u64, channel_expiry_blocks: u32,
+		announce_channel: bool, max_fee_total_sat: u64,
+	) -> Result<LSPS1OrderDetails, Error> {
+		let liquidity_source =
+			self.liquidity_source.as_ref().ok_or(Error::LiquiditySourceUnavailable)?;
+
+		let status = self.request_channel(
+			lsp_balance_sat,
+			client_balance_sat,
+			channel_expiry_blocks,
+			announce_channel,
+		)?;
+		let order_id = status.order_id.clone();
+
+		let bolt11 = status.payment_options.bolt11.as_ref();
+		let onchain = status.payment_options.onchain.as_ref();
+		let (fee_total_sat, order_total_sat) = bolt11
+			.map(|b| (b.fee_total_sat, b.order_total_sat))
+			.or(onchain.map(|o| (o.fee_total_sat, o.order_total_sat)))
+			.ok_or_else(|| {
+				log_error!(self.logger, "LSPS1 order {} has no payment options.", order_id.0);
+				Error::LiquidityRequestFailed
+			})?;
+
+		if fee_total_sat > max_fee_total_sat
+			|| order_total_sat > client_balance_sat.saturating_add(max_fee_total_sat)
+		{
+			log_error!(
+				self.logger,
+				"Not paying for LSPS1 order {} as the LSP's order total of {}sat (fee: {}sat) exceeds our fee limit of {}sat",
+				order_id.0,
+				order_total_sat,
+				fee_total_sat,
+				max_fee_total_sat
+			);
+			return Err(Error::LiquidityFeeTooHigh);
+		}
+
+		let invoice_amount_msat = bolt11.map(|b| b.invoice.amount_milli_satoshis());
+		let address = onchain.map(|o| &o.address);
+		let network = liquidity_source.config.network;
+		if let Err(e) =
+			check_lsps1_payment_details(invoice_amount_msat, address, order_total_sat, network)
+		{
+			log_error!(self.logger, "Not paying for LSPS1 order {}: {}", order_id.0, e);
+			return Err(e.into());
+		}
+
+		if let Some(bolt11) = bolt11 {
+			// Spend no more on routing fees than what's left of our fee limit.
+			let max_routing_fee_msat =
+				max_fee_total_sat.saturating_sub(fee_total_sat).saturating_mul(1000);
+			let sending_parameters = SendingParameters {
+				max_total_routing_fee_msat: Some(Some(max_routing_fee_msat).into()),
+				max_total_cltv_expiry_delta: None,
+				max_path_count: None,
+				max_channel_saturation_power_of_half: None,
+			};
+			let payment_id = self.bolt11_payment.send(&bolt11.invoice, Some(sending_parameters))?;
+			liquidity_source.lsps1_record_order_payment(
+				order_id.clone(),
+				Some(payment_id),
+				None,
+			)?;
+		} else if let Some(onchain) = onchain {
+			let txid = self.onchain_payment.send_to_address(
+				&onchain.address,
+				order_total_sat,
+				None,
+				None,
+			)?;
+			liquidity_source.lsps1_record_order_payment(order_id.clone(), None, Some(txid))?;
+		}
+		log_info!(self.logger, "Paid {}sat for LSPS1 order {}.", order_total_sat, order_id.0);
+
+		liquidity_source.lsps1_get_order(&order_id).ok_or(Error::LiquidityRequestFailed)
+	}
+
+	/// Returns the channel orders we placed with our LSP.
+	pub fn list_orders(&self) -> Vec<LSPS1OrderDetails> {
+		self.liquidity_source.as_ref().map(|l| l.lsps1_list_orders()).unwrap_or_default()
+	}
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum LSPS1PaymentDetailsError {
+	InvoiceAmountMismatch { invoice_amount_msat: Option<u64>, order_total_sat: u64 },
+	AddressNetworkMismatch { network: Network },
+}
+
+impl fmt::Display for LSPS1PaymentDetailsError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::InvoiceAmountMismatch { invoice_amount_msat, order_total_sat } => write!(
+				f,
+				"the invoice amount of {:?}msat doesn't match the order total of {}sat",
+				invoice_amount_msat, order_total_sat
+			),
+			Self::AddressNetworkMismatch { network } => {
+				write!(f, "the payment address is not valid on {}", network)
+			},
+		}
+	}
+}
+
+impl From<LSPS1PaymentDetailsError> for Error {
+	fn from(e: LSPS1PaymentDetailsError) -> Self {
+		match e {
+			LSPS1PaymentDetailsError::InvoiceAmountMismatch {
+				invoice_amount_msat: Some(invoice_amount_msat),
+				order_total_sat,
+			} if invoice_amount_msat > order_total_sat.saturating_mul(1000) => Error::LiquidityFeeTooHigh,
+			_ => Error::LiquidityRequestFailed,
+		}
+	}
+}
+
+// Checks the payment options returned by the LSP actually pay for the order total, as we otherwise
+// might pay more than agreed upon or send funds to an address we can't expect to be credited for.
+fn check_lsps1_payment_details(
+	invoice_amount_msat: Option<Option<u64>>, address: Option<&bitcoin::Address>,
+	order_total_sat: u64, network: Network,
+) -> Result<(), LSPS1PaymentDetailsError> {
+	if let Some(invoice_amount_msat) = invoice_amount_msat {
+		if invoice_amount_msat != Some(order_total_sat.saturating_mul(1000)) {
+			return Err(LSPS1PaymentDetailsError::InvoiceAmountMismatch {
+				invoice_amount_msat,
+				order_total_sat,
+			});
+		}
+	}
+
+	if let Some(address) = address {
+		if !address.as_unchecked().is_valid_for_network(network) {
+			return Err(LSPS1PaymentDetailsError::AddressNetworkMismatch { network });
+		}
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::fee_estimator::ConfirmationTarget;
+
+	use lightning::chain::chaininterface::ConfirmationTarget as LdkConfirmationTarget;
+
+	fn service_config() -> LSPS1ServiceConfig {
+		LSPS1ServiceConfig {
+			require_token: None,
+			advertise_service: false,
+			min_channel_size_sat: 100_000,
+			max_channel_size_sat: 1_000_000,
+			max_client_balance_sat: 50_000,
+			max_channel_expiry_blocks: 10_000,
+			min_required_channel_confirmations: 1,
+			min_funding_confirms_within_blocks: 6,
+			channel_opening_fee_base_sat: 1_000,
+			channel_opening_fee_ppm: 10_000,
+			accept_bolt11_payments: true,
+			accept_onchain_payments: false,
+			min_onchain_payment_confirmations: 1,
+			order_expiry_secs: 3600,
+		}
+	}
+
+	fn order_params(lsp_balance_sat: u64, client_balance_sat: u64) -> OrderParameters {
+		OrderParameters {
+			lsp_balance_sat,
+			client_balance_sat,
+			required_channel_confirmations: 1,
+			funding_confirms_within_blocks: 6,
+			channel_expiry_blocks: 1_000,
+			token: None,
+			announce_channel: false,
+		}
+	}
+
+	#[test]
+	fn lsps1_orders_are_checked_against_service_config() {
+		let config = service_config();
+		assert!(config.check_order(&order_params(500_000, 10_000)).is_ok());
+		assert!(config.check_order(&order_params(50_000, 10_000)).is_err());
+		assert!(config.check_order(&order_params(1_000_000, 10_000)).is_err());
+		assert!(config.check_order(&order_params(500_000, 60_000)).is_err());
+
+		let mut params = order_params(500_000, 0);
+		params.channel_expiry_blocks = 20_000;
+		assert!(config.check_order(&params).is_err());
+
+		let mut params = order_params(500_000, 0);
+		params.required_channel_confirmations = 0;
+		assert!(config.check_order(&params).is_err());
+
+		// The proportional fee only applies to the LSP-side balance and is rounded up.
+		assert_eq!(config.fee_total_sat(&order_params(500_000, 10_000)), 6_000);
+		assert_eq!(config.fee_total_sat(&order_params(100_001, 0)), 2_001);
+	}
+
+	#[test]
+	fn lsps1_order_ids_round_trip() {
+		let id = LSPS1ServiceOrderId([42; 32]);
+		assert_eq!(LSPS1ServiceOrderId::from_order_id(&id.to_order_id()), Some(id));
+		assert_eq!(LSPS1ServiceOrderId::from_order_id(&OrderId("unknown".to_string())), None);
+	}
+
+	#[test]
+	fn lsps1_payment_details_must_match_order() {
+		let address = parse_address("bcrt1qs758ursh4q9z627kt3pp5yysm78ddny6txaqgw").unwrap();
+		let network = Network::Regtest;
+
+		assert_eq!(
+			check_lsps1_payment_details(Some(Some(16_000_000)), None, 16_000, network),
+			Ok(())
+		);
+		assert_eq!(check_lsps1_payment_details(None, Some(&address), 16_000, network), Ok(()));
+		assert_eq!(
+			check_lsps1_payment_details(Some(Some(16_000_000)), Some(&address), 16_000, network),
+			Ok(())
+		);
+
+		// The invoice needs to be for exactly the order total.
+		for invoice_amount_msat in [Some(16_000_001), Some(15_999_999), None] {
+			let res = check_lsps1_payment_details(Some(invoice_amount_msat), None, 16_000, network);
+			assert_eq!(
+				res,
+				Err(LSPS1PaymentDetailsError::InvoiceAmountMismatch {
+					invoice_amount_msat,
+					order_total_sat: 16_000
+				})
+			);
+		}
+		let overpaying = LSPS1PaymentDetailsError::InvoiceAmountMismatch {
+			invoice_amount_msat: Some(16_000_001),
+			order_total_sat: 16_000,
+		};
+		assert_eq!(Error::from(overpaying), Error::LiquidityFeeTooHigh);
+		let underpaying = LSPS1PaymentDetailsError::InvoiceAmountMismatch {
+			invoice_amount_msat: Some(15_999_999),
+			order_total_sat: 16_000,
+		};
+		assert_eq!(Error::from(underpaying), Error::LiquidityRequestFailed);
+
+		// The address needs to be on our network.
+		let res = check_lsps1_payment_details(None, Some(&address), 16_000, Network::Bitcoin);
+		assert_eq!(
+			res,
+			Err(LSPS1PaymentDetailsError::AddressNetworkMismatch { network: Network::Bitcoin })
+		);
+		assert_eq!(Error::from(res.unwrap_err()), Error::LiquidityRequestFailed);
+	}
+
+	#[test]
+	fn lsps1_service_requests_are_intercepted() {
+		let list_protocols =
+			r#"{"jsonrpc":"2.0","id":"a","method":"lsps0.list_protocols","params":{}}"#;
+		let (request_id, method, _) = parse_lsps1_request(list_protocols).unwrap();
+		assert_eq!(request_id, RequestId("a".to_string()));
+		assert_eq!(method, LSPS0_LIST_PROTOCOLS_METHOD_NAME);
+
+		let get_info = r#"{"jsonrpc":"2.0","id":"b","method":"lsps1.get_info"}"#;
+		let (_, method, params) = parse_lsps1_request(get_info).unwrap();
+		assert_eq!(method, LSPS1_GET_INFO_METHOD_NAME);
+		assert_eq!(params, serde_json::Value::Object(Default::default()));
+
+		let lsps2_get_info = r#"{"jsonrpc":"2.0","id":"c","method":"lsps2.get_info","params":{}}"#;
+		assert!(parse_lsps1_request(lsps2_get_info).is_none());
+		let response = r#"{"jsonrpc":"2.0","id":"d","result":{"protocols":[2]}}"#;
+		assert!(parse_lsps1_request(response).is_none());
+	}
+
+	#[test]
+	fn lsps1_funding_target_honors_requested_blocks() {
+		assert_eq!(get_funding_target_for_num_blocks(144), ConfirmationTarget::ChannelFunding);
+		assert_eq!(get_funding_target_for_num_blocks(12), ConfirmationTarget::ChannelFunding);
+		assert_eq!(get_funding_target_for_num_blocks(6), ConfirmationTarget::OnchainPayment);
+		assert_eq!(
+			get_funding_target_for_num_blocks(1),
+			ConfirmationTarget::Lightning(LdkConfirmationTarget::MaximumFeeEstimate)
+		);
+		assert_eq!(
+			get_funding_target_for_num_blocks(0),
+			ConfirmationTarget::Lightning(LdkConfirmationTarget::MaximumFeeEstimate)
+		);
+	}
+
+	#[test]
+	fn lsps1_order_state_follows_order_status() {
+		let expires_at = 1_000_000;
+		let payment = LdkOnchainPaymentInfo {
+			state: PaymentState::ExpectPayment,
+			expires_at: timestamp_to_datetime(expires_at),
+			fee_total_sat: 6_000,
+			order_total_sat: 16_000,
+			address: parse_address("bcrt1qs758ursh4q9z627kt3pp5yysm78ddny6txaqgw").unwrap(),
+			min_onchain_payment_confirmations: Some(1),
+			min_fee_for_0conf: FeeRate::from_sat_per_kwu(253),
+			refund_onchain_address: None,
+		};
+		let mut status = LSPS1OrderStatus {
+			order_id: OrderId("order".to_string()),
+			order_params: order_params(500_000, 10_000),
+			payment_options: LdkPaymentInfo { bolt11: None, onchain: Some(payment) },
+			channel_state: None,
+		};
+
+		let state = |status: &LSPS1OrderStatus, timestamp| {
+			LSPS1OrderState::from_order_status(status, timestamp)
+		};
+		assert_eq!(state(&status, expires_at - 1), LSPS1OrderState::AwaitingPayment);
+		assert_eq!(state(&status, expires_at), LSPS1OrderState::Expired);
+
+		status.payment_options.onchain.as_mut().unwrap().state = PaymentState::Paid;
+		assert_eq!(state(&status, expires_at), LSPS1OrderState::Paid);
+
+		let funding_txo = OutPoint { txid: Txid::from_byte_array([1; 32]), vout: 0 };
+		status.channel_state = Some(ChannelInfo {
+			funded_at: timestamp_to_datetime(expires_at),
+			funding_outpoint: funding_txo,
+			expires_at: timestamp_to_datetime(expires_at * 2),
+		});
+		assert_eq!(state(&status, expires_at), LSPS1OrderState::ChannelOpened { funding_txo });
+		assert!(state(&status, expires_at).is_final());
+
+		let mut order = LSPS1OrderDetails::new(&status, PublicKey::from_slice(&[2; 33]).unwrap());
+		assert_eq!(order.order_total_sat, 16_000);
+		assert_eq!(order.payment_expires_at, expires_at);
+		order.payment_txid = Some(funding_txo.txid);
+		let decoded = LSPS1OrderDetails::read(&mut &order.encode()[..]).unwrap();
+		assert_eq!(decoded, order);
+	}
+
+	#[test]
+	fn lsps2_client_requests_are_limited() {
+		let config = LSPS2ServiceConfig {
+			require_token: None,
+			advertise_service: false,
+			channel_opening_fee_ppm: 10_000,
+			channel_over_provisioning_ppm: 100_000,
+			min_channel_opening_fee_msat: 0,
+			min_channel_lifetime: 100,
+			max_client_to_self_delay: 1024,
+			min_payment_size_msat: 0,
+			max_payment_size_msat: 1_000_000_000,
+			max_client_requests_per_hour: Some(3),
+			max_pending_requests_per_client: Some(2),
+			max_outstanding_liquidity_sat: None,
+		};
+		let cur_timestamp = 1_000_000;
+		let sale = |user_channel_id, created_at, state| LSPS2SaleDetails {
+			user_channel_id: UserChannelId(user_channel_id),
+			counterparty_node_id: PublicKey::from_slice(&[2; 33]).unwrap(),
+			intercept_scid: 42,
+			payment_size_msat: None,
+			channel_amount_sat: Some(110_000),
+			opening_fee_msat: None,
+			state,
+			created_at,
+			latest_update_timestamp: created_at,
+		};
+
+		let mut sales = vec![sale(0, cur_timestamp - 10, LSPS2SaleState::ChannelOpening)];
+		assert!(config.check_client_request(&sales, cur_timestamp).is_ok());
+		assert_eq!(sales[0].outstanding_liquidity_sat(), 110_000);
+
+		// Expired and completed sales don't count as pending.
+		let expired_at = cur_timestamp - LSPS2_GETINFO_REQUEST_EXPIRY.as_secs();
+		sales.push(sale(1, expired_at, LSPS2SaleState::AwaitingPayment));
+		let channel_id = ChannelId([1; 32]);
+		sales.push(sale(2, cur_timestamp - 20, LSPS2SaleState::Completed { channel_id }));
+		assert_eq!(sales[2].outstanding_liquidity_sat(), 0);
+		assert!(config.check_client_request(&sales, cur_timestamp).is_ok());
+
+		sales.push(sale(3, cur_timestamp - 30, LSPS2SaleState::AwaitingPayment));
+		assert_eq!(
+			config.check_client_request(&sales, cur_timestamp),
+			Err("too many requests within the last hour")
+		);
+		sales.truncate(2);
+		sales.push(sale(3, cur_timestamp - 30, LSPS2SaleState::AwaitingPayment));
+		assert_eq!(
+			config.check_client_request(&sales, cur_timestamp),
+			Err("too many pending requests")
+		);
+
+		let decoded = LSPS2SaleDetails::read(&mut &sales[0].encode()[..]).unwrap();
+		assert_eq!(decoded, sales[0]);
+	}
+
+	#[test]
+	fn lsps2_sales_only_await_channels_we_started_opening() {
+		let mut sale = LSPS2SaleDetails {
+			user_channel_id: UserChannelId(0),
+			counterparty_node_id: PublicKey::from_slice(&[2; 33]).unwrap(),
+			intercept_scid: 42,
+			payment_size_msat: None,
+			channel_amount_sat: Some(110_000),
+			opening_fee_msat: None,
+			state: LSPS2SaleState::ChannelOpening,
+			created_at: 1_000_000,
+			latest_update_timestamp: 1_000_000,
+		};
+
+		// We merely reserved liquidity for an intercepted payment, but didn't open a channel yet.
+		assert!(!sale.awaits_channel());
+		assert_eq!(sale.outstanding_liquidity_sat(), 110_000);
+
+		sale.opening_fee_msat = Some(1_000_000);
+		assert!(sale.awaits_channel());
+		sale.state = LSPS2SaleState::ChannelReady { channel_id: ChannelId([1; 32]) };
+		assert!(sale.awaits_channel());
+		assert!(sale.is_pending(1_000_000));
+
+		// Once the channel is gone, the sale is failed and releases its liquidity.
+		let mut update = LSPS2SaleDetailsUpdate::new(sale.user_channel_id);
+		update.state = Some(LSPS2SaleState::Failed);
+		assert!(sale.update(&update));
+		assert!(!sale.awaits_channel());
+		assert!(!sale.is_pending(1_000_000));
+		assert_eq!(sale.outstanding_liquidity_sat(), 0);
+
+		sale.state = LSPS2SaleState::Completed { channel_id: ChannelId([1; 32]) };
+		assert!(!sale.awaits_channel());
+	}
 }
@@ -12,6 +12,10 @@ pub(crate) use lightning::{log_bytes, log_debug, log_error, log_info, log_trace}
 
 pub use lightning::util::logger::Level as LogLevel;
 
+use lightning::ln::types::ChannelId;
+use lightning_types::payment::PaymentHash;
+
+use bitcoin::secp256k1::PublicKey;
 use chrono::Utc;
 use log::Level as LogFacadeLevel;
 use log::Record as LogFacadeRecord;
@@ -21,7 +25,8 @@ use core::fmt;
 use std::fs;
 use std::io::Write;
 use std::path::Path;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
 
 /// A unit of logging output with metadata to enable filtering `module_path`,
 /// `file`, and `line` to inform on log's source.
@@ -35,6 +40,12 @@ pub struct LogRecord<'a> {
 	pub module_path: &'a str,
 	/// The line containing the message.
 	pub line: u32,
+	/// The node id of the peer pertaining to the logged record, if any.
+	pub peer_id: Option<PublicKey>,
+	/// The channel id of the channel pertaining to the logged record, if any.
+	pub channel_id: Option<ChannelId>,
+	/// The payment hash of the payment pertaining to the logged record, if any.
+	pub payment_hash: Option<PaymentHash>,
 }
 
 /// A unit of logging output with metadata to enable filtering `module_path`,
@@ -53,6 +64,12 @@ pub struct LogRecord {
 	pub module_path: String,
 	/// The line containing the message.
 	pub line: u32,
+	/// The node id of the peer pertaining to the logged record, if any.
+	pub peer_id: Option<PublicKey>,
+	/// The channel id of the channel pertaining to the logged record, if any.
+	pub channel_id: Option<ChannelId>,
+	/// The payment hash of the payment pertaining to the logged record, if any.
+	pub payment_hash: Option<PaymentHash>,
 }
 
 #[cfg(feature = "uniffi")]
@@ -63,6 +80,9 @@ impl<'a> From<LdkRecord<'a>> for LogRecord {
 			args: record.args.to_string(),
 			module_path: record.module_path.to_string(),
 			line: record.line,
+			peer_id: record.peer_id,
+			channel_id: record.channel_id,
+			payment_hash: record.payment_hash,
 		}
 	}
 }
@@ -75,6 +95,9 @@ impl<'a> From<LdkRecord<'a>> for LogRecord<'a> {
 			args: record.args,
 			module_path: record.module_path,
 			line: record.line,
+			peer_id: record.peer_id,
+			channel_id: record.channel_id,
+			payment_hash: record.payment_hash,
 		}
 	}
 }
@@ -104,10 +127,147 @@ pub trait LogWriter: Send + Sync {
 	fn log(&self, record: LogRecord);
 }
 
+/// The format in which logs are written to the filesystem.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+	/// One human-readable line per record.
+	Text,
+	/// One JSON object per line, including any structured fields such as the `peer_id`,
+	/// `channel_id`, or `payment_hash` the record pertains to.
+	Json,
+}
+
+/// Specifies when the log file is rotated and how many rotated files are retained.
+///
+/// Rotated files are named after the log file with an increasing numeric suffix, i.e., the most
+/// recent one is `<log_file_path>.1`. Once more than `max_files` rotated files exist, the oldest one
+/// is deleted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogRotation {
+	/// Rotate the log file once it would exceed `max_file_size_bytes`.
+	Size {
+		/// The maximum size of the log file, in bytes.
+		max_file_size_bytes: u64,
+		/// The number of rotated files to retain.
+		max_files: u32,
+	},
+	/// Rotate the log file once it was written to for longer than `interval_secs`.
+	Interval {
+		/// The time after which the log file is rotated, in seconds.
+		interval_secs: u64,
+		/// The number of rotated files to retain.
+		max_files: u32,
+	},
+}
+
+impl LogRotation {
+	fn max_files(&self) -> u32 {
+		match self {
+			Self::Size { max_files, .. } | Self::Interval { max_files, .. } => *max_files,
+		}
+	}
+}
+
+/// Writes logs to the file system, rotating the file if configured.
+pub(crate) struct FsWriter {
+	file_path: String,
+	max_log_level: LogLevel,
+	log_format: LogFormat,
+	log_rotation: Option<LogRotation>,
+	// The time the current log file was started, guarding file access during rotation.
+	file_started_at: Mutex<SystemTime>,
+}
+
+impl FsWriter {
+	fn log(&self, record: LogRecord) {
+		if record.level < self.max_log_level {
+			return;
+		}
+
+		let log = match self.log_format {
+			LogFormat::Text => format!(
+				"{} {:<5} [{}:{}] {}\n",
+				Utc::now().format("%Y-%m-%d %H:%M:%S"),
+				record.level.to_string(),
+				record.module_path,
+				record.line,
+				record.args
+			),
+			LogFormat::Json => {
+				let mut entry = serde_json::Map::new();
+				entry.insert(
+					"timestamp".into(),
+					Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string().into(),
+				);
+				entry.insert("level".into(), record.level.to_string().into());
+				entry.insert("module_path".into(), record.module_path.to_string().into());
+				entry.insert("line".into(), record.line.into());
+				entry.insert("message".into(), record.args.to_string().into());
+				if let Some(peer_id) = record.peer_id {
+					entry.insert("peer_id".into(), peer_id.to_string().into());
+				}
+				if let Some(channel_id) = record.channel_id {
+					entry.insert("channel_id".into(), channel_id.to_string().into());
+				}
+				if let Some(payment_hash) = record.payment_hash {
+					entry.insert("payment_hash".into(), payment_hash.to_string().into());
+				}
+				let mut log = serde_json::Value::Object(entry).to_string();
+				log.push('\n');
+				log
+			},
+		};
+
+		let mut file_started_at = self.file_started_at.lock().unwrap();
+		if let Some(log_rotation) = &self.log_rotation {
+			let needs_rotation = match log_rotation {
+				LogRotation::Size { max_file_size_bytes, .. } => {
+					let file_size = fs::metadata(&self.file_path).map(|m| m.len()).unwrap_or(0);
+					file_size > 0 && file_size + log.len() as u64 > *max_file_size_bytes
+				},
+				LogRotation::Interval { interval_secs, .. } => {
+					let elapsed = file_started_at.elapsed().unwrap_or(Duration::ZERO);
+					elapsed >= Duration::from_secs(*interval_secs)
+				},
+			};
+
+			if needs_rotation {
+				rotate_log_files(&self.file_path, log_rotation.max_files());
+				*file_started_at = SystemTime::now();
+			}
+		}
+
+		fs::OpenOptions::new()
+			.create(true)
+			.append(true)
+			.open(&self.file_path)
+			.expect("Failed to open log file")
+			.write_all(log.as_bytes())
+			.expect("Failed to write to log file")
+	}
+}
+
+// Shifts `<file_path>.1` to `<file_path>.2` and so on, dropping any file beyond `max_files`, and
+// moves the current log file to `<file_path>.1`.
+fn rotate_log_files(file_path: &str, max_files: u32) {
+	if max_files == 0 {
+		let _ = fs::remove_file(file_path);
+		return;
+	}
+
+	let _ = fs::remove_file(format!("{}.{}", file_path, max_files));
+	for i in (1..max_files).rev() {
+		let _ = fs::rename(format!("{}.{}", file_path, i), format!("{}.{}", file_path, i + 1));
+	}
+	if let Err(e) = fs::rename(file_path, format!("{}.1", file_path)) {
+		eprintln!("ERROR: Failed to rotate log file: {}", e);
+	}
+}
+
 /// Defines a writer for [`Logger`].
 pub(crate) enum Writer {
 	/// Writes logs to the file system.
-	FileWriter { file_path: String, max_log_level: LogLevel },
+	FileWriter(FsWriter),
 	/// Forwards logs to the `log` facade.
 	LogFacadeWriter,
 	/// Forwards logs to a custom writer.
@@ -117,28 +277,7 @@ pub(crate) enum Writer {
 impl LogWriter for Writer {
 	fn log(&self, record: LogRecord) {
 		match self {
-			Writer::FileWriter { file_path, max_log_level } => {
-				if record.level < *max_log_level {
-					return;
-				}
-
-				let log = format!(
-					"{} {:<5} [{}:{}] {}\n",
-					Utc::now().format("%Y-%m-%d %H:%M:%S"),
-					record.level.to_string(),
-					record.module_path,
-					record.line,
-					record.args
-				);
-
-				fs::OpenOptions::new()
-					.create(true)
-					.append(true)
-					.open(file_path)
-					.expect("Failed to open log file")
-					.write_all(log.as_bytes())
-					.expect("Failed to write to log file")
-			},
+			Writer::FileWriter(fs_writer) => fs_writer.log(record),
 			Writer::LogFacadeWriter => {
 				let mut builder = LogFacadeRecord::builder();
 
@@ -181,8 +320,11 @@ pub(crate) struct Logger {
 
 impl Logger {
 	/// Creates a new logger with a filesystem writer. The parameters to this function
-	/// are the path to the log file, and the log level.
-	pub fn new_fs_writer(file_path: String, max_log_level: LogLevel) -> Result<Self, ()> {
+	/// are the path to the log file, the log level, the format, and the rotation policy.
+	pub fn new_fs_writer(
+		file_path: String, max_log_level: LogLevel, log_format: LogFormat,
+		log_rotation: Option<LogRotation>,
+	) -> Result<Self, ()> {
 		if let Some(parent_dir) = Path::new(&file_path).parent() {
 			fs::create_dir_all(parent_dir)
 				.map_err(|e| eprintln!("ERROR: Failed to create log parent directory: {}", e))?;
@@ -195,7 +337,19 @@ impl Logger {
 				.map_err(|e| eprintln!("ERROR: Failed to open log file: {}", e))?;
 		}
 
-		Ok(Self { writer: Writer::FileWriter { file_path, max_log_level } })
+		// Pick up where we left off if we're continuing to write an existing log file.
+		let file_started_at = fs::metadata(&file_path)
+			.and_then(|m| m.created())
+			.unwrap_or_else(|_| SystemTime::now());
+
+		let fs_writer = FsWriter {
+			file_path,
+			max_log_level,
+			log_format,
+			log_rotation,
+			file_started_at: Mutex::new(file_started_at),
+		};
+		Ok(Self { writer: Writer::FileWriter(fs_writer) })
 	}
 
 	pub fn new_log_facade() -> Self {
@@ -210,8 +364,8 @@ impl Logger {
 impl LdkLogger for Logger {
 	fn log(&self, record: LdkRecord) {
 		match &self.writer {
-			Writer::FileWriter { file_path: _, max_log_level } => {
-				if record.level < *max_log_level {
+			Writer::FileWriter(fs_writer) => {
+				if record.level < fs_writer.max_log_level {
 					return;
 				}
 				self.writer.log(record.into());
@@ -225,3 +379,122 @@ impl LdkLogger for Logger {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::io::test_utils::random_storage_path;
+
+	#[test]
+	fn json_logs_are_rotated_by_size() {
+		let mut log_dir = random_storage_path();
+		log_dir.push("logs");
+		let file_path = format!("{}/ldk_node.log", log_dir.display());
+
+		let log_rotation = LogRotation::Size { max_file_size_bytes: 512, max_files: 2 };
+		let logger = Logger::new_fs_writer(
+			file_path.clone(),
+			LogLevel::Trace,
+			LogFormat::Json,
+			Some(log_rotation),
+		)
+		.unwrap();
+
+		let channel_id = ChannelId([42; 32]);
+		let payment_hash = PaymentHash([43; 32]);
+		for i in 0..20 {
+			logger.log(LdkRecord::new(
+				LogLevel::Info,
+				None,
+				Some(channel_id),
+				format_args!("Log entry {}", i),
+				module_path!(),
+				file!(),
+				line!(),
+				Some(payment_hash),
+			));
+		}
+		log_info!(logger, "Last entry");
+
+		// Only the current and the two most recent rotated files are retained.
+		assert!(Path::new(&format!("{}.1", file_path)).exists());
+		assert!(Path::new(&format!("{}.2", file_path)).exists());
+		assert!(!Path::new(&format!("{}.3", file_path)).exists());
+
+		for path in [file_path.clone(), format!("{}.1", file_path)] {
+			assert!(fs::metadata(&path).unwrap().len() <= 512);
+		}
+
+		let rotated_log = fs::read_to_string(format!("{}.1", file_path)).unwrap();
+		let entry: serde_json::Value =
+			serde_json::from_str(rotated_log.lines().next().unwrap()).unwrap();
+		assert_eq!(entry["level"], "INFO");
+		assert_eq!(entry["module_path"], module_path!());
+		assert_eq!(entry["channel_id"], channel_id.to_string());
+		assert_eq!(entry["payment_hash"], payment_hash.to_string());
+		assert!(entry.get("peer_id").is_none());
+		assert!(entry["message"].as_str().unwrap().starts_with("Log entry"));
+
+		let current_log = fs::read_to_string(&file_path).unwrap();
+		let last_entry: serde_json::Value =
+			serde_json::from_str(current_log.lines().last().unwrap()).unwrap();
+		assert_eq!(last_entry["message"], "Last entry");
+
+		fs::remove_dir_all(log_dir).unwrap();
+	}
+
+	#[test]
+	fn text_logs_are_rotated_by_interval() {
+		let mut log_dir = random_storage_path();
+		log_dir.push("logs");
+		let file_path = format!("{}/ldk_node.log", log_dir.display());
+
+		let interval_secs = 3600;
+		let log_rotation = LogRotation::Interval { interval_secs, max_files: 1 };
+		let logger = Logger::new_fs_writer(
+			file_path.clone(),
+			LogLevel::Trace,
+			LogFormat::Text,
+			Some(log_rotation),
+		)
+		.unwrap();
+
+		// Pretends the current log file was started longer than the interval ago.
+		let expire_log_file = || match &logger.writer {
+			Writer::FileWriter(fs_writer) => {
+				*fs_writer.file_started_at.lock().unwrap() -=
+					Duration::from_secs(interval_secs + 1);
+			},
+			_ => panic!("Expected a file writer"),
+		};
+		let read_messages = |path: &str| {
+			fs::read_to_string(path)
+				.unwrap()
+				.lines()
+				.map(|l| l.rsplit("] ").next().unwrap().to_string())
+				.collect::<Vec<_>>()
+		};
+
+		log_info!(logger, "Entry 0");
+		log_info!(logger, "Entry 1");
+		assert!(!Path::new(&format!("{}.1", file_path)).exists());
+
+		expire_log_file();
+		log_info!(logger, "Entry 2");
+		assert_eq!(read_messages(&format!("{}.1", file_path)), vec!["Entry 0", "Entry 1"]);
+		assert_eq!(read_messages(&file_path), vec!["Entry 2"]);
+
+		// The interval restarts with the new log file.
+		log_info!(logger, "Entry 3");
+		assert_eq!(read_messages(&file_path), vec!["Entry 2", "Entry 3"]);
+
+		// Only the most recent rotated file is retained.
+		expire_log_file();
+		log_info!(logger, "Entry 4");
+		assert_eq!(read_messages(&format!("{}.1", file_path)), vec!["Entry 2", "Entry 3"]);
+		assert_eq!(read_messages(&file_path), vec!["Entry 4"]);
+		assert!(!Path::new(&format!("{}.2", file_path)).exists());
+
+		fs::remove_dir_all(log_dir).unwrap();
+	}
+}
@@ -69,7 +69,7 @@ where
 		match self {
 			Self::Ignoring => Ok(()), // Should be unreachable!() as the reader will return `None`
 			Self::Liquidity { liquidity_source, .. } => {
-				liquidity_source.liquidity_manager().handle_custom_message(msg, sender_node_id)
+				liquidity_source.handle_custom_message(msg, sender_node_id)
 			},
 		}
 	}
@@ -78,7 +78,7 @@ where
 		match self {
 			Self::Ignoring => Vec::new(),
 			Self::Liquidity { liquidity_source, .. } => {
-				liquidity_source.liquidity_manager().get_and_clear_pending_msg()
+				liquidity_source.get_and_clear_pending_msg()
 			},
 		}
 	}
@@ -0,0 +1,547 @@
+// This file is Copyright its original authors, visible in version control history.
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. You may not use this file except in
+// accordance with one or both of these licenses.
+
+//! Objects allowing to serve node metrics in the [Prometheus text format].
+//!
+//! [Prometheus text format]: https://prometheus.io/docs/instrumenting/exposition_formats/
+
+use crate::config::Config;
+use crate::event::EventQueue;
+use crate::logger::{log_debug, log_error, LdkLogger, Logger};
+use crate::payment::{PaymentStatus, PaymentType};
+use crate::peer_store::PeerStore;
+use crate::types::{ChainMonitor, ChannelManager, PaymentStore, PeerManager, Wallet};
+use crate::NodeMetrics;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+// The maximum size of a request we're willing to read.
+const MAX_REQUEST_SIZE: usize = 8192;
+
+// The time we wait for a client to send its request before closing the connection.
+const METRICS_REQUEST_READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Gathers the current state of the node and renders it as metrics.
+pub(crate) struct MetricsSource {
+	config: Arc<Config>,
+	wallet: Arc<Wallet>,
+	channel_manager: Arc<ChannelManager>,
+	chain_monitor: Arc<ChainMonitor>,
+	peer_manager: Arc<PeerManager>,
+	peer_store: Arc<PeerStore<Arc<Logger>>>,
+	payment_store: Arc<PaymentStore>,
+	event_queue: Arc<EventQueue<Arc<Logger>>>,
+	node_metrics: Arc<RwLock<NodeMetrics>>,
+	logger: Arc<Logger>,
+}
+
+impl MetricsSource {
+	pub(crate) fn new(
+		config: Arc<Config>, wallet: Arc<Wallet>, channel_manager: Arc<ChannelManager>,
+		chain_monitor: Arc<ChainMonitor>, peer_manager: Arc<PeerManager>,
+		peer_store: Arc<PeerStore<Arc<Logger>>>, payment_store: Arc<PaymentStore>,
+		event_queue: Arc<EventQueue<Arc<Logger>>>, node_metrics: Arc<RwLock<NodeMetrics>>,
+		logger: Arc<Logger>,
+	) -> Self {
+		Self {
+			config,
+			wallet,
+			channel_manager,
+			chain_monitor,
+			peer_manager,
+			peer_store,
+			payment_store,
+			event_queue,
+			node_metrics,
+			logger,
+		}
+	}
+
+	/// Renders the current metrics in the Prometheus text format.
+	pub(crate) fn render(&self) -> String {
+		let mut out = String::new();
+
+		let node_id = self.channel_manager.get_our_node_id();
+		write_metric(
+			&mut out,
+			"ldk_node_info",
+			"Information about the node.",
+			"gauge",
+			&[(format!("node_id=\"{}\",network=\"{}\"", node_id, self.config.network), 1)],
+		);
+
+		let best_block_height = self.channel_manager.current_best_block().height as u64;
+		write_gauge(
+			&mut out,
+			"ldk_node_best_block_height",
+			"The height of the current best block.",
+			best_block_height,
+		);
+
+		{
+			let locked_node_metrics = self.node_metrics.read().unwrap();
+			let timestamps = [
+				(
+					"ldk_node_latest_lightning_wallet_sync_timestamp_seconds",
+					"The time of the last successful sync of the Lightning wallet.",
+					locked_node_metrics.latest_lightning_wallet_sync_timestamp,
+				),
+				(
+					"ldk_node_latest_onchain_wallet_sync_timestamp_seconds",
+					"The time of the last successful sync of the on-chain wallet.",
+					locked_node_metrics.latest_onchain_wallet_sync_timestamp,
+				),
+				(
+					"ldk_node_latest_fee_rate_cache_update_timestamp_seconds",
+					"The time of the last successful update of the fee rate cache.",
+					locked_node_metrics.latest_fee_rate_cache_update_timestamp,
+				),
+				(
+					"ldk_node_latest_rgs_snapshot_timestamp_seconds",
+					"The timestamp of the last Rapid Gossip Sync snapshot applied.",
+					locked_node_metrics.latest_rgs_snapshot_timestamp.map(|t| t as u64),
+				),
+				(
+					"ldk_node_latest_node_announcement_broadcast_timestamp_seconds",
+					"The time of the last broadcast of our node announcement.",
+					locked_node_metrics.latest_node_announcement_broadcast_timestamp,
+				),
+			];
+			for (name, help, timestamp) in timestamps {
+				if let Some(timestamp) = timestamp {
+					write_gauge(&mut out, name, help, timestamp);
+				}
+			}
+		}
+
+		let channels = self.channel_manager.list_channels();
+		let num_ready = channels.iter().filter(|c| c.is_channel_ready).count() as u64;
+		let num_usable = channels.iter().filter(|c| c.is_usable).count() as u64;
+		let num_announced = channels.iter().filter(|c| c.is_announced).count() as u64;
+		let num_pending = channels.len() as u64 - num_ready;
+		write_metric(
+			&mut out,
+			"ldk_node_channels",
+			"The number of channels by state.",
+			"gauge",
+			&[
+				("state=\"pending\"".to_string(), num_pending),
+				("state=\"ready\"".to_string(), num_ready),
+				("state=\"usable\"".to_string(), num_usable),
+				("state=\"announced\"".to_string(), num_announced),
+			],
+		);
+		write_gauge(
+			&mut out,
+			"ldk_node_channel_capacity_sats",
+			"The total capacity of all channels.",
+			channels.iter().map(|c| c.channel_value_satoshis).sum(),
+		);
+		write_gauge(
+			&mut out,
+			"ldk_node_channel_outbound_capacity_msat",
+			"The total amount available to send over all channels.",
+			channels.iter().map(|c| c.outbound_capacity_msat).sum(),
+		);
+		write_gauge(
+			&mut out,
+			"ldk_node_channel_inbound_capacity_msat",
+			"The total amount available to receive over all channels.",
+			channels.iter().map(|c| c.inbound_capacity_msat).sum(),
+		);
+
+		let cur_anchor_reserve_sats =
+			crate::total_anchor_channels_reserve_sats(&self.channel_manager, &self.config);
+		let (total_onchain_balance_sats, spendable_onchain_balance_sats) =
+			self.wallet.get_balances(cur_anchor_reserve_sats).unwrap_or((0, 0));
+		let total_lightning_balance_sats = self
+			.chain_monitor
+			.list_monitors()
+			.into_iter()
+			.filter_map(|(funding_txo, _)| self.chain_monitor.get_monitor(funding_txo).ok())
+			.flat_map(|monitor| monitor.get_claimable_balances())
+			.map(|balance| balance.claimable_amount_satoshis())
+			.sum();
+		write_metric(
+			&mut out,
+			"ldk_node_balance_sats",
+			"The balances of the node.",
+			"gauge",
+			&[
+				("type=\"onchain_total\"".to_string(), total_onchain_balance_sats),
+				("type=\"onchain_spendable\"".to_string(), spendable_onchain_balance_sats),
+				(
+					"type=\"anchor_channels_reserve\"".to_string(),
+					std::cmp::min(cur_anchor_reserve_sats, total_onchain_balance_sats),
+				),
+				("type=\"lightning_total\"".to_string(), total_lightning_balance_sats),
+			],
+		);
+
+		let mut payment_counts: BTreeMap<(&'static str, &'static str), u64> = BTreeMap::new();
+		for ((payment_type, status), count) in self.payment_store.count_by_type_and_status() {
+			*payment_counts
+				.entry((payment_type_label(&payment_type), payment_status_label(&status)))
+				.or_default() += count;
+		}
+		let payment_samples = payment_counts
+			.into_iter()
+			.map(|((kind, status), count)| {
+				(format!("kind=\"{}\",status=\"{}\"", kind, status), count)
+			})
+			.collect::<Vec<_>>();
+		write_metric(
+			&mut out,
+			"ldk_node_payments",
+			"The number of known payments by kind and status.",
+			"gauge",
+			&payment_samples,
+		);
+
+		write_metric(
+			&mut out,
+			"ldk_node_peers",
+			"The number of peers by state.",
+			"gauge",
+			&[
+				("state=\"connected\"".to_string(), self.peer_manager.list_peers().len() as u64),
+				("state=\"persisted\"".to_string(), self.peer_store.list_peers().len() as u64),
+			],
+		);
+
+		write_gauge(
+			&mut out,
+			"ldk_node_event_queue_depth",
+			"The number of events waiting to be handled.",
+			self.event_queue.pending_event_count() as u64,
+		);
+
+		out
+	}
+
+	pub(crate) async fn handle_connection(&self, stream: TcpStream) {
+		serve_request(stream, METRICS_REQUEST_READ_TIMEOUT, || self.render(), &self.logger).await
+	}
+}
+
+async fn serve_request<S: AsyncRead + AsyncWrite + Unpin, F: FnOnce() -> String>(
+	mut stream: S, read_timeout: Duration, render: F, logger: &Arc<Logger>,
+) {
+	let mut buf = Vec::new();
+	let read_request = async {
+		let mut chunk = [0u8; 1024];
+		loop {
+			let n = stream.read(&mut chunk).await?;
+			if n == 0 {
+				break;
+			}
+			buf.extend_from_slice(&chunk[..n]);
+			if buf.windows(4).any(|w| w == b"\r\n\r\n") || buf.len() >= MAX_REQUEST_SIZE {
+				break;
+			}
+		}
+		Ok::<(), std::io::Error>(())
+	};
+	match tokio::time::timeout(read_timeout, read_request).await {
+		Ok(Ok(())) => {},
+		Ok(Err(e)) => {
+			log_debug!(logger, "Failed to read metrics request: {}", e);
+			return;
+		},
+		Err(_) => {
+			log_debug!(logger, "Timed out reading metrics request.");
+			return;
+		},
+	}
+
+	let request = String::from_utf8_lossy(&buf);
+	let mut request_line = request.lines().next().unwrap_or_default().split_whitespace();
+	let method = request_line.next().unwrap_or_default();
+	let path = request_line.next().unwrap_or_default().split('?').next().unwrap_or_default();
+
+	let response = match (method, path) {
+		("GET", "/metrics") => {
+			let body = render();
+			format!(
+				"HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+				body.len(),
+				body
+			)
+		},
+		(_, "/metrics") => {
+			"HTTP/1.1 405 Method Not Allowed\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+				.to_string()
+		},
+		_ => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+	};
+
+	if let Err(e) = stream.write_all(response.as_bytes()).await {
+		log_error!(logger, "Failed to write metrics response: {}", e);
+		return;
+	}
+	let _ = stream.shutdown().await;
+}
+
+fn payment_type_label(payment_type: &PaymentType) -> &'static str {
+	match payment_type {
+		PaymentType::Onchain => "onchain",
+		PaymentType::Bolt11 => "bolt11",
+		PaymentType::Bolt11Jit => "bolt11_jit",
+		PaymentType::Bolt12Offer => "bolt12_offer",
+		PaymentType::Bolt12Refund => "bolt12_refund",
+		PaymentType::Spontaneous => "spontaneous",
+		PaymentType::Rebalance => "rebalance",
+	}
+}
+
+fn payment_status_label(status: &PaymentStatus) -> &'static str {
+	match status {
+		PaymentStatus::Pending => "pending",
+		PaymentStatus::Succeeded => "succeeded",
+		PaymentStatus::Failed => "failed",
+		PaymentStatus::Held => "held",
+	}
+}
+
+fn write_gauge(out: &mut String, name: &str, help: &str, value: u64) {
+	write_metric(out, name, help, "gauge", &[(String::new(), value)]);
+}
+
+fn write_metric(out: &mut String, name: &str, help: &str, kind: &str, samples: &[(String, u64)]) {
+	let _ = writeln!(out, "# HELP {} {}", name, help);
+	let _ = writeln!(out, "# TYPE {} {}", name, kind);
+	for (labels, value) in samples {
+		if labels.is_empty() {
+			let _ = writeln!(out, "{} {}", name, value);
+		} else {
+			let _ = writeln!(out, "{}{{{}}} {}", name, labels, value);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::builder::NodeBuilder;
+	use crate::config::WALLET_KEYS_SEED_LEN;
+	use crate::io::test_utils::random_storage_path;
+	use crate::payment::{ConfirmationStatus, PaymentDetails, PaymentDirection, PaymentKind};
+	use crate::Node;
+
+	use lightning::ln::channelmanager::PaymentId;
+	use lightning::util::test_utils::TestStore;
+
+	use lightning_types::payment::PaymentHash;
+
+	use bitcoin::hashes::Hash;
+	use bitcoin::{Network, Txid};
+
+	#[test]
+	fn metrics_are_rendered_in_text_format() {
+		let mut out = String::new();
+		write_gauge(&mut out, "ldk_node_best_block_height", "The best block height.", 42);
+		write_metric(
+			&mut out,
+			"ldk_node_peers",
+			"The number of peers.",
+			"gauge",
+			&[("state=\"connected\"".to_string(), 2), ("state=\"persisted\"".to_string(), 3)],
+		);
+
+		let expected = "# HELP ldk_node_best_block_height The best block height.\n\
+			# TYPE ldk_node_best_block_height gauge\n\
+			ldk_node_best_block_height 42\n\
+			# HELP ldk_node_peers The number of peers.\n\
+			# TYPE ldk_node_peers gauge\n\
+			ldk_node_peers{state=\"connected\"} 2\n\
+			ldk_node_peers{state=\"persisted\"} 3\n";
+		assert_eq!(out, expected);
+	}
+
+	// Builds a node backed by a test store and returns it along with a metrics source gathering
+	// its state.
+	fn test_metrics_source() -> (Node, MetricsSource) {
+		let config = Config {
+			network: Network::Regtest,
+			storage_dir_path: random_storage_path().to_str().unwrap().to_owned(),
+			..Default::default()
+		};
+		let mut builder = NodeBuilder::from_config(config);
+		builder.set_entropy_seed_bytes([42; WALLET_KEYS_SEED_LEN]);
+		let node = builder.build_with_store(Arc::new(TestStore::new(false))).unwrap();
+
+		let metrics_source = MetricsSource::new(
+			Arc::clone(&node.config),
+			Arc::clone(&node.wallet),
+			Arc::clone(&node.channel_manager),
+			Arc::clone(&node.chain_monitor),
+			Arc::clone(&node.peer_manager),
+			Arc::clone(&node.peer_store),
+			Arc::clone(&node.payment_store),
+			Arc::clone(&node.event_queue),
+			Arc::clone(&node.node_metrics),
+			Arc::clone(&node.logger),
+		);
+		(node, metrics_source)
+	}
+
+	fn render_populated_metrics() -> String {
+		let (node, metrics_source) = test_metrics_source();
+		for idx in 0..3u8 {
+			let kind = PaymentKind::Bolt11 {
+				hash: PaymentHash([idx; 32]),
+				preimage: None,
+				secret: None,
+				claim_deadline: None,
+			};
+			let payment = PaymentDetails::new(
+				PaymentId([idx; 32]),
+				kind,
+				Some(1000),
+				None,
+				PaymentDirection::Outbound,
+				PaymentStatus::Succeeded,
+			);
+			node.payment_store.insert(payment).unwrap();
+		}
+		let kind = PaymentKind::Onchain {
+			txid: Txid::all_zeros(),
+			status: ConfirmationStatus::Unconfirmed,
+			replaced_txids: Vec::new(),
+		};
+		let payment = PaymentDetails::new(
+			PaymentId([3; 32]),
+			kind,
+			Some(1000),
+			Some(100),
+			PaymentDirection::Inbound,
+			PaymentStatus::Pending,
+		);
+		node.payment_store.insert(payment).unwrap();
+		node.node_metrics.write().unwrap().latest_onchain_wallet_sync_timestamp = Some(1234);
+
+		let rendered = metrics_source.render();
+		assert!(rendered.contains(&format!(
+			"ldk_node_info{{node_id=\"{}\",network=\"regtest\"}} 1\n",
+			node.node_id()
+		)));
+		rendered
+	}
+
+	#[test]
+	fn metrics_reflect_node_state() {
+		let rendered = render_populated_metrics();
+		let samples = rendered.lines().filter(|l| !l.starts_with('#')).collect::<Vec<_>>();
+		for expected in [
+			"ldk_node_best_block_height 0",
+			"ldk_node_latest_onchain_wallet_sync_timestamp_seconds 1234",
+			"ldk_node_channels{state=\"pending\"} 0",
+			"ldk_node_channel_capacity_sats 0",
+			"ldk_node_balance_sats{type=\"onchain_total\"} 0",
+			"ldk_node_payments{kind=\"bolt11\",status=\"succeeded\"} 3",
+			"ldk_node_payments{kind=\"onchain\",status=\"pending\"} 1",
+			"ldk_node_peers{state=\"connected\"} 0",
+			"ldk_node_peers{state=\"persisted\"} 0",
+			"ldk_node_event_queue_depth 0",
+		] {
+			assert!(samples.contains(&expected), "Missing sample {}", expected);
+		}
+
+		// We only report the timestamps of events that happened.
+		assert!(!rendered.contains("ldk_node_latest_lightning_wallet_sync_timestamp_seconds"));
+		assert_eq!(samples.iter().filter(|s| s.starts_with("ldk_node_payments")).count(), 2);
+	}
+
+	async fn request_metrics(request: &str) -> String {
+		let (mut client, server) = tokio::io::duplex(MAX_REQUEST_SIZE);
+		let logger = Arc::new(Logger::new_log_facade());
+		client.write_all(request.as_bytes()).await.unwrap();
+		let rendered = render_populated_metrics();
+		serve_request(server, METRICS_REQUEST_READ_TIMEOUT, || rendered, &logger).await;
+		let mut response = String::new();
+		client.read_to_string(&mut response).await.unwrap();
+		response
+	}
+
+	#[tokio::test]
+	async fn metrics_are_served_in_exposition_format() {
+		let response = request_metrics("GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n").await;
+		let (head, body) = response.split_once("\r\n\r\n").unwrap();
+		let mut head_lines = head.lines();
+		assert_eq!(head_lines.next(), Some("HTTP/1.1 200 OK"));
+		let headers = head_lines.collect::<Vec<_>>();
+		assert!(headers.contains(&"Content-Type: text/plain; version=0.0.4; charset=utf-8"));
+		assert!(headers.contains(&format!("Content-Length: {}", body.len()).as_str()));
+
+		// Every sample needs to be preceded by the `HELP` and `TYPE` lines of its metric family.
+		let mut families = Vec::new();
+		for line in body.lines() {
+			if let Some(help) = line.strip_prefix("# HELP ") {
+				families.push(help.split(' ').next().unwrap().to_string());
+			} else if let Some(kind) = line.strip_prefix("# TYPE ") {
+				assert_eq!(kind, format!("{} gauge", families.last().unwrap()));
+			} else {
+				let (name, value) = line.rsplit_once(' ').unwrap();
+				let family = name.split('{').next().unwrap();
+				assert_eq!(Some(&family.to_string()), families.last());
+				assert!(value.parse::<u64>().is_ok());
+			}
+		}
+		assert_eq!(
+			families,
+			vec![
+				"ldk_node_info",
+				"ldk_node_best_block_height",
+				"ldk_node_latest_onchain_wallet_sync_timestamp_seconds",
+				"ldk_node_channels",
+				"ldk_node_channel_capacity_sats",
+				"ldk_node_channel_outbound_capacity_msat",
+				"ldk_node_channel_inbound_capacity_msat",
+				"ldk_node_balance_sats",
+				"ldk_node_payments",
+				"ldk_node_peers",
+				"ldk_node_event_queue_depth",
+			]
+		);
+	}
+
+	#[tokio::test]
+	async fn unknown_requests_are_rejected() {
+		let response = request_metrics("POST /metrics HTTP/1.1\r\n\r\n").await;
+		assert!(response.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+
+		let response = request_metrics("GET / HTTP/1.1\r\n\r\n").await;
+		assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+	}
+
+	#[tokio::test]
+	async fn stalled_requests_time_out() {
+		let (mut client, server) = tokio::io::duplex(MAX_REQUEST_SIZE);
+		let logger = Arc::new(Logger::new_log_facade());
+
+		// Send an incomplete request and never finish it.
+		client.write_all(b"GET /metrics HTTP/1.1\r\n").await.unwrap();
+		let read_timeout = Duration::from_millis(100);
+		tokio::time::timeout(
+			Duration::from_secs(5),
+			serve_request(server, read_timeout, String::new, &logger),
+		)
+		.await
+		.expect("serving a stalled request should time out");
+
+		let mut response = String::new();
+		client.read_to_string(&mut response).await.unwrap();
+		assert!(response.is_empty());
+	}
+}
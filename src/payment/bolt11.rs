@@ -9,20 +9,22 @@
 //!
 //! [BOLT 11]: https://github.com/lightning/bolts/blob/master/11-payment-encoding.md
 
-use crate::config::{Config, LDK_PAYMENT_RETRY_TIMEOUT};
+use crate::config::{Config, HOLD_INVOICE_MIN_FINAL_CLTV_EXPIRY_DELTA, LDK_PAYMENT_RETRY_TIMEOUT};
 use crate::connection::ConnectionManager;
 use crate::data_store::DataStoreUpdateResult;
 use crate::error::Error;
 use crate::ffi::{maybe_deref, maybe_try_convert_enum, maybe_wrap};
 use crate::liquidity::LiquiditySource;
 use crate::logger::{log_error, log_info, LdkLogger, Logger};
+use crate::payment::estimate::{estimate_payment_fee, PaymentFeeEstimate};
+use crate::payment::index::PaymentQuery;
 use crate::payment::store::{
 	LSPFeeLimits, PaymentDetails, PaymentDetailsUpdate, PaymentDirection, PaymentKind,
 	PaymentStatus,
 };
 use crate::payment::SendingParameters;
 use crate::peer_store::{PeerInfo, PeerStore};
-use crate::types::{ChannelManager, PaymentStore};
+use crate::types::{ChannelManager, PaymentStore, Router, Scorer};
 
 use lightning::ln::bolt11_payment;
 use lightning::ln::channelmanager::{
@@ -38,7 +40,7 @@ use lightning_invoice::Bolt11InvoiceDescription as LdkBolt11InvoiceDescription;
 use bitcoin::hashes::sha256::Hash as Sha256;
 use bitcoin::hashes::Hash;
 
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 
 #[cfg(not(feature = "uniffi"))]
 type Bolt11Invoice = LdkBolt11Invoice;
@@ -59,6 +61,8 @@ type Bolt11InvoiceDescription = crate::ffi::Bolt11InvoiceDescription;
 pub struct Bolt11Payment {
 	runtime: Arc<RwLock<Option<Arc<tokio::runtime::Runtime>>>>,
 	channel_manager: Arc<ChannelManager>,
+	router: Arc<Router>,
+	scorer: Arc<Mutex<Scorer>>,
 	connection_manager: Arc<ConnectionManager<Arc<Logger>>>,
 	liquidity_source: Option<Arc<LiquiditySource<Arc<Logger>>>>,
 	payment_store: Arc<PaymentStore>,
@@ -70,7 +74,7 @@ pub struct Bolt11Payment {
 impl Bolt11Payment {
 	pub(crate) fn new(
 		runtime: Arc<RwLock<Option<Arc<tokio::runtime::Runtime>>>>,
-		channel_manager: Arc<ChannelManager>,
+		channel_manager: Arc<ChannelManager>, router: Arc<Router>, scorer: Arc<Mutex<Scorer>>,
 		connection_manager: Arc<ConnectionManager<Arc<Logger>>>,
 		liquidity_source: Option<Arc<LiquiditySource<Arc<Logger>>>>,
 		payment_store: Arc<PaymentStore>, peer_store: Arc<PeerStore<Arc<Logger>>>,
@@ -79,6 +83,8 @@ impl Bolt11Payment {
 		Self {
 			runtime,
 			channel_manager,
+			router,
+			scorer,
 			connection_manager,
 			liquidity_source,
 			payment_store,
@@ -150,6 +156,7 @@ impl Bolt11Payment {
 					hash: payment_hash,
 					preimage: None,
 					secret: payment_secret,
+					claim_deadline: None,
 				};
 				let payment = PaymentDetails::new(
 					payment_id,
@@ -173,6 +180,7 @@ impl Bolt11Payment {
 							hash: payment_hash,
 							preimage: None,
 							secret: payment_secret,
+							claim_deadline: None,
 						};
 						let payment = PaymentDetails::new(
 							payment_id,
@@ -285,6 +293,7 @@ impl Bolt11Payment {
 					hash: payment_hash,
 					preimage: None,
 					secret: Some(*payment_secret),
+					claim_deadline: None,
 				};
 
 				let payment = PaymentDetails::new(
@@ -309,6 +318,7 @@ impl Bolt11Payment {
 							hash: payment_hash,
 							preimage: None,
 							secret: Some(*payment_secret),
+							claim_deadline: None,
 						};
 						let payment = PaymentDetails::new(
 							payment_id,
@@ -360,6 +370,15 @@ impl Bolt11Payment {
 		}
 
 		if let Some(details) = self.payment_store.get(&payment_id) {
+			if details.status == PaymentStatus::Failed {
+				log_error!(
+					self.logger,
+					"Failed to manually claim payment {} as it was already failed back",
+					payment_id
+				);
+				return Err(Error::InvalidPaymentHash);
+			}
+
 			if let Some(expected_amount_msat) = details.amount_msat {
 				if claimable_amount_msat < expected_amount_msat {
 					log_error!(
@@ -429,6 +448,20 @@ impl Bolt11Payment {
 		Ok(())
 	}
 
+	/// Returns the payments that are currently held, i.e., that have been received but still need
+	/// to be manually claimed via [`claim_for_hash`] or failed via [`fail_for_hash`].
+	///
+	/// Held payments that aren't resolved in time will be failed back automatically, see
+	/// [`Config::hold_invoice_cancel_margin_blocks`] for more information.
+	///
+	/// [`claim_for_hash`]: Self::claim_for_hash
+	/// [`fail_for_hash`]: Self::fail_for_hash
+	/// [`Config::hold_invoice_cancel_margin_blocks`]: crate::config::Config::hold_invoice_cancel_margin_blocks
+	pub fn list_held_payments(&self) -> Vec<PaymentDetails> {
+		self.payment_store
+			.list(&PaymentQuery { status: Some(PaymentStatus::Held), ..Default::default() })
+	}
+
 	/// Returns a payable invoice that can be used to request and receive a payment of the amount
 	/// given.
 	///
@@ -452,9 +485,14 @@ impl Bolt11Payment {
 	/// payment hash. If they're unable to obtain the preimage, they *MUST* immediately fail the payment via
 	/// [`fail_for_hash`].
 	///
+	/// Until then, the payment will be [`PaymentStatus::Held`]. If it isn't resolved in time, it
+	/// will be failed back automatically as configured via
+	/// [`Config::hold_invoice_cancel_margin_blocks`].
+	///
 	/// [`PaymentClaimable`]: crate::Event::PaymentClaimable
 	/// [`claim_for_hash`]: Self::claim_for_hash
 	/// [`fail_for_hash`]: Self::fail_for_hash
+	/// [`Config::hold_invoice_cancel_margin_blocks`]: crate::config::Config::hold_invoice_cancel_margin_blocks
 	pub fn receive_for_hash(
 		&self, amount_msat: u64, description: &Bolt11InvoiceDescription, expiry_secs: u32,
 		payment_hash: PaymentHash,
@@ -488,9 +526,14 @@ impl Bolt11Payment {
 	/// payment hash. If they're unable to obtain the preimage, they *MUST* immediately fail the payment via
 	/// [`fail_for_hash`].
 	///
+	/// Until then, the payment will be [`PaymentStatus::Held`]. If it isn't resolved in time, it
+	/// will be failed back automatically as configured via
+	/// [`Config::hold_invoice_cancel_margin_blocks`].
+	///
 	/// [`PaymentClaimable`]: crate::Event::PaymentClaimable
 	/// [`claim_for_hash`]: Self::claim_for_hash
 	/// [`fail_for_hash`]: Self::fail_for_hash
+	/// [`Config::hold_invoice_cancel_margin_blocks`]: crate::config::Config::hold_invoice_cancel_margin_blocks
 	pub fn receive_variable_amount_for_hash(
 		&self, description: &Bolt11InvoiceDescription, expiry_secs: u32, payment_hash: PaymentHash,
 	) -> Result<Bolt11Invoice, Error> {
@@ -509,7 +552,8 @@ impl Bolt11Payment {
 				description: invoice_description.clone(),
 				invoice_expiry_delta_secs: Some(expiry_secs),
 				payment_hash: manual_claim_payment_hash,
-				..Default::default()
+				min_final_cltv_expiry_delta: manual_claim_payment_hash
+					.map(|_| HOLD_INVOICE_MIN_FINAL_CLTV_EXPIRY_DELTA),
 			};
 
 			match self.channel_manager.create_bolt11_invoice(invoice_params) {
@@ -543,6 +587,7 @@ impl Bolt11Payment {
 			hash: payment_hash,
 			preimage,
 			secret: Some(payment_secret.clone()),
+			claim_deadline: None,
 		};
 		let payment = PaymentDetails::new(
 			id,
@@ -783,4 +828,81 @@ impl Bolt11Payment {
 
 		Ok(())
 	}
+
+	/// Estimates the fees of paying the given invoice, without sending anything.
+	///
+	/// This runs the same route finding that [`send`] would, based on our current channels and
+	/// view of the network. Note that the route actually taken when sending may differ.
+	///
+	/// For "zero-amount" invoices the `amount_msat` to be paid needs to be given. If the invoice
+	/// specifies an amount, `amount_msat` may be used to estimate the fees of overpaying it.
+	///
+	/// If `sending_parameters` are provided they will override the default as well as the
+	/// node-wide parameters configured via [`Config::sending_parameters`] on a per-field basis.
+	///
+	/// [`send`]: Self::send
+	pub fn estimate_fee(
+		&self, invoice: &Bolt11Invoice, amount_msat: Option<u64>,
+		sending_parameters: Option<SendingParameters>,
+	) -> Result<PaymentFeeEstimate, Error> {
+		let invoice = maybe_deref(invoice);
+		let rt_lock = self.runtime.read().unwrap();
+		if rt_lock.is_none() {
+			return Err(Error::NotRunning);
+		}
+
+		let (_payment_hash, _recipient_onion, mut route_params) = match (
+			invoice.amount_milli_satoshis(),
+			amount_msat,
+		) {
+			(Some(invoice_amount_msat), amount_msat) => {
+				let mut params = bolt11_payment::payment_parameters_from_invoice(&invoice)
+					.map_err(|_| Error::InvalidInvoice)?;
+				if let Some(amount_msat) = amount_msat {
+					if amount_msat < invoice_amount_msat {
+						log_error!(
+								self.logger,
+								"Failed to estimate fee as the given amount needs to be at least the invoice amount: required {}msat, gave {}msat.", invoice_amount_msat, amount_msat);
+						return Err(Error::InvalidAmount);
+					}
+					params.2.final_value_msat = amount_msat;
+				}
+				params
+			},
+			(None, Some(amount_msat)) => {
+				bolt11_payment::payment_parameters_from_variable_amount_invoice(
+					&invoice,
+					amount_msat,
+				)
+				.map_err(|_| Error::InvalidInvoice)?
+			},
+			(None, None) => {
+				log_error!(self.logger, "Failed to estimate fee due to the given invoice being \"zero-amount\" and no amount being given.");
+				return Err(Error::InvalidAmount);
+			},
+		};
+
+		let override_params =
+			sending_parameters.as_ref().or(self.config.sending_parameters.as_ref());
+		if let Some(override_params) = override_params {
+			override_params
+				.max_total_routing_fee_msat
+				.map(|f| route_params.max_total_routing_fee_msat = f.into());
+			override_params
+				.max_total_cltv_expiry_delta
+				.map(|d| route_params.payment_params.max_total_cltv_expiry_delta = d);
+			override_params.max_path_count.map(|p| route_params.payment_params.max_path_count = p);
+			override_params
+				.max_channel_saturation_power_of_half
+				.map(|s| route_params.payment_params.max_channel_saturation_power_of_half = s);
+		};
+
+		estimate_payment_fee(
+			&route_params,
+			&self.channel_manager,
+			&self.router,
+			&self.scorer,
+			&self.logger,
+		)
+	}
 }
@@ -13,18 +13,21 @@ use crate::config::LDK_PAYMENT_RETRY_TIMEOUT;
 use crate::error::Error;
 use crate::ffi::{maybe_deref, maybe_wrap};
 use crate::logger::{log_error, log_info, LdkLogger, Logger};
+use crate::payment::estimate::{estimate_payment_fee, PaymentFeeEstimate};
 use crate::payment::store::{PaymentDetails, PaymentDirection, PaymentKind, PaymentStatus};
-use crate::types::{ChannelManager, PaymentStore};
+use crate::types::{ChannelManager, Graph, PaymentStore, Router, Scorer};
 
-use lightning::ln::channelmanager::{PaymentId, Retry};
+use lightning::blinded_path::IntroductionNode;
+use lightning::ln::channelmanager::{PaymentId, Retry, MIN_FINAL_CLTV_EXPIRY_DELTA};
 use lightning::offers::offer::{Amount, Offer as LdkOffer, Quantity};
 use lightning::offers::parse::Bolt12SemanticError;
+use lightning::routing::router::{PaymentParameters, RouteParameters};
 use lightning::util::string::UntrustedString;
 
 use rand::RngCore;
 
 use std::num::NonZeroU64;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 #[cfg(not(feature = "uniffi"))]
@@ -51,6 +54,9 @@ type Refund = Arc<crate::ffi::Refund>;
 pub struct Bolt12Payment {
 	runtime: Arc<RwLock<Option<Arc<tokio::runtime::Runtime>>>>,
 	channel_manager: Arc<ChannelManager>,
+	router: Arc<Router>,
+	scorer: Arc<Mutex<Scorer>>,
+	network_graph: Arc<Graph>,
 	payment_store: Arc<PaymentStore>,
 	logger: Arc<Logger>,
 }
@@ -58,10 +64,10 @@ pub struct Bolt12Payment {
 impl Bolt12Payment {
 	pub(crate) fn new(
 		runtime: Arc<RwLock<Option<Arc<tokio::runtime::Runtime>>>>,
-		channel_manager: Arc<ChannelManager>, payment_store: Arc<PaymentStore>,
-		logger: Arc<Logger>,
+		channel_manager: Arc<ChannelManager>, router: Arc<Router>, scorer: Arc<Mutex<Scorer>>,
+		network_graph: Arc<Graph>, payment_store: Arc<PaymentStore>, logger: Arc<Logger>,
 	) -> Self {
-		Self { runtime, channel_manager, payment_store, logger }
+		Self { runtime, channel_manager, router, scorer, network_graph, payment_store, logger }
 	}
 
 	/// Send a payment given an offer.
@@ -443,4 +449,80 @@ impl Bolt12Payment {
 
 		Ok(maybe_wrap(refund))
 	}
+
+	/// Estimates the fees of paying the given offer, without sending anything.
+	///
+	/// As the recipient's payment paths are only known once we received their invoice, this
+	/// estimates the fees of reaching the introduction node of the first of the offer's blinded
+	/// paths we can resolve, or, if the offer doesn't include any paths, the offer's issuer. The
+	/// fees of the blinded payment paths the recipient will include in their invoice are hence not
+	/// accounted for.
+	///
+	/// For "zero-amount" offers the `amount_msat` to be paid needs to be given. If the offer
+	/// specifies an amount, `amount_msat` may be used to estimate the fees of overpaying it.
+	pub fn estimate_fee(
+		&self, offer: &Offer, amount_msat: Option<u64>,
+	) -> Result<PaymentFeeEstimate, Error> {
+		let offer = maybe_deref(offer);
+		let rt_lock = self.runtime.read().unwrap();
+		if rt_lock.is_none() {
+			return Err(Error::NotRunning);
+		}
+
+		let amount_msat = match (offer.amount(), amount_msat) {
+			(Some(Amount::Bitcoin { amount_msats }), amount_msat) => {
+				let amount_msat = amount_msat.unwrap_or(amount_msats);
+				if amount_msat < amount_msats {
+					log_error!(
+						self.logger,
+						"Failed to estimate fee as the given amount needs to be at least the offer amount: required {}msat, gave {}msat.", amount_msats, amount_msat);
+					return Err(Error::InvalidAmount);
+				}
+				amount_msat
+			},
+			(Some(_), _) => {
+				log_error!(self.logger, "Failed to estimate fee as the provided offer was denominated in an unsupported currency.");
+				return Err(Error::UnsupportedCurrency);
+			},
+			(None, Some(amount_msat)) => amount_msat,
+			(None, None) => {
+				log_error!(self.logger, "Failed to estimate fee due to the given offer being \"zero-amount\" and no amount being given.");
+				return Err(Error::InvalidAmount);
+			},
+		};
+
+		// Offers including blinded paths are usually signed with a key derived for the offer, which
+		// isn't known to the network. We therefore only route to the issuer if there are no paths.
+		let payee_node_id = if offer.paths().is_empty() {
+			offer.issuer_signing_pubkey()
+		} else {
+			let network_graph = self.network_graph.read_only();
+			offer.paths().iter().find_map(|path| match path.introduction_node() {
+				IntroductionNode::NodeId(node_id) => Some(*node_id),
+				IntroductionNode::DirectedShortChannelId(..) => path
+					.public_introduction_node_id(&network_graph)
+					.and_then(|node_id| node_id.as_pubkey().ok()),
+			})
+		};
+		let payee_node_id = payee_node_id.ok_or_else(|| {
+			log_error!(
+				self.logger,
+				"Failed to estimate fee as the provided offer has no reachable issuer."
+			);
+			Error::InvalidOffer
+		})?;
+
+		let route_params = RouteParameters::from_payment_params_and_value(
+			PaymentParameters::from_node_id(payee_node_id, MIN_FINAL_CLTV_EXPIRY_DELTA as u32),
+			amount_msat,
+		);
+
+		estimate_payment_fee(
+			&route_params,
+			&self.channel_manager,
+			&self.router,
+			&self.scorer,
+			&self.logger,
+		)
+	}
 }
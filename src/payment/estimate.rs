@@ -0,0 +1,135 @@
+// This file is Copyright its original authors, visible in version control history.
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. You may not use this file except in
+// accordance with one or both of these licenses.
+
+//! Objects for estimating the cost of a payment before sending it.
+
+use crate::error::Error;
+use crate::logger::{log_debug, log_error, LdkLogger, Logger};
+use crate::types::{ChannelManager, PaymentPathHop, Router, Scorer};
+
+use lightning::routing::gossip::NodeId;
+use lightning::routing::router::{Path, RouteParameters, Router as _};
+use lightning::routing::scoring::ProbabilisticScoringFeeParameters;
+
+use std::sync::{Arc, Mutex};
+
+/// The estimated cost of a payment, as determined by finding a route without sending anything.
+///
+/// Note that the actual payment may take a different route, e.g., if the network graph or our
+/// scorer's view of the network changed in the meantime.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaymentFeeEstimate {
+	/// The paths the payment would be split across.
+	pub paths: Vec<PaymentPathEstimate>,
+	/// The total fee, in milli-satoshis, paid to intermediate hops along all paths.
+	pub total_fee_msat: u64,
+	/// The maximum total CLTV expiry delta of any of the paths.
+	///
+	/// This is the maximum number of blocks our funds could be locked up for if the payment gets
+	/// stuck.
+	pub max_total_cltv_expiry_delta: u32,
+	/// The estimated probability, between `0.0` and `1.0`, that all paths succeed.
+	pub success_probability: f64,
+}
+
+/// The estimated cost of a single path of a payment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaymentPathEstimate {
+	/// The hops of the path.
+	///
+	/// Note that this won't include the hops of a blinded path at the end of the path, if any.
+	pub hops: Vec<PaymentPathHop>,
+	/// The amount, in milli-satoshis, delivered to the recipient via the path.
+	pub amount_msat: u64,
+	/// The total fee, in milli-satoshis, paid to intermediate hops along the path.
+	pub fee_msat: u64,
+	/// The total CLTV expiry delta of the path.
+	pub total_cltv_expiry_delta: u32,
+	/// The estimated probability, between `0.0` and `1.0`, that the path succeeds.
+	///
+	/// Hops for which our scorer has no information, e.g., our own or unannounced channels, are
+	/// assumed to succeed.
+	pub success_probability: f64,
+}
+
+pub(crate) fn estimate_payment_fee(
+	route_params: &RouteParameters, channel_manager: &ChannelManager, router: &Router,
+	scorer: &Mutex<Scorer>, logger: &Arc<Logger>,
+) -> Result<PaymentFeeEstimate, Error> {
+	let payer = channel_manager.get_our_node_id();
+	let usable_channels = channel_manager.list_usable_channels();
+	let first_hops = usable_channels.iter().collect::<Vec<_>>();
+	let inflight_htlcs = channel_manager.compute_inflight_htlcs();
+
+	let route = router
+		.find_route(&payer, route_params, Some(&first_hops), inflight_htlcs)
+		.map_err(|e| {
+			log_error!(logger, "Failed to find route for fee estimation: {}", e.err);
+			Error::RouteNotFound
+		})?;
+
+	let score_params = ProbabilisticScoringFeeParameters::default();
+	let locked_scorer = scorer.lock().unwrap();
+	let paths = route
+		.paths
+		.iter()
+		.map(|path| estimate_path(path, &locked_scorer, &score_params))
+		.collect::<Vec<_>>();
+
+	let estimate = PaymentFeeEstimate {
+		total_fee_msat: paths.iter().map(|p| p.fee_msat).sum(),
+		max_total_cltv_expiry_delta: paths
+			.iter()
+			.map(|p| p.total_cltv_expiry_delta)
+			.max()
+			.unwrap_or(0),
+		success_probability: paths.iter().map(|p| p.success_probability).product(),
+		paths,
+	};
+	log_debug!(
+		logger,
+		"Estimated fee of {}msat across {} paths for sending {}msat",
+		estimate.total_fee_msat,
+		estimate.paths.len(),
+		route_params.final_value_msat
+	);
+	Ok(estimate)
+}
+
+fn estimate_path(
+	path: &Path, scorer: &Scorer, score_params: &ProbabilisticScoringFeeParameters,
+) -> PaymentPathEstimate {
+	// The amount we send along the path, which every hop's node forwards minus its fee.
+	let mut hop_amount_msat = path.final_value_msat() + path.fee_msat();
+	let mut success_probability = 1.0;
+	for (idx, hop) in path.hops.iter().enumerate() {
+		// We know the liquidity of our own channels, which the router already accounted for.
+		if idx > 0 {
+			let target = NodeId::from_pubkey(&hop.pubkey);
+			success_probability *= scorer
+				.live_estimated_payment_success_probability(
+					hop.short_channel_id,
+					&target,
+					hop_amount_msat,
+					score_params,
+				)
+				.unwrap_or(1.0);
+		}
+		hop_amount_msat = hop_amount_msat.saturating_sub(hop.fee_msat);
+	}
+
+	let total_cltv_expiry_delta = path.hops.iter().map(|h| h.cltv_expiry_delta).sum::<u32>()
+		+ path.blinded_tail.as_ref().map_or(0, |t| t.excess_final_cltv_expiry_delta);
+
+	PaymentPathEstimate {
+		hops: path.hops.iter().map(PaymentPathHop::from).collect(),
+		amount_msat: path.final_value_msat(),
+		fee_msat: path.fee_msat(),
+		total_cltv_expiry_delta,
+		success_probability,
+	}
+}
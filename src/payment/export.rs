@@ -0,0 +1,419 @@
+// This file is Copyright its original authors, visible in version control history.
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. You may not use this file except in
+// accordance with one or both of these licenses.
+
+//! Objects for exporting the payment history of the node.
+
+use crate::hex_utils;
+use crate::payment::forwarding::ForwardedPaymentDetails;
+use crate::payment::store::{
+	ConfirmationStatus, PaymentDetails, PaymentDirection, PaymentKind, PaymentStatus,
+};
+use crate::sweep::value_from_descriptor;
+
+use lightning::ln::types::ChannelId;
+use lightning::sign::SpendableOutputDescriptor;
+use lightning::util::sweep::{OutputSpendStatus, TrackedSpendableOutput};
+
+use lightning_types::payment::{PaymentHash, PaymentPreimage};
+
+use bitcoin::Txid;
+
+use serde_json::Value;
+
+use std::collections::HashMap;
+
+/// The format in which the payment history is exported by [`Node::export_payments`].
+///
+/// [`Node::export_payments`]: crate::Node::export_payments
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PaymentExportFormat {
+	/// Comma-separated values, with a header row naming the columns.
+	Csv,
+	/// A JSON array holding one object per record.
+	Json,
+}
+
+/// Restricts which records are included in an export by [`Node::export_payments`].
+///
+/// The default value includes all records.
+///
+/// [`Node::export_payments`]: crate::Node::export_payments
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PaymentExportFilter {
+	/// Only include records with a timestamp, in seconds since start of the UNIX epoch, at or
+	/// after this value.
+	pub start_timestamp: Option<u64>,
+	/// Only include records with a timestamp, in seconds since start of the UNIX epoch, before
+	/// this value.
+	pub end_timestamp: Option<u64>,
+	/// Only include records with the given status.
+	///
+	/// Note that forwards are always considered [`PaymentStatus::Succeeded`].
+	pub status: Option<PaymentStatus>,
+}
+
+/// The columns of an export, in order.
+const EXPORT_COLUMNS: [&str; 16] = [
+	"record_type",
+	"id",
+	"direction",
+	"kind",
+	"status",
+	"amount_msat",
+	"fee_msat",
+	"timestamp",
+	"payment_hash",
+	"preimage",
+	"txid",
+	"confirmation_block_hash",
+	"confirmation_height",
+	"confirmation_timestamp",
+	"channel_id",
+	"next_channel_id",
+];
+
+// A single row of an export.
+//
+// For payments the `fee_msat` is the fee we paid, while for forwards it is the fee we earned. The
+// `channel_id` is the closed channel for sweeps and the incoming channel for forwards.
+struct ExportRecord {
+	record_type: &'static str,
+	id: String,
+	direction: Option<PaymentDirection>,
+	kind: Option<&'static str>,
+	status: PaymentStatus,
+	amount_msat: Option<u64>,
+	fee_msat: Option<u64>,
+	timestamp: u64,
+	payment_hash: Option<PaymentHash>,
+	preimage: Option<PaymentPreimage>,
+	txid: Option<Txid>,
+	confirmation_status: Option<ConfirmationStatus>,
+	channel_id: Option<ChannelId>,
+	next_channel_id: Option<ChannelId>,
+}
+
+impl ExportRecord {
+	fn from_payment(
+		payment: PaymentDetails, sweep_channel_ids: &HashMap<Txid, Option<ChannelId>>,
+	) -> Self {
+		let (kind, payment_hash, preimage) = match payment.kind {
+			PaymentKind::Onchain { .. } => ("onchain", None, None),
+			PaymentKind::Bolt11 { hash, preimage, .. } => ("bolt11", Some(hash), preimage),
+			PaymentKind::Bolt11Jit { hash, preimage, .. } => ("bolt11_jit", Some(hash), preimage),
+			PaymentKind::Bolt12Offer { hash, preimage, .. } => ("bolt12_offer", hash, preimage),
+			PaymentKind::Bolt12Refund { hash, preimage, .. } => ("bolt12_refund", hash, preimage),
+			PaymentKind::Spontaneous { hash, preimage } => ("spontaneous", Some(hash), preimage),
+			PaymentKind::Rebalance { hash, preimage, .. } => ("rebalance", Some(hash), preimage),
+		};
+		let (txid, confirmation_status) = match payment.kind {
+			PaymentKind::Onchain { txid, status, .. } => (Some(txid), Some(status)),
+			_ => (None, None),
+		};
+
+		// On-chain payments spending outputs of closed channels are sweeps.
+		let sweep_channel_id = txid.and_then(|txid| sweep_channel_ids.get(&txid));
+		let record_type = if sweep_channel_id.is_some() { "sweep" } else { "payment" };
+
+		// Rebalances leave through one of our channels and come back through another one.
+		let (channel_id, next_channel_id) = match payment.kind {
+			PaymentKind::Rebalance { from_channel_id, to_channel_id, .. } => {
+				(Some(from_channel_id), Some(to_channel_id))
+			},
+			_ => (sweep_channel_id.copied().flatten(), None),
+		};
+
+		Self {
+			record_type,
+			id: hex_utils::to_string(&payment.id.0),
+			direction: Some(payment.direction),
+			kind: Some(kind),
+			status: payment.status,
+			amount_msat: payment.amount_msat,
+			fee_msat: payment.fee_paid_msat,
+			timestamp: payment.latest_update_timestamp,
+			payment_hash,
+			preimage,
+			txid,
+			confirmation_status,
+			channel_id,
+			next_channel_id,
+		}
+	}
+
+	fn from_forward(forward: ForwardedPaymentDetails) -> Self {
+		Self {
+			record_type: "forward",
+			id: hex_utils::to_string(&forward.id.0),
+			direction: None,
+			kind: None,
+			status: PaymentStatus::Succeeded,
+			amount_msat: forward.outbound_amount_forwarded_msat,
+			fee_msat: forward.total_fee_earned_msat,
+			timestamp: forward.forwarded_at_timestamp,
+			payment_hash: None,
+			preimage: None,
+			txid: None,
+			confirmation_status: None,
+			channel_id: Some(forward.prev_channel_id),
+			next_channel_id: Some(forward.next_channel_id),
+		}
+	}
+
+	// Sweeps that were not broadcast yet, and hence don't have a matching on-chain payment.
+	fn from_unbroadcast_sweep(output: &TrackedSpendableOutput, timestamp: u64) -> Self {
+		let outpoint = match &output.descriptor {
+			SpendableOutputDescriptor::StaticOutput { outpoint, .. } => outpoint,
+			SpendableOutputDescriptor::DelayedPaymentOutput(descriptor) => &descriptor.outpoint,
+			SpendableOutputDescriptor::StaticPaymentOutput(descriptor) => &descriptor.outpoint,
+		};
+		Self {
+			record_type: "sweep",
+			id: format!("{}:{}", outpoint.txid, outpoint.index),
+			direction: Some(PaymentDirection::Inbound),
+			kind: Some("onchain"),
+			status: PaymentStatus::Pending,
+			amount_msat: Some(value_from_descriptor(&output.descriptor).to_sat() * 1000),
+			fee_msat: None,
+			timestamp,
+			payment_hash: None,
+			preimage: None,
+			txid: None,
+			confirmation_status: None,
+			channel_id: output.channel_id,
+			next_channel_id: None,
+		}
+	}
+
+	fn values(&self) -> [Value; EXPORT_COLUMNS.len()] {
+		let string = |s: Option<String>| s.map_or(Value::Null, Value::String);
+		let number = |n: Option<u64>| n.map_or(Value::Null, Value::from);
+		let (block_hash, height, confirmation_timestamp) = match self.confirmation_status {
+			Some(ConfirmationStatus::Confirmed { block_hash, height, timestamp }) => {
+				(Some(block_hash.to_string()), Some(height as u64), Some(timestamp))
+			},
+			_ => (None, None, None),
+		};
+		let direction = self.direction.map(|d| match d {
+			PaymentDirection::Inbound => "inbound".to_string(),
+			PaymentDirection::Outbound => "outbound".to_string(),
+		});
+		let status = match self.status {
+			PaymentStatus::Pending => "pending",
+			PaymentStatus::Succeeded => "succeeded",
+			PaymentStatus::Failed => "failed",
+			PaymentStatus::Held => "held",
+		};
+
+		[
+			Value::from(self.record_type),
+			Value::from(self.id.clone()),
+			string(direction),
+			string(self.kind.map(str::to_string)),
+			Value::from(status),
+			number(self.amount_msat),
+			number(self.fee_msat),
+			Value::from(self.timestamp),
+			string(self.payment_hash.map(|h| hex_utils::to_string(&h.0))),
+			string(self.preimage.map(|p| hex_utils::to_string(&p.0))),
+			string(self.txid.map(|t| t.to_string())),
+			string(block_hash),
+			number(height),
+			number(confirmation_timestamp),
+			string(self.channel_id.map(|c| c.to_string())),
+			string(self.next_channel_id.map(|c| c.to_string())),
+		]
+	}
+}
+
+pub(crate) fn export_payments(
+	format: PaymentExportFormat, filter: &PaymentExportFilter, payments: Vec<PaymentDetails>,
+	forwards: Vec<ForwardedPaymentDetails>, tracked_outputs: Vec<TrackedSpendableOutput>,
+	cur_timestamp: u64,
+) -> String {
+	let mut sweep_channel_ids = HashMap::new();
+	let mut records = Vec::new();
+	for output in tracked_outputs {
+		match &output.status {
+			OutputSpendStatus::PendingInitialBroadcast { .. } => {
+				records.push(ExportRecord::from_unbroadcast_sweep(&output, cur_timestamp));
+			},
+			OutputSpendStatus::PendingFirstConfirmation { latest_spending_tx, .. }
+			| OutputSpendStatus::PendingThresholdConfirmations { latest_spending_tx, .. } => {
+				sweep_channel_ids.insert(latest_spending_tx.compute_txid(), output.channel_id);
+			},
+		}
+	}
+	records.extend(payments.into_iter().map(|p| ExportRecord::from_payment(p, &sweep_channel_ids)));
+	records.extend(forwards.into_iter().map(ExportRecord::from_forward));
+
+	records.retain(|r| {
+		filter.start_timestamp.map_or(true, |start| r.timestamp >= start)
+			&& filter.end_timestamp.map_or(true, |end| r.timestamp < end)
+			&& filter.status.map_or(true, |status| r.status == status)
+	});
+	records.sort_by(|a, b| a.timestamp.cmp(&b.timestamp).then_with(|| a.id.cmp(&b.id)));
+
+	match format {
+		PaymentExportFormat::Csv => {
+			let mut csv = EXPORT_COLUMNS.join(",");
+			csv.push('\n');
+			for record in records {
+				let row = record
+					.values()
+					.iter()
+					.map(|value| match value {
+						Value::Null => String::new(),
+						Value::String(s) => s.clone(),
+						v => v.to_string(),
+					})
+					.collect::<Vec<_>>();
+				csv.push_str(&row.join(","));
+				csv.push('\n');
+			}
+			csv
+		},
+		PaymentExportFormat::Json => {
+			let entries = records
+				.into_iter()
+				.map(|record| {
+					let entry = EXPORT_COLUMNS
+						.iter()
+						.map(|column| column.to_string())
+						.zip(record.values())
+						.collect::<serde_json::Map<_, _>>();
+					Value::Object(entry)
+				})
+				.collect::<Vec<_>>();
+			Value::Array(entries).to_string()
+		},
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::payment::forwarding::ForwardedPaymentId;
+
+	use lightning::ln::channelmanager::PaymentId;
+
+	use bitcoin::hashes::Hash;
+	use bitcoin::BlockHash;
+
+	fn test_payments() -> Vec<PaymentDetails> {
+		let preimage = PaymentPreimage([42u8; 32]);
+		let hash = PaymentHash::from(preimage);
+		let mut bolt11 = PaymentDetails::new(
+			PaymentId(hash.0),
+			PaymentKind::Bolt11 {
+				hash,
+				preimage: Some(preimage),
+				secret: None,
+				claim_deadline: None,
+			},
+			Some(10_000),
+			Some(21),
+			PaymentDirection::Outbound,
+			PaymentStatus::Succeeded,
+		);
+		bolt11.latest_update_timestamp = 100;
+
+		let txid = Txid::from_byte_array([1u8; 32]);
+		let status = ConfirmationStatus::Confirmed {
+			block_hash: BlockHash::from_byte_array([2u8; 32]),
+			height: 800_000,
+			timestamp: 150,
+		};
+		let mut onchain = PaymentDetails::new(
+			PaymentId(txid.to_byte_array()),
+			PaymentKind::Onchain { txid, status, replaced_txids: Vec::new() },
+			Some(50_000_000),
+			Some(1_000),
+			PaymentDirection::Inbound,
+			PaymentStatus::Pending,
+		);
+		onchain.latest_update_timestamp = 200;
+		vec![onchain, bolt11]
+	}
+
+	fn test_forward() -> ForwardedPaymentDetails {
+		let mut forward = ForwardedPaymentDetails::new(
+			ForwardedPaymentId([3u8; 32]),
+			ChannelId([4u8; 32]),
+			ChannelId([5u8; 32]),
+			None,
+			None,
+			None,
+			None,
+			Some(7),
+			None,
+			false,
+			Some(5_000),
+		);
+		forward.forwarded_at_timestamp = 300;
+		forward
+	}
+
+	#[test]
+	fn csv_export_contains_all_records() {
+		let filter = PaymentExportFilter::default();
+		let csv = export_payments(
+			PaymentExportFormat::Csv,
+			&filter,
+			test_payments(),
+			vec![test_forward()],
+			Vec::new(),
+			1_000,
+		);
+		let lines = csv.lines().collect::<Vec<_>>();
+		assert_eq!(lines.len(), 4);
+		assert_eq!(lines[0], EXPORT_COLUMNS.join(","));
+
+		// Records are ordered by timestamp.
+		let bolt11 = lines[1].split(',').collect::<Vec<_>>();
+		assert_eq!(bolt11.len(), EXPORT_COLUMNS.len());
+		assert_eq!(bolt11[0], "payment");
+		assert_eq!(&bolt11[2..8], &["outbound", "bolt11", "succeeded", "10000", "21", "100"]);
+		assert_eq!(bolt11[9], "2a".repeat(32));
+
+		let onchain = lines[2].split(',').collect::<Vec<_>>();
+		assert_eq!(onchain[0], "payment");
+		assert_eq!(onchain[10], Txid::from_byte_array([1u8; 32]).to_string());
+		assert_eq!(onchain[12], "800000");
+		assert_eq!(onchain[13], "150");
+
+		let forward = lines[3].split(',').collect::<Vec<_>>();
+		assert_eq!(forward[0], "forward");
+		assert_eq!(forward[2], "");
+		assert_eq!(forward[5], "5000");
+		assert_eq!(forward[6], "7");
+		assert_eq!(forward[14], ChannelId([4u8; 32]).to_string());
+		assert_eq!(forward[15], ChannelId([5u8; 32]).to_string());
+	}
+
+	#[test]
+	fn json_export_applies_filter() {
+		let filter = PaymentExportFilter {
+			start_timestamp: Some(150),
+			end_timestamp: None,
+			status: Some(PaymentStatus::Succeeded),
+		};
+		let json = export_payments(
+			PaymentExportFormat::Json,
+			&filter,
+			test_payments(),
+			vec![test_forward()],
+			Vec::new(),
+			1_000,
+		);
+		let entries: Vec<serde_json::Value> = serde_json::from_str(&json).unwrap();
+		assert_eq!(entries.len(), 1);
+		assert_eq!(entries[0]["record_type"], "forward");
+		assert_eq!(entries[0]["fee_msat"], 7);
+		assert_eq!(entries[0]["direction"], Value::Null);
+	}
+}
@@ -0,0 +1,315 @@
+// This file is Copyright its original authors, visible in version control history.
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. You may not use this file except in
+// accordance with one or both of these licenses.
+
+use crate::data_store::{StorableObject, StorableObjectId, StorableObjectUpdate};
+use crate::hex_utils;
+use crate::types::UserChannelId;
+
+use lightning::impl_writeable_tlv_based;
+use lightning::ln::msgs::DecodeError;
+use lightning::ln::types::ChannelId;
+use lightning::util::ser::{Readable, Writeable, Writer};
+
+use bitcoin::secp256k1::PublicKey;
+
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A locally unique identifier of a forwarded payment.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct ForwardedPaymentId(pub [u8; 32]);
+
+impl Writeable for ForwardedPaymentId {
+	fn write<W: Writer>(&self, writer: &mut W) -> Result<(), lightning::io::Error> {
+		self.0.write(writer)
+	}
+}
+
+impl Readable for ForwardedPaymentId {
+	fn read<R: lightning::io::Read>(reader: &mut R) -> Result<Self, DecodeError> {
+		Ok(Self(Readable::read(reader)?))
+	}
+}
+
+impl StorableObjectId for ForwardedPaymentId {
+	fn encode_to_hex_str(&self) -> String {
+		hex_utils::to_string(&self.0)
+	}
+}
+
+/// Represents a payment we forwarded from one of our channels to another.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ForwardedPaymentDetails {
+	/// The identifier of this forward.
+	pub id: ForwardedPaymentId,
+	/// The channel id of the incoming channel between the previous node and us.
+	pub prev_channel_id: ChannelId,
+	/// The channel id of the outgoing channel between the next node and us.
+	pub next_channel_id: ChannelId,
+	/// The `user_channel_id` of the incoming channel between the previous node and us.
+	pub prev_user_channel_id: Option<UserChannelId>,
+	/// The `user_channel_id` of the outgoing channel between the next node and us.
+	pub next_user_channel_id: Option<UserChannelId>,
+	/// The node id of the previous node.
+	pub prev_node_id: Option<PublicKey>,
+	/// The node id of the next node.
+	pub next_node_id: Option<PublicKey>,
+	/// The total fee, in milli-satoshis, which was earned as a result of the payment.
+	///
+	/// See [`Event::PaymentForwarded::total_fee_earned_msat`] for more information.
+	///
+	/// [`Event::PaymentForwarded::total_fee_earned_msat`]: crate::Event::PaymentForwarded::total_fee_earned_msat
+	pub total_fee_earned_msat: Option<u64>,
+	/// The share of the total fee, in milli-satoshis, which was withheld in addition to the
+	/// forwarding fee.
+	pub skimmed_fee_msat: Option<u64>,
+	/// If this is `true`, the forwarded HTLC was claimed by our counterparty via an on-chain
+	/// transaction.
+	pub claim_from_onchain_tx: bool,
+	/// The final amount forwarded, in milli-satoshis, after the fee is deducted.
+	pub outbound_amount_forwarded_msat: Option<u64>,
+	/// The timestamp, in seconds since start of the UNIX epoch, when the forward was completed.
+	pub forwarded_at_timestamp: u64,
+}
+
+impl ForwardedPaymentDetails {
+	#[allow(clippy::too_many_arguments)]
+	pub(crate) fn new(
+		id: ForwardedPaymentId, prev_channel_id: ChannelId, next_channel_id: ChannelId,
+		prev_user_channel_id: Option<UserChannelId>, next_user_channel_id: Option<UserChannelId>,
+		prev_node_id: Option<PublicKey>, next_node_id: Option<PublicKey>,
+		total_fee_earned_msat: Option<u64>, skimmed_fee_msat: Option<u64>,
+		claim_from_onchain_tx: bool, outbound_amount_forwarded_msat: Option<u64>,
+	) -> Self {
+		let forwarded_at_timestamp = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap_or(Duration::from_secs(0))
+			.as_secs();
+		Self {
+			id,
+			prev_channel_id,
+			next_channel_id,
+			prev_user_channel_id,
+			next_user_channel_id,
+			prev_node_id,
+			next_node_id,
+			total_fee_earned_msat,
+			skimmed_fee_msat,
+			claim_from_onchain_tx,
+			outbound_amount_forwarded_msat,
+			forwarded_at_timestamp,
+		}
+	}
+}
+
+impl_writeable_tlv_based!(ForwardedPaymentDetails, {
+	(0, id, required),
+	(2, prev_channel_id, required),
+	(4, next_channel_id, required),
+	(6, prev_user_channel_id, option),
+	(8, next_user_channel_id, option),
+	(10, prev_node_id, option),
+	(12, next_node_id, option),
+	(14, total_fee_earned_msat, option),
+	(16, skimmed_fee_msat, option),
+	(18, claim_from_onchain_tx, required),
+	(20, outbound_amount_forwarded_msat, option),
+	(22, forwarded_at_timestamp, required),
+});
+
+impl StorableObject for ForwardedPaymentDetails {
+	type Id = ForwardedPaymentId;
+	type Update = ForwardedPaymentDetailsUpdate;
+
+	fn id(&self) -> Self::Id {
+		self.id
+	}
+
+	fn update(&mut self, update: &Self::Update) -> bool {
+		debug_assert_eq!(
+			self.id, update.id,
+			"We should only ever override forwarding data for the same forward id"
+		);
+
+		// Forwards are only ever recorded once they completed and hence never change.
+		false
+	}
+
+	fn to_update(&self) -> Self::Update {
+		ForwardedPaymentDetailsUpdate { id: self.id }
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct ForwardedPaymentDetailsUpdate {
+	pub id: ForwardedPaymentId,
+}
+
+impl StorableObjectUpdate<ForwardedPaymentDetails> for ForwardedPaymentDetailsUpdate {
+	fn id(&self) -> <ForwardedPaymentDetails as StorableObject>::Id {
+		self.id
+	}
+}
+
+/// Restricts which forwards are considered by [`Node::list_forwards`] and
+/// [`Node::forwarding_summary`].
+///
+/// The default value includes all forwards.
+///
+/// [`Node::list_forwards`]: crate::Node::list_forwards
+/// [`Node::forwarding_summary`]: crate::Node::forwarding_summary
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ForwardFilter {
+	/// Only include forwards completed at or after this timestamp, in seconds since start of the
+	/// UNIX epoch.
+	pub start_timestamp: Option<u64>,
+	/// Only include forwards completed before this timestamp, in seconds since start of the UNIX
+	/// epoch.
+	pub end_timestamp: Option<u64>,
+	/// Only include forwards that were received or forwarded via the channel with the given id.
+	pub channel_id: Option<ChannelId>,
+}
+
+impl ForwardFilter {
+	pub(crate) fn matches(&self, forward: &ForwardedPaymentDetails) -> bool {
+		self.start_timestamp.map_or(true, |start| forward.forwarded_at_timestamp >= start)
+			&& self.end_timestamp.map_or(true, |end| forward.forwarded_at_timestamp < end)
+			&& self.channel_id.map_or(true, |channel_id| {
+				forward.prev_channel_id == channel_id || forward.next_channel_id == channel_id
+			})
+	}
+}
+
+/// Aggregated statistics over a set of forwards, as returned by [`Node::forwarding_summary`].
+///
+/// [`Node::forwarding_summary`]: crate::Node::forwarding_summary
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ForwardingSummary {
+	/// The number of forwards.
+	pub forward_count: u64,
+	/// The total amount forwarded, in milli-satoshis, after fees were deducted.
+	pub total_amount_forwarded_msat: u64,
+	/// The total fees earned, in milli-satoshis.
+	pub total_fee_earned_msat: u64,
+	/// The statistics of each channel that took part in any of the forwards, ordered by the fees
+	/// earned.
+	pub channels: Vec<ChannelForwardingStats>,
+}
+
+/// The forwarding statistics of a single channel.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChannelForwardingStats {
+	/// The channel id of the channel.
+	pub channel_id: ChannelId,
+	/// The number of forwards we received via this channel.
+	pub inbound_forward_count: u64,
+	/// The number of forwards we sent out via this channel.
+	pub outbound_forward_count: u64,
+	/// The total amount, in milli-satoshis, we sent out via this channel.
+	pub outbound_amount_forwarded_msat: u64,
+	/// The total fees earned, in milli-satoshis, by forwarding out via this channel.
+	///
+	/// As forwarding fees are charged according to the outgoing channel's fee policy, fees are
+	/// attributed to the outgoing channel only.
+	pub fee_earned_msat: u64,
+}
+
+impl ChannelForwardingStats {
+	fn new(channel_id: ChannelId) -> Self {
+		Self {
+			channel_id,
+			inbound_forward_count: 0,
+			outbound_forward_count: 0,
+			outbound_amount_forwarded_msat: 0,
+			fee_earned_msat: 0,
+		}
+	}
+}
+
+pub(crate) fn summarize_forwards(forwards: &[ForwardedPaymentDetails]) -> ForwardingSummary {
+	let mut summary = ForwardingSummary::default();
+	let mut channels: HashMap<ChannelId, ChannelForwardingStats> = HashMap::new();
+	for forward in forwards {
+		let amount_msat = forward.outbound_amount_forwarded_msat.unwrap_or(0);
+		let fee_msat = forward.total_fee_earned_msat.unwrap_or(0);
+		summary.forward_count += 1;
+		summary.total_amount_forwarded_msat += amount_msat;
+		summary.total_fee_earned_msat += fee_msat;
+
+		channels
+			.entry(forward.prev_channel_id)
+			.or_insert_with(|| ChannelForwardingStats::new(forward.prev_channel_id))
+			.inbound_forward_count += 1;
+		let outbound = channels
+			.entry(forward.next_channel_id)
+			.or_insert_with(|| ChannelForwardingStats::new(forward.next_channel_id));
+		outbound.outbound_forward_count += 1;
+		outbound.outbound_amount_forwarded_msat += amount_msat;
+		outbound.fee_earned_msat += fee_msat;
+	}
+
+	summary.channels = channels.into_values().collect();
+	summary.channels.sort_by(|a, b| {
+		b.fee_earned_msat.cmp(&a.fee_earned_msat).then_with(|| a.channel_id.cmp(&b.channel_id))
+	});
+	summary
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn forward(
+		id: u8, prev: u8, next: u8, fee_msat: u64, timestamp: u64,
+	) -> ForwardedPaymentDetails {
+		let mut forward = ForwardedPaymentDetails::new(
+			ForwardedPaymentId([id; 32]),
+			ChannelId([prev; 32]),
+			ChannelId([next; 32]),
+			None,
+			None,
+			None,
+			None,
+			Some(fee_msat),
+			None,
+			false,
+			Some(100_000),
+		);
+		forward.forwarded_at_timestamp = timestamp;
+		forward
+	}
+
+	#[test]
+	fn forwards_are_filtered_and_summarized() {
+		let forwards =
+			vec![forward(0, 1, 2, 10, 100), forward(1, 2, 1, 30, 200), forward(2, 1, 3, 5, 300)];
+
+		let filter = ForwardFilter { start_timestamp: Some(200), ..Default::default() };
+		assert_eq!(forwards.iter().filter(|f| filter.matches(f)).count(), 2);
+		let filter = ForwardFilter { end_timestamp: Some(200), ..Default::default() };
+		assert_eq!(forwards.iter().filter(|f| filter.matches(f)).count(), 1);
+		let filter = ForwardFilter { channel_id: Some(ChannelId([3; 32])), ..Default::default() };
+		assert_eq!(forwards.iter().filter(|f| filter.matches(f)).count(), 1);
+
+		let summary = summarize_forwards(&forwards);
+		assert_eq!(summary.forward_count, 3);
+		assert_eq!(summary.total_amount_forwarded_msat, 300_000);
+		assert_eq!(summary.total_fee_earned_msat, 45);
+		assert_eq!(summary.channels.len(), 3);
+
+		// Fees are attributed to the outgoing channel, and channels ordered by fees earned.
+		let channel_1 = &summary.channels[0];
+		assert_eq!(channel_1.channel_id, ChannelId([1; 32]));
+		assert_eq!(channel_1.inbound_forward_count, 2);
+		assert_eq!(channel_1.outbound_forward_count, 1);
+		assert_eq!(channel_1.fee_earned_msat, 30);
+		assert_eq!(summary.channels[1].channel_id, ChannelId([2; 32]));
+		assert_eq!(summary.channels[1].fee_earned_msat, 10);
+		assert_eq!(summary.channels[2].channel_id, ChannelId([3; 32]));
+		assert_eq!(summary.channels[2].inbound_forward_count, 0);
+	}
+}
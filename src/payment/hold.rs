@@ -0,0 +1,103 @@
+// This file is Copyright its original authors, visible in version control history.
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. You may not use this file except in
+// accordance with one or both of these licenses.
+
+//! Holds a watchdog making sure held payments are resolved before their claim deadline.
+
+use crate::config::{Config, HELD_PAYMENT_EXPIRY_WARNING_BLOCKS};
+use crate::event::{Event, EventQueue};
+use crate::logger::{log_error, log_info, LdkLogger, Logger};
+use crate::payment::index::PaymentQuery;
+use crate::payment::store::{PaymentDetailsUpdate, PaymentKind, PaymentStatus};
+use crate::types::{ChannelManager, PaymentStore};
+
+use lightning::ln::channelmanager::PaymentId;
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+/// Regularly checks the claim deadlines of held payments, warns the user when they're about to
+/// expire, and fails them back once they're within the configured margin.
+pub(crate) struct HeldPaymentWatchdog {
+	channel_manager: Arc<ChannelManager>,
+	payment_store: Arc<PaymentStore>,
+	event_queue: Arc<EventQueue<Arc<Logger>>>,
+	config: Arc<Config>,
+	logger: Arc<Logger>,
+	// The payments we already emitted an `Event::PaymentHoldExpiring` for.
+	warned_payments: Mutex<HashSet<PaymentId>>,
+}
+
+impl HeldPaymentWatchdog {
+	pub(crate) fn new(
+		channel_manager: Arc<ChannelManager>, payment_store: Arc<PaymentStore>,
+		event_queue: Arc<EventQueue<Arc<Logger>>>, config: Arc<Config>, logger: Arc<Logger>,
+	) -> Self {
+		let warned_payments = Mutex::new(HashSet::new());
+		Self { channel_manager, payment_store, event_queue, config, logger, warned_payments }
+	}
+
+	pub(crate) fn check_held_payments(&self) {
+		let cur_height = self.channel_manager.current_best_block().height;
+		let held_payments = self
+			.payment_store
+			.list(&PaymentQuery { status: Some(PaymentStatus::Held), ..Default::default() });
+
+		let mut warned_payments = self.warned_payments.lock().unwrap();
+		warned_payments.retain(|id| held_payments.iter().any(|p| p.id == *id));
+
+		for payment in held_payments {
+			let (payment_hash, claim_deadline) = match payment.kind {
+				PaymentKind::Bolt11 { hash, claim_deadline: Some(claim_deadline), .. } => {
+					(hash, claim_deadline)
+				},
+				_ => continue,
+			};
+
+			let cancel_height =
+				claim_deadline.saturating_sub(self.config.hold_invoice_cancel_margin_blocks);
+
+			if cur_height >= cancel_height {
+				log_info!(
+					self.logger,
+					"Failing back held payment with hash {} as it is within {} blocks of its claim deadline {}",
+					payment_hash,
+					self.config.hold_invoice_cancel_margin_blocks,
+					claim_deadline,
+				);
+				let update = PaymentDetailsUpdate {
+					status: Some(PaymentStatus::Failed),
+					..PaymentDetailsUpdate::new(payment.id)
+				};
+				if let Err(e) = self.payment_store.update(&update) {
+					log_error!(self.logger, "Failed to access payment store: {}", e);
+					continue;
+				}
+				self.channel_manager.fail_htlc_backwards(&payment_hash);
+				warned_payments.remove(&payment.id);
+				continue;
+			}
+
+			let warning_height = cancel_height.saturating_sub(HELD_PAYMENT_EXPIRY_WARNING_BLOCKS);
+			if cur_height >= warning_height && !warned_payments.contains(&payment.id) {
+				let event = Event::PaymentHoldExpiring {
+					payment_id: payment.id,
+					payment_hash,
+					claim_deadline,
+					cancel_height,
+				};
+				match self.event_queue.add_event(event) {
+					Ok(()) => {
+						warned_payments.insert(payment.id);
+					},
+					Err(e) => {
+						log_error!(self.logger, "Failed to push to event queue: {}", e);
+					},
+				}
+			}
+		}
+	}
+}
@@ -0,0 +1,661 @@
+// This file is Copyright its original authors, visible in version control history.
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. You may not use this file except in
+// accordance with one or both of these licenses.
+
+//! Objects for indexed and paginated payment lookups.
+
+use crate::config::PaymentRetentionConfig;
+use crate::data_store::{DataStore, DataStoreUpdateResult, StorableObjectUpdate};
+use crate::io::utils::write_node_metrics;
+use crate::logger::{log_info, LdkLogger, Logger};
+use crate::payment::store::{
+	PaymentDetails, PaymentDetailsUpdate, PaymentDirection, PaymentKind, PaymentStatus, PaymentType,
+};
+use crate::types::{ArchivedPaymentStore, DynStore, PaymentStore};
+use crate::{Error, NodeMetrics};
+
+use lightning::ln::channelmanager::PaymentId;
+
+use lightning_types::payment::PaymentHash;
+
+use bitcoin::Txid;
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::hash::Hash;
+use std::ops::{Bound, Deref};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// The number of recently used payments we keep in memory.
+const PAYMENT_CACHE_SIZE: usize = 1000;
+
+// The number of recently used archived payments we keep in memory.
+pub(crate) const ARCHIVED_PAYMENT_CACHE_SIZE: usize = 100;
+
+/// The criteria payments are selected by in [`Node::list_payments_page`].
+///
+/// Payments need to match all criteria that are set.
+///
+/// [`Node::list_payments_page`]: crate::Node::list_payments_page
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PaymentQuery {
+	/// Only select payments with the given payment hash.
+	pub payment_hash: Option<PaymentHash>,
+	/// Only select payments with the given status.
+	pub status: Option<PaymentStatus>,
+	/// Only select payments with the given direction.
+	pub direction: Option<PaymentDirection>,
+	/// Only select payments of the given type.
+	pub payment_type: Option<PaymentType>,
+}
+
+/// The position in the list of payments from which the next page of results continues.
+///
+/// Payments are ordered by [`PaymentDetails::latest_update_timestamp`], most recently updated
+/// first. Note that a payment that is updated while paging through the results may therefore be
+/// returned twice or not at all.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PaymentCursor {
+	/// The [`PaymentDetails::latest_update_timestamp`] of the last payment of the previous page.
+	pub latest_update_timestamp: u64,
+	/// The [`PaymentDetails::id`] of the last payment of the previous page.
+	pub id: PaymentId,
+}
+
+/// A page of payments as returned by [`Node::list_payments_page`].
+///
+/// [`Node::list_payments_page`]: crate::Node::list_payments_page
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PaymentPage {
+	/// The payments of this page, most recently updated first.
+	pub payments: Vec<PaymentDetails>,
+	/// The cursor to retrieve the next page with.
+	///
+	/// Will be `None` if there are no further payments matching the query.
+	pub next_cursor: Option<PaymentCursor>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct PaymentIndexEntry {
+	latest_update_timestamp: u64,
+	hash: Option<PaymentHash>,
+	txid: Option<Txid>,
+	status: PaymentStatus,
+	direction: PaymentDirection,
+	payment_type: PaymentType,
+}
+
+impl PaymentIndexEntry {
+	fn matches(&self, query: &PaymentQuery) -> bool {
+		query.payment_hash.map_or(true, |h| self.hash == Some(h))
+			&& query.status.map_or(true, |s| self.status == s)
+			&& query.direction.map_or(true, |d| self.direction == d)
+			&& query.payment_type.map_or(true, |t| self.payment_type == t)
+	}
+}
+
+impl From<&PaymentDetails> for PaymentIndexEntry {
+	fn from(payment: &PaymentDetails) -> Self {
+		Self {
+			latest_update_timestamp: payment.latest_update_timestamp,
+			hash: payment.kind.hash(),
+			txid: match payment.kind {
+				PaymentKind::Onchain { txid, .. } => Some(txid),
+				_ => None,
+			},
+			status: payment.status,
+			direction: payment.direction,
+			payment_type: PaymentType::from(&payment.kind),
+		}
+	}
+}
+
+/// An in-memory index of the payments held by a [`PaymentStore`], allowing to look them up
+/// without reading them from the underlying store.
+///
+/// [`PaymentStore`]: crate::types::PaymentStore
+#[derive(Default)]
+pub(crate) struct PaymentIndex {
+	entries: HashMap<PaymentId, PaymentIndexEntry>,
+	by_timestamp: BTreeSet<(u64, [u8; 32])>,
+	by_hash: HashMap<PaymentHash, HashSet<PaymentId>>,
+	// On-chain payments by the txid of their current transaction, which differs from the one
+	// their id is derived from if they were fee-bumped via RBF.
+	by_txid: HashMap<Txid, PaymentId>,
+	by_status: HashMap<PaymentStatus, HashSet<PaymentId>>,
+	by_direction: HashMap<PaymentDirection, HashSet<PaymentId>>,
+	by_type: HashMap<PaymentType, HashSet<PaymentId>>,
+}
+
+impl PaymentIndex {
+	pub(crate) fn new() -> Self {
+		Self::default()
+	}
+
+	pub(crate) fn insert(&mut self, payment: &PaymentDetails) {
+		let entry = PaymentIndexEntry::from(payment);
+		if self.entries.get(&payment.id) == Some(&entry) {
+			return;
+		}
+
+		self.remove(&payment.id);
+		let id = payment.id;
+		self.by_timestamp.insert((entry.latest_update_timestamp, id.0));
+		if let Some(hash) = entry.hash {
+			self.by_hash.entry(hash).or_default().insert(id);
+		}
+		if let Some(txid) = entry.txid {
+			self.by_txid.insert(txid, id);
+		}
+		self.by_status.entry(entry.status).or_default().insert(id);
+		self.by_direction.entry(entry.direction).or_default().insert(id);
+		self.by_type.entry(entry.payment_type).or_default().insert(id);
+		self.entries.insert(id, entry);
+	}
+
+	pub(crate) fn remove(&mut self, id: &PaymentId) {
+		if let Some(entry) = self.entries.remove(id) {
+			self.by_timestamp.remove(&(entry.latest_update_timestamp, id.0));
+			if let Some(hash) = entry.hash {
+				remove_from_set(&mut self.by_hash, &hash, id);
+			}
+			if let Some(txid) = entry.txid {
+				if self.by_txid.get(&txid) == Some(id) {
+					self.by_txid.remove(&txid);
+				}
+			}
+			remove_from_set(&mut self.by_status, &entry.status, id);
+			remove_from_set(&mut self.by_direction, &entry.direction, id);
+			remove_from_set(&mut self.by_type, &entry.payment_type, id);
+		}
+	}
+
+	fn len(&self) -> usize {
+		self.entries.len()
+	}
+
+	fn payment_id_for_txid(&self, txid: &Txid) -> Option<PaymentId> {
+		self.by_txid.get(txid).copied()
+	}
+
+	/// Returns the ids of up to `limit` payments matching the given query, ordered by their
+	/// latest update, most recent first, and starting after the given cursor.
+	fn query(
+		&self, query: &PaymentQuery, cursor: Option<&PaymentCursor>, limit: usize,
+	) -> Vec<PaymentId> {
+		let upper_bound = match cursor {
+			Some(cursor) => Bound::Excluded((cursor.latest_update_timestamp, cursor.id.0)),
+			None => Bound::Unbounded,
+		};
+
+		// Start from the smallest set of candidates any of the indexes gives us, and only fall
+		// back to walking all payments if the query isn't restricted at all.
+		let empty = HashSet::new();
+		let candidate_sets = [
+			query.payment_hash.map(|h| self.by_hash.get(&h).unwrap_or(&empty)),
+			query.status.map(|s| self.by_status.get(&s).unwrap_or(&empty)),
+			query.direction.map(|d| self.by_direction.get(&d).unwrap_or(&empty)),
+			query.payment_type.map(|t| self.by_type.get(&t).unwrap_or(&empty)),
+		];
+		let candidates = candidate_sets.into_iter().flatten().min_by_key(|set| set.len());
+
+		match candidates {
+			Some(candidates) => {
+				let mut keys = candidates
+					.iter()
+					.filter_map(|id| self.entries.get(id).map(|entry| (id, entry)))
+					.filter(|(_, entry)| entry.matches(query))
+					.map(|(id, entry)| (entry.latest_update_timestamp, id.0))
+					.filter(|key| match upper_bound {
+						Bound::Excluded(ref bound) => key < bound,
+						_ => true,
+					})
+					.collect::<Vec<_>>();
+				keys.sort_unstable_by(|a, b| b.cmp(a));
+				keys.into_iter().take(limit).map(|(_, id)| PaymentId(id)).collect()
+			},
+			None => self
+				.by_timestamp
+				.range((Bound::Unbounded, upper_bound))
+				.rev()
+				.take(limit)
+				.map(|(_, id)| PaymentId(*id))
+				.collect(),
+		}
+	}
+
+	/// Returns the ids of all payments with one of the given statuses that were last updated
+	/// before the given timestamp.
+	fn last_updated_before(&self, timestamp: u64, statuses: &[PaymentStatus]) -> Vec<PaymentId> {
+		self.by_timestamp
+			.range(..(timestamp, [0u8; 32]))
+			.map(|(_, id)| PaymentId(*id))
+			.filter(|id| self.entries.get(id).map_or(false, |e| statuses.contains(&e.status)))
+			.collect()
+	}
+
+	#[cfg(any(feature = "metrics", test))]
+	fn count_by_type_and_status(&self) -> HashMap<(PaymentType, PaymentStatus), u64> {
+		let mut counts = HashMap::new();
+		for entry in self.entries.values() {
+			*counts.entry((entry.payment_type, entry.status)).or_default() += 1;
+		}
+		counts
+	}
+}
+
+fn remove_from_set<K: Eq + Hash>(
+	map: &mut HashMap<K, HashSet<PaymentId>>, key: &K, id: &PaymentId,
+) {
+	if let Some(set) = map.get_mut(key) {
+		set.remove(id);
+		if set.is_empty() {
+			map.remove(key);
+		}
+	}
+}
+
+/// A store of [`PaymentDetails`] that only keeps an index and recently used payments in memory.
+pub(crate) struct IndexedPaymentStore<L: Deref>
+where
+	L::Target: LdkLogger,
+{
+	store: DataStore<PaymentDetails, L>,
+	index: Mutex<PaymentIndex>,
+}
+
+impl<L: Deref> IndexedPaymentStore<L>
+where
+	L::Target: LdkLogger,
+{
+	pub(crate) fn new(
+		index: PaymentIndex, primary_namespace: String, secondary_namespace: String,
+		kv_store: Arc<DynStore>, logger: L,
+	) -> Self {
+		let store = DataStore::with_cache_limit(
+			PAYMENT_CACHE_SIZE,
+			primary_namespace,
+			secondary_namespace,
+			kv_store,
+			logger,
+		);
+		Self { store, index: Mutex::new(index) }
+	}
+
+	pub(crate) fn insert(&self, payment: PaymentDetails) -> Result<bool, Error> {
+		let mut locked_index = self.index.lock().unwrap();
+		let updated = self.store.insert(payment.clone())?;
+		locked_index.insert(&payment);
+		Ok(updated)
+	}
+
+	pub(crate) fn insert_or_update(&self, payment: PaymentDetails) -> Result<bool, Error> {
+		let mut locked_index = self.index.lock().unwrap();
+		let id = payment.id;
+		let updated = self.store.insert_or_update(payment)?;
+		if updated {
+			self.reindex(&mut locked_index, &id);
+		}
+		Ok(updated)
+	}
+
+	pub(crate) fn remove(&self, id: &PaymentId) -> Result<(), Error> {
+		let mut locked_index = self.index.lock().unwrap();
+		self.store.remove(id)?;
+		locked_index.remove(id);
+		Ok(())
+	}
+
+	pub(crate) fn get(&self, id: &PaymentId) -> Option<PaymentDetails> {
+		self.store.get(id)
+	}
+
+	/// Returns the id of the on-chain payment whose current transaction has the given txid.
+	pub(crate) fn payment_id_for_txid(&self, txid: &Txid) -> Option<PaymentId> {
+		self.index.lock().unwrap().payment_id_for_txid(txid)
+	}
+
+	pub(crate) fn update(
+		&self, update: &PaymentDetailsUpdate,
+	) -> Result<DataStoreUpdateResult, Error> {
+		let mut locked_index = self.index.lock().unwrap();
+		let res = self.store.update(update)?;
+		if res == DataStoreUpdateResult::Updated {
+			self.reindex(&mut locked_index, &update.id());
+		}
+		Ok(res)
+	}
+
+	/// Returns all payments matching the given filter.
+	///
+	/// Note that this reads all payments from the underlying store. Prefer [`Self::list`] or
+	/// [`Self::list_page`] whenever possible.
+	pub(crate) fn list_filter<F: FnMut(&&PaymentDetails) -> bool>(
+		&self, f: F,
+	) -> Vec<PaymentDetails> {
+		self.store.list_filter(f)
+	}
+
+	/// Returns all payments matching the given query, most recently updated first.
+	pub(crate) fn list(&self, query: &PaymentQuery) -> Vec<PaymentDetails> {
+		let locked_index = self.index.lock().unwrap();
+		let ids = locked_index.query(query, None, locked_index.len());
+		ids.iter().filter_map(|id| self.store.get(id)).collect()
+	}
+
+	/// Returns up to `limit` payments matching the given query, starting after the given cursor.
+	pub(crate) fn list_page(
+		&self, query: &PaymentQuery, cursor: Option<&PaymentCursor>, limit: usize,
+	) -> PaymentPage {
+		let locked_index = self.index.lock().unwrap();
+		// We query for one more payment than requested to learn whether there's a next page.
+		let mut ids = locked_index.query(query, cursor, limit.saturating_add(1));
+		let has_more = ids.len() > limit;
+		ids.truncate(limit);
+
+		let payments = ids.iter().filter_map(|id| self.store.get(id)).collect::<Vec<_>>();
+		let next_cursor = if has_more {
+			payments.last().map(|p| PaymentCursor {
+				latest_update_timestamp: p.latest_update_timestamp,
+				id: p.id,
+			})
+		} else {
+			None
+		};
+		PaymentPage { payments, next_cursor }
+	}
+
+	/// Returns the number of payments per type and status.
+	#[cfg(any(feature = "metrics", test))]
+	pub(crate) fn count_by_type_and_status(&self) -> HashMap<(PaymentType, PaymentStatus), u64> {
+		self.index.lock().unwrap().count_by_type_and_status()
+	}
+
+	/// Moves all payments with one of the given statuses that were last updated before the given
+	/// timestamp to the given archive store.
+	///
+	/// Payments that are still pending or held are never archived, as they may still be updated.
+	/// On-chain payments that were fee-bumped via RBF are never archived either, as we couldn't
+	/// otherwise tell them apart from new payments when syncing the wallet.
+	///
+	/// Returns the number of archived payments.
+	pub(crate) fn archive<AL: Deref>(
+		&self, archive_store: &DataStore<PaymentDetails, AL>, before_timestamp: u64,
+		statuses: &[PaymentStatus],
+	) -> Result<usize, Error>
+	where
+		AL::Target: LdkLogger,
+	{
+		let statuses = statuses
+			.iter()
+			.filter(|s| matches!(s, PaymentStatus::Succeeded | PaymentStatus::Failed))
+			.copied()
+			.collect::<Vec<_>>();
+
+		let mut locked_index = self.index.lock().unwrap();
+		let mut num_archived = 0;
+		for id in locked_index.last_updated_before(before_timestamp, &statuses) {
+			if let Some(payment) = self.store.get(&id) {
+				if matches!(&payment.kind, PaymentKind::Onchain { replaced_txids, .. } if !replaced_txids.is_empty())
+				{
+					continue;
+				}
+				// We first write the archived copy, so we never lose a payment if removing it fails.
+				archive_store.insert(payment)?;
+				self.store.remove(&id)?;
+				num_archived += 1;
+			}
+			locked_index.remove(&id);
+		}
+		Ok(num_archived)
+	}
+
+	fn reindex(&self, locked_index: &mut PaymentIndex, id: &PaymentId) {
+		match self.store.get(id) {
+			Some(payment) => locked_index.insert(&payment),
+			None => locked_index.remove(id),
+		}
+	}
+}
+
+/// Archives all payments that are due per the given retention policy.
+pub(crate) fn archive_expired_payments(
+	payment_store: &PaymentStore, archived_payment_store: &ArchivedPaymentStore,
+	retention: &PaymentRetentionConfig, node_metrics: &RwLock<NodeMetrics>,
+	kv_store: Arc<DynStore>, logger: Arc<Logger>,
+) -> Result<(), Error> {
+	let cur_timestamp =
+		SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or(Duration::from_secs(0)).as_secs();
+
+	let policies = [
+		(retention.succeeded_payment_retention_days, PaymentStatus::Succeeded),
+		(retention.failed_payment_retention_days, PaymentStatus::Failed),
+	];
+	for (retention_days, status) in policies {
+		if let Some(retention_days) = retention_days {
+			let before_timestamp =
+				cur_timestamp.saturating_sub(retention_days as u64 * 24 * 60 * 60);
+			let num_archived =
+				payment_store.archive(archived_payment_store, before_timestamp, &[status])?;
+			if num_archived > 0 {
+				log_info!(
+					logger,
+					"Archived {} {:?} payments last updated before {}.",
+					num_archived,
+					status,
+					before_timestamp
+				);
+			}
+		}
+	}
+
+	let mut locked_node_metrics = node_metrics.write().unwrap();
+	locked_node_metrics.latest_payment_archival_timestamp = Some(cur_timestamp);
+	write_node_metrics(&*locked_node_metrics, kv_store, logger)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::payment::store::ConfirmationStatus;
+
+	use bitcoin::hashes::Hash;
+
+	use lightning::util::test_utils::{TestLogger, TestStore};
+
+	fn payment(
+		idx: u8, timestamp: u64, status: PaymentStatus, direction: PaymentDirection,
+	) -> PaymentDetails {
+		let kind = PaymentKind::Spontaneous { hash: PaymentHash([idx; 32]), preimage: None };
+		let mut payment =
+			PaymentDetails::new(PaymentId([idx; 32]), kind, Some(1000), None, direction, status);
+		payment.latest_update_timestamp = timestamp;
+		payment
+	}
+
+	#[test]
+	fn payments_are_paginated_by_latest_update() {
+		let kv_store: Arc<DynStore> = Arc::new(TestStore::new(false));
+		let logger = Arc::new(TestLogger::new());
+		let store = IndexedPaymentStore::new(
+			PaymentIndex::new(),
+			"payments".to_string(),
+			"".to_string(),
+			kv_store,
+			logger,
+		);
+
+		for idx in 0..10u8 {
+			let status =
+				if idx % 2 == 0 { PaymentStatus::Succeeded } else { PaymentStatus::Pending };
+			let direction =
+				if idx < 5 { PaymentDirection::Inbound } else { PaymentDirection::Outbound };
+			store.insert(payment(idx, 100 + idx as u64 / 2, status, direction)).unwrap();
+		}
+
+		// Check we page through all payments, most recently updated first.
+		let query = PaymentQuery::default();
+		let mut cursor = None;
+		let mut seen = Vec::new();
+		loop {
+			let page = store.list_page(&query, cursor.as_ref(), 3);
+			assert!(page.payments.len() <= 3);
+			seen.extend(page.payments.iter().map(|p| p.id.0[0]));
+			match page.next_cursor {
+				Some(next_cursor) => cursor = Some(next_cursor),
+				None => break,
+			}
+		}
+		assert_eq!(seen, vec![9, 8, 7, 6, 5, 4, 3, 2, 1, 0]);
+
+		// Check lookups by index.
+		let query = PaymentQuery { payment_hash: Some(PaymentHash([4; 32])), ..Default::default() };
+		assert_eq!(store.list(&query), vec![store.get(&PaymentId([4; 32])).unwrap()]);
+
+		let query = PaymentQuery {
+			status: Some(PaymentStatus::Succeeded),
+			direction: Some(PaymentDirection::Outbound),
+			..Default::default()
+		};
+		let page = store.list_page(&query, None, 1);
+		assert_eq!(page.payments.iter().map(|p| p.id.0[0]).collect::<Vec<_>>(), vec![8]);
+		let page = store.list_page(&query, page.next_cursor.as_ref(), 1);
+		assert_eq!(page.payments.iter().map(|p| p.id.0[0]).collect::<Vec<_>>(), vec![6]);
+		assert!(page.next_cursor.is_none());
+
+		// Check updates are reflected in the index.
+		let mut update = PaymentDetailsUpdate::new(PaymentId([1; 32]));
+		update.status = Some(PaymentStatus::Succeeded);
+		assert_eq!(Ok(DataStoreUpdateResult::Updated), store.update(&update));
+		let query = PaymentQuery { status: Some(PaymentStatus::Pending), ..Default::default() };
+		assert_eq!(store.list(&query).len(), 4);
+		let query = PaymentQuery {
+			status: Some(PaymentStatus::Succeeded),
+			payment_type: Some(PaymentType::Spontaneous),
+			..Default::default()
+		};
+		assert_eq!(store.list(&query).len(), 6);
+		assert_eq!(store.list(&query)[0].id, PaymentId([1; 32]));
+
+		store.remove(&PaymentId([1; 32])).unwrap();
+		assert_eq!(store.list(&query).len(), 5);
+		assert_eq!(
+			store
+				.count_by_type_and_status()
+				.get(&(PaymentType::Spontaneous, PaymentStatus::Pending)),
+			Some(&4)
+		);
+	}
+
+	#[test]
+	fn onchain_payments_are_indexed_by_current_txid() {
+		let kv_store: Arc<DynStore> = Arc::new(TestStore::new(false));
+		let logger = Arc::new(TestLogger::new());
+		let store = IndexedPaymentStore::new(
+			PaymentIndex::new(),
+			"payments".to_string(),
+			"".to_string(),
+			kv_store,
+			logger,
+		);
+
+		let original_txid = Txid::from_byte_array([1; 32]);
+		let replacement_txid = Txid::from_byte_array([2; 32]);
+		let id = PaymentId(original_txid.to_byte_array());
+		let onchain_payment = |txid, replaced_txids| {
+			let kind = PaymentKind::Onchain {
+				txid,
+				status: ConfirmationStatus::Unconfirmed,
+				replaced_txids,
+			};
+			PaymentDetails::new(
+				id,
+				kind,
+				Some(1000),
+				None,
+				PaymentDirection::Outbound,
+				PaymentStatus::Pending,
+			)
+		};
+
+		store.insert(onchain_payment(original_txid, Vec::new())).unwrap();
+		store.insert(payment(3, 100, PaymentStatus::Pending, PaymentDirection::Inbound)).unwrap();
+		assert_eq!(store.payment_id_for_txid(&original_txid), Some(id));
+		assert_eq!(store.payment_id_for_txid(&replacement_txid), None);
+
+		// Once replaced, the payment is only found via its replacement transaction.
+		store.insert(onchain_payment(replacement_txid, vec![original_txid])).unwrap();
+		assert_eq!(store.payment_id_for_txid(&original_txid), None);
+		assert_eq!(store.payment_id_for_txid(&replacement_txid), Some(id));
+
+		store.remove(&id).unwrap();
+		assert_eq!(store.payment_id_for_txid(&replacement_txid), None);
+	}
+
+	#[test]
+	fn payments_are_archived() {
+		let kv_store: Arc<DynStore> = Arc::new(TestStore::new(false));
+		let logger = Arc::new(TestLogger::new());
+		let store = IndexedPaymentStore::new(
+			PaymentIndex::new(),
+			"payments".to_string(),
+			"".to_string(),
+			Arc::clone(&kv_store),
+			Arc::clone(&logger),
+		);
+		let archive_store = DataStore::with_cache_limit(
+			ARCHIVED_PAYMENT_CACHE_SIZE,
+			"archived_payments".to_string(),
+			"".to_string(),
+			kv_store,
+			logger,
+		);
+
+		let statuses = [PaymentStatus::Succeeded, PaymentStatus::Failed, PaymentStatus::Pending];
+		for idx in 0..6u8 {
+			let status = statuses[idx as usize % 3];
+			store
+				.insert(payment(idx, 100 + idx as u64, status, PaymentDirection::Inbound))
+				.unwrap();
+		}
+
+		// Check pending payments are never archived.
+		let all_statuses =
+			[PaymentStatus::Succeeded, PaymentStatus::Failed, PaymentStatus::Pending];
+		assert_eq!(Ok(2), store.archive(&archive_store, 103, &all_statuses));
+		assert_eq!(Ok(1), store.archive(&archive_store, 200, &[PaymentStatus::Succeeded]));
+
+		let archived_ids = [0u8, 1, 3].map(|idx| PaymentId([idx; 32]));
+		for id in &archived_ids {
+			assert!(store.get(id).is_none());
+			assert_eq!(archive_store.get(id).unwrap().id, *id);
+		}
+		assert_eq!(archive_store.list_filter(|_| true).len(), 3);
+
+		let remaining = store.list(&PaymentQuery::default());
+		assert_eq!(remaining.iter().map(|p| p.id.0[0]).collect::<Vec<_>>(), vec![5, 4, 2]);
+
+		// Check fee-bumped on-chain payments are never archived.
+		let kind = PaymentKind::Onchain {
+			txid: Txid::from_byte_array([7; 32]),
+			status: ConfirmationStatus::Unconfirmed,
+			replaced_txids: vec![Txid::from_byte_array([6; 32])],
+		};
+		let mut bumped = PaymentDetails::new(
+			PaymentId([6; 32]),
+			kind,
+			Some(1000),
+			None,
+			PaymentDirection::Outbound,
+			PaymentStatus::Succeeded,
+		);
+		bumped.latest_update_timestamp = 100;
+		store.insert(bumped).unwrap();
+		assert_eq!(Ok(0), store.archive(&archive_store, 200, &[PaymentStatus::Succeeded]));
+		assert!(store.get(&PaymentId([6; 32])).is_some());
+		assert!(archive_store.get(&PaymentId([6; 32])).is_none());
+	}
+}
@@ -0,0 +1,516 @@
+// This file is Copyright its original authors, visible in version control history.
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. You may not use this file except in
+// accordance with one or both of these licenses.
+
+//! Holds a payment handler allowing to pay and withdraw via [LNURL].
+//!
+//! [LNURL]: https://github.com/lnurl/luds
+
+use crate::config::{Config, LNURL_REQUEST_TIMEOUT_SECS, LNURL_WITHDRAW_INVOICE_EXPIRY_SECS};
+use crate::error::Error;
+use crate::ffi::maybe_wrap;
+use crate::logger::{log_error, log_info, LdkLogger, Logger};
+use crate::payment::Bolt11Payment;
+
+use lightning::ln::channelmanager::PaymentId;
+
+use lightning_invoice::Bolt11Invoice as LdkBolt11Invoice;
+use lightning_invoice::{Bolt11InvoiceDescription, Bolt11InvoiceDescriptionRef, Description};
+
+use bitcoin::bech32::primitives::decode::CheckedHrpstring;
+use bitcoin::bech32::{Bech32, Checksum};
+use bitcoin::hashes::sha256::Hash as Sha256;
+use bitcoin::hashes::Hash;
+use bitcoin::Network;
+
+use reqwest::Url;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use std::net::IpAddr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+#[cfg(not(feature = "uniffi"))]
+type Bolt11Invoice = LdkBolt11Invoice;
+#[cfg(feature = "uniffi")]
+type Bolt11Invoice = Arc<crate::ffi::Bolt11Invoice>;
+
+const LNURL_HRP: &str = "lnurl";
+const LIGHTNING_URI_PREFIX: &str = "lightning:";
+const PAY_REQUEST_TAG: &str = "payRequest";
+const WITHDRAW_REQUEST_TAG: &str = "withdrawRequest";
+
+/// The bech32 checksum as used by LNURL, which doesn't limit the length of the encoded URL.
+enum LnurlChecksum {}
+
+impl Checksum for LnurlChecksum {
+	type MidstateRepr = <Bech32 as Checksum>::MidstateRepr;
+	const CODE_LENGTH: usize = usize::MAX;
+	const CHECKSUM_LENGTH: usize = <Bech32 as Checksum>::CHECKSUM_LENGTH;
+	const GENERATOR_SH: [Self::MidstateRepr; 5] = <Bech32 as Checksum>::GENERATOR_SH;
+	const TARGET_RESIDUE: Self::MidstateRepr = <Bech32 as Checksum>::TARGET_RESIDUE;
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PayRequest {
+	tag: String,
+	callback: String,
+	min_sendable: u64,
+	max_sendable: u64,
+	metadata: String,
+	#[serde(default)]
+	comment_allowed: u16,
+}
+
+#[derive(Debug, Deserialize)]
+struct PayResponse {
+	pr: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WithdrawRequest {
+	tag: String,
+	callback: String,
+	k1: String,
+	min_withdrawable: u64,
+	max_withdrawable: u64,
+	#[serde(default)]
+	default_description: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct StatusResponse {
+	status: String,
+	reason: Option<String>,
+}
+
+/// A payment handler allowing to pay [LNURL-pay] requests and [Lightning Addresses], and to
+/// withdraw funds from [LNURL-withdraw] services.
+///
+/// Should be retrieved by calling [`Node::lnurl`].
+///
+/// [LNURL-pay]: https://github.com/lnurl/luds/blob/luds/06.md
+/// [Lightning Addresses]: https://github.com/lnurl/luds/blob/luds/16.md
+/// [LNURL-withdraw]: https://github.com/lnurl/luds/blob/luds/03.md
+/// [`Node::lnurl`]: crate::Node::lnurl
+pub struct LnurlPayment {
+	runtime: Arc<RwLock<Option<Arc<tokio::runtime::Runtime>>>>,
+	bolt11_payment: Arc<Bolt11Payment>,
+	config: Arc<Config>,
+	logger: Arc<Logger>,
+}
+
+impl LnurlPayment {
+	pub(crate) fn new(
+		runtime: Arc<RwLock<Option<Arc<tokio::runtime::Runtime>>>>,
+		bolt11_payment: Arc<Bolt11Payment>, config: Arc<Config>, logger: Arc<Logger>,
+	) -> Self {
+		Self { runtime, bolt11_payment, config, logger }
+	}
+
+	/// Pays the given amount to an LNURL-pay service.
+	///
+	/// The `lnurl` may be given as a bech32-encoded LNURL, an `lnurlp://` URL, or a Lightning
+	/// Address of the form `user@domain`. The invoice returned by the service is checked to commit
+	/// to the service's metadata and the requested amount before it is paid via
+	/// [`Bolt11Payment::send`].
+	///
+	/// If the service doesn't accept a `comment` of the given length, it will be omitted.
+	pub fn pay(
+		&self, lnurl: &str, amount_msat: u64, comment: Option<String>,
+	) -> Result<PaymentId, Error> {
+		let rt_lock = self.runtime.read().unwrap();
+		let runtime = rt_lock.as_ref().ok_or(Error::NotRunning)?;
+
+		let url = decode_lnurl(lnurl)?;
+		let pay_request: PayRequest = self.fetch(runtime, url)?;
+		if pay_request.tag != PAY_REQUEST_TAG {
+			log_error!(self.logger, "Expected LNURL-pay request, got tag {}", pay_request.tag);
+			return Err(Error::InvalidLnurl);
+		}
+
+		if amount_msat < pay_request.min_sendable || amount_msat > pay_request.max_sendable {
+			log_error!(
+				self.logger,
+				"Amount of {}msat is not within the LNURL-pay range of {}msat to {}msat",
+				amount_msat,
+				pay_request.min_sendable,
+				pay_request.max_sendable,
+			);
+			return Err(Error::InvalidAmount);
+		}
+
+		let mut callback_url = parse_callback_url(&pay_request.callback)?;
+		callback_url.query_pairs_mut().append_pair("amount", &amount_msat.to_string());
+		if let Some(comment) = comment {
+			if comment.chars().count() <= pay_request.comment_allowed as usize {
+				callback_url.query_pairs_mut().append_pair("comment", &comment);
+			} else {
+				log_info!(
+					self.logger,
+					"Omitting comment as the LNURL-pay service only allows {} characters",
+					pay_request.comment_allowed
+				);
+			}
+		}
+
+		let pay_response: PayResponse = self.fetch(runtime, callback_url)?;
+		let invoice = pay_response.pr.parse::<LdkBolt11Invoice>().map_err(|e| {
+			log_error!(self.logger, "Failed to parse invoice returned by LNURL service: {}", e);
+			Error::InvalidInvoice
+		})?;
+		check_invoice(
+			&invoice,
+			amount_msat,
+			&pay_request.metadata,
+			self.config.network,
+			&self.logger,
+		)?;
+
+		// Release the runtime lock, as it is re-acquired while sending the payment.
+		drop(rt_lock);
+		self.bolt11_payment.send(&maybe_wrap(invoice), None)
+	}
+
+	/// Withdraws funds from an LNURL-withdraw service.
+	///
+	/// The `lnurl` may be given as a bech32-encoded LNURL or an `lnurlw://` URL. If no
+	/// `amount_msat` is given, the maximum amount offered by the service will be withdrawn.
+	///
+	/// Returns the invoice created via [`Bolt11Payment::receive`] and handed to the service. The
+	/// withdrawal completes once the service paid it.
+	pub fn withdraw(&self, lnurl: &str, amount_msat: Option<u64>) -> Result<Bolt11Invoice, Error> {
+		let rt_lock = self.runtime.read().unwrap();
+		let runtime = rt_lock.as_ref().ok_or(Error::NotRunning)?;
+
+		let url = decode_lnurl(lnurl)?;
+		let withdraw_request: WithdrawRequest = self.fetch(runtime, url)?;
+		if withdraw_request.tag != WITHDRAW_REQUEST_TAG {
+			log_error!(
+				self.logger,
+				"Expected LNURL-withdraw request, got tag {}",
+				withdraw_request.tag
+			);
+			return Err(Error::InvalidLnurl);
+		}
+
+		let amount_msat = amount_msat.unwrap_or(withdraw_request.max_withdrawable);
+		if amount_msat == 0
+			|| amount_msat < withdraw_request.min_withdrawable
+			|| amount_msat > withdraw_request.max_withdrawable
+		{
+			log_error!(
+				self.logger,
+				"Amount of {}msat is not within the LNURL-withdraw range of {}msat to {}msat",
+				amount_msat,
+				withdraw_request.min_withdrawable,
+				withdraw_request.max_withdrawable,
+			);
+			return Err(Error::InvalidAmount);
+		}
+
+		let description = Bolt11InvoiceDescription::Direct(
+			Description::new(withdraw_request.default_description)
+				.map_err(|_| Error::InvoiceCreationFailed)?,
+		);
+		let invoice = self.bolt11_payment.receive_inner(
+			Some(amount_msat),
+			&description,
+			LNURL_WITHDRAW_INVOICE_EXPIRY_SECS,
+			None,
+		)?;
+
+		let mut callback_url = parse_callback_url(&withdraw_request.callback)?;
+		callback_url
+			.query_pairs_mut()
+			.append_pair("k1", &withdraw_request.k1)
+			.append_pair("pr", &invoice.to_string());
+
+		let status: StatusResponse = self.fetch(runtime, callback_url)?;
+		if !status.status.eq_ignore_ascii_case("OK") {
+			log_error!(
+				self.logger,
+				"LNURL-withdraw service rejected our invoice: {}",
+				status.reason.unwrap_or_default()
+			);
+			return Err(Error::LnurlRequestFailed);
+		}
+
+		log_info!(self.logger, "Requested LNURL withdrawal of {}msat", amount_msat);
+		Ok(maybe_wrap(invoice))
+	}
+
+	fn fetch<T: DeserializeOwned>(
+		&self, runtime: &tokio::runtime::Runtime, url: Url,
+	) -> Result<T, Error> {
+		let response = tokio::task::block_in_place(|| {
+			runtime.block_on(async {
+				let response = tokio::time::timeout(
+					Duration::from_secs(LNURL_REQUEST_TIMEOUT_SECS),
+					reqwest::get(url),
+				)
+				.await
+				.map_err(|e| {
+					log_error!(self.logger, "LNURL request timed out: {}", e);
+					Error::LnurlRequestFailed
+				})?
+				.map_err(|e| {
+					log_error!(self.logger, "LNURL request failed: {}", e);
+					Error::LnurlRequestFailed
+				})?;
+				response.json::<serde_json::Value>().await.map_err(|e| {
+					log_error!(self.logger, "Failed to read LNURL response: {}", e);
+					Error::LnurlRequestFailed
+				})
+			})
+		})?;
+
+		if let Ok(status) = serde_json::from_value::<StatusResponse>(response.clone()) {
+			if status.status.eq_ignore_ascii_case("ERROR") {
+				log_error!(
+					self.logger,
+					"LNURL service returned an error: {}",
+					status.reason.unwrap_or_default()
+				);
+				return Err(Error::LnurlRequestFailed);
+			}
+		}
+
+		serde_json::from_value(response).map_err(|e| {
+			log_error!(self.logger, "Failed to parse LNURL response: {}", e);
+			Error::LnurlRequestFailed
+		})
+	}
+}
+
+/// Checks that the invoice returned by an LNURL-pay service is for our network and commits to the
+/// requested amount and the service's metadata.
+fn check_invoice(
+	invoice: &LdkBolt11Invoice, amount_msat: u64, metadata: &str, network: Network, logger: &Logger,
+) -> Result<(), Error> {
+	if invoice.network() != network {
+		log_error!(logger, "Invoice returned by LNURL service is for the wrong network");
+		return Err(Error::InvalidNetwork);
+	}
+
+	if invoice.amount_milli_satoshis() != Some(amount_msat) {
+		log_error!(
+			logger,
+			"Invoice returned by LNURL service doesn't match the requested amount of {}msat",
+			amount_msat
+		);
+		return Err(Error::InvalidInvoice);
+	}
+
+	let metadata_hash = Sha256::hash(metadata.as_bytes());
+	match invoice.description() {
+		Bolt11InvoiceDescriptionRef::Hash(hash) if hash.0 == metadata_hash => Ok(()),
+		_ => {
+			log_error!(
+				logger,
+				"Invoice returned by LNURL service doesn't commit to the service's metadata"
+			);
+			Err(Error::InvalidInvoice)
+		},
+	}
+}
+
+/// Decodes the given bech32-encoded LNURL, LUD-17 URL, or Lightning Address to the URL of the
+/// service.
+fn decode_lnurl(lnurl: &str) -> Result<Url, Error> {
+	let lnurl = lnurl.trim();
+	let lnurl = match lnurl.get(..LIGHTNING_URI_PREFIX.len()) {
+		Some(prefix) if prefix.eq_ignore_ascii_case(LIGHTNING_URI_PREFIX) => {
+			&lnurl[LIGHTNING_URI_PREFIX.len()..]
+		},
+		_ => lnurl,
+	};
+	let lower = lnurl.to_ascii_lowercase();
+
+	let url = if let Some(rest) =
+		lower.strip_prefix("lnurlp://").or_else(|| lower.strip_prefix("lnurlw://"))
+	{
+		// Keep the original case of the path and query.
+		service_url(&lnurl[lnurl.len() - rest.len()..])?
+	} else if lower.starts_with(LNURL_HRP) && !lower.contains('@') {
+		let checked =
+			CheckedHrpstring::new::<LnurlChecksum>(&lower).map_err(|_| Error::InvalidLnurl)?;
+		if checked.hrp().to_lowercase() != LNURL_HRP {
+			return Err(Error::InvalidLnurl);
+		}
+		String::from_utf8(checked.byte_iter().collect()).map_err(|_| Error::InvalidLnurl)?
+	} else if let Some((user, domain)) = lnurl.split_once('@') {
+		let valid_user = !user.is_empty()
+			&& user.chars().all(|c| {
+				c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_' || c == '.'
+			});
+		if !valid_user || domain.is_empty() {
+			return Err(Error::InvalidLnurl);
+		}
+		service_url(&format!("{}/.well-known/lnurlp/{}", domain, user))?
+	} else {
+		return Err(Error::InvalidLnurl);
+	};
+
+	parse_callback_url(&url)
+}
+
+/// Returns the URL of the service reachable at the given host and path, using `http` only if the
+/// service may be reached via plain `http`.
+fn service_url(host_and_path: &str) -> Result<String, Error> {
+	let mut url =
+		Url::parse(&format!("https://{}", host_and_path)).map_err(|_| Error::InvalidLnurl)?;
+	if allows_http(&url) {
+		url.set_scheme("http").map_err(|_| Error::InvalidLnurl)?;
+	}
+	Ok(url.into())
+}
+
+/// Parses a service URL, which per LUD-01 needs to be `https` unless it's an onion service. We
+/// also allow `http` for services on loopback addresses, e.g., run for testing.
+fn parse_callback_url(url: &str) -> Result<Url, Error> {
+	let url = Url::parse(url).map_err(|_| Error::InvalidLnurl)?;
+	match url.scheme() {
+		"https" if url.host().is_some() => Ok(url),
+		"http" if allows_http(&url) => Ok(url),
+		_ => Err(Error::InvalidLnurl),
+	}
+}
+
+fn allows_http(url: &Url) -> bool {
+	match url.host_str() {
+		Some(host) => {
+			let ip = host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>();
+			host.ends_with(".onion")
+				|| host == "localhost"
+				|| ip.map_or(false, |ip| ip.is_loopback())
+		},
+		None => false,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use lightning_invoice::{Currency, InvoiceBuilder};
+	use lightning_types::payment::PaymentSecret;
+
+	use bitcoin::secp256k1::{Secp256k1, SecretKey};
+
+	#[test]
+	fn decodes_bech32_lnurl() {
+		// Test vector from LUD-01.
+		let lnurl = "LNURL1DP68GURN8GHJ7UM9WFMXJCM99E3K7MF0V9CXJ0M385EKVCENXC6R2C35XVUKXEFCV5MKVV34X5EKZD3EV56NYD3HXQURZEPEXEJXXEPNXSCRVWFNV9NXZCN9XQ6XYEFHVGCXXCMYXYMNSERXFQ5FNS";
+		let expected =
+			"https://service.com/api?q=3fc3645b439ce8e7f2553a69e5267081d96dcd340693afabe04be7b0ccd178df";
+		assert_eq!(decode_lnurl(lnurl).unwrap().as_str(), expected);
+		assert_eq!(
+			decode_lnurl(&format!("lightning:{}", lnurl.to_lowercase())).unwrap().as_str(),
+			expected
+		);
+
+		// A corrupted checksum is rejected.
+		let corrupted = lnurl.replace("FNS", "FNQ");
+		assert_eq!(decode_lnurl(&corrupted), Err(Error::InvalidLnurl));
+	}
+
+	#[test]
+	fn decodes_lightning_addresses_and_lud17_urls() {
+		assert_eq!(
+			decode_lnurl("satoshi@example.com").unwrap().as_str(),
+			"https://example.com/.well-known/lnurlp/satoshi"
+		);
+		assert_eq!(
+			decode_lnurl("satoshi@example.onion").unwrap().as_str(),
+			"http://example.onion/.well-known/lnurlp/satoshi"
+		);
+		assert_eq!(
+			decode_lnurl("lnurlp://example.com/pay?id=AbC").unwrap().as_str(),
+			"https://example.com/pay?id=AbC"
+		);
+		assert_eq!(
+			decode_lnurl("lnurlw://example.com/withdraw").unwrap().as_str(),
+			"https://example.com/withdraw"
+		);
+		assert_eq!(decode_lnurl("Satoshi@example.com"), Err(Error::InvalidLnurl));
+		assert_eq!(decode_lnurl("@example.com"), Err(Error::InvalidLnurl));
+		assert_eq!(decode_lnurl("https://example.com"), Err(Error::InvalidLnurl));
+		assert_eq!(parse_callback_url("http://example.com/cb"), Err(Error::InvalidLnurl));
+
+		// Plain `http` is only allowed for onion services and loopback addresses.
+		assert_eq!(
+			decode_lnurl("lnurlp://127.0.0.1:8080/pay").unwrap().as_str(),
+			"http://127.0.0.1:8080/pay"
+		);
+		assert_eq!(
+			decode_lnurl("satoshi@localhost").unwrap().as_str(),
+			"http://localhost/.well-known/lnurlp/satoshi"
+		);
+		assert!(parse_callback_url("http://[::1]:8080/cb").is_ok());
+		assert_eq!(parse_callback_url("http://192.168.0.1/cb"), Err(Error::InvalidLnurl));
+	}
+
+	fn invoice(
+		network: Network, amount_msat: u64, description: Bolt11InvoiceDescription,
+	) -> LdkBolt11Invoice {
+		let secret_key = SecretKey::from_slice(&[42; 32]).unwrap();
+		InvoiceBuilder::new(Currency::from(network))
+			.invoice_description(description)
+			.payment_hash(Sha256::hash(&[0; 32]))
+			.payment_secret(PaymentSecret([0; 32]))
+			.current_timestamp()
+			.min_final_cltv_expiry_delta(144)
+			.amount_milli_satoshis(amount_msat)
+			.build_signed(|hash| Secp256k1::new().sign_ecdsa_recoverable(hash, &secret_key))
+			.unwrap()
+	}
+
+	#[test]
+	fn checks_invoices_returned_by_pay_services() {
+		let logger = Logger::new_log_facade();
+		let metadata = "[[\"text/plain\",\"Pay satoshi\"]]";
+		let amount_msat = 100_000;
+		let description_hash = Bolt11InvoiceDescription::Hash(lightning_invoice::Sha256(
+			Sha256::hash(metadata.as_bytes()),
+		));
+
+		let valid = invoice(Network::Regtest, amount_msat, description_hash.clone());
+		assert_eq!(check_invoice(&valid, amount_msat, metadata, Network::Regtest, &logger), Ok(()));
+
+		let wrong_network = invoice(Network::Bitcoin, amount_msat, description_hash.clone());
+		assert_eq!(
+			check_invoice(&wrong_network, amount_msat, metadata, Network::Regtest, &logger),
+			Err(Error::InvalidNetwork)
+		);
+
+		let wrong_amount = invoice(Network::Regtest, amount_msat + 1, description_hash);
+		assert_eq!(
+			check_invoice(&wrong_amount, amount_msat, metadata, Network::Regtest, &logger),
+			Err(Error::InvalidInvoice)
+		);
+
+		let other_hash = Bolt11InvoiceDescription::Hash(lightning_invoice::Sha256(Sha256::hash(
+			b"other metadata",
+		)));
+		let wrong_hash = invoice(Network::Regtest, amount_msat, other_hash);
+		assert_eq!(
+			check_invoice(&wrong_hash, amount_msat, metadata, Network::Regtest, &logger),
+			Err(Error::InvalidInvoice)
+		);
+
+		let direct =
+			Bolt11InvoiceDescription::Direct(Description::new(metadata.to_string()).unwrap());
+		let direct_description = invoice(Network::Regtest, amount_msat, direct);
+		assert_eq!(
+			check_invoice(&direct_description, amount_msat, metadata, Network::Regtest, &logger),
+			Err(Error::InvalidInvoice)
+		);
+	}
+}
@@ -9,17 +9,35 @@
 
 mod bolt11;
 mod bolt12;
+mod estimate;
+pub(crate) mod export;
+pub(crate) mod forwarding;
+mod hold;
+pub(crate) mod index;
+mod lnurl;
 mod onchain;
+mod rebalance;
 mod spontaneous;
 pub(crate) mod store;
 mod unified_qr;
 
 pub use bolt11::Bolt11Payment;
 pub use bolt12::Bolt12Payment;
+pub use estimate::{PaymentFeeEstimate, PaymentPathEstimate};
+pub use export::{PaymentExportFilter, PaymentExportFormat};
+pub use forwarding::{
+	ChannelForwardingStats, ForwardFilter, ForwardedPaymentDetails, ForwardedPaymentId,
+	ForwardingSummary,
+};
+pub(crate) use hold::HeldPaymentWatchdog;
+pub use index::{PaymentCursor, PaymentPage, PaymentQuery};
+pub use lnurl::LnurlPayment;
 pub use onchain::OnchainPayment;
+pub(crate) use rebalance::send_rebalance;
 pub use spontaneous::SpontaneousPayment;
 pub use store::{
 	ConfirmationStatus, LSPFeeLimits, PaymentDetails, PaymentDirection, PaymentKind, PaymentStatus,
+	PaymentType,
 };
 pub use unified_qr::{QrPaymentResult, UnifiedQrPayment};
 
@@ -10,10 +10,10 @@
 use crate::config::Config;
 use crate::error::Error;
 use crate::logger::{log_info, LdkLogger, Logger};
-use crate::types::{ChannelManager, Wallet};
+use crate::types::{ChannelManager, UtxoDetails, Wallet};
 use crate::wallet::OnchainSendAmount;
 
-use bitcoin::{Address, Txid};
+use bitcoin::{Address, OutPoint, Txid};
 
 use std::sync::{Arc, RwLock};
 
@@ -35,6 +35,19 @@ macro_rules! maybe_map_fee_rate_opt {
 	}};
 }
 
+macro_rules! maybe_map_fee_rate {
+	($fee_rate: expr) => {{
+		#[cfg(not(feature = "uniffi"))]
+		{
+			$fee_rate
+		}
+		#[cfg(feature = "uniffi")]
+		{
+			*$fee_rate
+		}
+	}};
+}
+
 /// A payment handler allowing to send and receive on-chain payments.
 ///
 /// Should be retrieved by calling [`Node::onchain_payment`].
@@ -71,9 +84,14 @@ impl OnchainPayment {
 	/// If `fee_rate` is set it will be used on the resulting transaction. Otherwise we'll retrieve
 	/// a reasonable estimate from the configured chain source.
 	///
+	/// If `utxos` is set, only the given UTXOs will be spent by the resulting transaction. Note
+	/// that any of them that are locked via [`lock_utxo`] can't be selected.
+	///
 	/// [`BalanceDetails::total_anchor_channels_reserve_sats`]: crate::BalanceDetails::total_anchor_channels_reserve_sats
+	/// [`lock_utxo`]: Self::lock_utxo
 	pub fn send_to_address(
 		&self, address: &bitcoin::Address, amount_sats: u64, fee_rate: Option<FeeRate>,
+		utxos: Option<Vec<OutPoint>>,
 	) -> Result<Txid, Error> {
 		let rt_lock = self.runtime.read().unwrap();
 		if rt_lock.is_none() {
@@ -85,7 +103,10 @@ impl OnchainPayment {
 		let send_amount =
 			OnchainSendAmount::ExactRetainingReserve { amount_sats, cur_anchor_reserve_sats };
 		let fee_rate_opt = maybe_map_fee_rate_opt!(fee_rate);
-		self.wallet.send_to_address(address, send_amount, fee_rate_opt)
+		if let Some(utxos) = &utxos {
+			self.wallet.check_utxo_selection(utxos)?;
+		}
+		self.wallet.send_to_address(address, send_amount, fee_rate_opt, utxos)
 	}
 
 	/// Send an on-chain payment to the given address, draining the available funds.
@@ -120,6 +141,83 @@ impl OnchainPayment {
 		};
 
 		let fee_rate_opt = maybe_map_fee_rate_opt!(fee_rate);
-		self.wallet.send_to_address(address, send_amount, fee_rate_opt)
+		self.wallet.send_to_address(address, send_amount, fee_rate_opt, None)
+	}
+
+	/// Returns the list of unspent outputs currently held by the on-chain wallet.
+	pub fn list_utxos(&self) -> Vec<UtxoDetails> {
+		self.wallet.list_utxos()
+	}
+
+	/// Locks the given UTXO, i.e., excludes it from coin selection when sending on-chain payments,
+	/// bumping fees, or funding channels.
+	///
+	/// The set of locked UTXOs is persisted across restarts. Note that locked UTXOs might still be
+	/// spent when bumping the fees of Anchor channel transactions, as these are required to
+	/// protect our funds.
+	pub fn lock_utxo(&self, outpoint: OutPoint) -> Result<(), Error> {
+		self.wallet.lock_utxo(outpoint)
+	}
+
+	/// Unlocks the given UTXO that was previously locked via [`lock_utxo`].
+	///
+	/// [`lock_utxo`]: Self::lock_utxo
+	pub fn unlock_utxo(&self, outpoint: OutPoint) -> Result<(), Error> {
+		self.wallet.unlock_utxo(outpoint)
+	}
+
+	/// Bumps the fee of a previously sent, still unconfirmed on-chain payment via
+	/// Replace-By-Fee (RBF).
+	///
+	/// This will create and broadcast a replacement transaction for the transaction with the given
+	/// `txid`, paying the given `fee_rate`. The additional fee is taken from our change output
+	/// and will respect any on-chain reserve we need to keep, i.e., won't allow to cut into
+	/// [`BalanceDetails::total_anchor_channels_reserve_sats`].
+	///
+	/// The corresponding payment will continue to be tracked under its original [`PaymentId`],
+	/// with its [`PaymentKind::Onchain`] record being updated to the replacement's `txid`. Any
+	/// previously used transaction identifiers will be retained as `replaced_txids`.
+	///
+	/// Returns the `txid` of the replacement transaction.
+	///
+	/// [`BalanceDetails::total_anchor_channels_reserve_sats`]: crate::BalanceDetails::total_anchor_channels_reserve_sats
+	/// [`PaymentId`]: lightning::ln::channelmanager::PaymentId
+	/// [`PaymentKind::Onchain`]: crate::payment::PaymentKind::Onchain
+	pub fn bump_fee_rbf(&self, txid: Txid, fee_rate: FeeRate) -> Result<Txid, Error> {
+		let rt_lock = self.runtime.read().unwrap();
+		if rt_lock.is_none() {
+			return Err(Error::NotRunning);
+		}
+
+		let cur_anchor_reserve_sats =
+			crate::total_anchor_channels_reserve_sats(&self.channel_manager, &self.config);
+		let fee_rate = maybe_map_fee_rate!(fee_rate);
+		self.wallet.bump_fee_rbf(txid, fee_rate, cur_anchor_reserve_sats)
+	}
+
+	/// Bumps the fee of an unconfirmed on-chain transaction via Child-Pays-For-Parent (CPFP).
+	///
+	/// This will create and broadcast a child transaction spending our outputs of the transaction
+	/// with the given `txid` back to our on-chain wallet. The child's fee is chosen so that the
+	/// package of both transactions reaches the given `fee_rate`. As this only requires the
+	/// transaction to have an output to our wallet, it may also be used to speed up the
+	/// confirmation of inbound payments.
+	///
+	/// This will respect any on-chain reserve we need to keep, i.e., won't allow to cut into
+	/// [`BalanceDetails::total_anchor_channels_reserve_sats`].
+	///
+	/// Returns the `txid` of the child transaction.
+	///
+	/// [`BalanceDetails::total_anchor_channels_reserve_sats`]: crate::BalanceDetails::total_anchor_channels_reserve_sats
+	pub fn bump_fee_cpfp(&self, txid: Txid, fee_rate: FeeRate) -> Result<Txid, Error> {
+		let rt_lock = self.runtime.read().unwrap();
+		if rt_lock.is_none() {
+			return Err(Error::NotRunning);
+		}
+
+		let cur_anchor_reserve_sats =
+			crate::total_anchor_channels_reserve_sats(&self.channel_manager, &self.config);
+		let fee_rate = maybe_map_fee_rate!(fee_rate);
+		self.wallet.bump_fee_cpfp(txid, fee_rate, cur_anchor_reserve_sats)
 	}
 }
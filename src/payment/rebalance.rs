@@ -0,0 +1,181 @@
+// This file is Copyright its original authors, visible in version control history.
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. You may not use this file except in
+// accordance with one or both of these licenses.
+
+//! Holds the logic for circular payments rebalancing liquidity between our own channels.
+
+use crate::error::Error;
+use crate::logger::{log_error, log_info, LdkLogger, Logger};
+use crate::payment::store::{PaymentDetails, PaymentDirection, PaymentKind, PaymentStatus};
+use crate::types::{ChannelManager, KeysManager, PaymentStore, Router};
+
+use lightning::ln::channelmanager::{
+	PaymentId, RecipientOnionFields, RetryableSendFailure, MIN_FINAL_CLTV_EXPIRY_DELTA,
+};
+use lightning::ln::types::ChannelId;
+use lightning::routing::router::{PaymentParameters, RouteHop, RouteParameters, Router as _};
+use lightning::sign::EntropySource;
+
+use lightning_types::payment::{PaymentHash, PaymentPreimage};
+
+use std::sync::Arc;
+
+// The time, in seconds, we accept the returning payment for after sending it.
+const REBALANCE_PAYMENT_EXPIRY_SECS: u32 = 3600;
+
+/// Sends a payment of `amount_msat` leaving via the channel with `from_channel_id` and returning
+/// via the channel with `to_channel_id`, paying at most `max_fee_msat` in routing fees.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn send_rebalance(
+	from_channel_id: ChannelId, to_channel_id: ChannelId, amount_msat: u64, max_fee_msat: u64,
+	channel_manager: &ChannelManager, router: &Router, keys_manager: &KeysManager,
+	payment_store: &PaymentStore, logger: &Arc<Logger>,
+) -> Result<PaymentId, Error> {
+	if from_channel_id == to_channel_id {
+		log_error!(logger, "Failed to rebalance: source and destination channel are identical.");
+		return Err(Error::InvalidChannelId);
+	}
+
+	let usable_channels = channel_manager.list_usable_channels();
+	let find_channel = |channel_id: &ChannelId| {
+		usable_channels.iter().find(|c| c.channel_id == *channel_id).ok_or_else(|| {
+			log_error!(logger, "Failed to rebalance: channel {} is not usable.", channel_id);
+			Error::InvalidChannelId
+		})
+	};
+	let from_channel = find_channel(&from_channel_id)?;
+	let to_channel = find_channel(&to_channel_id)?;
+
+	let inbound_scid = to_channel.get_inbound_payment_scid().ok_or(Error::InvalidChannelId)?;
+
+	// The fee our counterparty charges for forwarding the payment back to us via `to_channel`.
+	let last_hop_node_id = to_channel.counterparty.node_id;
+	let last_hop_info = to_channel.counterparty.forwarding_info.clone().ok_or_else(|| {
+		log_error!(
+			logger,
+			"Failed to rebalance: forwarding info of channel {} is unknown.",
+			to_channel_id
+		);
+		Error::RouteNotFound
+	})?;
+	let last_hop_fee_msat = last_hop_info.fee_base_msat as u64
+		+ (amount_msat as u128 * last_hop_info.fee_proportional_millionths as u128 / 1_000_000)
+			as u64;
+	if last_hop_fee_msat > max_fee_msat {
+		log_error!(
+			logger,
+			"Failed to rebalance: the fee of {}msat charged by {} exceeds the limit of {}msat.",
+			last_hop_fee_msat,
+			last_hop_node_id,
+			max_fee_msat
+		);
+		return Err(Error::RouteNotFound);
+	}
+
+	// Find a route to our counterparty on `to_channel` that leaves via `from_channel`, which we
+	// then extend by the final hop back to us.
+	let mut route_params = RouteParameters::from_payment_params_and_value(
+		PaymentParameters::from_node_id(last_hop_node_id, last_hop_info.cltv_expiry_delta as u32),
+		amount_msat + last_hop_fee_msat,
+	);
+	route_params.max_total_routing_fee_msat = Some(max_fee_msat - last_hop_fee_msat);
+	route_params.payment_params.max_path_count = 1;
+
+	let payer = channel_manager.get_our_node_id();
+	let inflight_htlcs = channel_manager.compute_inflight_htlcs();
+	let mut route = router
+		.find_route(&payer, &route_params, Some(&[from_channel]), inflight_htlcs)
+		.map_err(|e| {
+			log_error!(logger, "Failed to find route for rebalance: {}", e.err);
+			Error::RouteNotFound
+		})?;
+	route.route_params = None;
+
+	let path = match route.paths.as_mut_slice() {
+		[path] => path,
+		_ => {
+			log_error!(logger, "Failed to find single-path route for rebalance.");
+			return Err(Error::RouteNotFound);
+		},
+	};
+	if let Some(last_hop) = path.hops.last_mut() {
+		last_hop.fee_msat = last_hop_fee_msat;
+	}
+	path.hops.push(RouteHop {
+		pubkey: payer,
+		node_features: channel_manager.node_features(),
+		short_channel_id: inbound_scid,
+		channel_features: channel_manager.channel_features(),
+		fee_msat: amount_msat,
+		cltv_expiry_delta: MIN_FINAL_CLTV_EXPIRY_DELTA as u32,
+		maybe_announced_channel: to_channel.is_announced,
+	});
+
+	let payment_preimage = PaymentPreimage(keys_manager.get_secure_random_bytes());
+	let payment_hash = PaymentHash::from(payment_preimage);
+	let payment_id = PaymentId(payment_hash.0);
+
+	let payment_secret = channel_manager
+		.create_inbound_payment_for_hash(
+			payment_hash,
+			Some(amount_msat),
+			REBALANCE_PAYMENT_EXPIRY_SECS,
+			None,
+		)
+		.map_err(|()| {
+			log_error!(logger, "Failed to register rebalance payment.");
+			Error::PaymentSendingFailed
+		})?;
+
+	let kind = PaymentKind::Rebalance {
+		hash: payment_hash,
+		preimage: Some(payment_preimage),
+		secret: Some(payment_secret),
+		from_channel_id,
+		to_channel_id,
+	};
+	let mut payment = PaymentDetails::new(
+		payment_id,
+		kind,
+		Some(amount_msat),
+		None,
+		PaymentDirection::Outbound,
+		PaymentStatus::Pending,
+	);
+	// We need to persist the payment before sending, as we'll need its preimage to claim it once
+	// it returns to us.
+	payment_store.insert(payment.clone())?;
+
+	match channel_manager.send_payment_with_route(
+		route,
+		payment_hash,
+		RecipientOnionFields::secret_only(payment_secret),
+		payment_id,
+	) {
+		Ok(()) => {
+			log_info!(
+				logger,
+				"Initiated rebalancing {}msat from channel {} to channel {}.",
+				amount_msat,
+				from_channel_id,
+				to_channel_id
+			);
+			Ok(payment_id)
+		},
+		Err(e) => {
+			log_error!(logger, "Failed to send rebalance payment: {:?}", e);
+
+			match e {
+				RetryableSendFailure::DuplicatePayment => Err(Error::DuplicatePayment),
+				_ => {
+					payment.status = PaymentStatus::Failed;
+					payment_store.insert_or_update(payment)?;
+					Err(Error::PaymentSendingFailed)
+				},
+			}
+		},
+	}
+}
@@ -10,9 +10,10 @@
 use crate::config::{Config, LDK_PAYMENT_RETRY_TIMEOUT};
 use crate::error::Error;
 use crate::logger::{log_error, log_info, LdkLogger, Logger};
+use crate::payment::estimate::{estimate_payment_fee, PaymentFeeEstimate};
 use crate::payment::store::{PaymentDetails, PaymentDirection, PaymentKind, PaymentStatus};
 use crate::payment::SendingParameters;
-use crate::types::{ChannelManager, CustomTlvRecord, KeysManager, PaymentStore};
+use crate::types::{ChannelManager, CustomTlvRecord, KeysManager, PaymentStore, Router, Scorer};
 
 use lightning::ln::channelmanager::{PaymentId, RecipientOnionFields, Retry, RetryableSendFailure};
 use lightning::routing::router::{PaymentParameters, RouteParameters};
@@ -22,7 +23,7 @@ use lightning_types::payment::{PaymentHash, PaymentPreimage};
 
 use bitcoin::secp256k1::PublicKey;
 
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 
 // The default `final_cltv_expiry_delta` we apply when not set.
 const LDK_DEFAULT_FINAL_CLTV_EXPIRY_DELTA: u32 = 144;
@@ -35,6 +36,8 @@ const LDK_DEFAULT_FINAL_CLTV_EXPIRY_DELTA: u32 = 144;
 pub struct SpontaneousPayment {
 	runtime: Arc<RwLock<Option<Arc<tokio::runtime::Runtime>>>>,
 	channel_manager: Arc<ChannelManager>,
+	router: Arc<Router>,
+	scorer: Arc<Mutex<Scorer>>,
 	keys_manager: Arc<KeysManager>,
 	payment_store: Arc<PaymentStore>,
 	config: Arc<Config>,
@@ -44,10 +47,20 @@ pub struct SpontaneousPayment {
 impl SpontaneousPayment {
 	pub(crate) fn new(
 		runtime: Arc<RwLock<Option<Arc<tokio::runtime::Runtime>>>>,
-		channel_manager: Arc<ChannelManager>, keys_manager: Arc<KeysManager>,
-		payment_store: Arc<PaymentStore>, config: Arc<Config>, logger: Arc<Logger>,
+		channel_manager: Arc<ChannelManager>, router: Arc<Router>, scorer: Arc<Mutex<Scorer>>,
+		keys_manager: Arc<KeysManager>, payment_store: Arc<PaymentStore>, config: Arc<Config>,
+		logger: Arc<Logger>,
 	) -> Self {
-		Self { runtime, channel_manager, keys_manager, payment_store, config, logger }
+		Self {
+			runtime,
+			channel_manager,
+			router,
+			scorer,
+			keys_manager,
+			payment_store,
+			config,
+			logger,
+		}
 	}
 
 	/// Send a spontaneous aka. "keysend", payment.
@@ -201,4 +214,51 @@ impl SpontaneousPayment {
 
 		Ok(())
 	}
+
+	/// Estimates the fees of sending a spontaneous payment of the given amount to the given
+	/// `node_id`, without sending anything.
+	///
+	/// This runs the same route finding that [`send`] would, based on our current channels and
+	/// view of the network. Note that the route actually taken when sending may differ.
+	///
+	/// If `sending_parameters` are provided they will override the default as well as the
+	/// node-wide parameters configured via [`Config::sending_parameters`] on a per-field basis.
+	///
+	/// [`send`]: Self::send
+	pub fn estimate_fee(
+		&self, amount_msat: u64, node_id: PublicKey, sending_parameters: Option<SendingParameters>,
+	) -> Result<PaymentFeeEstimate, Error> {
+		let rt_lock = self.runtime.read().unwrap();
+		if rt_lock.is_none() {
+			return Err(Error::NotRunning);
+		}
+
+		let mut route_params = RouteParameters::from_payment_params_and_value(
+			PaymentParameters::from_node_id(node_id, LDK_DEFAULT_FINAL_CLTV_EXPIRY_DELTA),
+			amount_msat,
+		);
+
+		let override_params =
+			sending_parameters.as_ref().or(self.config.sending_parameters.as_ref());
+		if let Some(override_params) = override_params {
+			override_params
+				.max_total_routing_fee_msat
+				.map(|f| route_params.max_total_routing_fee_msat = f.into());
+			override_params
+				.max_total_cltv_expiry_delta
+				.map(|d| route_params.payment_params.max_total_cltv_expiry_delta = d);
+			override_params.max_path_count.map(|p| route_params.payment_params.max_path_count = p);
+			override_params
+				.max_channel_saturation_power_of_half
+				.map(|s| route_params.payment_params.max_channel_saturation_power_of_half = s);
+		};
+
+		estimate_payment_fee(
+			&route_params,
+			&self.channel_manager,
+			&self.router,
+			&self.scorer,
+			&self.logger,
+		)
+	}
 }
@@ -7,6 +7,7 @@
 
 use lightning::ln::channelmanager::PaymentId;
 use lightning::ln::msgs::DecodeError;
+use lightning::ln::types::ChannelId;
 use lightning::offers::offer::OfferId;
 use lightning::util::ser::{Readable, Writeable};
 use lightning::util::string::UntrustedString;
@@ -140,7 +141,7 @@ impl Readable for PaymentDetails {
 						lsp_fee_limits,
 					}
 				} else {
-					PaymentKind::Bolt11 { hash, preimage, secret }
+					PaymentKind::Bolt11 { hash, preimage, secret, claim_deadline: None }
 				}
 			} else {
 				PaymentKind::Spontaneous { hash, preimage }
@@ -238,6 +239,9 @@ impl StorableObject for PaymentDetails {
 				PaymentKind::Spontaneous { ref mut preimage, .. } => {
 					update_if_necessary!(*preimage, preimage_opt)
 				},
+				PaymentKind::Rebalance { ref mut preimage, .. } => {
+					update_if_necessary!(*preimage, preimage_opt)
+				},
 				_ => {},
 			}
 		}
@@ -256,10 +260,25 @@ impl StorableObject for PaymentDetails {
 				PaymentKind::Bolt12Refund { ref mut secret, .. } => {
 					update_if_necessary!(*secret, secret_opt)
 				},
+				PaymentKind::Rebalance { ref mut secret, .. } => {
+					update_if_necessary!(*secret, secret_opt)
+				},
 				_ => {},
 			}
 		}
 
+		if let Some(claim_deadline_opt) = update.claim_deadline {
+			match self.kind {
+				PaymentKind::Bolt11 { ref mut claim_deadline, .. } => {
+					update_if_necessary!(*claim_deadline, claim_deadline_opt)
+				},
+				_ => debug_assert!(
+					false,
+					"We should only ever override claim_deadline for BOLT 11 payments"
+				),
+			}
+		}
+
 		if let Some(amount_opt) = update.amount_msat {
 			update_if_necessary!(self.amount_msat, amount_opt);
 		}
@@ -309,7 +328,7 @@ impl StorableObject for PaymentDetails {
 }
 
 /// Represents the direction of a payment.
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum PaymentDirection {
 	/// The payment is inbound.
 	Inbound,
@@ -323,7 +342,7 @@ impl_writeable_tlv_based_enum!(PaymentDirection,
 );
 
 /// Represents the current status of a payment.
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum PaymentStatus {
 	/// The payment is still pending.
 	Pending,
@@ -331,12 +350,21 @@ pub enum PaymentStatus {
 	Succeeded,
 	/// The payment failed.
 	Failed,
+	/// The payment was received but is held until it is manually claimed or failed back.
+	///
+	/// This only applies to inbound payments registered via [`Bolt11Payment::receive_for_hash`]
+	/// or [`Bolt11Payment::receive_variable_amount_for_hash`].
+	///
+	/// [`Bolt11Payment::receive_for_hash`]: crate::payment::Bolt11Payment::receive_for_hash
+	/// [`Bolt11Payment::receive_variable_amount_for_hash`]: crate::payment::Bolt11Payment::receive_variable_amount_for_hash
+	Held,
 }
 
 impl_writeable_tlv_based_enum!(PaymentStatus,
 	(0, Pending) => {},
 	(2, Succeeded) => {},
-	(4, Failed) => {}
+	(4, Failed) => {},
+	(6, Held) => {}
 );
 
 /// Represents the kind of a payment.
@@ -353,6 +381,11 @@ pub enum PaymentKind {
 		txid: Txid,
 		/// The confirmation status of this payment.
 		status: ConfirmationStatus,
+		/// The identifiers of any previous transactions of this payment that were replaced via
+		/// Replace-By-Fee (RBF), in the order they were replaced.
+		///
+		/// Will be empty for payments that were never fee-bumped via RBF.
+		replaced_txids: Vec<Txid>,
 	},
 	/// A [BOLT 11] payment.
 	///
@@ -364,6 +397,11 @@ pub enum PaymentKind {
 		preimage: Option<PaymentPreimage>,
 		/// The secret used by the payment.
 		secret: Option<PaymentSecret>,
+		/// The block height at which the HTLCs of a held payment will be failed back and will no
+		/// longer be eligible for claiming.
+		///
+		/// Will only be `Some` for inbound payments that were [`PaymentStatus::Held`] at some point.
+		claim_deadline: Option<u32>,
 	},
 	/// A [BOLT 11] payment intended to open an [bLIP-52 / LSPS 2] just-in-time channel.
 	///
@@ -445,15 +483,33 @@ pub enum PaymentKind {
 		/// The pre-image used by the payment.
 		preimage: Option<PaymentPreimage>,
 	},
+	/// A circular payment to ourselves shifting liquidity between two of our channels, as sent
+	/// via [`Node::rebalance`].
+	///
+	/// [`Node::rebalance`]: crate::Node::rebalance
+	Rebalance {
+		/// The payment hash, i.e., the hash of the `preimage`.
+		hash: PaymentHash,
+		/// The pre-image used by the payment.
+		preimage: Option<PaymentPreimage>,
+		/// The secret used by the payment.
+		secret: Option<PaymentSecret>,
+		/// The channel through which the payment leaves, i.e., whose outbound liquidity decreases.
+		from_channel_id: ChannelId,
+		/// The channel through which the payment returns, i.e., whose outbound liquidity increases.
+		to_channel_id: ChannelId,
+	},
 }
 
 impl_writeable_tlv_based_enum!(PaymentKind,
 	(0, Onchain) => {
 		(0, txid, required),
+		(1, replaced_txids, optional_vec),
 		(2, status, required),
 	},
 	(2, Bolt11) => {
 		(0, hash, required),
+		(1, claim_deadline, option),
 		(2, preimage, option),
 		(4, secret, option),
 	},
@@ -482,9 +538,63 @@ impl_writeable_tlv_based_enum!(PaymentKind,
 		(2, preimage, option),
 		(3, quantity, option),
 		(4, secret, option),
+	},
+	(12, Rebalance) => {
+		(0, hash, required),
+		(2, preimage, option),
+		(4, secret, option),
+		(6, from_channel_id, required),
+		(8, to_channel_id, required),
 	}
 );
 
+impl PaymentKind {
+	pub(crate) fn hash(&self) -> Option<PaymentHash> {
+		match self {
+			PaymentKind::Onchain { .. } => None,
+			PaymentKind::Bolt11 { hash, .. } => Some(*hash),
+			PaymentKind::Bolt11Jit { hash, .. } => Some(*hash),
+			PaymentKind::Bolt12Offer { hash, .. } => *hash,
+			PaymentKind::Bolt12Refund { hash, .. } => *hash,
+			PaymentKind::Spontaneous { hash, .. } => Some(*hash),
+			PaymentKind::Rebalance { hash, .. } => Some(*hash),
+		}
+	}
+}
+
+/// Represents the type of a payment, i.e., the variant of its [`PaymentKind`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PaymentType {
+	/// An on-chain payment, see [`PaymentKind::Onchain`].
+	Onchain,
+	/// A BOLT 11 payment, see [`PaymentKind::Bolt11`].
+	Bolt11,
+	/// A BOLT 11 payment opening a just-in-time channel, see [`PaymentKind::Bolt11Jit`].
+	Bolt11Jit,
+	/// A BOLT 12 'offer' payment, see [`PaymentKind::Bolt12Offer`].
+	Bolt12Offer,
+	/// A BOLT 12 'refund' payment, see [`PaymentKind::Bolt12Refund`].
+	Bolt12Refund,
+	/// A spontaneous ("keysend") payment, see [`PaymentKind::Spontaneous`].
+	Spontaneous,
+	/// A circular payment to ourselves, see [`PaymentKind::Rebalance`].
+	Rebalance,
+}
+
+impl From<&PaymentKind> for PaymentType {
+	fn from(kind: &PaymentKind) -> Self {
+		match kind {
+			PaymentKind::Onchain { .. } => PaymentType::Onchain,
+			PaymentKind::Bolt11 { .. } => PaymentType::Bolt11,
+			PaymentKind::Bolt11Jit { .. } => PaymentType::Bolt11Jit,
+			PaymentKind::Bolt12Offer { .. } => PaymentType::Bolt12Offer,
+			PaymentKind::Bolt12Refund { .. } => PaymentType::Bolt12Refund,
+			PaymentKind::Spontaneous { .. } => PaymentType::Spontaneous,
+			PaymentKind::Rebalance { .. } => PaymentType::Rebalance,
+		}
+	}
+}
+
 /// Represents the confirmation status of a transaction.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum ConfirmationStatus {
@@ -539,6 +649,7 @@ pub(crate) struct PaymentDetailsUpdate {
 	pub amount_msat: Option<Option<u64>>,
 	pub fee_paid_msat: Option<Option<u64>>,
 	pub counterparty_skimmed_fee_msat: Option<Option<u64>>,
+	pub claim_deadline: Option<Option<u32>>,
 	pub direction: Option<PaymentDirection>,
 	pub status: Option<PaymentStatus>,
 	pub confirmation_status: Option<ConfirmationStatus>,
@@ -554,6 +665,7 @@ impl PaymentDetailsUpdate {
 			amount_msat: None,
 			fee_paid_msat: None,
 			counterparty_skimmed_fee_msat: None,
+			claim_deadline: None,
 			direction: None,
 			status: None,
 			confirmation_status: None,
@@ -569,6 +681,7 @@ impl From<&PaymentDetails> for PaymentDetailsUpdate {
 			PaymentKind::Bolt12Offer { hash, preimage, secret, .. } => (hash, preimage, secret),
 			PaymentKind::Bolt12Refund { hash, preimage, secret, .. } => (hash, preimage, secret),
 			PaymentKind::Spontaneous { hash, preimage, .. } => (Some(hash), preimage, None),
+			PaymentKind::Rebalance { hash, preimage, secret, .. } => (Some(hash), preimage, secret),
 			_ => (None, None, None),
 		};
 
@@ -584,6 +697,11 @@ impl From<&PaymentDetails> for PaymentDetailsUpdate {
 			_ => None,
 		};
 
+		let claim_deadline = match value.kind {
+			PaymentKind::Bolt11 { claim_deadline, .. } => Some(claim_deadline),
+			_ => None,
+		};
+
 		Self {
 			id: value.id,
 			hash: Some(hash),
@@ -592,6 +710,7 @@ impl From<&PaymentDetails> for PaymentDetailsUpdate {
 			amount_msat: Some(value.amount_msat),
 			fee_paid_msat: Some(value.fee_paid_msat),
 			counterparty_skimmed_fee_msat,
+			claim_deadline,
 			direction: Some(value.direction),
 			status: Some(value.status),
 			confirmation_status,
@@ -670,10 +789,11 @@ mod tests {
 			);
 
 			match bolt11_decoded.kind {
-				PaymentKind::Bolt11 { hash: h, preimage: p, secret: s } => {
+				PaymentKind::Bolt11 { hash: h, preimage: p, secret: s, claim_deadline: c } => {
 					assert_eq!(hash, h);
 					assert_eq!(preimage, p);
 					assert_eq!(secret, s);
+					assert_eq!(None, c);
 				},
 				_ => {
 					panic!("Unexpected kind!");
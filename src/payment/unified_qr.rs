@@ -13,6 +13,7 @@
 //! [BOLT 12]: https://github.com/lightning/bolts/blob/master/12-offer-encoding.md
 use crate::error::Error;
 use crate::ffi::maybe_wrap;
+use crate::hrn::{resolve_hrn, HrnResolver};
 use crate::logger::{log_error, LdkLogger, Logger};
 use crate::payment::{Bolt11Payment, Bolt12Payment, OnchainPayment};
 use crate::Config;
@@ -50,6 +51,7 @@ pub struct UnifiedQrPayment {
 	onchain_payment: Arc<OnchainPayment>,
 	bolt11_invoice: Arc<Bolt11Payment>,
 	bolt12_payment: Arc<Bolt12Payment>,
+	hrn_resolver: Arc<dyn HrnResolver>,
 	config: Arc<Config>,
 	logger: Arc<Logger>,
 }
@@ -57,9 +59,10 @@ pub struct UnifiedQrPayment {
 impl UnifiedQrPayment {
 	pub(crate) fn new(
 		onchain_payment: Arc<OnchainPayment>, bolt11_invoice: Arc<Bolt11Payment>,
-		bolt12_payment: Arc<Bolt12Payment>, config: Arc<Config>, logger: Arc<Logger>,
+		bolt12_payment: Arc<Bolt12Payment>, hrn_resolver: Arc<dyn HrnResolver>,
+		config: Arc<Config>, logger: Arc<Logger>,
 	) -> Self {
-		Self { onchain_payment, bolt11_invoice, bolt12_payment, config, logger }
+		Self { onchain_payment, bolt11_invoice, bolt12_payment, hrn_resolver, config, logger }
 	}
 
 	/// Generates a URI with an on-chain address, [BOLT 11] invoice and [BOLT 12] offer.
@@ -140,46 +143,96 @@ impl UnifiedQrPayment {
 	///
 	/// [BIP 21]: https://github.com/bitcoin/bips/blob/master/bip-0021.mediawiki
 	pub fn send(&self, uri_str: &str) -> Result<QrPaymentResult, Error> {
+		self.send_inner(uri_str, None)
+	}
+
+	/// Sends a payment to the given [BIP 353] human-readable name (e.g., `₿user@domain`).
+	///
+	/// The name is resolved to a [BIP 21] URI via the configured [`HrnResolver`] which is then paid
+	/// as described in [`Self::send`].
+	///
+	/// As the payment instructions published for a human-readable name commonly don't specify an
+	/// amount, it may be given via `amount_msat`. If set, it takes precedence over the amount of
+	/// the URI and is used to pay the offer, invoice, or on-chain address, respectively. The
+	/// payment will fail with [`Error::InvalidAmount`] if neither the URI nor `amount_msat` specify
+	/// an amount.
+	///
+	/// [BIP 353]: https://github.com/bitcoin/bips/blob/master/bip-0353.mediawiki
+	/// [BIP 21]: https://github.com/bitcoin/bips/blob/master/bip-0021.mediawiki
+	pub fn send_to_hrn(
+		&self, hrn: &str, amount_msat: Option<u64>,
+	) -> Result<QrPaymentResult, Error> {
+		let uri_str = resolve_hrn(&*self.hrn_resolver, hrn, &self.logger)?;
+		self.send_inner(&uri_str, amount_msat)
+	}
+
+	fn send_inner(
+		&self, uri_str: &str, amount_msat: Option<u64>,
+	) -> Result<QrPaymentResult, Error> {
 		let uri: bip21::Uri<NetworkUnchecked, Extras> =
 			uri_str.parse().map_err(|_| Error::InvalidUri)?;
 
 		let uri_network_checked =
 			uri.clone().require_network(self.config.network).map_err(|_| Error::InvalidNetwork)?;
 
+		let uri_amount_msat = uri_network_checked.amount.map(|amount| amount.to_sat() * 1000);
+
 		if let Some(offer) = uri_network_checked.extras.bolt12_offer {
+			let offer_amount_msat =
+				amount_msat.or(if offer.amount().is_none() { uri_amount_msat } else { None });
 			let offer = maybe_wrap(offer);
-			match self.bolt12_payment.send(&offer, None, None) {
+			let res = match offer_amount_msat {
+				Some(amount_msat) => {
+					self.bolt12_payment.send_using_amount(&offer, amount_msat, None, None)
+				},
+				None => self.bolt12_payment.send(&offer, None, None),
+			};
+			match res {
 				Ok(payment_id) => return Ok(QrPaymentResult::Bolt12 { payment_id }),
 				Err(e) => log_error!(self.logger, "Failed to send BOLT12 offer: {:?}. This is part of a unified QR code payment. Falling back to the BOLT11 invoice.", e),
 			}
 		}
 
 		if let Some(invoice) = uri_network_checked.extras.bolt11_invoice {
+			let invoice_amount_msat = amount_msat
+				.or(if invoice.amount_milli_satoshis().is_none() { uri_amount_msat } else { None });
 			let invoice = maybe_wrap(invoice);
-			match self.bolt11_invoice.send(&invoice, None) {
+			let res = match invoice_amount_msat {
+				Some(amount_msat) => {
+					self.bolt11_invoice.send_using_amount(&invoice, amount_msat, None)
+				},
+				None => self.bolt11_invoice.send(&invoice, None),
+			};
+			match res {
 				Ok(payment_id) => return Ok(QrPaymentResult::Bolt11 { payment_id }),
 				Err(e) => log_error!(self.logger, "Failed to send BOLT11 invoice: {:?}. This is part of a unified QR code payment. Falling back to the on-chain transaction.", e),
 			}
 		}
 
-		let amount = match uri_network_checked.amount {
-			Some(amount) => amount,
-			None => {
-				log_error!(self.logger, "No amount specified in the URI. Aborting the payment.");
-				return Err(Error::InvalidAmount);
+		let amount_sats = match amount_msat.map(|amount_msat| amount_msat / 1000) {
+			Some(amount_sats) => amount_sats,
+			None => match uri_network_checked.amount {
+				Some(amount) => amount.to_sat(),
+				None => {
+					log_error!(
+						self.logger,
+						"No amount specified in the URI. Aborting the payment."
+					);
+					return Err(Error::InvalidAmount);
+				},
 			},
 		};
 
 		let txid = self.onchain_payment.send_to_address(
 			&uri_network_checked.address,
-			amount.to_sat(),
+			amount_sats,
+			None,
 			None,
 		)?;
 
 		Ok(QrPaymentResult::Onchain { txid })
 	}
 }
-
 /// Represents the result of a payment made using a [BIP 21] QR code.
 ///
 /// After a successful on-chain transaction, the transaction ID ([`Txid`]) is returned.
@@ -10,9 +10,11 @@ use crate::config::ChannelConfig;
 use crate::data_store::DataStore;
 use crate::fee_estimator::OnchainFeeEstimator;
 use crate::gossip::RuntimeSpawner;
+use crate::liquidity::{LSPS1OrderDetails, LSPS1ServiceOrder, LSPS2SaleDetails};
 use crate::logger::Logger;
 use crate::message_handler::NodeCustomMessageHandler;
-use crate::payment::PaymentDetails;
+use crate::payment::index::IndexedPaymentStore;
+use crate::payment::{ConfirmationStatus, ForwardedPaymentDetails, PaymentDetails};
 
 use lightning::chain::chainmonitor;
 use lightning::impl_writeable_tlv_based;
@@ -22,17 +24,20 @@ use lightning::ln::msgs::SocketAddress;
 use lightning::ln::peer_handler::IgnoringMessageHandler;
 use lightning::ln::types::ChannelId;
 use lightning::routing::gossip;
-use lightning::routing::router::DefaultRouter;
+use lightning::routing::router::{DefaultRouter, RouteHop};
 use lightning::routing::scoring::{ProbabilisticScorer, ProbabilisticScoringFeeParameters};
 use lightning::sign::InMemorySigner;
 use lightning::util::persist::KVStore;
-use lightning::util::ser::{Readable, Writeable, Writer};
+use lightning::util::ser::{Readable, WithoutLength, Writeable, Writer};
 use lightning::util::sweep::OutputSweeper;
 
 use lightning_block_sync::gossip::{GossipVerifier, UtxoSource};
 
 use lightning_net_tokio::SocketDescriptor;
 
+use bdk_wallet::KeychainKind;
+
+use bitcoin::psbt::Psbt;
 use bitcoin::secp256k1::PublicKey;
 use bitcoin::OutPoint;
 
@@ -145,12 +150,22 @@ pub(crate) type BumpTransactionEventHandler =
 		Arc<Logger>,
 	>;
 
-pub(crate) type PaymentStore = DataStore<PaymentDetails, Arc<Logger>>;
+pub(crate) type PaymentStore = IndexedPaymentStore<Arc<Logger>>;
+
+pub(crate) type ArchivedPaymentStore = DataStore<PaymentDetails, Arc<Logger>>;
+
+pub(crate) type ForwardingStore = DataStore<ForwardedPaymentDetails, Arc<Logger>>;
+
+pub(crate) type LSPS1ServiceOrderStore = DataStore<LSPS1ServiceOrder, Arc<Logger>>;
+
+pub(crate) type LSPS1OrderStore = DataStore<LSPS1OrderDetails, Arc<Logger>>;
+
+pub(crate) type LSPS2SaleStore = DataStore<LSPS2SaleDetails, Arc<Logger>>;
 
 /// A local, potentially user-provided, identifier of a channel.
 ///
 /// By default, this will be randomly generated for the user to ensure local uniqueness.
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct UserChannelId(pub u128);
 
 impl Writeable for UserChannelId {
@@ -167,6 +182,33 @@ impl Readable for UserChannelId {
 	}
 }
 
+/// The unsigned PSBT of a channel funding transaction that needs to be provided externally.
+///
+/// See [`Event::FundingPsbtReady`] for more information.
+///
+/// [`Event::FundingPsbtReady`]: crate::Event::FundingPsbtReady
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FundingPsbt(pub Psbt);
+
+// Note that the serialized PSBT isn't length-prefixed, i.e., it will consume the remainder of the
+// given reader, as is the case when it's read as a TLV record.
+impl Writeable for FundingPsbt {
+	fn write<W: Writer>(&self, writer: &mut W) -> Result<(), lightning::io::Error> {
+		WithoutLength(&self.0.serialize()).write(writer)
+	}
+}
+
+impl Readable for FundingPsbt {
+	fn read<R: lightning::io::Read>(
+		reader: &mut R,
+	) -> Result<Self, lightning::ln::msgs::DecodeError> {
+		let psbt_bytes: WithoutLength<Vec<u8>> = Readable::read(reader)?;
+		let psbt = Psbt::deserialize(&psbt_bytes.0)
+			.map_err(|_| lightning::ln::msgs::DecodeError::InvalidValue)?;
+		Ok(Self(psbt))
+	}
+}
+
 /// Details of a channel as returned by [`Node::list_channels`].
 ///
 /// [`Node::list_channels`]: crate::Node::list_channels
@@ -393,6 +435,42 @@ pub struct PeerDetails {
 	pub is_connected: bool,
 }
 
+/// Details of an unspent transaction output of the on-chain wallet as returned by
+/// [`OnchainPayment::list_utxos`].
+///
+/// [`OnchainPayment::list_utxos`]: crate::payment::OnchainPayment::list_utxos
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UtxoDetails {
+	/// The outpoint referencing the output.
+	pub outpoint: OutPoint,
+	/// The value of the output, in satoshis.
+	pub value_sats: u64,
+	/// The confirmation status of the transaction creating the output.
+	pub confirmation_status: ConfirmationStatus,
+	/// The keychain the output's script belongs to.
+	pub keychain: WalletKeychain,
+	/// Indicates whether the output is currently locked, i.e., excluded from coin selection.
+	pub is_locked: bool,
+}
+
+/// The keychain of the on-chain wallet an output belongs to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WalletKeychain {
+	/// The external keychain, used for addresses handed out to receive funds.
+	External,
+	/// The internal keychain, used for change outputs.
+	Internal,
+}
+
+impl From<KeychainKind> for WalletKeychain {
+	fn from(value: KeychainKind) -> Self {
+		match value {
+			KeychainKind::External => Self::External,
+			KeychainKind::Internal => Self::Internal,
+		}
+	}
+}
+
 /// Custom TLV entry.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct CustomTlvRecord {
@@ -412,3 +490,38 @@ impl From<&(u64, Vec<u8>)> for CustomTlvRecord {
 		CustomTlvRecord { type_num: tlv.0, value: tlv.1.clone() }
 	}
 }
+
+/// A hop of the path a payment or probe was sent along.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaymentPathHop {
+	/// The node id of the node at this hop.
+	pub node_id: PublicKey,
+	/// The short channel id of the channel used to reach the node at this hop.
+	pub short_channel_id: u64,
+	/// The fee, in milli-satoshis, taken by the node at this hop.
+	///
+	/// For the last hop, this is the amount delivered to the recipient instead.
+	pub fee_msat: u64,
+	/// The CLTV expiry delta required by the node at this hop.
+	///
+	/// For the last hop, this is the final CLTV expiry delta instead.
+	pub cltv_expiry_delta: u32,
+}
+
+impl_writeable_tlv_based!(PaymentPathHop, {
+	(0, node_id, required),
+	(2, short_channel_id, required),
+	(4, fee_msat, required),
+	(6, cltv_expiry_delta, required),
+});
+
+impl From<&RouteHop> for PaymentPathHop {
+	fn from(hop: &RouteHop) -> Self {
+		PaymentPathHop {
+			node_id: hop.pubkey,
+			short_channel_id: hop.short_channel_id,
+			fee_msat: hop.fee_msat,
+			cltv_expiry_delta: hop.cltv_expiry_delta,
+		}
+	}
+}
@@ -11,9 +11,13 @@ use crate::config::Config;
 use crate::logger::{log_debug, log_error, log_info, log_trace, LdkLogger};
 
 use crate::fee_estimator::{ConfirmationTarget, FeeEstimator};
+use crate::io::{
+	LOCKED_UTXOS_PERSISTENCE_KEY, LOCKED_UTXOS_PERSISTENCE_PRIMARY_NAMESPACE,
+	LOCKED_UTXOS_PERSISTENCE_SECONDARY_NAMESPACE,
+};
 use crate::payment::store::ConfirmationStatus;
-use crate::payment::{PaymentDetails, PaymentDirection, PaymentStatus};
-use crate::types::PaymentStore;
+use crate::payment::{PaymentDetails, PaymentDirection, PaymentKind, PaymentStatus};
+use crate::types::{ArchivedPaymentStore, DynStore, PaymentStore, UtxoDetails};
 use crate::Error;
 
 use lightning::chain::chaininterface::BroadcasterInterface;
@@ -21,6 +25,7 @@ use lightning::chain::channelmonitor::ANTI_REORG_DELAY;
 use lightning::chain::{BestBlock, Listen};
 
 use lightning::events::bump_transaction::{Utxo, WalletSource};
+use lightning::impl_writeable_tlv_based;
 use lightning::ln::channelmanager::PaymentId;
 use lightning::ln::inbound_payment::ExpandedKey;
 use lightning::ln::msgs::{DecodeError, UnsignedGossipMessage};
@@ -31,9 +36,11 @@ use lightning::sign::{
 };
 
 use lightning::util::message_signing;
+use lightning::util::ser::Writeable;
 use lightning_invoice::RawBolt11Invoice;
 
 use bdk_chain::spk_client::{FullScanRequest, SyncRequest};
+use bdk_wallet::error::{BuildFeeBumpError, CreateTxError as BdkCreateTxError};
 use bdk_wallet::{Balance, KeychainKind, PersistedWallet, SignOptions, Update};
 
 use bitcoin::address::NetworkUnchecked;
@@ -46,13 +53,15 @@ use bitcoin::secp256k1::ecdh::SharedSecret;
 use bitcoin::secp256k1::ecdsa::{RecoverableSignature, Signature};
 use bitcoin::secp256k1::{PublicKey, Scalar, Secp256k1, SecretKey, Signing};
 use bitcoin::{
-	Address, Amount, FeeRate, Network, ScriptBuf, Transaction, TxOut, Txid, WPubkeyHash,
+	Address, Amount, FeeRate, Network, OutPoint, ScriptBuf, Transaction, TxOut, Txid, WPubkeyHash,
 	WitnessProgram, WitnessVersion,
 };
 
+use std::collections::{HashMap, HashSet};
 use std::ops::Deref;
 use std::str::FromStr;
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 pub(crate) enum OnchainSendAmount {
 	ExactRetainingReserve { amount_sats: u64, cur_anchor_reserve_sats: u64 },
@@ -63,6 +72,16 @@ pub(crate) enum OnchainSendAmount {
 pub(crate) mod persist;
 pub(crate) mod ser;
 
+/// The set of UTXOs that were locked by the user, i.e., are excluded from coin selection.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct LockedUtxos {
+	pub outpoints: Vec<OutPoint>,
+}
+
+impl_writeable_tlv_based!(LockedUtxos, {
+	(0, outpoints, required_vec),
+});
+
 pub(crate) struct Wallet<B: Deref, E: Deref, L: Deref>
 where
 	B::Target: BroadcasterInterface,
@@ -75,6 +94,20 @@ where
 	broadcaster: B,
 	fee_estimator: E,
 	payment_store: Arc<PaymentStore>,
+	archived_payment_store: Arc<ArchivedPaymentStore>,
+	// The UTXOs excluded from coin selection.
+	locked_utxos: Mutex<HashSet<OutPoint>>,
+	// The UTXOs selected to fund channels we initiated, keyed by `user_channel_id`.
+	funding_utxos: Mutex<HashMap<u128, Vec<OutPoint>>>,
+	// The confirmation targets of funding transactions for channels we initiated, keyed by
+	// `user_channel_id`, if they differ from `ConfirmationTarget::ChannelFunding`.
+	funding_confirmation_targets: Mutex<HashMap<u128, ConfirmationTarget>>,
+	// The payment and confirmation status last recorded in the payment store for each wallet
+	// transaction, allowing us to skip unchanged transactions when updating the payment store.
+	synced_tx_statuses: Mutex<HashMap<Txid, (PaymentStatus, ConfirmationStatus)>>,
+	// The wallet transactions whose payments were archived.
+	archived_txids: Mutex<HashSet<Txid>>,
+	kv_store: Arc<DynStore>,
 	config: Arc<Config>,
 	logger: L,
 }
@@ -88,11 +121,32 @@ where
 	pub(crate) fn new(
 		wallet: bdk_wallet::PersistedWallet<KVStoreWalletPersister>,
 		wallet_persister: KVStoreWalletPersister, broadcaster: B, fee_estimator: E,
-		payment_store: Arc<PaymentStore>, config: Arc<Config>, logger: L,
+		payment_store: Arc<PaymentStore>, archived_payment_store: Arc<ArchivedPaymentStore>,
+		locked_utxos: LockedUtxos, kv_store: Arc<DynStore>, config: Arc<Config>, logger: L,
 	) -> Self {
 		let inner = Mutex::new(wallet);
 		let persister = Mutex::new(wallet_persister);
-		Self { inner, persister, broadcaster, fee_estimator, payment_store, config, logger }
+		let locked_utxos = Mutex::new(locked_utxos.outpoints.into_iter().collect());
+		let funding_utxos = Mutex::new(HashMap::new());
+		let funding_confirmation_targets = Mutex::new(HashMap::new());
+		let synced_tx_statuses = Mutex::new(HashMap::new());
+		let archived_txids = Mutex::new(HashSet::new());
+		Self {
+			inner,
+			persister,
+			broadcaster,
+			fee_estimator,
+			payment_store,
+			archived_payment_store,
+			locked_utxos,
+			funding_utxos,
+			funding_confirmation_targets,
+			synced_tx_statuses,
+			archived_txids,
+			kv_store,
+			config,
+			logger,
+		}
 	}
 
 	pub(crate) fn get_full_scan_request(&self) -> FullScanRequest<KeychainKind> {
@@ -165,9 +219,14 @@ where
 	fn update_payment_store<'a>(
 		&self, locked_wallet: &'a mut PersistedWallet<KVStoreWalletPersister>,
 	) -> Result<(), Error> {
+		let mut locked_synced_tx_statuses = self.synced_tx_statuses.lock().unwrap();
+		let mut locked_archived_txids = self.archived_txids.lock().unwrap();
 		for wtx in locked_wallet.transactions() {
-			let id = PaymentId(wtx.tx_node.txid.to_byte_array());
 			let txid = wtx.tx_node.txid;
+			if locked_archived_txids.contains(&txid) {
+				continue;
+			}
+
 			let (payment_status, confirmation_status) = match wtx.chain_position {
 				bdk_chain::ChainPosition::Confirmed { anchor, .. } => {
 					let confirmation_height = anchor.block_id.height;
@@ -189,6 +248,32 @@ where
 					(PaymentStatus::Pending, ConfirmationStatus::Unconfirmed)
 				},
 			};
+
+			// Payments that were fee-bumped via RBF are still tracked under their original
+			// identifier, so we need to map the replacement transactions back to them.
+			let id = match self.payment_store.payment_id_for_txid(&txid) {
+				Some(id) => {
+					// Skip transactions whose payment is already up-to-date, avoiding to read it
+					// from the underlying store.
+					if locked_synced_tx_statuses.get(&txid)
+						== Some(&(payment_status, confirmation_status))
+					{
+						continue;
+					}
+					id
+				},
+				None => {
+					// Don't resurrect payments we archived. As fee-bumped payments are never
+					// archived, archived ones are always tracked under their txid.
+					let id = PaymentId(txid.to_byte_array());
+					if self.archived_payment_store.get(&id).is_some() {
+						locked_synced_tx_statuses.remove(&txid);
+						locked_archived_txids.insert(txid);
+						continue;
+					}
+					id
+				},
+			};
 			// TODO: It would be great to introduce additional variants for
 			// `ChannelFunding` and `ChannelClosing`. For the former, we could just
 			// take a reference to `ChannelManager` here and check against
@@ -200,7 +285,11 @@ where
 			// create and persist a list of 'static pending outputs' that we could use
 			// here to determine the `PaymentKind`, but that's not really satisfactory, so
 			// we're punting on it until we can come up with a better solution.
-			let kind = crate::payment::PaymentKind::Onchain { txid, status: confirmation_status };
+			let kind = PaymentKind::Onchain {
+				txid,
+				status: confirmation_status,
+				replaced_txids: Vec::new(),
+			};
 			let fee = locked_wallet.calculate_fee(&wtx.tx_node.tx).unwrap_or(Amount::ZERO);
 			let (sent, received) = locked_wallet.sent_and_received(&wtx.tx_node.tx);
 			let (direction, amount_msat) = if sent > received {
@@ -231,6 +320,7 @@ where
 			);
 
 			self.payment_store.insert_or_update(payment)?;
+			locked_synced_tx_statuses.insert(txid, (payment_status, confirmation_status));
 		}
 
 		Ok(())
@@ -238,14 +328,33 @@ where
 
 	pub(crate) fn create_funding_transaction(
 		&self, output_script: ScriptBuf, amount: Amount, confirmation_target: ConfirmationTarget,
-		locktime: LockTime,
+		locktime: LockTime, utxos: Option<Vec<OutPoint>>,
 	) -> Result<Transaction, Error> {
 		let fee_rate = self.fee_estimator.estimate_fee_rate(confirmation_target);
 
 		let mut locked_wallet = self.inner.lock().unwrap();
+		let locked_utxos = self.locked_utxos.lock().unwrap().iter().copied().collect();
 		let mut tx_builder = locked_wallet.build_tx();
 
-		tx_builder.add_recipient(output_script, amount).fee_rate(fee_rate).nlocktime(locktime);
+		tx_builder
+			.add_recipient(output_script, amount)
+			.fee_rate(fee_rate)
+			.nlocktime(locktime)
+			.unspendable(locked_utxos);
+
+		if let Some(utxos) = utxos {
+			tx_builder
+				.add_utxos(&utxos)
+				.map_err(|e| {
+					log_error!(
+						self.logger,
+						"Failed to select UTXOs for funding transaction: {}",
+						e
+					);
+					Error::InvalidUtxo
+				})?
+				.manually_selected_only();
+		}
 
 		let mut psbt = match tx_builder.finish() {
 			Ok(psbt) => {
@@ -284,6 +393,137 @@ where
 		Ok(tx)
 	}
 
+	pub(crate) fn list_utxos(&self) -> Vec<UtxoDetails> {
+		let locked_wallet = self.inner.lock().unwrap();
+		let locked_utxos = self.locked_utxos.lock().unwrap();
+		locked_wallet
+			.list_unspent()
+			.map(|u| {
+				let confirmation_status = match u.chain_position {
+					bdk_chain::ChainPosition::Confirmed { anchor, .. } => {
+						ConfirmationStatus::Confirmed {
+							block_hash: anchor.block_id.hash,
+							height: anchor.block_id.height,
+							timestamp: anchor.confirmation_time,
+						}
+					},
+					bdk_chain::ChainPosition::Unconfirmed { .. } => ConfirmationStatus::Unconfirmed,
+				};
+				UtxoDetails {
+					outpoint: u.outpoint,
+					value_sats: u.txout.value.to_sat(),
+					confirmation_status,
+					keychain: u.keychain.into(),
+					is_locked: locked_utxos.contains(&u.outpoint),
+				}
+			})
+			.collect()
+	}
+
+	pub(crate) fn lock_utxo(&self, outpoint: OutPoint) -> Result<(), Error> {
+		let locked_wallet = self.inner.lock().unwrap();
+		if locked_wallet.get_utxo(outpoint).map_or(true, |u| u.is_spent) {
+			log_error!(self.logger, "Failed to lock unknown or spent UTXO {}", outpoint);
+			return Err(Error::InvalidUtxo);
+		}
+
+		let mut locked_utxos = self.locked_utxos.lock().unwrap();
+		if locked_utxos.insert(outpoint) {
+			self.persist_locked_utxos(&locked_utxos)?;
+			log_info!(self.logger, "Locked UTXO {}", outpoint);
+		}
+		Ok(())
+	}
+
+	pub(crate) fn unlock_utxo(&self, outpoint: OutPoint) -> Result<(), Error> {
+		let mut locked_utxos = self.locked_utxos.lock().unwrap();
+		if locked_utxos.remove(&outpoint) {
+			self.persist_locked_utxos(&locked_utxos)?;
+			log_info!(self.logger, "Unlocked UTXO {}", outpoint);
+		}
+		Ok(())
+	}
+
+	fn persist_locked_utxos(&self, locked_utxos: &HashSet<OutPoint>) -> Result<(), Error> {
+		let data = LockedUtxos { outpoints: locked_utxos.iter().copied().collect() }.encode();
+		self.kv_store
+			.write(
+				LOCKED_UTXOS_PERSISTENCE_PRIMARY_NAMESPACE,
+				LOCKED_UTXOS_PERSISTENCE_SECONDARY_NAMESPACE,
+				LOCKED_UTXOS_PERSISTENCE_KEY,
+				&data,
+			)
+			.map_err(|e| {
+				log_error!(
+					self.logger,
+					"Write for key {}/{}/{} failed due to: {}",
+					LOCKED_UTXOS_PERSISTENCE_PRIMARY_NAMESPACE,
+					LOCKED_UTXOS_PERSISTENCE_SECONDARY_NAMESPACE,
+					LOCKED_UTXOS_PERSISTENCE_KEY,
+					e
+				);
+				Error::PersistenceFailed
+			})
+	}
+
+	/// Checks the given UTXOs may be spent, i.e., are known, unspent, and not locked, and returns
+	/// their total value.
+	pub(crate) fn check_utxo_selection(&self, utxos: &[OutPoint]) -> Result<Amount, Error> {
+		let locked_wallet = self.inner.lock().unwrap();
+		let locked_utxos = self.locked_utxos.lock().unwrap();
+		let mut total = Amount::ZERO;
+		for outpoint in utxos {
+			if locked_utxos.contains(outpoint) {
+				log_error!(self.logger, "Failed to select locked UTXO {}", outpoint);
+				return Err(Error::InvalidUtxo);
+			}
+			match locked_wallet.get_utxo(*outpoint) {
+				Some(utxo) if !utxo.is_spent => total += utxo.txout.value,
+				_ => {
+					log_error!(self.logger, "Failed to select unknown or spent UTXO {}", outpoint);
+					return Err(Error::InvalidUtxo);
+				},
+			}
+		}
+		Ok(total)
+	}
+
+	pub(crate) fn set_funding_utxos(&self, user_channel_id: u128, utxos: Vec<OutPoint>) {
+		self.funding_utxos.lock().unwrap().insert(user_channel_id, utxos);
+	}
+
+	pub(crate) fn take_funding_utxos(&self, user_channel_id: u128) -> Option<Vec<OutPoint>> {
+		self.funding_utxos.lock().unwrap().remove(&user_channel_id)
+	}
+
+	pub(crate) fn set_funding_confirmation_target(
+		&self, user_channel_id: u128, confirmation_target: ConfirmationTarget,
+	) {
+		self.funding_confirmation_targets
+			.lock()
+			.unwrap()
+			.insert(user_channel_id, confirmation_target);
+	}
+
+	pub(crate) fn take_funding_confirmation_target(
+		&self, user_channel_id: u128,
+	) -> Option<ConfirmationTarget> {
+		self.funding_confirmation_targets.lock().unwrap().remove(&user_channel_id)
+	}
+
+	fn locked_utxos_amount_sats(
+		&self, locked_wallet: &PersistedWallet<KVStoreWalletPersister>,
+	) -> u64 {
+		self.locked_utxos
+			.lock()
+			.unwrap()
+			.iter()
+			.filter_map(|o| locked_wallet.get_utxo(*o))
+			.filter(|u| !u.is_spent)
+			.map(|u| u.txout.value.to_sat())
+			.sum()
+	}
+
 	pub(crate) fn get_new_address(&self) -> Result<bitcoin::Address, Error> {
 		let mut locked_wallet = self.inner.lock().unwrap();
 		let mut locked_persister = self.persister.lock().unwrap();
@@ -296,6 +536,27 @@ where
 		Ok(address_info.address)
 	}
 
+	/// Returns the total amount received by the given address in outputs with at least
+	/// `min_confirmations` confirmations.
+	pub(crate) fn get_received_amount_sats(
+		&self, address: &bitcoin::Address, min_confirmations: u32,
+	) -> u64 {
+		let locked_wallet = self.inner.lock().unwrap();
+		let cur_height = locked_wallet.latest_checkpoint().height();
+		let script_pubkey = address.script_pubkey();
+		locked_wallet
+			.list_output()
+			.filter(|o| o.txout.script_pubkey == script_pubkey)
+			.filter(|o| match o.chain_position {
+				bdk_chain::ChainPosition::Confirmed { anchor, .. } => {
+					cur_height.saturating_sub(anchor.block_id.height) + 1 >= min_confirmations
+				},
+				bdk_chain::ChainPosition::Unconfirmed { .. } => min_confirmations == 0,
+			})
+			.map(|o| o.txout.value.to_sat())
+			.sum()
+	}
+
 	fn get_new_internal_address(&self) -> Result<bitcoin::Address, Error> {
 		let mut locked_wallet = self.inner.lock().unwrap();
 		let mut locked_persister = self.persister.lock().unwrap();
@@ -354,7 +615,7 @@ where
 
 	pub(crate) fn send_to_address(
 		&self, address: &bitcoin::Address, send_amount: OnchainSendAmount,
-		fee_rate: Option<FeeRate>,
+		fee_rate: Option<FeeRate>, utxos: Option<Vec<OutPoint>>,
 	) -> Result<Txid, Error> {
 		self.parse_and_validate_address(self.config.network, &address)?;
 
@@ -365,14 +626,26 @@ where
 
 		let tx = {
 			let mut locked_wallet = self.inner.lock().unwrap();
+			let locked_utxos: Vec<OutPoint> =
+				self.locked_utxos.lock().unwrap().iter().copied().collect();
+			let locked_utxos_amount_sats = self.locked_utxos_amount_sats(&locked_wallet);
 
 			// Prepare the tx_builder. We properly check the reserve requirements (again) further down.
 			const DUST_LIMIT_SATS: u64 = 546;
-			let tx_builder = match send_amount {
+			let mut tx_builder = match send_amount {
 				OnchainSendAmount::ExactRetainingReserve { amount_sats, .. } => {
 					let mut tx_builder = locked_wallet.build_tx();
 					let amount = Amount::from_sat(amount_sats);
 					tx_builder.add_recipient(address.script_pubkey(), amount).fee_rate(fee_rate);
+					if let Some(utxos) = utxos {
+						tx_builder
+							.add_utxos(&utxos)
+							.map_err(|e| {
+								log_error!(self.logger, "Failed to select UTXOs: {}", e);
+								Error::InvalidUtxo
+							})?
+							.manually_selected_only();
+					}
 					tx_builder
 				},
 				OnchainSendAmount::AllRetainingReserve { cur_anchor_reserve_sats }
@@ -383,10 +656,12 @@ where
 					let spendable_amount_sats = self
 						.get_balances_inner(balance, cur_anchor_reserve_sats)
 						.map(|(_, s)| s)
-						.unwrap_or(0);
+						.unwrap_or(0)
+						.saturating_sub(locked_utxos_amount_sats);
 					let tmp_tx = {
 						let mut tmp_tx_builder = locked_wallet.build_tx();
 						tmp_tx_builder
+							.unspendable(locked_utxos.clone())
 							.drain_wallet()
 							.drain_to(address.script_pubkey())
 							.add_recipient(
@@ -446,6 +721,8 @@ where
 				},
 			};
 
+			tx_builder.unspendable(locked_utxos);
+
 			let mut psbt = match tx_builder.finish() {
 				Ok(psbt) => {
 					log_trace!(self.logger, "Created PSBT: {:?}", psbt);
@@ -467,7 +744,8 @@ where
 					let spendable_amount_sats = self
 						.get_balances_inner(balance, cur_anchor_reserve_sats)
 						.map(|(_, s)| s)
-						.unwrap_or(0);
+						.unwrap_or(0)
+						.saturating_sub(locked_utxos_amount_sats);
 					let tx_fee_sats = locked_wallet
 						.calculate_fee(&psbt.unsigned_tx)
 						.map_err(|e| {
@@ -494,7 +772,8 @@ where
 					let spendable_amount_sats = self
 						.get_balances_inner(balance, cur_anchor_reserve_sats)
 						.map(|(_, s)| s)
-						.unwrap_or(0);
+						.unwrap_or(0)
+						.saturating_sub(locked_utxos_amount_sats);
 					let (sent, received) = locked_wallet.sent_and_received(&psbt.unsigned_tx);
 					let drain_amount = sent - received;
 					if spendable_amount_sats < drain_amount.to_sat() {
@@ -568,6 +847,368 @@ where
 
 		Ok(txid)
 	}
+
+	pub(crate) fn bump_fee_rbf(
+		&self, txid: Txid, fee_rate: FeeRate, cur_anchor_reserve_sats: u64,
+	) -> Result<Txid, Error> {
+		let (tx, fee_paid) = {
+			let mut locked_wallet = self.inner.lock().unwrap();
+
+			let original_fee = {
+				let wtx = locked_wallet.get_tx(txid).ok_or_else(|| {
+					log_error!(self.logger, "Failed to bump fee of unknown transaction {}", txid);
+					Error::TransactionNotFound
+				})?;
+				if wtx.chain_position.is_confirmed() {
+					log_error!(self.logger, "Failed to bump fee of confirmed transaction {}", txid);
+					return Err(Error::TransactionAlreadyConfirmed);
+				}
+				locked_wallet.calculate_fee(&wtx.tx_node.tx).map_err(|e| {
+					log_error!(
+						self.logger,
+						"Failed to calculate fee of transaction {}: {}",
+						txid,
+						e
+					);
+					e
+				})?
+			};
+
+			let mut tx_builder = locked_wallet.build_fee_bump(txid).map_err(|e| {
+				log_error!(
+					self.logger,
+					"Failed to create fee bump for transaction {}: {}",
+					txid,
+					e
+				);
+				match e {
+					BuildFeeBumpError::TransactionNotFound(_) => Error::TransactionNotFound,
+					BuildFeeBumpError::TransactionConfirmed(_) => {
+						Error::TransactionAlreadyConfirmed
+					},
+					_ => Error::OnchainTxCreationFailed,
+				}
+			})?;
+			let locked_utxos = self.locked_utxos.lock().unwrap().iter().copied().collect();
+			tx_builder.fee_rate(fee_rate).unspendable(locked_utxos);
+
+			let mut psbt = match tx_builder.finish() {
+				Ok(psbt) => {
+					log_trace!(self.logger, "Created RBF PSBT: {:?}", psbt);
+					psbt
+				},
+				Err(err @ BdkCreateTxError::FeeRateTooLow { .. })
+				| Err(err @ BdkCreateTxError::FeeTooLow { .. }) => {
+					log_error!(self.logger, "Failed to create replacement transaction: {}", err);
+					return Err(Error::InvalidFeeRate);
+				},
+				Err(err) => {
+					log_error!(self.logger, "Failed to create replacement transaction: {}", err);
+					return Err(err.into());
+				},
+			};
+
+			// The replacement spends the same inputs as the original transaction, i.e., the
+			// additional fee is what we need to cover without cutting into the reserve.
+			let new_fee = locked_wallet.calculate_fee(&psbt.unsigned_tx).map_err(|e| {
+				log_error!(
+					self.logger,
+					"Failed to calculate fee of replacement transaction: {}",
+					e
+				);
+				e
+			})?;
+			let additional_fee_sats = new_fee.to_sat().saturating_sub(original_fee.to_sat());
+			let balance = locked_wallet.balance();
+			let spendable_amount_sats = self
+				.get_balances_inner(balance, cur_anchor_reserve_sats)
+				.map(|(_, s)| s)
+				.unwrap_or(0);
+			if spendable_amount_sats < additional_fee_sats {
+				log_error!(self.logger,
+					"Unable to bump fee due to insufficient funds. Available: {}sats, Required: {}sats additional fee",
+					spendable_amount_sats,
+					additional_fee_sats,
+				);
+				locked_wallet.cancel_tx(&psbt.unsigned_tx);
+				return Err(Error::InsufficientFunds);
+			}
+
+			match locked_wallet.sign(&mut psbt, SignOptions::default()) {
+				Ok(finalized) => {
+					if !finalized {
+						return Err(Error::OnchainTxCreationFailed);
+					}
+				},
+				Err(err) => {
+					log_error!(self.logger, "Failed to create replacement transaction: {}", err);
+					return Err(err.into());
+				},
+			}
+
+			let tx = psbt.extract_tx().map_err(|e| {
+				log_error!(self.logger, "Failed to extract transaction: {}", e);
+				e
+			})?;
+
+			// Have the wallet consider the replacement right away, so that the original
+			// transaction is evicted from the canonical set and its outputs aren't reused.
+			let last_seen = SystemTime::now()
+				.duration_since(UNIX_EPOCH)
+				.unwrap_or(Duration::from_secs(0))
+				.as_secs();
+			locked_wallet.apply_unconfirmed_txs(vec![(tx.clone(), last_seen)]);
+
+			let mut locked_persister = self.persister.lock().unwrap();
+			locked_wallet.persist(&mut locked_persister).map_err(|e| {
+				log_error!(self.logger, "Failed to persist wallet: {}", e);
+				Error::PersistenceFailed
+			})?;
+
+			(tx, new_fee)
+		};
+
+		self.broadcaster.broadcast_transactions(&[&tx]);
+
+		let new_txid = tx.compute_txid();
+
+		self.record_replacement(txid, new_txid, fee_paid)?;
+
+		log_info!(
+			self.logger,
+			"Created replacement transaction {} for transaction {}, bumping the fee to {}sats",
+			new_txid,
+			txid,
+			fee_paid.to_sat(),
+		);
+
+		Ok(new_txid)
+	}
+
+	pub(crate) fn bump_fee_cpfp(
+		&self, txid: Txid, fee_rate: FeeRate, cur_anchor_reserve_sats: u64,
+	) -> Result<Txid, Error> {
+		let tx = {
+			let mut locked_wallet = self.inner.lock().unwrap();
+
+			let parent_tx = {
+				let wtx = locked_wallet.get_tx(txid).ok_or_else(|| {
+					log_error!(self.logger, "Failed to bump fee of unknown transaction {}", txid);
+					Error::TransactionNotFound
+				})?;
+				if wtx.chain_position.is_confirmed() {
+					log_error!(self.logger, "Failed to bump fee of confirmed transaction {}", txid);
+					return Err(Error::TransactionAlreadyConfirmed);
+				}
+				wtx.tx_node.tx
+			};
+
+			let parent_fee = locked_wallet.calculate_fee(&parent_tx).map_err(|e| {
+				log_error!(self.logger, "Failed to calculate fee of transaction {}: {}", txid, e);
+				e
+			})?;
+			let parent_weight = parent_tx.weight();
+			let parent_fee_at_target = fee_rate.fee_wu(parent_weight).ok_or_else(|| {
+				log_error!(self.logger, "Failed to calculate target fee for transaction {}", txid);
+				Error::InvalidFeeRate
+			})?;
+			if parent_fee >= parent_fee_at_target {
+				log_error!(
+					self.logger,
+					"Transaction {} already pays {}sats which meets the target fee rate of {}sat/vB",
+					txid,
+					parent_fee.to_sat(),
+					fee_rate.to_sat_per_vb_ceil(),
+				);
+				return Err(Error::InvalidFeeRate);
+			}
+
+			let locked_utxos: Vec<OutPoint> =
+				self.locked_utxos.lock().unwrap().iter().copied().collect();
+			let outpoints: Vec<OutPoint> = (0..parent_tx.output.len() as u32)
+				.map(|vout| OutPoint { txid, vout })
+				.filter(|outpoint| !locked_utxos.contains(outpoint))
+				.filter(|outpoint| locked_wallet.get_utxo(*outpoint).is_some())
+				.collect();
+			if outpoints.is_empty() {
+				log_error!(
+					self.logger,
+					"Failed to bump fee of transaction {} as it has no outputs spendable by us",
+					txid
+				);
+				return Err(Error::OnchainTxCreationFailed);
+			}
+
+			let change_script =
+				locked_wallet.next_unused_address(KeychainKind::Internal).address.script_pubkey();
+
+			// We first create a temporary transaction to figure out the fee the child would need to
+			// pay on its own at the target fee rate.
+			let tmp_tx = {
+				let mut tmp_tx_builder = locked_wallet.build_tx();
+				tmp_tx_builder
+					.add_utxos(&outpoints)
+					.map_err(|e| {
+						log_error!(
+							self.logger,
+							"Failed to add outputs to child transaction: {}",
+							e
+						);
+						Error::OnchainTxCreationFailed
+					})?
+					.drain_to(change_script.clone())
+					.unspendable(locked_utxos.clone())
+					.fee_rate(fee_rate);
+				match tmp_tx_builder.finish() {
+					Ok(psbt) => psbt.unsigned_tx,
+					Err(err) => {
+						log_error!(self.logger, "Failed to create temporary transaction: {}", err);
+						return Err(err.into());
+					},
+				}
+			};
+
+			let child_fee_at_target = locked_wallet.calculate_fee(&tmp_tx).map_err(|e| {
+				log_error!(self.logger, "Failed to calculate fee of temporary transaction: {}", e);
+				e
+			})?;
+
+			// 'cancel' the transaction to free up any used change addresses
+			locked_wallet.cancel_tx(&tmp_tx);
+
+			// The child needs to pay for itself and make up for the parent's fee deficit so that
+			// the package reaches the target fee rate.
+			let child_fee = child_fee_at_target + (parent_fee_at_target - parent_fee);
+
+			// The child may spend unconfirmed outputs received from third parties, which we
+			// therefore also consider here.
+			let balance = locked_wallet.balance();
+			let available_amount_sats = (balance.trusted_spendable() + balance.untrusted_pending)
+				.to_sat()
+				.saturating_sub(cur_anchor_reserve_sats);
+			if available_amount_sats < child_fee.to_sat() {
+				log_error!(self.logger,
+					"Unable to bump fee due to insufficient funds. Available: {}sats, Required: {}sats fee",
+					available_amount_sats,
+					child_fee.to_sat(),
+				);
+				return Err(Error::InsufficientFunds);
+			}
+
+			let mut tx_builder = locked_wallet.build_tx();
+			tx_builder
+				.add_utxos(&outpoints)
+				.map_err(|e| {
+					log_error!(self.logger, "Failed to add outputs to child transaction: {}", e);
+					Error::OnchainTxCreationFailed
+				})?
+				.drain_to(change_script)
+				.unspendable(locked_utxos)
+				.fee_absolute(child_fee);
+
+			let mut psbt = match tx_builder.finish() {
+				Ok(psbt) => {
+					log_trace!(self.logger, "Created CPFP PSBT: {:?}", psbt);
+					psbt
+				},
+				Err(err) => {
+					log_error!(self.logger, "Failed to create child transaction: {}", err);
+					return Err(err.into());
+				},
+			};
+
+			match locked_wallet.sign(&mut psbt, SignOptions::default()) {
+				Ok(finalized) => {
+					if !finalized {
+						return Err(Error::OnchainTxCreationFailed);
+					}
+				},
+				Err(err) => {
+					log_error!(self.logger, "Failed to create child transaction: {}", err);
+					return Err(err.into());
+				},
+			}
+
+			let mut locked_persister = self.persister.lock().unwrap();
+			locked_wallet.persist(&mut locked_persister).map_err(|e| {
+				log_error!(self.logger, "Failed to persist wallet: {}", e);
+				Error::PersistenceFailed
+			})?;
+
+			psbt.extract_tx().map_err(|e| {
+				log_error!(self.logger, "Failed to extract transaction: {}", e);
+				e
+			})?
+		};
+
+		self.broadcaster.broadcast_transactions(&[&tx]);
+
+		let child_txid = tx.compute_txid();
+
+		log_info!(
+			self.logger,
+			"Created child transaction {} bumping the fee of transaction {} to {}sat/vB",
+			child_txid,
+			txid,
+			fee_rate.to_sat_per_vb_ceil(),
+		);
+
+		Ok(child_txid)
+	}
+
+	// Updates the payment tracking `replaced_txid` to point to its replacement, retaining the
+	// history of replaced transactions.
+	fn record_replacement(
+		&self, replaced_txid: Txid, new_txid: Txid, fee_paid: Amount,
+	) -> Result<(), Error> {
+		let existing = self
+			.payment_store
+			.payment_id_for_txid(&replaced_txid)
+			.and_then(|id| self.payment_store.get(&id));
+
+		let payment = match existing {
+			Some(payment) => {
+				let mut replaced_txids = match payment.kind {
+					PaymentKind::Onchain { replaced_txids, .. } => replaced_txids,
+					_ => Vec::new(),
+				};
+				replaced_txids.push(replaced_txid);
+				let kind = PaymentKind::Onchain {
+					txid: new_txid,
+					status: ConfirmationStatus::Unconfirmed,
+					replaced_txids,
+				};
+				PaymentDetails::new(
+					payment.id,
+					kind,
+					payment.amount_msat,
+					Some(fee_paid.to_sat() * 1000),
+					payment.direction,
+					PaymentStatus::Pending,
+				)
+			},
+			None => {
+				// We haven't tracked the original transaction yet, so we start tracking the
+				// payment under the original identifier.
+				let kind = PaymentKind::Onchain {
+					txid: new_txid,
+					status: ConfirmationStatus::Unconfirmed,
+					replaced_txids: vec![replaced_txid],
+				};
+				PaymentDetails::new(
+					PaymentId(replaced_txid.to_byte_array()),
+					kind,
+					None,
+					Some(fee_paid.to_sat() * 1000),
+					PaymentDirection::Outbound,
+					PaymentStatus::Pending,
+				)
+			},
+		};
+
+		self.payment_store.insert(payment)?;
+		Ok(())
+	}
 }
 
 impl<B: Deref, E: Deref, L: Deref> Listen for Wallet<B, E, L>
@@ -13,6 +13,7 @@ pub(crate) mod logging;
 use logging::TestLogWriter;
 
 use ldk_node::config::{Config, ElectrumSyncConfig, EsploraSyncConfig};
+use ldk_node::hrn::HrnResolver;
 use ldk_node::io::sqlite_store::SqliteStore;
 use ldk_node::payment::{PaymentDirection, PaymentKind, PaymentStatus};
 use ldk_node::{
@@ -265,6 +266,7 @@ pub(crate) enum TestChainSource<'a> {
 pub(crate) struct TestConfig {
 	pub node_config: Config,
 	pub log_writer: TestLogWriter,
+	pub hrn_resolver: Option<Arc<dyn HrnResolver>>,
 }
 
 macro_rules! setup_builder {
@@ -348,7 +350,7 @@ pub(crate) fn setup_node(
 
 	match &config.log_writer {
 		TestLogWriter::FileWriter => {
-			builder.set_filesystem_logger(None, None);
+			builder.set_filesystem_logger(None, None, None, None);
 		},
 		TestLogWriter::LogFacade => {
 			builder.set_log_facade_logger();
@@ -358,6 +360,10 @@ pub(crate) fn setup_node(
 		},
 	}
 
+	if let Some(hrn_resolver) = &config.hrn_resolver {
+		builder.set_custom_hrn_resolver(Arc::clone(hrn_resolver));
+	}
+
 	if let Some(seed) = seed_bytes {
 		#[cfg(feature = "uniffi")]
 		{
@@ -494,6 +500,7 @@ pub fn open_channel(
 				funding_amount_sat,
 				None,
 				None,
+				None,
 			)
 			.unwrap();
 	} else {
@@ -504,6 +511,7 @@ pub fn open_channel(
 				funding_amount_sat,
 				None,
 				None,
+				None,
 			)
 			.unwrap();
 	}
@@ -579,6 +587,7 @@ pub(crate) fn do_channel_full_cycle<E: ElectrumApi>(
 			funding_amount_sat,
 			Some(push_msat),
 			None,
+			None,
 		)
 		.unwrap();
 
@@ -0,0 +1,46 @@
+// This file is Copyright its original authors, visible in version control history.
+//
+// This file is licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. You may not use this file except in
+// accordance with one or both of these licenses.
+
+#![cfg(all(postgres_test, feature = "postgres"))]
+
+mod common;
+
+use ldk_node::Builder;
+
+#[test]
+fn channel_full_cycle_with_postgres_store() {
+	let (bitcoind, electrsd) = common::setup_bitcoind_and_electrsd();
+	let esplora_url = format!("http://{}", electrsd.esplora_url.as_ref().unwrap());
+	let postgres_base_url = std::env::var("TEST_POSTGRES_URL").unwrap();
+
+	// Each node gets its own database, as they would otherwise share the same table.
+	println!("== Node A ==");
+	let config_a = common::random_config(true);
+	let mut builder_a = Builder::from_config(config_a.node_config);
+	builder_a.set_chain_source_esplora(esplora_url.clone(), None);
+	let node_a =
+		builder_a.build_with_postgres_store(format!("{}/ldk_node_a", postgres_base_url)).unwrap();
+	node_a.start().unwrap();
+
+	println!("\n== Node B ==");
+	let config_b = common::random_config(true);
+	let mut builder_b = Builder::from_config(config_b.node_config);
+	builder_b.set_chain_source_esplora(esplora_url.clone(), None);
+	let node_b =
+		builder_b.build_with_postgres_store(format!("{}/ldk_node_b", postgres_base_url)).unwrap();
+	node_b.start().unwrap();
+
+	common::do_channel_full_cycle(
+		node_a,
+		node_b,
+		&bitcoind.client,
+		&electrsd.client,
+		false,
+		true,
+		false,
+	);
+}
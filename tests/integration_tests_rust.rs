@@ -9,20 +9,26 @@ mod common;
 
 use common::{
 	do_channel_full_cycle, expect_channel_pending_event, expect_channel_ready_event, expect_event,
-	expect_payment_received_event, expect_payment_successful_event, generate_blocks_and_wait,
+	expect_payment_claimable_event, expect_payment_received_event, expect_payment_successful_event,
+	generate_blocks_and_wait,
 	logging::{init_log_logger, validate_log_entry, TestLogWriter},
 	open_channel, premine_and_distribute_funds, random_config, random_listening_addresses,
-	setup_bitcoind_and_electrsd, setup_builder, setup_node, setup_two_nodes, wait_for_tx,
-	TestChainSource, TestSyncStore,
+	setup_bitcoind_and_electrsd, setup_builder, setup_node, setup_two_nodes,
+	wait_for_outpoint_spend, wait_for_tx, TestChainSource, TestSyncStore,
 };
 
 use ldk_node::config::EsploraSyncConfig;
-use ldk_node::liquidity::LSPS2ServiceConfig;
+use ldk_node::hrn::HrnResolver;
+use ldk_node::io::sqlite_store::SqliteStore;
+use ldk_node::lightning_liquidity::lsps1::msgs::OrderId;
+use ldk_node::liquidity::{
+	LSPS1OrderState, LSPS1ServiceConfig, LSPS2SaleState, LSPS2ServiceConfig,
+};
 use ldk_node::payment::{
-	ConfirmationStatus, PaymentDirection, PaymentKind, PaymentStatus, QrPaymentResult,
-	SendingParameters,
+	ConfirmationStatus, ForwardFilter, PaymentDirection, PaymentExportFilter, PaymentExportFormat,
+	PaymentKind, PaymentQuery, PaymentStatus, PaymentType, QrPaymentResult, SendingParameters,
 };
-use ldk_node::{Builder, Event, NodeError};
+use ldk_node::{migrate_store, Builder, Event, Node, NodeError};
 
 use lightning::ln::channelmanager::PaymentId;
 use lightning::routing::gossip::{NodeAlias, NodeId};
@@ -30,14 +36,20 @@ use lightning::util::persist::KVStore;
 
 use lightning_invoice::{Bolt11InvoiceDescription, Description};
 
+use lightning_types::payment::{PaymentHash, PaymentPreimage};
+
 use bitcoin::address::NetworkUnchecked;
+use bitcoin::hashes::sha256::Hash as Sha256;
 use bitcoin::hashes::Hash;
 use bitcoin::Address;
 use bitcoin::Amount;
+use bitcoin::FeeRate;
 use log::LevelFilter;
 
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
 use std::str::FromStr;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 #[test]
 fn channel_full_cycle() {
@@ -134,8 +146,384 @@ fn channel_open_fails_when_funds_insufficient() {
 			120000,
 			None,
 			None,
+			None,
+		)
+	);
+}
+
+#[test]
+fn channel_open_with_external_funding() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+
+	let esplora_url = format!("http://{}", electrsd.esplora_url.as_ref().unwrap());
+	let sync_config = EsploraSyncConfig { background_sync_config: None };
+
+	let mut config_a = random_config(true);
+	config_a.node_config.use_external_channel_funding = true;
+	let store_a: Arc<dyn KVStore + Sync + Send> =
+		Arc::new(TestSyncStore::new(config_a.node_config.storage_dir_path.clone().into()));
+	setup_builder!(builder_a, config_a.node_config);
+	builder_a.set_chain_source_esplora(esplora_url.clone(), Some(sync_config));
+	let node_a = builder_a.build_with_store(Arc::clone(&store_a)).unwrap();
+	node_a.start().unwrap();
+	let node_b = setup_node(&chain_source, random_config(true), None);
+
+	// Our on-chain wallet only needs to cover the Anchor reserve.
+	let addr_a = node_a.onchain_payment().new_address().unwrap();
+	let premine_amount_sat = 100_000;
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![addr_a],
+		Amount::from_sat(premine_amount_sat),
+	);
+	node_a.sync_wallets().unwrap();
+
+	let funding_amount_sat = 1_000_000;
+	let user_channel_id = node_a
+		.open_channel(
+			node_b.node_id(),
+			node_b.listening_addresses().unwrap().first().unwrap().clone(),
+			funding_amount_sat,
+			None,
+			None,
+			None,
+		)
+		.unwrap();
+
+	let unsigned_psbt = match node_a.wait_next_event() {
+		Event::FundingPsbtReady {
+			user_channel_id: event_user_channel_id,
+			counterparty_node_id,
+			channel_value_sats,
+			psbt,
+		} => {
+			assert_eq!(event_user_channel_id, user_channel_id);
+			assert_eq!(counterparty_node_id, node_b.node_id());
+			assert_eq!(channel_value_sats, funding_amount_sat);
+			node_a.event_handled().unwrap();
+			psbt.0
+		},
+		e => panic!("Unexpected event: {:?}", e),
+	};
+	assert!(unsigned_psbt.unsigned_tx.input.is_empty());
+	assert_eq!(unsigned_psbt.unsigned_tx.output.len(), 1);
+
+	// The unsigned PSBT can't be used as-is.
+	assert_eq!(
+		Err(NodeError::InvalidPsbt),
+		node_a.finalize_channel_funding(&user_channel_id, unsigned_psbt.clone())
+	);
+
+	// Have bitcoind's wallet fund and sign the funding transaction.
+	let funding_output = &unsigned_psbt.unsigned_tx.output[0];
+	let funding_address =
+		Address::from_script(&funding_output.script_pubkey, bitcoin::Network::Regtest).unwrap();
+	let funded_psbt = bitcoind
+		.client
+		.wallet_create_funded_psbt(
+			vec![],
+			vec![std::collections::BTreeMap::from([(funding_address, funding_output.value)])],
+		)
+		.unwrap();
+	let funded_psbt = bitcoin::Psbt::from_str(&funded_psbt.psbt).unwrap();
+	let signed_psbt = bitcoind.client.wallet_process_psbt(&funded_psbt).unwrap();
+	let signed_psbt = bitcoin::Psbt::from_str(&signed_psbt.psbt).unwrap();
+
+	node_a.finalize_channel_funding(&user_channel_id, signed_psbt).unwrap();
+
+	let funding_txo = expect_channel_pending_event!(node_a, node_b.node_id());
+	expect_channel_pending_event!(node_b, node_a.node_id());
+	wait_for_tx(&electrsd.client, funding_txo.txid);
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+
+	expect_channel_ready_event!(node_a, node_b.node_id());
+	expect_channel_ready_event!(node_b, node_a.node_id());
+
+	// The channel was funded without touching our on-chain wallet.
+	assert_eq!(node_a.list_balances().total_onchain_balance_sats, premine_amount_sat);
+	assert_eq!(node_a.list_channels()[0].channel_value_sats, funding_amount_sat);
+
+	// Channels still awaiting their funding transaction are abandoned on restart.
+	let user_channel_id = node_a
+		.open_channel(
+			node_b.node_id(),
+			node_b.listening_addresses().unwrap().first().unwrap().clone(),
+			funding_amount_sat,
+			None,
+			None,
+			None,
 		)
+		.unwrap();
+	let unsigned_psbt = match node_a.wait_next_event() {
+		Event::FundingPsbtReady { user_channel_id: event_user_channel_id, psbt, .. } => {
+			assert_eq!(event_user_channel_id, user_channel_id);
+			node_a.event_handled().unwrap();
+			psbt.0
+		},
+		e => panic!("Unexpected event: {:?}", e),
+	};
+
+	node_a.stop().unwrap();
+	drop(node_a);
+
+	setup_builder!(builder_a, config_a.node_config);
+	builder_a.set_chain_source_esplora(esplora_url.clone(), Some(sync_config));
+	let node_a = builder_a.build_with_store(Arc::clone(&store_a)).unwrap();
+	node_a.start().unwrap();
+
+	match node_a.wait_next_event() {
+		Event::ChannelClosed { user_channel_id: event_user_channel_id, .. } => {
+			assert_eq!(event_user_channel_id, user_channel_id);
+			node_a.event_handled().unwrap();
+		},
+		e => panic!("Unexpected event: {:?}", e),
+	}
+	assert_eq!(
+		Err(NodeError::InvalidChannelId),
+		node_a.finalize_channel_funding(&user_channel_id, unsigned_psbt)
+	);
+	assert_eq!(node_a.list_channels().len(), 1);
+
+	node_a.stop().unwrap();
+	node_b.stop().unwrap();
+}
+
+#[test]
+fn hold_invoice_expiry_watchdog() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+	let (node_a, node_b) = setup_two_nodes(&chain_source, false, true, false);
+
+	let addr_a = node_a.onchain_payment().new_address().unwrap();
+	let addr_b = node_b.onchain_payment().new_address().unwrap();
+	let premine_amount_sat = 2_125_000;
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![addr_a, addr_b],
+		Amount::from_sat(premine_amount_sat),
+	);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+
+	open_channel(&node_a, &node_b, 1_000_000, false, &electrsd);
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+	expect_channel_ready_event!(node_a, node_b.node_id());
+	expect_channel_ready_event!(node_b, node_a.node_id());
+
+	let invoice_description =
+		Bolt11InvoiceDescription::Direct(Description::new(String::from("asdf")).unwrap());
+	let amount_msat = 2_500_000;
+	let preimage = PaymentPreimage([42u8; 32]);
+	let payment_hash = PaymentHash(Sha256::hash(&preimage.0).to_byte_array());
+	let invoice = node_b
+		.bolt11_payment()
+		.receive_for_hash(amount_msat, &invoice_description.into(), 9217, payment_hash)
+		.unwrap();
+	let payment_id = node_a.bolt11_payment().send(&invoice, None).unwrap();
+	expect_payment_claimable_event!(node_b, payment_id, payment_hash, amount_msat);
+
+	// The payment is now held until we either claim or fail it.
+	let held_payments = node_b.bolt11_payment().list_held_payments();
+	assert_eq!(held_payments.len(), 1);
+	assert_eq!(held_payments[0].id, payment_id);
+	assert_eq!(held_payments[0].status, PaymentStatus::Held);
+	let claim_deadline = match held_payments[0].kind {
+		PaymentKind::Bolt11 { claim_deadline, .. } => claim_deadline.unwrap(),
+		ref kind => panic!("Unexpected payment kind: {:?}", kind),
+	};
+
+	// Approach the deadline, which should have us warn the user before failing back.
+	let cancel_height = claim_deadline - node_b.config().hold_invoice_cancel_margin_blocks;
+	let cur_height = node_b.status().current_best_block.height;
+	generate_blocks_and_wait(
+		&bitcoind.client,
+		&electrsd.client,
+		(cancel_height - cur_height - 1) as usize,
+	);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+	match node_b.wait_next_event() {
+		Event::PaymentHoldExpiring {
+			payment_id: expiring_payment_id,
+			claim_deadline: expiring_claim_deadline,
+			cancel_height: expiring_cancel_height,
+			..
+		} => {
+			assert_eq!(expiring_payment_id, payment_id);
+			assert_eq!(expiring_claim_deadline, claim_deadline);
+			assert_eq!(expiring_cancel_height, cancel_height);
+			node_b.event_handled().unwrap();
+		},
+		e => panic!("Unexpected event: {:?}", e),
+	}
+
+	// Once the cancel height is reached, the payment is failed back automatically.
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 1);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+	expect_event!(node_a, PaymentFailed);
+	assert_eq!(node_a.payment(&payment_id).unwrap().status, PaymentStatus::Failed);
+	assert_eq!(node_b.payment(&payment_id).unwrap().status, PaymentStatus::Failed);
+	assert!(node_b.bolt11_payment().list_held_payments().is_empty());
+	assert_eq!(
+		node_b.bolt11_payment().claim_for_hash(payment_hash, amount_msat, preimage),
+		Err(NodeError::InvalidPaymentHash)
+	);
+}
+
+#[test]
+fn channel_backup_restore() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+	let seed_bytes = vec![42u8; 64];
+	let config_a = random_config(true);
+	let node_a = setup_node(&chain_source, config_a, Some(seed_bytes.clone()));
+	let node_b = setup_node(&chain_source, random_config(true), None);
+
+	let addr_a = node_a.onchain_payment().new_address().unwrap();
+	let addr_b = node_b.onchain_payment().new_address().unwrap();
+	let premine_amount_sat = 2_125_000;
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![addr_a, addr_b],
+		Amount::from_sat(premine_amount_sat),
+	);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+
+	let funding_amount_sat = 1_000_000;
+	open_channel(&node_a, &node_b, funding_amount_sat, false, &electrsd);
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+	expect_channel_ready_event!(node_a, node_b.node_id());
+	expect_channel_ready_event!(node_b, node_a.node_id());
+	let funding_txo = node_b.list_channels()[0].funding_txo.unwrap();
+
+	// Export a backup and lose node A's storage.
+	let backup = node_a.export_channel_backup();
+	node_a.stop().unwrap();
+	drop(node_a);
+
+	// Restoring into a fresh store has node B force-close the channel upon reconnection.
+	let config_restored = random_config(true);
+	let esplora_url = format!("http://{}", electrsd.esplora_url.as_ref().unwrap());
+	setup_builder!(builder, config_restored.node_config);
+	builder.set_chain_source_esplora(esplora_url, None);
+	#[cfg(feature = "uniffi")]
+	builder.set_entropy_seed_bytes(seed_bytes).unwrap();
+	#[cfg(not(feature = "uniffi"))]
+	builder.set_entropy_seed_bytes(seed_bytes.try_into().unwrap());
+	builder.restore_from_channel_backup(backup);
+	let test_sync_store =
+		Arc::new(TestSyncStore::new(config_restored.node_config.storage_dir_path.into()));
+	let restored_node = builder.build_with_store(test_sync_store).unwrap();
+	restored_node.start().unwrap();
+
+	expect_event!(node_b, ChannelClosed);
+	wait_for_outpoint_spend(&electrsd.client, funding_txo);
+
+	// Once confirmed, the restored node sweeps its channel balance back to the on-chain wallet.
+	for _ in 0..3 {
+		generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+		restored_node.sync_wallets().unwrap();
+		node_b.sync_wallets().unwrap();
+	}
+	assert!(restored_node.list_balances().lightning_balances.is_empty());
+	assert!(
+		restored_node.list_balances().spendable_onchain_balance_sats
+			> premine_amount_sat - funding_amount_sat
+	);
+}
+
+#[test]
+fn channel_open_with_manual_acceptance() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+
+	let node_a = setup_node(&chain_source, random_config(true), None);
+	let mut config_b = random_config(true);
+	config_b.node_config.channel_acceptance_policy.min_funding_sats = Some(50_000);
+	config_b.node_config.channel_acceptance_policy.manual_acceptance = true;
+	let node_b = setup_node(&chain_source, config_b, None);
+
+	let addr_a = node_a.onchain_payment().new_address().unwrap();
+	let addr_b = node_b.onchain_payment().new_address().unwrap();
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![addr_a, addr_b],
+		Amount::from_sat(2_100_000),
 	);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+
+	let node_b_addr = node_b.listening_addresses().unwrap().first().unwrap().clone();
+
+	// Requests violating the policy are rejected without being surfaced.
+	node_a.open_channel(node_b.node_id(), node_b_addr.clone(), 40_000, None, None, None).unwrap();
+	expect_event!(node_a, ChannelClosed);
+
+	// Requests passing the policy may be rejected manually.
+	let funding_amount_sat = 1_000_000;
+	node_a
+		.open_channel(node_b.node_id(), node_b_addr.clone(), funding_amount_sat, None, None, None)
+		.unwrap();
+	let temporary_channel_id = match node_b.wait_next_event() {
+		Event::InboundChannelRequest {
+			temporary_channel_id,
+			counterparty_node_id,
+			funding_sats,
+			push_msat,
+			is_announced,
+		} => {
+			assert_eq!(counterparty_node_id, node_a.node_id());
+			assert_eq!(funding_sats, funding_amount_sat);
+			assert_eq!(push_msat, 0);
+			assert!(!is_announced);
+			node_b.event_handled().unwrap();
+			temporary_channel_id
+		},
+		e => panic!("Unexpected event: {:?}", e),
+	};
+	node_b.reject_inbound_channel(&temporary_channel_id, node_a.node_id()).unwrap();
+	expect_event!(node_a, ChannelClosed);
+
+	// ... or be accepted.
+	let user_channel_id_a = node_a
+		.open_channel(node_b.node_id(), node_b_addr, funding_amount_sat, None, None, None)
+		.unwrap();
+	let temporary_channel_id = match node_b.wait_next_event() {
+		Event::InboundChannelRequest { temporary_channel_id, counterparty_node_id, .. } => {
+			assert_eq!(counterparty_node_id, node_a.node_id());
+			node_b.event_handled().unwrap();
+			temporary_channel_id
+		},
+		e => panic!("Unexpected event: {:?}", e),
+	};
+	let user_channel_id_b =
+		node_b.accept_inbound_channel(&temporary_channel_id, node_a.node_id()).unwrap();
+
+	let funding_txo = expect_channel_pending_event!(node_a, node_b.node_id());
+	expect_channel_pending_event!(node_b, node_a.node_id());
+	wait_for_tx(&electrsd.client, funding_txo.txid);
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+
+	assert_eq!(expect_channel_ready_event!(node_a, node_b.node_id()), user_channel_id_a);
+	assert_eq!(expect_channel_ready_event!(node_b, node_a.node_id()), user_channel_id_b);
+
+	node_a.stop().unwrap();
+	node_b.stop().unwrap();
 }
 
 #[test]
@@ -233,53 +621,375 @@ fn multi_hop_sending() {
 	let payment_id = expect_payment_received_event!(&nodes[4], 2_500_000);
 	let fee_paid_msat = Some(2000);
 	expect_payment_successful_event!(nodes[0], payment_id, Some(fee_paid_msat));
+
+	// The forward shows up in the exported payment history of the forwarding node.
+	let export = nodes[1].export_payments(PaymentExportFormat::Csv, PaymentExportFilter::default());
+	let forwards = export.lines().filter(|l| l.starts_with("forward,")).collect::<Vec<_>>();
+	assert_eq!(forwards.len(), 1);
+	let forward_fields = forwards[0].split(',').collect::<Vec<_>>();
+	assert_eq!(forward_fields[4], "succeeded");
+	assert_eq!(forward_fields[6], "1000");
+
+	// The forward is persisted and can be queried, including per channel.
+	let forwards = nodes[1].list_forwards(ForwardFilter::default());
+	assert_eq!(forwards.len(), 1);
+	assert_eq!(forwards[0].total_fee_earned_msat, Some(1000));
+	let next_channel_id = forwards[0].next_channel_id;
+	let channel_filter = ForwardFilter { channel_id: Some(next_channel_id), ..Default::default() };
+	assert_eq!(nodes[1].list_forwards(channel_filter.clone()), forwards);
+
+	let summary = nodes[1].forwarding_summary(channel_filter);
+	assert_eq!(summary.forward_count, 1);
+	assert_eq!(summary.total_fee_earned_msat, 1000);
+	assert_eq!(summary.channels[0].channel_id, next_channel_id);
+	assert_eq!(summary.channels[0].fee_earned_msat, 1000);
+
+	let future_filter = ForwardFilter {
+		start_timestamp: Some(forwards[0].forwarded_at_timestamp + 1),
+		..Default::default()
+	};
+	assert!(nodes[1].list_forwards(future_filter).is_empty());
 }
 
 #[test]
-fn start_stop_reinit() {
+fn probe_and_payment_path_events() {
 	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
-	let config = random_config(true);
+	let chain_source = TestChainSource::Esplora(&electrsd);
 
-	let esplora_url = format!("http://{}", electrsd.esplora_url.as_ref().unwrap());
+	let mut config_a = random_config(true);
+	config_a.node_config.emit_payment_path_events = true;
+	let node_a = setup_node(&chain_source, config_a, None);
+	let node_b = setup_node(&chain_source, random_config(true), None);
+	let node_c = setup_node(&chain_source, random_config(true), None);
 
-	let test_sync_store: Arc<dyn KVStore + Sync + Send> =
-		Arc::new(TestSyncStore::new(config.node_config.storage_dir_path.clone().into()));
+	let addresses = vec![
+		node_a.onchain_payment().new_address().unwrap(),
+		node_b.onchain_payment().new_address().unwrap(),
+	];
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		addresses,
+		Amount::from_sat(2_100_000),
+	);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
 
-	let sync_config = EsploraSyncConfig { background_sync_config: None };
-	setup_builder!(builder, config.node_config);
-	builder.set_chain_source_esplora(esplora_url.clone(), Some(sync_config));
+	// Probes are only sent along paths with at least two hops, i.e., A -> B -> C.
+	open_channel(&node_a, &node_b, 1_000_000, true, &electrsd);
+	open_channel(&node_b, &node_c, 1_000_000, true, &electrsd);
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+	node_c.sync_wallets().unwrap();
 
-	let node = builder.build_with_store(Arc::clone(&test_sync_store)).unwrap();
-	node.start().unwrap();
+	expect_event!(node_a, ChannelReady);
+	expect_event!(node_b, ChannelReady);
+	expect_event!(node_b, ChannelReady);
+	expect_event!(node_c, ChannelReady);
 
-	let expected_node_id = node.node_id();
-	assert_eq!(node.start(), Err(NodeError::AlreadyRunning));
+	// Sleep a bit for gossip to propagate.
+	std::thread::sleep(std::time::Duration::from_secs(1));
 
-	let funding_address = node.onchain_payment().new_address().unwrap();
+	let amount_msat = 100_000_000;
+	let invoice_description =
+		Bolt11InvoiceDescription::Direct(Description::new(String::from("asdf")).unwrap());
+	let invoice =
+		node_c.bolt11_payment().receive(amount_msat, &invoice_description.into(), 9217).unwrap();
+
+	node_a.bolt11_payment().send_probes(&invoice).unwrap();
+	match node_a.wait_next_event() {
+		Event::ProbeSucceeded { hops, amount_msat: probed_amount_msat, .. } => {
+			assert_eq!(hops.len(), 2);
+			assert_eq!(hops[0].node_id, node_b.node_id());
+			assert_eq!(hops[1].node_id, node_c.node_id());
+			assert_eq!(probed_amount_msat, amount_msat);
+			node_a.event_handled().unwrap();
+		},
+		e => panic!("Unexpected event: {:?}", e),
+	}
 
-	assert_eq!(node.list_balances().total_onchain_balance_sats, 0);
+	let payment_id = node_a.bolt11_payment().send(&invoice, None).unwrap();
+	expect_event!(node_b, PaymentForwarded);
+	expect_payment_received_event!(node_c, amount_msat);
+	expect_payment_successful_event!(node_a, Some(payment_id), None::<Option<u64>>);
+
+	// As we opted into path events, we're also informed about the successful path.
+	let fee_paid_msat = node_a.payment(&payment_id).unwrap().fee_paid_msat;
+	match node_a.wait_next_event() {
+		Event::PaymentPathSuccessful {
+			payment_id: path_payment_id,
+			hops,
+			amount_msat: path_amount_msat,
+			fee_msat,
+			..
+		} => {
+			assert_eq!(path_payment_id, payment_id);
+			assert_eq!(hops.len(), 2);
+			assert_eq!(hops[1].node_id, node_c.node_id());
+			assert_eq!(path_amount_msat, amount_msat);
+			assert_eq!(Some(fee_msat), fee_paid_msat);
+			node_a.event_handled().unwrap();
+		},
+		e => panic!("Unexpected event: {:?}", e),
+	}
 
-	let expected_amount = Amount::from_sat(100000);
+	node_a.stop().unwrap();
+	node_b.stop().unwrap();
+	node_c.stop().unwrap();
+}
+
+#[test]
+fn estimate_payment_fees() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+
+	let node_a = setup_node(&chain_source, random_config(true), None);
+	let node_b = setup_node(&chain_source, random_config(true), None);
+	let node_c = setup_node(&chain_source, random_config(true), None);
+
+	let addresses = vec![
+		node_a.onchain_payment().new_address().unwrap(),
+		node_b.onchain_payment().new_address().unwrap(),
+	];
 	premine_and_distribute_funds(
 		&bitcoind.client,
 		&electrsd.client,
-		vec![funding_address],
-		expected_amount,
+		addresses,
+		Amount::from_sat(2_100_000),
 	);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
 
-	node.sync_wallets().unwrap();
-	assert_eq!(node.list_balances().spendable_onchain_balance_sats, expected_amount.to_sat());
+	open_channel(&node_a, &node_b, 1_000_000, true, &electrsd);
+	open_channel(&node_b, &node_c, 1_000_000, true, &electrsd);
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+	node_c.sync_wallets().unwrap();
 
-	let log_file = format!("{}/ldk_node.log", config.node_config.clone().storage_dir_path);
-	assert!(std::path::Path::new(&log_file).exists());
+	expect_event!(node_a, ChannelReady);
+	expect_event!(node_b, ChannelReady);
+	expect_event!(node_b, ChannelReady);
+	expect_event!(node_c, ChannelReady);
 
-	node.stop().unwrap();
-	assert_eq!(node.stop(), Err(NodeError::NotRunning));
+	// Sleep a bit for gossip to propagate.
+	std::thread::sleep(std::time::Duration::from_secs(1));
 
-	node.start().unwrap();
-	assert_eq!(node.start(), Err(NodeError::AlreadyRunning));
+	let amount_msat = 100_000_000;
+	let invoice_description =
+		Bolt11InvoiceDescription::Direct(Description::new(String::from("asdf")).unwrap());
+	let invoice =
+		node_c.bolt11_payment().receive(amount_msat, &invoice_description.into(), 9217).unwrap();
+
+	let estimate = node_a.bolt11_payment().estimate_fee(&invoice, None, None).unwrap();
+	assert_eq!(estimate.paths.len(), 1);
+	assert_eq!(estimate.paths[0].hops.len(), 2);
+	assert_eq!(estimate.paths[0].hops[1].node_id, node_c.node_id());
+	assert_eq!(estimate.paths[0].amount_msat, amount_msat);
+	assert!(estimate.total_fee_msat > 0);
+	assert!(estimate.success_probability > 0.0 && estimate.success_probability <= 1.0);
+
+	// Paying less than the invoice amount is rejected.
+	assert_eq!(
+		Err(NodeError::InvalidAmount),
+		node_a.bolt11_payment().estimate_fee(&invoice, Some(amount_msat - 1), None)
+	);
 
-	node.stop().unwrap();
+	// A fee limit below the estimated fee leaves us without a route.
+	let sending_params = SendingParameters {
+		max_total_routing_fee_msat: Some(Some(estimate.total_fee_msat - 1)),
+		max_total_cltv_expiry_delta: None,
+		max_path_count: None,
+		max_channel_saturation_power_of_half: None,
+	};
+	assert_eq!(
+		Err(NodeError::RouteNotFound),
+		node_a.bolt11_payment().estimate_fee(&invoice, None, Some(sending_params))
+	);
+
+	let keysend_estimate =
+		node_a.spontaneous_payment().estimate_fee(amount_msat, node_c.node_id(), None).unwrap();
+	assert_eq!(keysend_estimate.total_fee_msat, estimate.total_fee_msat);
+
+	// For offers we estimate the fees of reaching the introduction node of the offer's blinded
+	// paths, i.e., node_b, as the offer's signing key isn't known to the network.
+	let offer = node_c.bolt12_payment().receive(amount_msat, "asdf", None, None).unwrap();
+	let offer_estimate = node_a.bolt12_payment().estimate_fee(&offer, None).unwrap();
+	assert_eq!(offer_estimate.paths.len(), 1);
+	assert_eq!(offer_estimate.paths[0].hops.last().unwrap().node_id, node_b.node_id());
+	assert_eq!(offer_estimate.paths[0].amount_msat, amount_msat);
+	assert_eq!(
+		Err(NodeError::InvalidAmount),
+		node_a.bolt12_payment().estimate_fee(&offer, Some(amount_msat - 1))
+	);
+
+	// Estimating doesn't send anything, so paying still succeeds.
+	let payment_id = node_a.bolt11_payment().send(&invoice, None).unwrap();
+	expect_event!(node_b, PaymentForwarded);
+	expect_payment_received_event!(node_c, amount_msat);
+	expect_payment_successful_event!(node_a, Some(payment_id), None::<Option<u64>>);
+	assert_eq!(node_a.payment(&payment_id).unwrap().fee_paid_msat, Some(estimate.total_fee_msat));
+
+	node_a.stop().unwrap();
+	node_b.stop().unwrap();
+	node_c.stop().unwrap();
+}
+
+#[test]
+fn rebalance_between_own_channels() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+
+	let node_a = setup_node(&chain_source, random_config(true), None);
+	let node_b = setup_node(&chain_source, random_config(true), None);
+	let node_c = setup_node(&chain_source, random_config(true), None);
+
+	let addresses = vec![
+		node_a.onchain_payment().new_address().unwrap(),
+		node_b.onchain_payment().new_address().unwrap(),
+		node_c.onchain_payment().new_address().unwrap(),
+	];
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		addresses,
+		Amount::from_sat(2_100_000),
+	);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+	node_c.sync_wallets().unwrap();
+
+	// Build a cycle A -> B -> C -> A, leaving A with all the liquidity on the first and none of
+	// it on the last channel.
+	open_channel(&node_a, &node_b, 1_000_000, true, &electrsd);
+	open_channel(&node_b, &node_c, 1_000_000, true, &electrsd);
+	open_channel(&node_c, &node_a, 1_000_000, true, &electrsd);
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+	node_c.sync_wallets().unwrap();
+
+	expect_event!(node_a, ChannelReady);
+	expect_event!(node_a, ChannelReady);
+	expect_event!(node_b, ChannelReady);
+	expect_event!(node_b, ChannelReady);
+	expect_event!(node_c, ChannelReady);
+	expect_event!(node_c, ChannelReady);
+
+	// Sleep a bit for gossip to propagate.
+	std::thread::sleep(std::time::Duration::from_secs(1));
+
+	let channel_to_b = node_a
+		.list_channels()
+		.into_iter()
+		.find(|c| c.counterparty_node_id == node_b.node_id())
+		.unwrap();
+	let channel_to_c = node_a
+		.list_channels()
+		.into_iter()
+		.find(|c| c.counterparty_node_id == node_c.node_id())
+		.unwrap();
+
+	let amount_msat = 100_000_000;
+	assert_eq!(
+		Err(NodeError::InvalidChannelId),
+		node_a.rebalance(channel_to_b.channel_id, channel_to_b.channel_id, amount_msat, 10_000)
+	);
+	assert_eq!(
+		Err(NodeError::RouteNotFound),
+		node_a.rebalance(channel_to_b.channel_id, channel_to_c.channel_id, amount_msat, 0)
+	);
+
+	let payment_id = node_a
+		.rebalance(channel_to_b.channel_id, channel_to_c.channel_id, amount_msat, 1_000_000)
+		.unwrap();
+	expect_event!(node_b, PaymentForwarded);
+	expect_event!(node_c, PaymentForwarded);
+	expect_payment_successful_event!(node_a, Some(payment_id), None::<Option<u64>>);
+
+	let payment = node_a.payment(&payment_id).unwrap();
+	assert_eq!(payment.status, PaymentStatus::Succeeded);
+	assert_eq!(payment.direction, PaymentDirection::Outbound);
+	assert_eq!(payment.amount_msat, Some(amount_msat));
+	let fee_paid_msat = payment.fee_paid_msat.unwrap();
+	assert!(fee_paid_msat > 0 && fee_paid_msat <= 1_000_000);
+	assert!(matches!(
+		payment.kind,
+		PaymentKind::Rebalance { from_channel_id, to_channel_id, .. }
+			if from_channel_id == channel_to_b.channel_id && to_channel_id == channel_to_c.channel_id
+	));
+
+	// The liquidity moved from the channel with B to the one with C.
+	let new_channel_to_b = node_a
+		.list_channels()
+		.into_iter()
+		.find(|c| c.counterparty_node_id == node_b.node_id())
+		.unwrap();
+	let new_channel_to_c = node_a
+		.list_channels()
+		.into_iter()
+		.find(|c| c.counterparty_node_id == node_c.node_id())
+		.unwrap();
+	assert_eq!(
+		new_channel_to_b.outbound_capacity_msat,
+		channel_to_b.outbound_capacity_msat - amount_msat - fee_paid_msat
+	);
+	assert!(new_channel_to_c.outbound_capacity_msat > channel_to_c.outbound_capacity_msat);
+
+	// The returning payment isn't reported as received.
+	assert!(node_a.next_event().is_none());
+
+	node_a.stop().unwrap();
+	node_b.stop().unwrap();
+	node_c.stop().unwrap();
+}
+
+#[test]
+fn start_stop_reinit() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let config = random_config(true);
+
+	let esplora_url = format!("http://{}", electrsd.esplora_url.as_ref().unwrap());
+
+	let test_sync_store: Arc<dyn KVStore + Sync + Send> =
+		Arc::new(TestSyncStore::new(config.node_config.storage_dir_path.clone().into()));
+
+	let sync_config = EsploraSyncConfig { background_sync_config: None };
+	setup_builder!(builder, config.node_config);
+	builder.set_chain_source_esplora(esplora_url.clone(), Some(sync_config));
+
+	let node = builder.build_with_store(Arc::clone(&test_sync_store)).unwrap();
+	node.start().unwrap();
+
+	let expected_node_id = node.node_id();
+	assert_eq!(node.start(), Err(NodeError::AlreadyRunning));
+
+	let funding_address = node.onchain_payment().new_address().unwrap();
+
+	assert_eq!(node.list_balances().total_onchain_balance_sats, 0);
+
+	let expected_amount = Amount::from_sat(100000);
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![funding_address],
+		expected_amount,
+	);
+
+	node.sync_wallets().unwrap();
+	assert_eq!(node.list_balances().spendable_onchain_balance_sats, expected_amount.to_sat());
+
+	let log_file = format!("{}/ldk_node.log", config.node_config.clone().storage_dir_path);
+	assert!(std::path::Path::new(&log_file).exists());
+
+	node.stop().unwrap();
+	assert_eq!(node.stop(), Err(NodeError::NotRunning));
+
+	node.start().unwrap();
+	assert_eq!(node.start(), Err(NodeError::AlreadyRunning));
+
+	node.stop().unwrap();
 	assert_eq!(node.stop(), Err(NodeError::NotRunning));
 	drop(node);
 
@@ -304,6 +1014,63 @@ fn start_stop_reinit() {
 	reinitialized_node.stop().unwrap();
 }
 
+#[test]
+fn migrate_store_to_different_backend() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let config = random_config(true);
+
+	let esplora_url = format!("http://{}", electrsd.esplora_url.as_ref().unwrap());
+
+	let source_store: Arc<dyn KVStore + Sync + Send> =
+		Arc::new(TestSyncStore::new(config.node_config.storage_dir_path.clone().into()));
+
+	let sync_config = EsploraSyncConfig { background_sync_config: None };
+	setup_builder!(builder, config.node_config);
+	builder.set_chain_source_esplora(esplora_url.clone(), Some(sync_config));
+
+	let node = builder.build_with_store(Arc::clone(&source_store)).unwrap();
+	node.start().unwrap();
+	let expected_node_id = node.node_id();
+
+	let funding_address = node.onchain_payment().new_address().unwrap();
+	let expected_amount = Amount::from_sat(100000);
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![funding_address],
+		expected_amount,
+	);
+	node.sync_wallets().unwrap();
+	assert_eq!(node.list_balances().spendable_onchain_balance_sats, expected_amount.to_sat());
+
+	let mut destination_path = std::path::PathBuf::from(&config.node_config.storage_dir_path);
+	destination_path.push("migrated");
+	let destination_store: Arc<dyn KVStore + Sync + Send> =
+		Arc::new(SqliteStore::new(destination_path, None, None).unwrap());
+
+	// We refuse to migrate while the node is running.
+	assert_eq!(
+		migrate_store(Arc::clone(&source_store), Arc::clone(&destination_store)),
+		Err(NodeError::StoreInUse)
+	);
+
+	node.stop().unwrap();
+	drop(node);
+
+	migrate_store(Arc::clone(&source_store), Arc::clone(&destination_store)).unwrap();
+
+	setup_builder!(builder, config.node_config);
+	builder.set_chain_source_esplora(esplora_url.clone(), Some(sync_config));
+	let migrated_node = builder.build_with_store(Arc::clone(&destination_store)).unwrap();
+	migrated_node.start().unwrap();
+	assert_eq!(migrated_node.node_id(), expected_node_id);
+	assert_eq!(
+		migrated_node.list_balances().spendable_onchain_balance_sats,
+		expected_amount.to_sat()
+	);
+	migrated_node.stop().unwrap();
+}
+
 #[test]
 fn onchain_send_receive() {
 	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
@@ -365,6 +1132,19 @@ fn onchain_send_receive() {
 		node_b.list_payments_with_filter(|p| matches!(p.kind, PaymentKind::Onchain { .. }));
 	assert_eq!(node_b_payments.len(), 2);
 
+	let onchain_query =
+		PaymentQuery { payment_type: Some(PaymentType::Onchain), ..Default::default() };
+	let first_page = node_b.list_payments_page(onchain_query.clone(), None, 1);
+	assert_eq!(first_page.payments.len(), 1);
+	let second_page = node_b.list_payments_page(onchain_query, first_page.next_cursor, 1);
+	assert_eq!(second_page.payments.len(), 1);
+	assert!(second_page.next_cursor.is_none());
+	assert_ne!(first_page.payments[0].id, second_page.payments[0].id);
+	assert!(
+		first_page.payments[0].latest_update_timestamp
+			>= second_page.payments[0].latest_update_timestamp
+	);
+
 	let onchain_fee_buffer_sat = 1000;
 	let expected_node_a_balance = premine_amount_sat - reserve_amount_sat;
 	let expected_node_b_balance_lower =
@@ -377,12 +1157,12 @@ fn onchain_send_receive() {
 
 	assert_eq!(
 		Err(NodeError::InsufficientFunds),
-		node_a.onchain_payment().send_to_address(&addr_b, expected_node_a_balance + 1, None)
+		node_a.onchain_payment().send_to_address(&addr_b, expected_node_a_balance + 1, None, None)
 	);
 
 	assert_eq!(
 		Err(NodeError::InvalidAddress),
-		node_a.onchain_payment().send_to_address(&addr_c, expected_node_a_balance + 1, None)
+		node_a.onchain_payment().send_to_address(&addr_c, expected_node_a_balance + 1, None, None)
 	);
 
 	assert_eq!(
@@ -392,7 +1172,7 @@ fn onchain_send_receive() {
 
 	let amount_to_send_sats = 54321;
 	let txid =
-		node_b.onchain_payment().send_to_address(&addr_a, amount_to_send_sats, None).unwrap();
+		node_b.onchain_payment().send_to_address(&addr_a, amount_to_send_sats, None, None).unwrap();
 	wait_for_tx(&electrsd.client, txid);
 	node_a.sync_wallets().unwrap();
 	node_b.sync_wallets().unwrap();
@@ -440,7 +1220,7 @@ fn onchain_send_receive() {
 
 	let payment_a = node_a.payment(&payment_id).unwrap();
 	match payment_a.kind {
-		PaymentKind::Onchain { txid: _txid, status } => {
+		PaymentKind::Onchain { txid: _txid, status, .. } => {
 			assert_eq!(_txid, txid);
 			assert!(matches!(status, ConfirmationStatus::Confirmed { .. }));
 		},
@@ -449,7 +1229,7 @@ fn onchain_send_receive() {
 
 	let payment_b = node_a.payment(&payment_id).unwrap();
 	match payment_b.kind {
-		PaymentKind::Onchain { txid: _txid, status } => {
+		PaymentKind::Onchain { txid: _txid, status, .. } => {
 			assert_eq!(_txid, txid);
 			assert!(matches!(status, ConfirmationStatus::Confirmed { .. }));
 		},
@@ -502,6 +1282,41 @@ fn onchain_send_receive() {
 	let node_b_payments =
 		node_b.list_payments_with_filter(|p| matches!(p.kind, PaymentKind::Onchain { .. }));
 	assert_eq!(node_b_payments.len(), 5);
+
+	// Check succeeded payments can be archived and are still retrievable afterwards.
+	let num_payments = node_a.list_payments().len();
+	let num_archived = node_a.prune_payments(u64::MAX, vec![PaymentStatus::Succeeded]).unwrap();
+	assert!(num_archived > 0);
+	let archived_payments = node_a.list_archived_payments();
+	assert_eq!(archived_payments.len() as u64, num_archived);
+	assert!(archived_payments.iter().all(|p| p.status == PaymentStatus::Succeeded));
+	assert_eq!(node_a.list_payments().len() + archived_payments.len(), num_payments);
+	let archived_id = archived_payments[0].id;
+	assert!(node_a.payment(&archived_id).is_none());
+	assert_eq!(node_a.archived_payment(&archived_id), Some(archived_payments[0].clone()));
+
+	// Check archived payments are still included in the exported payment history.
+	let export = node_a.export_payments(PaymentExportFormat::Csv, PaymentExportFilter::default());
+	let num_exported_payments =
+		export.lines().filter(|l| l.starts_with("payment,") || l.starts_with("sweep,")).count();
+	assert_eq!(num_exported_payments, num_payments);
+	let archived_id_hex = archived_id.0.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+	assert!(export.contains(&archived_id_hex));
+
+	// Check archived on-chain payments stay archived when syncing the wallet.
+	let archived_onchain_payment = archived_payments
+		.iter()
+		.find(|p| matches!(p.kind, PaymentKind::Onchain { .. }))
+		.expect("on-chain payments should have been archived")
+		.clone();
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 1);
+	node_a.sync_wallets().unwrap();
+	assert!(node_a.payment(&archived_onchain_payment.id).is_none());
+	assert_eq!(
+		node_a.archived_payment(&archived_onchain_payment.id),
+		Some(archived_onchain_payment)
+	);
+	assert_eq!(node_a.list_payments().len() + archived_payments.len(), num_payments);
 }
 
 #[test]
@@ -587,6 +1402,190 @@ fn onchain_send_all_retains_reserve() {
 		.contains(&node_a.list_balances().spendable_onchain_balance_sats));
 }
 
+#[test]
+fn onchain_fee_bumping() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+	let (node_a, node_b) = setup_two_nodes(&chain_source, false, true, false);
+
+	let addr_a = node_a.onchain_payment().new_address().unwrap();
+	let addr_b = node_b.onchain_payment().new_address().unwrap();
+
+	let premine_amount_sat = 1_000_000;
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![addr_a.clone()],
+		Amount::from_sat(premine_amount_sat),
+	);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+
+	// Send a low-fee transaction and replace it via RBF.
+	let amount_to_send_sats = 100_000;
+	let low_fee_rate = FeeRate::from_sat_per_vb(2).unwrap();
+	let original_txid = node_a
+		.onchain_payment()
+		.send_to_address(&addr_b, amount_to_send_sats, Some(low_fee_rate), None)
+		.unwrap();
+	wait_for_tx(&electrsd.client, original_txid);
+	node_a.sync_wallets().unwrap();
+
+	assert_eq!(
+		Err(NodeError::InvalidFeeRate),
+		node_a.onchain_payment().bump_fee_rbf(original_txid, low_fee_rate)
+	);
+
+	let payment_id = PaymentId(original_txid.to_byte_array());
+	let original_fee_paid_msat = node_a.payment(&payment_id).unwrap().fee_paid_msat.unwrap();
+
+	let high_fee_rate = FeeRate::from_sat_per_vb(10).unwrap();
+	let replacement_txid =
+		node_a.onchain_payment().bump_fee_rbf(original_txid, high_fee_rate).unwrap();
+	assert_ne!(original_txid, replacement_txid);
+	wait_for_tx(&electrsd.client, replacement_txid);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+
+	let payment_a = node_a.payment(&payment_id).unwrap();
+	assert_eq!(payment_a.status, PaymentStatus::Pending);
+	assert_eq!(payment_a.amount_msat, Some(amount_to_send_sats * 1000));
+	assert!(payment_a.fee_paid_msat.unwrap() > original_fee_paid_msat);
+	match payment_a.kind {
+		PaymentKind::Onchain { txid, status, replaced_txids } => {
+			assert_eq!(txid, replacement_txid);
+			assert_eq!(replaced_txids, vec![original_txid]);
+			assert!(matches!(status, ConfirmationStatus::Unconfirmed));
+		},
+		_ => panic!("Unexpected payment kind"),
+	}
+	// We don't track the replacement as a separate payment.
+	assert!(node_a.payment(&PaymentId(replacement_txid.to_byte_array())).is_none());
+
+	// Have the receiver speed up the replacement via CPFP.
+	let cpfp_fee_rate = FeeRate::from_sat_per_vb(20).unwrap();
+	let child_txid =
+		node_b.onchain_payment().bump_fee_cpfp(replacement_txid, cpfp_fee_rate).unwrap();
+	wait_for_tx(&electrsd.client, child_txid);
+
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+
+	assert_eq!(
+		Err(NodeError::TransactionAlreadyConfirmed),
+		node_a.onchain_payment().bump_fee_rbf(replacement_txid, cpfp_fee_rate)
+	);
+
+	let payment_a = node_a.payment(&payment_id).unwrap();
+	assert_eq!(payment_a.status, PaymentStatus::Succeeded);
+	match payment_a.kind {
+		PaymentKind::Onchain { txid, status, .. } => {
+			assert_eq!(txid, replacement_txid);
+			assert!(matches!(status, ConfirmationStatus::Confirmed { .. }));
+		},
+		_ => panic!("Unexpected payment kind"),
+	}
+	assert!(node_b.list_balances().spendable_onchain_balance_sats < amount_to_send_sats);
+}
+
+#[test]
+fn onchain_coin_control() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+	let (node_a, node_b) = setup_two_nodes(&chain_source, false, true, false);
+
+	let addr_a_1 = node_a.onchain_payment().new_address().unwrap();
+	let addr_a_2 = node_a.onchain_payment().new_address().unwrap();
+	let addr_b = node_b.onchain_payment().new_address().unwrap();
+
+	let premine_amount_sat = 1_000_000;
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![addr_a_1, addr_a_2],
+		Amount::from_sat(premine_amount_sat),
+	);
+	node_a.sync_wallets().unwrap();
+
+	let utxos = node_a.onchain_payment().list_utxos();
+	assert_eq!(utxos.len(), 2);
+	assert!(utxos.iter().all(|u| u.value_sats == premine_amount_sat && !u.is_locked));
+	assert!(utxos
+		.iter()
+		.all(|u| matches!(u.confirmation_status, ConfirmationStatus::Confirmed { .. })));
+
+	// Lock the first UTXO and check it can't be selected anymore.
+	let locked_outpoint = utxos[0].outpoint;
+	let selected_outpoint = utxos[1].outpoint;
+	node_a.onchain_payment().lock_utxo(locked_outpoint).unwrap();
+	assert!(node_a
+		.onchain_payment()
+		.list_utxos()
+		.iter()
+		.any(|u| u.outpoint == locked_outpoint && u.is_locked));
+
+	let amount_to_send_sats = 100_000;
+	assert_eq!(
+		Err(NodeError::InvalidUtxo),
+		node_a.onchain_payment().send_to_address(
+			&addr_b,
+			amount_to_send_sats,
+			None,
+			Some(vec![locked_outpoint])
+		)
+	);
+
+	// Spending the locked UTXO's value should fail, as it's excluded from coin selection.
+	assert!(node_a
+		.onchain_payment()
+		.send_to_address(&addr_b, premine_amount_sat + amount_to_send_sats, None, None)
+		.is_err());
+
+	let txid = node_a
+		.onchain_payment()
+		.send_to_address(&addr_b, amount_to_send_sats, None, Some(vec![selected_outpoint]))
+		.unwrap();
+	wait_for_tx(&electrsd.client, txid);
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	node_a.sync_wallets().unwrap();
+
+	let utxos = node_a.onchain_payment().list_utxos();
+	assert!(utxos.iter().all(|u| u.outpoint != selected_outpoint));
+	assert!(utxos.iter().any(|u| u.outpoint == locked_outpoint && u.is_locked));
+
+	// The anchor channel reserve needs to be covered by UTXOs we may spend, i.e., not by the
+	// locked one.
+	open_channel(&node_a, &node_b, 500_000, false, &electrsd);
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+	expect_channel_ready_event!(node_a, node_b.node_id());
+	expect_channel_ready_event!(node_b, node_a.node_id());
+
+	let reserve_sats = node_a.list_balances().total_anchor_channels_reserve_sats;
+	assert!(reserve_sats > 0);
+	let unlocked_sats: u64 = node_a
+		.onchain_payment()
+		.list_utxos()
+		.iter()
+		.filter(|u| !u.is_locked)
+		.map(|u| u.value_sats)
+		.sum();
+	assert_eq!(
+		Err(NodeError::InsufficientFunds),
+		node_a.onchain_payment().send_to_address(
+			&addr_b,
+			unlocked_sats - reserve_sats / 2,
+			None,
+			None
+		)
+	);
+
+	node_a.onchain_payment().unlock_utxo(locked_outpoint).unwrap();
+	assert!(node_a.onchain_payment().list_utxos().iter().all(|u| !u.is_locked));
+}
+
 #[test]
 fn onchain_wallet_recovery() {
 	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
@@ -1245,6 +2244,224 @@ fn unified_qr_send_receive() {
 	assert_eq!(node_b.list_balances().total_lightning_balance_sats, 200_000);
 }
 
+struct StaticHrnResolver {
+	records: Mutex<Vec<String>>,
+	queried_names: Mutex<Vec<String>>,
+}
+
+impl HrnResolver for StaticHrnResolver {
+	fn resolve_txt_records(&self, name: String) -> Result<Vec<String>, NodeError> {
+		self.queried_names.lock().unwrap().push(name);
+		Ok(self.records.lock().unwrap().clone())
+	}
+}
+
+#[test]
+fn unified_qr_send_to_hrn() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+
+	let hrn_resolver = Arc::new(StaticHrnResolver {
+		records: Mutex::new(Vec::new()),
+		queried_names: Mutex::new(Vec::new()),
+	});
+	let mut config_a = random_config(true);
+	config_a.hrn_resolver = Some(Arc::clone(&hrn_resolver) as Arc<dyn HrnResolver>);
+	let node_a = setup_node(&chain_source, config_a, None);
+	let node_b = setup_node(&chain_source, random_config(true), None);
+
+	let address_a = node_a.onchain_payment().new_address().unwrap();
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![address_a],
+		Amount::from_sat(5_000_000),
+	);
+
+	node_a.sync_wallets().unwrap();
+	open_channel(&node_a, &node_b, 4_000_000, true, &electrsd);
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+
+	expect_channel_ready_event!(node_a, node_b.node_id());
+	expect_channel_ready_event!(node_b, node_a.node_id());
+
+	// Sleep until we broadcast a node announcement.
+	while node_b.status().latest_node_announcement_broadcast_timestamp.is_none() {
+		std::thread::sleep(std::time::Duration::from_millis(10));
+	}
+
+	// Sleep one more sec to make sure the node announcement propagates.
+	std::thread::sleep(std::time::Duration::from_secs(1));
+
+	// Publish payment instructions without any amount, as is common for human-readable names.
+	let offer = node_b.bolt12_payment().receive_variable_amount("asdf", None).unwrap();
+	let address_b = node_b.onchain_payment().new_address().unwrap();
+	let uri = format!("bitcoin:{}?lno={}", address_b, offer);
+	*hrn_resolver.records.lock().unwrap() = vec!["v=spf1 -all".to_string(), uri];
+
+	let hrn = "₿bob@example.com";
+	assert_eq!(
+		node_a.unified_qr_payment().send_to_hrn(hrn, None).unwrap_err(),
+		NodeError::InvalidAmount
+	);
+
+	let amount_msat = 50_000_000;
+	let payment_id = match node_a.unified_qr_payment().send_to_hrn(hrn, Some(amount_msat)) {
+		Ok(QrPaymentResult::Bolt12 { payment_id }) => payment_id,
+		Ok(res) => panic!("Expected Bolt12 payment but got {:?}", res),
+		Err(e) => panic!("Expected Bolt12 payment but got error: {:?}", e),
+	};
+	expect_payment_successful_event!(node_a, Some(payment_id), None);
+	assert_eq!(node_a.payment(&payment_id).unwrap().amount_msat, Some(amount_msat));
+
+	assert_eq!(
+		*hrn_resolver.queried_names.lock().unwrap(),
+		vec!["bob.user._bitcoin-payment.example.com.".to_string(); 2]
+	);
+}
+
+// Answers HTTP requests on the given listener with the response `handler` returns for each
+// request's path and query.
+fn serve_http_requests<F: Fn(&str) -> String + Send + 'static>(listener: TcpListener, handler: F) {
+	std::thread::spawn(move || {
+		for stream in listener.incoming() {
+			let mut stream = match stream {
+				Ok(stream) => stream,
+				Err(_) => continue,
+			};
+			let mut reader = BufReader::new(stream.try_clone().unwrap());
+			let mut request_line = String::new();
+			reader.read_line(&mut request_line).unwrap();
+			// Skip the headers.
+			loop {
+				let mut line = String::new();
+				if reader.read_line(&mut line).unwrap() <= 2 {
+					break;
+				}
+			}
+
+			let target = request_line.split_whitespace().nth(1).unwrap_or_default();
+			let body = handler(target);
+			let response = format!(
+				"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+				body.len(),
+				body
+			);
+			stream.write_all(response.as_bytes()).unwrap();
+		}
+	});
+}
+
+#[test]
+fn lnurl_pay_and_withdraw() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+	let chain_source = TestChainSource::Esplora(&electrsd);
+	let (node_a, node_b) = setup_two_nodes(&chain_source, false, true, false);
+	let node_b = Arc::new(node_b);
+
+	let address_a = node_a.onchain_payment().new_address().unwrap();
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![address_a],
+		Amount::from_sat(5_000_000),
+	);
+
+	node_a.sync_wallets().unwrap();
+	open_channel(&node_a, &node_b, 4_000_000, true, &electrsd);
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+
+	node_a.sync_wallets().unwrap();
+	node_b.sync_wallets().unwrap();
+
+	expect_channel_ready_event!(node_a, node_b.node_id());
+	expect_channel_ready_event!(node_b, node_a.node_id());
+
+	// Run an LNURL service for node B, which we may reach via plain `http` on a loopback address.
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let service_url = format!("http://{}", listener.local_addr().unwrap());
+	let metadata = "[[\"text/plain\",\"Pay node B\"]]";
+	let requests = Arc::new(Mutex::new(Vec::new()));
+	let withdraw_invoices = Arc::new(Mutex::new(Vec::new()));
+	{
+		let node_b = Arc::clone(&node_b);
+		let service_url = service_url.clone();
+		let requests = Arc::clone(&requests);
+		let withdraw_invoices = Arc::clone(&withdraw_invoices);
+		serve_http_requests(listener, move |target| {
+			requests.lock().unwrap().push(target.to_string());
+			let (path, query) = target.split_once('?').unwrap_or((target, ""));
+			let param = |name: &str| {
+				query.split('&').find_map(|pair| pair.strip_prefix(&format!("{}=", name)))
+			};
+			let response = match path {
+				"/pay" => serde_json::json!({
+					"tag": "payRequest",
+					"callback": format!("{}/pay/callback", service_url),
+					"minSendable": 1_000,
+					"maxSendable": 100_000_000,
+					"metadata": metadata,
+				}),
+				"/pay/callback" => {
+					let amount_msat = param("amount").unwrap().parse().unwrap();
+					let description = Bolt11InvoiceDescription::Hash(lightning_invoice::Sha256(
+						Sha256::hash(metadata.as_bytes()),
+					));
+					let invoice =
+						node_b.bolt11_payment().receive(amount_msat, &description, 3600).unwrap();
+					serde_json::json!({ "pr": invoice.to_string() })
+				},
+				"/withdraw" => serde_json::json!({
+					"tag": "withdrawRequest",
+					"callback": format!("{}/withdraw/callback", service_url),
+					"k1": "withdrawal-secret",
+					"minWithdrawable": 1_000,
+					"maxWithdrawable": 20_000_000,
+					"defaultDescription": "Withdraw from node B",
+				}),
+				"/withdraw/callback" => {
+					assert_eq!(param("k1"), Some("withdrawal-secret"));
+					withdraw_invoices.lock().unwrap().push(param("pr").unwrap().to_string());
+					serde_json::json!({ "status": "OK" })
+				},
+				_ => serde_json::json!({ "status": "ERROR", "reason": "Unknown path" }),
+			};
+			response.to_string()
+		});
+	}
+
+	// Amounts outside of the service's range are rejected before requesting an invoice.
+	let pay_lnurl = format!("lnurlp://{}/pay", service_url.trim_start_matches("http://"));
+	assert_eq!(
+		node_a.lnurl().pay(&pay_lnurl, 200_000_000, None).unwrap_err(),
+		NodeError::InvalidAmount
+	);
+
+	// As the service doesn't allow comments, ours is omitted.
+	let amount_msat = 50_000_000;
+	let payment_id =
+		node_a.lnurl().pay(&pay_lnurl, amount_msat, Some("Thanks!".to_string())).unwrap();
+	expect_payment_successful_event!(node_a, Some(payment_id), None);
+	expect_payment_received_event!(node_b, amount_msat);
+	assert_eq!(
+		requests.lock().unwrap().last().unwrap(),
+		&format!("/pay/callback?amount={}", amount_msat)
+	);
+
+	// Without an amount we withdraw the maximum the service offers.
+	let withdraw_lnurl = format!("lnurlw://{}/withdraw", service_url.trim_start_matches("http://"));
+	let invoice = node_a.lnurl().withdraw(&withdraw_lnurl, None).unwrap();
+	assert_eq!(invoice.amount_milli_satoshis(), Some(20_000_000));
+	assert_eq!(*withdraw_invoices.lock().unwrap(), vec![invoice.to_string()]);
+
+	let withdraw_payment_id = node_b.bolt11_payment().send(&invoice, None).unwrap();
+	expect_payment_successful_event!(node_b, Some(withdraw_payment_id), None);
+	expect_payment_received_event!(node_a, 20_000_000);
+}
+
 #[test]
 fn lsps2_client_service_integration() {
 	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
@@ -1264,8 +2481,11 @@ fn lsps2_client_service_integration() {
 		max_payment_size_msat: 1_000_000_000,
 		min_payment_size_msat: 0,
 		min_channel_lifetime: 100,
-		min_channel_opening_fee_msat: 0,
+		min_channel_opening_fee_msat: 800_000,
 		max_client_to_self_delay: 1024,
+		max_client_requests_per_hour: Some(4),
+		max_pending_requests_per_client: None,
+		max_outstanding_liquidity_sat: Some(200_000),
 	};
 
 	let service_config = random_config(true);
@@ -1370,6 +2590,377 @@ fn lsps2_client_service_integration() {
 	let payment_id = payer_node.bolt11_payment().send(&invoice, None).unwrap();
 	expect_payment_successful_event!(payer_node, Some(payment_id), None);
 	expect_payment_received_event!(client_node, amount_msat);
+
+	// Payments to JIT channels exceeding the outstanding liquidity budget are failed back.
+	println!("Generating JIT invoice exceeding the liquidity budget!");
+	let invoice_description =
+		Bolt11InvoiceDescription::Direct(Description::new(String::from("asdf")).unwrap());
+	let jit_invoice = client_node
+		.bolt11_payment()
+		.receive_via_jit_channel(500_000_000, &invoice_description.into(), 1024, None)
+		.unwrap();
+	payer_node.bolt11_payment().send(&jit_invoice, None).unwrap();
+	expect_event!(payer_node, PaymentFailed);
+	assert_eq!(client_node.list_channels().len(), 1);
+
+	let sales = service_node.list_lsps2_sales();
+	assert_eq!(sales.len(), 2);
+	let completed_sale =
+		sales.iter().find(|s| matches!(s.state, LSPS2SaleState::Completed { .. })).unwrap();
+	assert_eq!(completed_sale.counterparty_node_id, client_node.node_id());
+	assert_eq!(completed_sale.opening_fee_msat, Some(service_fee_msat));
+	assert_eq!(completed_sale.channel_amount_sat, Some(expected_channel_size_sat));
+	assert!(sales.iter().any(|s| s.state == LSPS2SaleState::AwaitingPayment));
+
+	// Have the client refuse a payment to a JIT channel, as the LSP's minimum opening fee exceeds
+	// the proportional fee it agreed to, leaving us with a JIT channel we didn't get paid for.
+	println!("Paying variable-amount JIT invoice the client refuses!");
+	let invoice_description =
+		Bolt11InvoiceDescription::Direct(Description::new(String::from("asdf")).unwrap());
+	let jit_invoice = client_node
+		.bolt11_payment()
+		.receive_variable_amount_via_jit_channel(&invoice_description.into(), 1024, None)
+		.unwrap();
+	payer_node.bolt11_payment().send_using_amount(&jit_invoice, 50_000_000, None).unwrap();
+	expect_channel_pending_event!(service_node, client_node.node_id());
+	expect_channel_ready_event!(service_node, client_node.node_id());
+	expect_channel_pending_event!(client_node, service_node.node_id());
+	expect_channel_ready_event!(client_node, service_node.node_id());
+	expect_event!(payer_node, PaymentFailed);
+
+	let pending_sale = service_node
+		.list_lsps2_sales()
+		.into_iter()
+		.find(|s| matches!(s.state, LSPS2SaleState::ChannelReady { .. }))
+		.unwrap();
+	let pending_channel_amount_sat = pending_sale.channel_amount_sat.unwrap();
+
+	// Closing the channel fails the sale and releases the liquidity it reserved.
+	println!("Closing the unpaid JIT channel!");
+	service_node.close_channel(&pending_sale.user_channel_id, client_node.node_id()).unwrap();
+	expect_event!(service_node, ChannelClosed);
+	expect_event!(client_node, ChannelClosed);
+	let failed_sale = service_node
+		.list_lsps2_sales()
+		.into_iter()
+		.find(|s| s.user_channel_id == pending_sale.user_channel_id)
+		.unwrap();
+	assert_eq!(failed_sale.state, LSPS2SaleState::Failed);
+
+	// This only fits our budget as the liquidity of the closed channel was released.
+	println!("Paying JIT invoice requiring the released liquidity!");
+	let jit_amount_msat = 150_000_000;
+	let service_fee_msat = (jit_amount_msat * channel_opening_fee_ppm as u64) / 1_000_000;
+	let expected_received_amount_msat = jit_amount_msat - service_fee_msat;
+	let expected_channel_size_sat = (expected_received_amount_msat
+		+ (expected_received_amount_msat * channel_over_provisioning_ppm as u64) / 1_000_000)
+		/ 1000;
+	assert!(expected_channel_size_sat + pending_channel_amount_sat > 200_000);
+	let invoice_description =
+		Bolt11InvoiceDescription::Direct(Description::new(String::from("asdf")).unwrap());
+	let jit_invoice = client_node
+		.bolt11_payment()
+		.receive_via_jit_channel(jit_amount_msat, &invoice_description.into(), 1024, None)
+		.unwrap();
+	let payment_id = payer_node.bolt11_payment().send(&jit_invoice, None).unwrap();
+	expect_channel_pending_event!(service_node, client_node.node_id());
+	expect_channel_ready_event!(service_node, client_node.node_id());
+	expect_channel_pending_event!(client_node, service_node.node_id());
+	expect_channel_ready_event!(client_node, service_node.node_id());
+	expect_payment_successful_event!(payer_node, Some(payment_id), None);
+	expect_payment_received_event!(client_node, expected_received_amount_msat);
+
+	// Clients exceeding the rate limit are rejected.
+	let invoice_description =
+		Bolt11InvoiceDescription::Direct(Description::new(String::from("asdf")).unwrap());
+	assert!(client_node
+		.bolt11_payment()
+		.receive_via_jit_channel(jit_amount_msat, &invoice_description.into(), 1024, None)
+		.is_err());
+	assert_eq!(service_node.list_lsps2_sales().len(), 4);
+}
+
+#[test]
+fn lsps1_client_service_integration() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+
+	let esplora_url = format!("http://{}", electrsd.esplora_url.as_ref().unwrap());
+
+	let sync_config = EsploraSyncConfig { background_sync_config: None };
+
+	// Setup three nodes: service, client, and payer
+	let channel_opening_fee_base_sat = 1_000;
+	let channel_opening_fee_ppm = 10_000;
+	let lsps1_service_config = LSPS1ServiceConfig {
+		require_token: None,
+		advertise_service: false,
+		min_channel_size_sat: 100_000,
+		max_channel_size_sat: 1_000_000,
+		max_client_balance_sat: 100_000,
+		max_channel_expiry_blocks: 10_000,
+		min_required_channel_confirmations: 0,
+		min_funding_confirms_within_blocks: 6,
+		channel_opening_fee_base_sat,
+		channel_opening_fee_ppm,
+		accept_bolt11_payments: true,
+		accept_onchain_payments: true,
+		min_onchain_payment_confirmations: 1,
+		order_expiry_secs: 3600,
+	};
+
+	let service_config = random_config(true);
+	setup_builder!(service_builder, service_config.node_config);
+	service_builder.set_chain_source_esplora(esplora_url.clone(), Some(sync_config));
+	service_builder.set_liquidity_provider_lsps1(lsps1_service_config);
+	let service_node = service_builder.build().unwrap();
+	service_node.start().unwrap();
+
+	let service_node_id = service_node.node_id();
+	let service_addr = service_node.listening_addresses().unwrap().first().unwrap().clone();
+
+	let client_config = random_config(true);
+	setup_builder!(client_builder, client_config.node_config);
+	client_builder.set_chain_source_esplora(esplora_url.clone(), Some(sync_config));
+	client_builder.set_liquidity_source_lsps1(service_node_id, service_addr, None);
+	let client_node = client_builder.build().unwrap();
+	client_node.start().unwrap();
+
+	let payer_config = random_config(true);
+	setup_builder!(payer_builder, payer_config.node_config);
+	payer_builder.set_chain_source_esplora(esplora_url.clone(), Some(sync_config));
+	let payer_node = payer_builder.build().unwrap();
+	payer_node.start().unwrap();
+
+	let service_addr = service_node.onchain_payment().new_address().unwrap();
+	let client_addr = client_node.onchain_payment().new_address().unwrap();
+	let payer_addr = payer_node.onchain_payment().new_address().unwrap();
+
+	let premine_amount_sat = 10_000_000;
+
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![service_addr, client_addr, payer_addr],
+		Amount::from_sat(premine_amount_sat),
+	);
+	service_node.sync_wallets().unwrap();
+	client_node.sync_wallets().unwrap();
+	payer_node.sync_wallets().unwrap();
+
+	// Open a channel payer -> service that will allow paying for the order
+	println!("Opening channel payer_node -> service_node!");
+	open_channel(&payer_node, &service_node, 5_000_000, false, &electrsd);
+
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	service_node.sync_wallets().unwrap();
+	payer_node.sync_wallets().unwrap();
+	expect_channel_ready_event!(payer_node, service_node.node_id());
+	expect_channel_ready_event!(service_node, payer_node.node_id());
+
+	// Orders outside of the configured limits are rejected.
+	assert!(matches!(
+		client_node.lsps1_liquidity().request_channel(2_000_000, 0, 1_000, false),
+		Err(NodeError::LiquidityRequestFailed)
+	));
+
+	println!("Requesting channel!");
+	let lsp_balance_sat = 500_000;
+	let client_balance_sat = 10_000;
+	let order = client_node
+		.lsps1_liquidity()
+		.request_channel(lsp_balance_sat, client_balance_sat, 1_000, false)
+		.unwrap();
+	assert!(order.channel_state.is_none());
+
+	let expected_fee_sat =
+		channel_opening_fee_base_sat + lsp_balance_sat * channel_opening_fee_ppm as u64 / 1_000_000;
+	let bolt11 = order.payment_options.bolt11.unwrap();
+	assert_eq!(bolt11.fee_total_sat, expected_fee_sat);
+	assert_eq!(bolt11.order_total_sat, expected_fee_sat + client_balance_sat);
+	let onchain = order.payment_options.onchain.unwrap();
+	assert_eq!(onchain.order_total_sat, expected_fee_sat + client_balance_sat);
+
+	// Have the payer_node pay the invoice, thereby triggering channel open service_node -> client_node.
+	println!("Paying order invoice!");
+	let payment_id = payer_node.bolt11_payment().send(&bolt11.invoice, None).unwrap();
+	expect_payment_successful_event!(payer_node, Some(payment_id), None);
+	expect_payment_received_event!(service_node, (expected_fee_sat + client_balance_sat) * 1000);
+	expect_channel_pending_event!(service_node, client_node.node_id());
+	expect_channel_ready_event!(service_node, client_node.node_id());
+	expect_channel_pending_event!(client_node, service_node.node_id());
+	expect_channel_ready_event!(client_node, service_node.node_id());
+
+	let channel = client_node.list_channels().into_iter().next().unwrap();
+	assert_eq!(channel.channel_value_sats, lsp_balance_sat + client_balance_sat);
+	assert_eq!(channel.counterparty_node_id, service_node_id);
+
+	let order_status =
+		client_node.lsps1_liquidity().check_order_status(order.order_id.clone()).unwrap();
+	assert!(order_status.channel_state.is_some());
+	expect_lsps1_order_opened(&client_node, &order.order_id);
+
+	let orders = client_node.lsps1_liquidity().list_orders();
+	assert_eq!(orders.len(), 1);
+	assert_eq!(orders[0].order_id, order.order_id);
+	assert_eq!(orders[0].fee_total_sat, expected_fee_sat);
+	assert!(orders[0].payment_id.is_none());
+
+	// Orders exceeding our fee limit are left unpaid.
+	let lsp_balance_sat = 100_000;
+	let expected_fee_sat =
+		channel_opening_fee_base_sat + lsp_balance_sat * channel_opening_fee_ppm as u64 / 1_000_000;
+	assert!(matches!(
+		client_node.lsps1_liquidity().request_channel_with_auto_pay(
+			lsp_balance_sat,
+			0,
+			1_000,
+			false,
+			expected_fee_sat - 1
+		),
+		Err(NodeError::LiquidityFeeTooHigh)
+	));
+	let unpaid_order = client_node
+		.lsps1_liquidity()
+		.list_orders()
+		.into_iter()
+		.find(|o| o.order_id != order.order_id)
+		.unwrap();
+	assert_eq!(unpaid_order.state, LSPS1OrderState::AwaitingPayment);
+	assert!(unpaid_order.payment_id.is_none());
+
+	// Otherwise, the client pays for the order via its channel to the service.
+	println!("Requesting channel with auto-pay!");
+	let paid_order = client_node
+		.lsps1_liquidity()
+		.request_channel_with_auto_pay(lsp_balance_sat, 0, 1_000, false, expected_fee_sat)
+		.unwrap();
+	let payment_id = paid_order.payment_id.unwrap();
+	expect_payment_successful_event!(client_node, Some(payment_id), None);
+	expect_payment_received_event!(service_node, expected_fee_sat * 1000);
+	expect_channel_pending_event!(service_node, client_node.node_id());
+	expect_channel_ready_event!(service_node, client_node.node_id());
+	expect_channel_pending_event!(client_node, service_node.node_id());
+	expect_channel_ready_event!(client_node, service_node.node_id());
+	assert_eq!(client_node.list_channels().len(), 2);
+
+	client_node.lsps1_liquidity().check_order_status(paid_order.order_id.clone()).unwrap();
+	expect_lsps1_order_opened(&client_node, &paid_order.order_id);
+}
+
+#[test]
+fn lsps1_late_onchain_payments() {
+	let (bitcoind, electrsd) = setup_bitcoind_and_electrsd();
+
+	let esplora_url = format!("http://{}", electrsd.esplora_url.as_ref().unwrap());
+
+	let sync_config = EsploraSyncConfig { background_sync_config: None };
+
+	// Setup three nodes: service, client, and payer
+	let lsps1_service_config = LSPS1ServiceConfig {
+		require_token: None,
+		advertise_service: false,
+		min_channel_size_sat: 100_000,
+		max_channel_size_sat: 1_000_000,
+		max_client_balance_sat: 0,
+		max_channel_expiry_blocks: 10_000,
+		min_required_channel_confirmations: 0,
+		min_funding_confirms_within_blocks: 6,
+		channel_opening_fee_base_sat: 1_000,
+		channel_opening_fee_ppm: 10_000,
+		accept_bolt11_payments: false,
+		accept_onchain_payments: true,
+		min_onchain_payment_confirmations: 1,
+		order_expiry_secs: 1,
+	};
+
+	let service_config = random_config(true);
+	setup_builder!(service_builder, service_config.node_config);
+	service_builder.set_chain_source_esplora(esplora_url.clone(), Some(sync_config));
+	service_builder.set_liquidity_provider_lsps1(lsps1_service_config);
+	let service_node = service_builder.build().unwrap();
+	service_node.start().unwrap();
+
+	let service_node_id = service_node.node_id();
+	let service_addr = service_node.listening_addresses().unwrap().first().unwrap().clone();
+
+	let client_config = random_config(true);
+	setup_builder!(client_builder, client_config.node_config);
+	client_builder.set_chain_source_esplora(esplora_url.clone(), Some(sync_config));
+	client_builder.set_liquidity_source_lsps1(service_node_id, service_addr, None);
+	let client_node = client_builder.build().unwrap();
+	client_node.start().unwrap();
+
+	let payer_config = random_config(true);
+	setup_builder!(payer_builder, payer_config.node_config);
+	payer_builder.set_chain_source_esplora(esplora_url.clone(), Some(sync_config));
+	let payer_node = payer_builder.build().unwrap();
+	payer_node.start().unwrap();
+
+	let service_addr = service_node.onchain_payment().new_address().unwrap();
+	let payer_addr = payer_node.onchain_payment().new_address().unwrap();
+	premine_and_distribute_funds(
+		&bitcoind.client,
+		&electrsd.client,
+		vec![service_addr, payer_addr],
+		Amount::from_sat(10_000_000),
+	);
+	service_node.sync_wallets().unwrap();
+	payer_node.sync_wallets().unwrap();
+
+	let paid_order =
+		client_node.lsps1_liquidity().request_channel(500_000, 0, 1_000, false).unwrap();
+	let underpaid_order =
+		client_node.lsps1_liquidity().request_channel(500_000, 0, 1_000, false).unwrap();
+	let paid_onchain = paid_order.payment_options.onchain.unwrap();
+	let underpaid_onchain = underpaid_order.payment_options.onchain.unwrap();
+	assert!(underpaid_onchain.refund_onchain_address.is_some());
+
+	// Wait for the service to check on the orders after they expired.
+	std::thread::sleep(std::time::Duration::from_secs(35));
+
+	let underpaid_amount_sat = underpaid_onchain.order_total_sat / 2;
+	payer_node
+		.onchain_payment()
+		.send_to_address(&paid_onchain.address, paid_onchain.order_total_sat, None, None)
+		.unwrap();
+	payer_node
+		.onchain_payment()
+		.send_to_address(&underpaid_onchain.address, underpaid_amount_sat, None, None)
+		.unwrap();
+	generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 6);
+	service_node.sync_wallets().unwrap();
+
+	// The order paid in full is still fulfilled.
+	expect_channel_pending_event!(service_node, client_node.node_id());
+	expect_channel_ready_event!(service_node, client_node.node_id());
+
+	// The partial payment is refunded to the client.
+	assert_eq!(client_node.list_balances().total_onchain_balance_sats, 0);
+	for _ in 0..30 {
+		generate_blocks_and_wait(&bitcoind.client, &electrsd.client, 1);
+		client_node.sync_wallets().unwrap();
+		if client_node.list_balances().total_onchain_balance_sats > 0 {
+			break;
+		}
+		std::thread::sleep(std::time::Duration::from_secs(1));
+	}
+	assert_eq!(client_node.list_balances().total_onchain_balance_sats, underpaid_amount_sat);
+}
+
+fn expect_lsps1_order_opened(node: &Node, order_id: &OrderId) {
+	// As orders are also polled in the background, we might see intermediate updates.
+	loop {
+		match node.wait_next_event() {
+			Event::LSPS1OrderUpdated { order_id: updated_order_id, state } => {
+				assert_eq!(&updated_order_id.0, order_id);
+				node.event_handled().unwrap();
+				if let LSPS1OrderState::ChannelOpened { .. } = state {
+					break;
+				}
+			},
+			e => panic!("Unexpected event: {:?}", e),
+		}
+	}
 }
 
 #[test]